@@ -11,7 +11,7 @@ pub mod deps_analyzer;
 mod commands;
 mod device;
 mod error;
-mod gguf;
+pub mod gguf;
 pub mod load_probe;
 mod path;
 mod process;
@@ -14,7 +14,7 @@ use indicatif::{ProgressBar, ProgressStyle};
 // The lib target is named "app_lib" (see [lib] section in Cargo.toml).
 use app_lib::core::cli::{
     cli_delete_thread, cli_get_data_folder, cli_get_thread,
-    cli_list_messages, cli_list_threads, discover_llamacpp_binary,
+    cli_list_messages, cli_list_threads, cli_usage_report, discover_llamacpp_binary,
     download_hf_model, fetch_hf_gguf_files, init_llamacpp_state,
     list_models, looks_like_hf_repo, resolve_model_engine, HfFileInfo,
 };
@@ -23,6 +23,7 @@ use app_lib::core::cli::{
 use app_lib::core::cli::{
     discover_mlx_binary, init_mlx_state, load_mlx_model_impl, resolve_model_by_id, MlxConfig,
 };
+use app_lib::core::server::usage::{UsageDimension, UsageQuery};
 use tauri_plugin_llamacpp::router as llamacpp_router;
 use tauri_plugin_llamacpp::state::LlamacppState;
 use std::path::PathBuf;
@@ -44,7 +45,8 @@ Models downloaded in the Jan desktop app are automatically available here.",
   jan serve janhq/Jan-code-4b-gguf                       # expose a model at localhost:6767/v1\n  \
   jan serve janhq/Jan-code-4b-gguf --fit                 # auto-fit context to available VRAM\n  \
   jan serve janhq/Jan-code-4b-gguf --detach              # run in the background\n  \
-  jan models list                                        # show all installed models",
+  jan models list                                        # show all installed models\n  \
+  jan usage --by day,model                               # token and latency totals from the API server",
     version
 )]
 struct Cli {
@@ -109,6 +111,22 @@ enum Commands {
         #[command(subcommand)]
         cmd: ModelsCommands,
     },
+    /// Show token and latency totals recorded by the local API server
+    #[command(display_order = 12)]
+    Usage {
+        /// First day to include (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        since: Option<String>,
+        /// Last day to include (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        until: Option<String>,
+        /// Group rows by day, model, provider and/or client (comma-separated)
+        #[arg(long, value_delimiter = ',', default_value = "model")]
+        by: Vec<UsageDimension>,
+        /// Only count requests made with this client key
+        #[arg(long)]
+        client: Option<String>,
+    },
 }
 
 
@@ -289,6 +307,17 @@ async fn main() {
     match cli.command {
         Commands::Threads { cmd } => handle_threads(cmd).await,
         Commands::Models { cmd } => handle_models(cmd).await,
+        Commands::Usage { since, until, by, client } => {
+            let query = UsageQuery {
+                since,
+                until,
+                client,
+                group_by: by,
+                ..UsageQuery::default()
+            };
+            let report = cli_usage_report(&query);
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        }
         Commands::Serve { args } => handle_serve(args).await,
         Commands::Launch { program, program_args, model, bin, port, api_key, n_gpu_layers, ctx_size, fit, verbose, select } => {
             let program = program.unwrap_or_else(select_program_interactively);
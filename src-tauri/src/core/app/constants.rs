@@ -22,7 +22,17 @@ pub const JAN_DATA_DIRS_MODELS: &[&str] = &["models", "llamacpp", "mlx", "opencl
 pub const JAN_DATA_FILES_CONFIGS: &[&str] = &["mcp_config.json"];
 
 /// Extensions, logs, and caches — always cleaned during any reset.
-pub const JAN_DATA_DIRS_COMMON: &[&str] = &["extensions", "logs", ".npx", ".uvx"];
+/// `tls` holds the API server's generated self-signed certificate
+/// (`core::server::tls`), which is recreated on the next HTTPS start;
+/// `response_cache` its cached model replies (`core::server::response_cache`).
+pub const JAN_DATA_DIRS_COMMON: &[&str] = &[
+    "extensions",
+    "logs",
+    ".npx",
+    ".uvx",
+    "tls",
+    "response_cache",
+];
 
 /// Cross-category settings file (contains data spanning conversations, models,
 /// and UI preferences). Only deleted during a full wipe — i.e. when the user
@@ -31,7 +41,17 @@ pub const JAN_DATA_DIRS_COMMON: &[&str] = &["extensions", "logs", ".npx", ".uvx"
 /// persists webview zustand blobs here keyed by store namespace.
 /// `provider_secrets.enc` is the encrypted OS-keyring fallback for provider API
 /// keys (`core::server::provider_secrets`); it must also be wiped on a full reset.
-pub const JAN_DATA_FILES_SETTINGS: &[&str] = &["settings.json", "provider_secrets.enc"];
+/// `client_keys.json` holds the local API server's per-client keys
+/// (`core::server::client_keys`); `usage.json` its usage ledger
+/// (`core::server::usage`); `model_aliases.json` its model aliases
+/// (`core::server::model_aliases`).
+pub const JAN_DATA_FILES_SETTINGS: &[&str] = &[
+    "settings.json",
+    "provider_secrets.enc",
+    "client_keys.json",
+    "usage.json",
+    "model_aliases.json",
+];
 
 /// All known data subdirectories (union of every category above).
 pub const JAN_DATA_SUBDIRS: &[&str] = &[
@@ -45,10 +65,19 @@ pub const JAN_DATA_SUBDIRS: &[&str] = &[
     "openclaw",
     ".npx",
     ".uvx",
+    "tls",
+    "response_cache",
 ];
 
 /// All known data files (union of every file category above).
-pub const JAN_DATA_FILES: &[&str] = &["mcp_config.json", "settings.json", "provider_secrets.enc"];
+pub const JAN_DATA_FILES: &[&str] = &[
+    "mcp_config.json",
+    "settings.json",
+    "provider_secrets.enc",
+    "client_keys.json",
+    "usage.json",
+    "model_aliases.json",
+];
 
 #[cfg(test)]
 mod tests {
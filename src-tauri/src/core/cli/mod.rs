@@ -7,6 +7,9 @@ use std::sync::Arc;
 
 use crate::core::app::commands::{resolve_config_file_path, resolve_jan_data_folder};
 use crate::core::server::proxy;
+use crate::core::server::usage::{
+    read_usage_file, summarize, UsageQuery, UsageReport, USAGE_FILE_NAME,
+};
 use crate::core::state::AppState;
 use crate::core::threads::{
     constants::THREADS_FILE,
@@ -521,6 +524,14 @@ pub async fn download_hf_model(
     Ok(repo_id.to_string())
 }
 
+// ── Usage ─────────────────────────────────────────────────────────────────
+
+/// Summarize the usage the local API server has recorded in `usage.json`.
+pub fn cli_usage_report(query: &UsageQuery) -> UsageReport {
+    let path = resolve_jan_data_folder().join(USAGE_FILE_NAME);
+    summarize(&read_usage_file(&path), query)
+}
+
 // ── App config ────────────────────────────────────────────────────────────
 
 pub fn cli_get_data_folder() -> PathBuf {
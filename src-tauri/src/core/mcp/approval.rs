@@ -0,0 +1,361 @@
+//! Human-in-the-loop approval for tools the local API server runs itself.
+//!
+//! When [`ToolApprovalPolicy`] says `ask` for a call, one of two things
+//! happens depending on who is driving the orchestration:
+//!
+//! - Desktop: the request goes to the app (the `mcp-tool-approval` event) and
+//!   the call waits until someone answers with `resolve_tool_approval`, or
+//!   [`DESKTOP_APPROVAL_TIMEOUT`] passes, which counts as a denial.
+//! - Client: for client keys set to approve tool calls themselves, the call
+//!   is held for that key and reported back to the API client as pending. The
+//!   client approves or denies it by call id in a follow-up request. Calls are
+//!   only held as the model makes them, and a resumed call is only answered
+//!   when it matches one held for the same key with the same server, tool and
+//!   arguments, so a client cannot approve a call the model never made.
+//!
+//! Every decision is logged with where it came from.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+use super::models::{ToolApproval, ToolApprovalPolicy};
+
+/// Event name the frontend listens on for approval requests.
+pub const MCP_TOOL_APPROVAL_EVENT: &str = "mcp-tool-approval";
+
+/// How long a desktop approval request waits for an answer.
+pub const DESKTOP_APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long a call held for an API client stays resumable.
+const HELD_CALL_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A tool call waiting for a decision.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRequest {
+    /// The tool call id the model gave the call.
+    pub id: String,
+    pub server: String,
+    pub tool: String,
+    pub arguments: Value,
+}
+
+/// Who answers `ask` decisions for an orchestration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalMode {
+    Desktop,
+    /// The API client using the client key of this name.
+    Client(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Allowed,
+    /// Refused, with the reason given to the model.
+    Denied(String),
+    /// Held until the API client decides.
+    Pending,
+}
+
+/// Shows an approval request to the user. Erases the Tauri runtime, like the
+/// progress sink.
+type ApprovalSink = Arc<dyn Fn(&ApprovalRequest) + Send + Sync>;
+
+#[derive(Default)]
+pub struct ToolApprovals {
+    waiting: Mutex<HashMap<String, (ApprovalRequest, oneshot::Sender<bool>)>>,
+    /// Calls held for API clients, by client key name and call id.
+    held: Mutex<HashMap<(String, String), (ApprovalRequest, Instant)>>,
+    sink: Mutex<Option<ApprovalSink>>,
+}
+
+static APPROVALS: OnceLock<ToolApprovals> = OnceLock::new();
+
+pub fn tool_approvals() -> &'static ToolApprovals {
+    APPROVALS.get_or_init(ToolApprovals::default)
+}
+
+fn log_decision(request: &ApprovalRequest, outcome: &str, source: &str) {
+    log::info!(
+        "MCP tool call {} ({}/{}) {outcome} by {source}",
+        request.id,
+        request.server,
+        request.tool
+    );
+}
+
+impl ToolApprovals {
+    /// Set where desktop approval requests are shown.
+    pub fn set_sink(&self, sink: ApprovalSink) {
+        *self.sink.lock().unwrap_or_else(|e| e.into_inner()) = Some(sink);
+    }
+
+    /// Decide whether `request`, a call the model just made, may run.
+    pub async fn check(
+        &self,
+        policy: &ToolApprovalPolicy,
+        mode: &ApprovalMode,
+        request: &ApprovalRequest,
+    ) -> Verdict {
+        match policy.decide(&request.server, &request.tool) {
+            ToolApproval::Allow => {
+                log_decision(request, "allowed", "policy");
+                Verdict::Allowed
+            }
+            ToolApproval::Deny => {
+                log_decision(request, "denied", "policy");
+                Verdict::Denied("denied by policy".to_string())
+            }
+            ToolApproval::Ask => match mode {
+                ApprovalMode::Client(client) => self.hold(client, request),
+                ApprovalMode::Desktop => {
+                    self.check_desktop(request, DESKTOP_APPROVAL_TIMEOUT).await
+                }
+            },
+        }
+    }
+
+    fn held_calls(&self) -> MutexGuard<'_, HashMap<(String, String), (ApprovalRequest, Instant)>> {
+        let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+        held.retain(|_, (_, since)| since.elapsed() < HELD_CALL_TTL);
+        held
+    }
+
+    fn hold(&self, client: &str, request: &ApprovalRequest) -> Verdict {
+        self.held_calls().insert(
+            (client.to_string(), request.id.clone()),
+            (request.clone(), Instant::now()),
+        );
+        log_decision(request, "held for approval", "policy");
+        Verdict::Pending
+    }
+
+    /// Decide a call `client` resumes a held orchestration with; `decision` is
+    /// its answer for the call id, if it sent one. A call that was not held for
+    /// `client` exactly as given is refused, not held.
+    pub fn resume(
+        &self,
+        policy: &ToolApprovalPolicy,
+        client: &str,
+        request: &ApprovalRequest,
+        decision: Option<bool>,
+    ) -> Verdict {
+        let mut held = self.held_calls();
+        let key = (client.to_string(), request.id.clone());
+        if !held.get(&key).is_some_and(|(h, _)| h == request) {
+            log_decision(request, "refused", "policy (not held for this client)");
+            return Verdict::Denied("it was not held for approval".to_string());
+        }
+        if policy.decide(&request.server, &request.tool) == ToolApproval::Deny {
+            held.remove(&key);
+            log_decision(request, "denied", "policy");
+            return Verdict::Denied("denied by policy".to_string());
+        }
+        match decision {
+            None => Verdict::Pending,
+            Some(approved) => {
+                held.remove(&key);
+                if approved {
+                    log_decision(request, "approved", "API client");
+                    Verdict::Allowed
+                } else {
+                    log_decision(request, "denied", "API client");
+                    Verdict::Denied("denied by the client".to_string())
+                }
+            }
+        }
+    }
+
+    async fn check_desktop(&self, request: &ApprovalRequest, timeout: Duration) -> Verdict {
+        let Some(sink) = self.sink.lock().unwrap_or_else(|e| e.into_inner()).clone() else {
+            log_decision(request, "denied", "policy (no approval UI available)");
+            return Verdict::Denied("no approval UI is available".to_string());
+        };
+        let (tx, rx) = oneshot::channel();
+        self.waiting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(request.id.clone(), (request.clone(), tx));
+        sink(request);
+
+        let answer = tokio::time::timeout(timeout, rx).await;
+        self.waiting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&request.id);
+        match answer {
+            Ok(Ok(true)) => {
+                log_decision(request, "approved", "user");
+                Verdict::Allowed
+            }
+            Ok(Ok(false)) => {
+                log_decision(request, "denied", "user");
+                Verdict::Denied("denied by the user".to_string())
+            }
+            Ok(Err(_)) | Err(_) => {
+                log_decision(request, "denied", "timeout");
+                Verdict::Denied("approval timed out".to_string())
+            }
+        }
+    }
+
+    /// Answer a desktop approval request.
+    pub fn resolve(&self, id: &str, approved: bool) -> Result<(), String> {
+        let (_, tx) = self
+            .waiting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id)
+            .ok_or_else(|| format!("No tool call {id} is waiting for approval"))?;
+        tx.send(approved)
+            .map_err(|_| format!("Tool call {id} is no longer waiting for approval"))
+    }
+
+    /// Desktop requests still waiting, for a window that opened after they
+    /// were announced.
+    pub fn pending(&self) -> Vec<ApprovalRequest> {
+        self.waiting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|(request, _)| request.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(id: &str, arguments: Value) -> ApprovalRequest {
+        ApprovalRequest {
+            id: id.to_string(),
+            server: "shell".to_string(),
+            tool: "run".to_string(),
+            arguments,
+        }
+    }
+
+    fn ask() -> ToolApprovalPolicy {
+        ToolApprovalPolicy {
+            default: ToolApproval::Ask,
+            ..Default::default()
+        }
+    }
+
+    fn client(name: &str) -> ApprovalMode {
+        ApprovalMode::Client(name.to_string())
+    }
+
+    #[tokio::test]
+    async fn client_decisions_only_apply_to_held_calls() {
+        let approvals = ToolApprovals::default();
+        let call = request("call_1", json!({"cmd": "ls"}));
+        let not_held = Verdict::Denied("it was not held for approval".to_string());
+
+        // A call the model never made is refused, and not held either.
+        assert_eq!(approvals.resume(&ask(), "ci", &call, Some(true)), not_held);
+        assert_eq!(approvals.resume(&ask(), "ci", &call, None), not_held);
+
+        assert_eq!(
+            approvals.check(&ask(), &client("ci"), &call).await,
+            Verdict::Pending
+        );
+        // Same id, different arguments, or another key: refused.
+        let altered = request("call_1", json!({"cmd": "rm -rf /"}));
+        assert_eq!(
+            approvals.resume(&ask(), "ci", &altered, Some(true)),
+            not_held
+        );
+        assert_eq!(
+            approvals.resume(&ask(), "other", &call, Some(true)),
+            not_held
+        );
+
+        // No answer yet: still held.
+        assert_eq!(
+            approvals.resume(&ask(), "ci", &call, None),
+            Verdict::Pending
+        );
+        assert_eq!(
+            approvals.resume(&ask(), "ci", &call, Some(true)),
+            Verdict::Allowed
+        );
+        // An answer is used once.
+        assert_eq!(approvals.resume(&ask(), "ci", &call, Some(true)), not_held);
+
+        approvals.check(&ask(), &client("ci"), &call).await;
+        assert_eq!(
+            approvals.resume(&ask(), "ci", &call, Some(false)),
+            Verdict::Denied("denied by the client".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn desktop_requests_wait_for_the_user() {
+        let approvals = Arc::new(ToolApprovals::default());
+        let call = request("call_1", json!({}));
+        assert_eq!(
+            approvals.check(&ask(), &ApprovalMode::Desktop, &call).await,
+            Verdict::Denied("no approval UI is available".to_string())
+        );
+
+        let (shown_tx, mut shown) = tokio::sync::mpsc::unbounded_channel();
+        approvals.set_sink(Arc::new(move |r: &ApprovalRequest| {
+            let _ = shown_tx.send(r.id.clone());
+        }));
+        let waiter = {
+            let approvals = approvals.clone();
+            let call = call.clone();
+            tokio::spawn(
+                async move { approvals.check(&ask(), &ApprovalMode::Desktop, &call).await },
+            )
+        };
+        assert_eq!(shown.recv().await.as_deref(), Some("call_1"));
+        assert_eq!(approvals.pending(), vec![call.clone()]);
+        approvals.resolve("call_1", false).unwrap();
+        assert_eq!(
+            waiter.await.unwrap(),
+            Verdict::Denied("denied by the user".to_string())
+        );
+        assert!(approvals.resolve("call_1", true).is_err());
+
+        assert_eq!(
+            approvals
+                .check_desktop(&call, Duration::from_millis(10))
+                .await,
+            Verdict::Denied("approval timed out".to_string())
+        );
+        assert!(approvals.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn policy_decisions_need_no_answer() {
+        let approvals = ToolApprovals::default();
+        let mut policy = ask();
+        policy
+            .servers
+            .insert("shell".to_string(), ToolApproval::Deny);
+        policy
+            .tools
+            .insert("shell/run".to_string(), ToolApproval::Allow);
+        let call = request("call_1", json!({}));
+        assert_eq!(
+            approvals
+                .check(&policy, &ApprovalMode::Desktop, &call)
+                .await,
+            Verdict::Allowed
+        );
+        policy.tools.clear();
+        assert_eq!(
+            approvals.check(&policy, &client("ci"), &call).await,
+            Verdict::Denied("denied by policy".to_string())
+        );
+    }
+}
@@ -1,12 +1,14 @@
-use rmcp::model::{CallToolRequestParam, CallToolResult};
+use rmcp::model::{CallToolRequestParam, CallToolResult, GetPromptResult, ReadResourceResult};
 use serde_json::{json, Map, Value};
 use tauri::{AppHandle, Emitter, Manager, Runtime, State};
 use tokio::sync::oneshot;
 use tokio::time::timeout;
 
 use super::{
+    approval::{tool_approvals, ApprovalRequest},
     constants::DEFAULT_MCP_CONFIG,
     helpers::{restart_active_mcp_servers, start_mcp_server, terminate_browser_mcp},
+    resources,
 };
 use crate::core::{
     app::commands::get_jan_data_folder_path,
@@ -14,7 +16,7 @@ use crate::core::{
     state::AppState,
 };
 use crate::core::{
-    mcp::models::ToolWithServer,
+    mcp::models::{PromptWithServer, ResourceWithServer, ToolWithServer},
     state::{RunningMcpService, SharedMcpServers},
 };
 use std::{collections::HashSet, fs, time::Duration};
@@ -548,6 +550,109 @@ pub async fn cancel_tool_call(
     }
 }
 
+/// Approves or denies a server-side tool call waiting in the approval dialog
+#[tauri::command]
+pub async fn resolve_tool_approval(id: String, approved: bool) -> Result<(), String> {
+    tool_approvals().resolve(&id, approved)
+}
+
+/// Lists server-side tool calls still waiting for approval
+#[tauri::command]
+pub async fn list_tool_approvals() -> Result<Vec<ApprovalRequest>, String> {
+    Ok(tool_approvals().pending())
+}
+
+/// Lists resources published by connected MCP servers, optionally only by
+/// `server_names`
+#[tauri::command]
+pub async fn list_resources(
+    state: State<'_, AppState>,
+    server_names: Option<Vec<String>>,
+) -> Result<Vec<ResourceWithServer>, String> {
+    let filter: Option<HashSet<String>> = server_names.map(|names| names.into_iter().collect());
+    let timeout_duration = tool_call_timeout(&state).await;
+    Ok(resources::list_resources(&state.mcp_servers, filter.as_ref(), timeout_duration).await)
+}
+
+/// Reads the contents of a resource from an MCP server
+#[tauri::command]
+pub async fn read_resource(
+    state: State<'_, AppState>,
+    server_name: String,
+    uri: String,
+) -> Result<ReadResourceResult, String> {
+    let timeout_duration = tool_call_timeout(&state).await;
+    resources::read_resource(&state.mcp_servers, &server_name, uri, timeout_duration).await
+}
+
+/// Subscribes to changes of a resource; updates are emitted as
+/// `mcp-resource-updated` events
+#[tauri::command]
+pub async fn subscribe_resource(
+    state: State<'_, AppState>,
+    server_name: String,
+    uri: String,
+) -> Result<(), String> {
+    let timeout_duration = tool_call_timeout(&state).await;
+    resources::set_resource_subscription(
+        &state.mcp_servers,
+        &server_name,
+        uri,
+        true,
+        timeout_duration,
+    )
+    .await
+}
+
+/// Stops `mcp-resource-updated` events for a resource
+#[tauri::command]
+pub async fn unsubscribe_resource(
+    state: State<'_, AppState>,
+    server_name: String,
+    uri: String,
+) -> Result<(), String> {
+    let timeout_duration = tool_call_timeout(&state).await;
+    resources::set_resource_subscription(
+        &state.mcp_servers,
+        &server_name,
+        uri,
+        false,
+        timeout_duration,
+    )
+    .await
+}
+
+/// Lists prompts published by connected MCP servers, optionally only by
+/// `server_names`
+#[tauri::command]
+pub async fn list_prompts(
+    state: State<'_, AppState>,
+    server_names: Option<Vec<String>>,
+) -> Result<Vec<PromptWithServer>, String> {
+    let filter: Option<HashSet<String>> = server_names.map(|names| names.into_iter().collect());
+    let timeout_duration = tool_call_timeout(&state).await;
+    Ok(resources::list_prompts(&state.mcp_servers, filter.as_ref(), timeout_duration).await)
+}
+
+/// Fetches a prompt's messages from an MCP server, filled in with `arguments`
+#[tauri::command]
+pub async fn get_prompt(
+    state: State<'_, AppState>,
+    server_name: String,
+    name: String,
+    arguments: Option<Map<String, Value>>,
+) -> Result<GetPromptResult, String> {
+    let timeout_duration = tool_call_timeout(&state).await;
+    resources::get_prompt(
+        &state.mcp_servers,
+        &server_name,
+        name,
+        arguments,
+        timeout_duration,
+    )
+    .await
+}
+
 fn parse_mcp_settings(value: Option<&Value>) -> McpSettings {
     value
         .and_then(|v| serde_json::from_value::<McpSettings>(v.clone()).ok())
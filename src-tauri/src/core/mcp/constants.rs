@@ -1,5 +1,6 @@
 // Default MCP runtime settings
 pub const DEFAULT_MCP_TOOL_CALL_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_MCP_MAX_PARALLEL_TOOL_CALLS: usize = 4;
 
 // Browser MCP teardown: how long to wait for the port to free, and the poll cadence.
 pub const MCP_PORT_FREE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(2000);
@@ -57,6 +58,7 @@ pub const DEFAULT_MCP_CONFIG: &str = r#"{
   },
   "mcpSettings": {
     "toolCallTimeoutSeconds": 30,
+    "maxParallelToolCalls": 4,
     "baseRestartDelayMs": 1000,
     "maxRestartDelayMs": 30000,
     "backoffMultiplier": 2.0,
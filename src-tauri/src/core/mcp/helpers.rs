@@ -50,6 +50,22 @@ impl ShutdownContext {
     }
 }
 
+/// Await an MCP request, giving up after `timeout_duration`. `what` opens the
+/// error message, e.g. `Tool call 'search'`.
+pub async fn with_timeout<T, E: std::fmt::Display>(
+    request: impl std::future::Future<Output = Result<T, E>>,
+    timeout_duration: Duration,
+    what: &str,
+) -> Result<T, String> {
+    match timeout(timeout_duration, request).await {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err(format!(
+            "{what} timed out after {} seconds",
+            timeout_duration.as_secs()
+        )),
+    }
+}
+
 /// Runs MCP commands by reading configuration from a JSON file and initializing servers
 ///
 /// # Arguments
@@ -1,9 +1,11 @@
+pub mod approval;
 pub mod commands;
 pub mod constants;
 pub mod helpers;
 pub mod lockfile;
 pub mod models;
 pub mod progress;
+pub mod resources;
 
 #[cfg(test)]
 mod tests;
@@ -1,5 +1,7 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
+use rmcp::model::{Prompt, PromptArgument, Resource};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -19,6 +21,10 @@ fn default_tool_call_timeout_seconds() -> u64 {
     super::constants::DEFAULT_MCP_TOOL_CALL_TIMEOUT_SECS
 }
 
+fn default_max_parallel_tool_calls() -> usize {
+    super::constants::DEFAULT_MCP_MAX_PARALLEL_TOOL_CALLS
+}
+
 fn default_base_restart_delay_ms() -> u64 {
     super::constants::DEFAULT_MCP_BASE_RESTART_DELAY_MS
 }
@@ -47,12 +53,53 @@ fn default_router_model_id() -> String {
     String::new()
 }
 
+/// What the local API server does when a model asks it to run an MCP tool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolApproval {
+    /// Run the tool.
+    #[default]
+    Allow,
+    /// Refuse the call; the model gets an error result instead.
+    Deny,
+    /// Hold the call until someone approves it, in the app or from the API client.
+    Ask,
+}
+
+/// Per-server and per-tool approval rules for server-side tool execution.
+/// The most specific rule wins: `tools` (keyed `server/tool`, or a bare tool
+/// name), then `servers`, then `default`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolApprovalPolicy {
+    #[serde(default)]
+    pub default: ToolApproval,
+    #[serde(default)]
+    pub servers: HashMap<String, ToolApproval>,
+    #[serde(default)]
+    pub tools: HashMap<String, ToolApproval>,
+}
+
+impl ToolApprovalPolicy {
+    pub fn decide(&self, server: &str, tool: &str) -> ToolApproval {
+        self.tools
+            .get(&format!("{server}/{tool}"))
+            .or_else(|| self.tools.get(tool))
+            .or_else(|| self.servers.get(server))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
 /// Runtime MCP settings that can be adjusted via UI
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct McpSettings {
     #[serde(default = "default_tool_call_timeout_seconds")]
     pub tool_call_timeout_seconds: u64,
+    /// How many tool calls of one model turn the local API server runs at once.
+    #[serde(default = "default_max_parallel_tool_calls")]
+    pub max_parallel_tool_calls: usize,
     #[serde(default = "default_base_restart_delay_ms")]
     pub base_restart_delay_ms: u64,
     #[serde(default = "default_max_restart_delay_ms")]
@@ -67,12 +114,16 @@ pub struct McpSettings {
     pub router_model_provider: String,
     #[serde(default = "default_router_model_id")]
     pub router_model_id: String,
+    /// Approval rules for tools the local API server runs on a model's behalf.
+    #[serde(default)]
+    pub tool_approval: ToolApprovalPolicy,
 }
 
 impl Default for McpSettings {
     fn default() -> Self {
         Self {
             tool_call_timeout_seconds: super::constants::DEFAULT_MCP_TOOL_CALL_TIMEOUT_SECS,
+            max_parallel_tool_calls: super::constants::DEFAULT_MCP_MAX_PARALLEL_TOOL_CALLS,
             base_restart_delay_ms: super::constants::DEFAULT_MCP_BASE_RESTART_DELAY_MS,
             max_restart_delay_ms: super::constants::DEFAULT_MCP_MAX_RESTART_DELAY_MS,
             backoff_multiplier: super::constants::DEFAULT_MCP_BACKOFF_MULTIPLIER,
@@ -80,6 +131,7 @@ impl Default for McpSettings {
             use_lightweight_router_model: false,
             router_model_provider: String::new(),
             router_model_id: String::new(),
+            tool_approval: ToolApprovalPolicy::default(),
         }
     }
 }
@@ -101,6 +153,53 @@ pub struct ToolWithServer {
     pub server: String,
 }
 
+/// Resource with server information
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceWithServer {
+    pub uri: String,
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    pub server: String,
+}
+
+impl ResourceWithServer {
+    pub fn new(server: &str, resource: &Resource) -> Self {
+        Self {
+            uri: resource.uri.clone(),
+            name: resource.name.clone(),
+            title: resource.title.clone(),
+            description: resource.description.clone(),
+            mime_type: resource.mime_type.clone(),
+            server: server.to_string(),
+        }
+    }
+}
+
+/// Prompt with server information
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptWithServer {
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub arguments: Vec<PromptArgument>,
+    pub server: String,
+}
+
+impl PromptWithServer {
+    pub fn new(server: &str, prompt: &Prompt) -> Self {
+        Self {
+            name: prompt.name.clone(),
+            title: prompt.title.clone(),
+            description: prompt.description.clone(),
+            arguments: prompt.arguments.clone().unwrap_or_default(),
+            server: server.to_string(),
+        }
+    }
+}
+
 /// Lightweight server metadata used by the frontend orchestrator for tool routing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerSummary {
@@ -1,16 +1,20 @@
 use rmcp::{
     handler::client::ClientHandler,
-    model::{ClientInfo, ProgressNotificationParam},
+    model::{ClientInfo, ProgressNotificationParam, ResourceUpdatedNotificationParam},
     service::NotificationContext,
     RoleClient,
 };
 use serde::Serialize;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tauri::{AppHandle, Emitter, Runtime};
+use tokio::sync::broadcast;
 
 /// Event name the frontend listens on for MCP tool progress.
 pub const MCP_TOOL_PROGRESS_EVENT: &str = "mcp-tool-progress";
 
+/// Event name the frontend listens on for updated MCP resources.
+pub const MCP_RESOURCE_UPDATED_EVENT: &str = "mcp-resource-updated";
+
 /// A `notifications/progress` update from an MCP server.
 ///
 /// The notification carries no tool name -- only the progress token, which rmcp
@@ -45,31 +49,70 @@ pub fn tool_progress(server: &str, params: &ProgressNotificationParam) -> ToolPr
     }
 }
 
+/// A `notifications/resources/updated` from an MCP server, for a resource
+/// subscribed to with `subscribe_resource`. Read it again to get the change.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUpdate {
+    pub server: String,
+    pub uri: String,
+}
+
+/// In-process listeners for the same updates: streamed `/orchestrations`
+/// relay them to their client while one of their tool calls runs.
+static PROGRESS_LISTENERS: OnceLock<broadcast::Sender<ToolProgress>> = OnceLock::new();
+
+fn progress_listeners() -> &'static broadcast::Sender<ToolProgress> {
+    PROGRESS_LISTENERS.get_or_init(|| broadcast::channel(64).0)
+}
+
+/// Receive every progress update from now on, from all servers.
+pub fn subscribe_progress() -> broadcast::Receiver<ToolProgress> {
+    progress_listeners().subscribe()
+}
+
 /// Emits one progress update. Erases the Tauri runtime parameter, which would
 /// otherwise spread from the handler through the shared MCP server map and
 /// every function that touches it.
 type ProgressSink = Arc<dyn Fn(ToolProgress) + Send + Sync>;
 
+/// Emits one resource update, erasing the runtime like [`ProgressSink`].
+type ResourceUpdateSink = Arc<dyn Fn(ResourceUpdate) + Send + Sync>;
+
 /// Client handler for one MCP server connection.
 ///
-/// Exists so progress notifications are observed at all: rmcp routes them to
-/// the handler, and the previous `()` handler dropped them.
+/// Exists so progress and resource-updated notifications are observed at
+/// all: rmcp routes them to the handler, and the previous `()` handler
+/// dropped them.
 #[derive(Clone)]
 pub struct JanClientHandler {
     info: ClientInfo,
     server: String,
     emit: ProgressSink,
+    emit_resource_update: ResourceUpdateSink,
 }
 
 impl JanClientHandler {
     pub fn new<R: Runtime>(info: ClientInfo, server: String, app: AppHandle<R>) -> Self {
         let name = server.clone();
+        let progress_app = app.clone();
         let emit: ProgressSink = Arc::new(move |payload| {
-            if let Err(e) = app.emit(MCP_TOOL_PROGRESS_EVENT, &payload) {
+            if let Err(e) = progress_app.emit(MCP_TOOL_PROGRESS_EVENT, &payload) {
                 log::warn!("Failed to emit MCP progress for {name}: {e}");
             }
         });
-        Self { info, server, emit }
+        let name = server.clone();
+        let emit_resource_update: ResourceUpdateSink = Arc::new(move |payload| {
+            if let Err(e) = app.emit(MCP_RESOURCE_UPDATED_EVENT, &payload) {
+                log::warn!("Failed to emit MCP resource update for {name}: {e}");
+            }
+        });
+        Self {
+            info,
+            server,
+            emit,
+            emit_resource_update,
+        }
     }
 }
 
@@ -83,7 +126,21 @@ impl ClientHandler for JanClientHandler {
         params: ProgressNotificationParam,
         _context: NotificationContext<RoleClient>,
     ) {
-        (self.emit)(tool_progress(&self.server, &params));
+        let payload = tool_progress(&self.server, &params);
+        // Fails only when nobody is subscribed.
+        let _ = progress_listeners().send(payload.clone());
+        (self.emit)(payload);
+    }
+
+    async fn on_resource_updated(
+        &self,
+        params: ResourceUpdatedNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        (self.emit_resource_update)(ResourceUpdate {
+            server: self.server.clone(),
+            uri: params.uri,
+        });
     }
 }
 
@@ -0,0 +1,170 @@
+//! MCP resources and prompts from connected servers.
+//!
+//! Tools are listed and called in `commands`; these are the spec's other two
+//! server features. The Tauri commands and the local API server's `/mcp/*`
+//! routes both go through here. Only servers that advertise a feature are
+//! asked for it, and a server that fails or times out while listing is
+//! skipped with a warning, as when listing tools.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use rmcp::model::{
+    GetPromptRequestParam, GetPromptResult, ReadResourceRequestParam, ReadResourceResult,
+    ServerCapabilities, SubscribeRequestParam, UnsubscribeRequestParam,
+};
+use serde_json::{Map, Value};
+
+use super::helpers::with_timeout;
+use super::models::{PromptWithServer, ResourceWithServer};
+use crate::core::state::{RunningMcpService, SharedMcpServers};
+
+fn offers(service: &RunningMcpService, feature: impl Fn(&ServerCapabilities) -> bool) -> bool {
+    service
+        .peer_info()
+        .is_some_and(|info| feature(&info.capabilities))
+}
+
+fn connected<'a>(
+    servers: &'a HashMap<String, RunningMcpService>,
+    server: &str,
+) -> Result<&'a RunningMcpService, String> {
+    servers
+        .get(server)
+        .ok_or_else(|| format!("Server '{server}' not found"))
+}
+
+/// Resources of every connected server offering them, or only of those in
+/// `server_filter`.
+pub async fn list_resources(
+    mcp_servers: &SharedMcpServers,
+    server_filter: Option<&HashSet<String>>,
+    timeout_duration: Duration,
+) -> Vec<ResourceWithServer> {
+    let servers = mcp_servers.lock().await;
+    let mut resources = Vec::new();
+    for (name, service) in servers.iter() {
+        if server_filter.is_some_and(|f| !f.contains(name))
+            || !offers(service, |c| c.resources.is_some())
+        {
+            continue;
+        }
+        let listed = match with_timeout(
+            service.list_all_resources(),
+            timeout_duration,
+            "Listing resources",
+        )
+        .await
+        {
+            Ok(listed) => listed,
+            Err(e) => {
+                log::warn!("MCP server {name} failed to list resources: {e}");
+                continue;
+            }
+        };
+        resources.extend(listed.iter().map(|r| ResourceWithServer::new(name, r)));
+    }
+    resources
+}
+
+pub async fn read_resource(
+    mcp_servers: &SharedMcpServers,
+    server: &str,
+    uri: String,
+    timeout_duration: Duration,
+) -> Result<ReadResourceResult, String> {
+    let servers = mcp_servers.lock().await;
+    let service = connected(&servers, server)?;
+    with_timeout(
+        service.read_resource(ReadResourceRequestParam { uri }),
+        timeout_duration,
+        "Reading the resource",
+    )
+    .await
+}
+
+/// Start or stop `notifications/resources/updated` for one resource; the
+/// updates arrive through `JanClientHandler`.
+pub async fn set_resource_subscription(
+    mcp_servers: &SharedMcpServers,
+    server: &str,
+    uri: String,
+    subscribed: bool,
+    timeout_duration: Duration,
+) -> Result<(), String> {
+    let servers = mcp_servers.lock().await;
+    let service = connected(&servers, server)?;
+    if !offers(service, |c| {
+        c.resources.as_ref().and_then(|r| r.subscribe) == Some(true)
+    }) {
+        return Err(format!(
+            "Server '{server}' does not support resource subscriptions"
+        ));
+    }
+    if subscribed {
+        with_timeout(
+            service.subscribe(SubscribeRequestParam { uri }),
+            timeout_duration,
+            "Subscribing to the resource",
+        )
+        .await
+    } else {
+        with_timeout(
+            service.unsubscribe(UnsubscribeRequestParam { uri }),
+            timeout_duration,
+            "Unsubscribing from the resource",
+        )
+        .await
+    }
+}
+
+/// Prompts of every connected server offering them, or only of those in
+/// `server_filter`.
+pub async fn list_prompts(
+    mcp_servers: &SharedMcpServers,
+    server_filter: Option<&HashSet<String>>,
+    timeout_duration: Duration,
+) -> Vec<PromptWithServer> {
+    let servers = mcp_servers.lock().await;
+    let mut prompts = Vec::new();
+    for (name, service) in servers.iter() {
+        if server_filter.is_some_and(|f| !f.contains(name))
+            || !offers(service, |c| c.prompts.is_some())
+        {
+            continue;
+        }
+        let listed = match with_timeout(
+            service.list_all_prompts(),
+            timeout_duration,
+            "Listing prompts",
+        )
+        .await
+        {
+            Ok(listed) => listed,
+            Err(e) => {
+                log::warn!("MCP server {name} failed to list prompts: {e}");
+                continue;
+            }
+        };
+        prompts.extend(listed.iter().map(|p| PromptWithServer::new(name, p)));
+    }
+    prompts
+}
+
+/// A prompt's messages, filled in with `arguments`.
+pub async fn get_prompt(
+    mcp_servers: &SharedMcpServers,
+    server: &str,
+    name: String,
+    arguments: Option<Map<String, Value>>,
+    timeout_duration: Duration,
+) -> Result<GetPromptResult, String> {
+    let servers = mcp_servers.lock().await;
+    let service = connected(&servers, server)?;
+    with_timeout(
+        service.get_prompt(GetPromptRequestParam { name, arguments }),
+        timeout_duration,
+        "Getting the prompt",
+    )
+    .await
+}
@@ -640,6 +640,10 @@ fn test_mcp_settings_default_matches_constants() {
         s.tool_call_timeout_seconds,
         constants::DEFAULT_MCP_TOOL_CALL_TIMEOUT_SECS
     );
+    assert_eq!(
+        s.max_parallel_tool_calls,
+        constants::DEFAULT_MCP_MAX_PARALLEL_TOOL_CALLS
+    );
     assert_eq!(
         s.base_restart_delay_ms,
         constants::DEFAULT_MCP_BASE_RESTART_DELAY_MS
@@ -687,6 +691,7 @@ impl super::models::McpSettings {
 impl PartialEq for super::models::McpSettings {
     fn eq(&self, other: &Self) -> bool {
         self.tool_call_timeout_seconds == other.tool_call_timeout_seconds
+            && self.max_parallel_tool_calls == other.max_parallel_tool_calls
             && self.base_restart_delay_ms == other.base_restart_delay_ms
             && self.max_restart_delay_ms == other.max_restart_delay_ms
             && (self.backoff_multiplier - other.backoff_multiplier).abs() < f64::EPSILON
@@ -694,6 +699,7 @@ impl PartialEq for super::models::McpSettings {
             && self.use_lightweight_router_model == other.use_lightweight_router_model
             && self.router_model_provider == other.router_model_provider
             && self.router_model_id == other.router_model_id
+            && self.tool_approval == other.tool_approval
     }
 }
 
@@ -728,6 +734,35 @@ fn test_mcp_settings_partial_deserialize_preserves_provided_values() {
     assert_eq!(s.enable_smart_tool_routing, d.enable_smart_tool_routing);
 }
 
+#[test]
+fn test_tool_approval_policy_prefers_the_most_specific_rule() {
+    use super::models::{McpSettings, ToolApproval};
+    let json = r#"{"toolApproval": {
+        "default": "ask",
+        "servers": {"filesystem": "deny"},
+        "tools": {"filesystem/read_file": "allow", "fetch": "allow"}
+    }}"#;
+    let policy = serde_json::from_str::<McpSettings>(json)
+        .unwrap()
+        .tool_approval;
+    assert_eq!(
+        policy.decide("filesystem", "read_file"),
+        ToolApproval::Allow
+    );
+    assert_eq!(
+        policy.decide("filesystem", "write_file"),
+        ToolApproval::Deny
+    );
+    assert_eq!(policy.decide("web", "fetch"), ToolApproval::Allow);
+    assert_eq!(policy.decide("shell", "run"), ToolApproval::Ask);
+    // Settings written before the policy existed keep running every tool.
+    let legacy: McpSettings = serde_json::from_str("{}").unwrap();
+    assert_eq!(
+        legacy.tool_approval.decide("shell", "run"),
+        ToolApproval::Allow
+    );
+}
+
 #[test]
 fn test_tool_with_server_serialization_uses_input_schema_camel_case() {
     use super::models::ToolWithServer;
@@ -1095,3 +1130,40 @@ async fn terminate_browser_mcp_reaps_process_group() {
         "group leader should have been signalled, got {status:?}"
     );
 }
+
+#[test]
+fn test_resource_and_prompt_listings_carry_their_server() {
+    use super::models::{PromptWithServer, ResourceWithServer};
+    use rmcp::model::{AnnotateAble, Prompt, PromptArgument, RawResource};
+
+    let mut raw = RawResource::new("file:///notes/today.md", "today");
+    raw.mime_type = Some("text/markdown".to_string());
+    let resource = ResourceWithServer::new("notes", &raw.no_annotation());
+    assert_eq!(
+        serde_json::to_value(&resource).unwrap(),
+        serde_json::json!({
+            "uri": "file:///notes/today.md",
+            "name": "today",
+            "title": null,
+            "description": null,
+            "mimeType": "text/markdown",
+            "server": "notes"
+        })
+    );
+
+    let argument = PromptArgument {
+        name: "topic".to_string(),
+        title: None,
+        description: None,
+        required: Some(true),
+    };
+    let prompt = PromptWithServer::new(
+        "notes",
+        &Prompt::new("summarize", Some("Summarize notes"), Some(vec![argument])),
+    );
+    assert_eq!(prompt.server, "notes");
+    assert_eq!(prompt.arguments[0].name, "topic");
+    // Prompts without arguments list an empty set rather than null.
+    let bare = PromptWithServer::new("notes", &Prompt::new("daily", None::<String>, None));
+    assert!(bare.arguments.is_empty());
+}
@@ -0,0 +1,275 @@
+//! JSONL access log for the local API server.
+//!
+//! With `access_log` enabled, one record per request is appended to
+//! `<jan_data>/logs/server_access.jsonl`: route, model, resolved upstream
+//! (provider name, `mlx` or `llamacpp`), status, latency and token counts.
+//! `access_log_capture_bodies` adds the request and response bodies, truncated
+//! and with every provider key, custom header value and API key replaced by
+//! `[REDACTED]`. The file is created `0600` on unix, since it may hold
+//! prompts and replies. It rotates at 10 MiB, keeping a few older
+//! generations (`server_access.1.jsonl`, ...).
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::core::state::ProviderConfig;
+
+const LOGS_DIR_NAME: &str = "logs";
+const ACCESS_LOG_FILE_STEM: &str = "server_access";
+const ROTATE_AT_BYTES: u64 = 10 * 1024 * 1024;
+/// Rotated generations kept besides the live file.
+const KEEP_ROTATED: usize = 4;
+/// Bytes of each body kept when capturing.
+pub const CAPTURE_LIMIT: usize = 64 * 1024;
+const REDACTED: &str = "[REDACTED]";
+
+/// Access-log settings from `StartServerConfig`.
+#[derive(Debug, Clone, Default)]
+pub struct AccessLogOptions {
+    pub enabled: bool,
+    pub capture_bodies: bool,
+}
+
+/// One line of the access log.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AccessRecord {
+    /// RFC 3339 time the request arrived.
+    pub timestamp: String,
+    pub method: String,
+    /// Request path with the API prefix removed.
+    pub route: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<String>,
+    /// Client key name, when one authenticated the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttft_ms: Option<u64>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<String>,
+}
+
+/// Replaces known secrets in captured text.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    /// Longest first, so a secret containing another is replaced whole.
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets: Vec<String> = secrets.into_iter().filter(|s| !s.is_empty()).collect();
+        secrets.sort();
+        secrets.dedup();
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        Self { secrets }
+    }
+
+    /// The proxy key, a presented client key and every provider key and
+    /// custom header value.
+    pub fn for_request<'a>(
+        proxy_api_key: &str,
+        presented_key: Option<&str>,
+        providers: impl IntoIterator<Item = &'a ProviderConfig>,
+    ) -> Self {
+        let mut secrets = vec![proxy_api_key.to_string()];
+        secrets.extend(presented_key.map(str::to_string));
+        for provider in providers {
+            secrets.extend(provider.api_key.iter().cloned());
+            secrets.extend(provider.api_keys.iter().cloned());
+            secrets.extend(provider.custom_headers.iter().map(|h| h.value.clone()));
+        }
+        Self::new(secrets)
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for secret in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = text.replace(secret.as_str(), REDACTED);
+            }
+        }
+        text
+    }
+
+    /// A redacted, truncated copy of `body` for the log.
+    pub fn capture(&self, body: &[u8]) -> String {
+        let truncated = body.len() > CAPTURE_LIMIT;
+        let text = String::from_utf8_lossy(&body[..body.len().min(CAPTURE_LIMIT)]);
+        let mut text = self.redact(&text);
+        if truncated {
+            text.push_str("…[truncated]");
+        }
+        text
+    }
+}
+
+/// Appends records to the access log, rotating it as it grows.
+#[derive(Debug)]
+pub struct AccessLog {
+    path: PathBuf,
+    capture_bodies: bool,
+    rotate_at: u64,
+    write_lock: Mutex<()>,
+}
+
+impl AccessLog {
+    /// The log under `data_folder`, or `None` when it is disabled.
+    pub fn new(data_folder: &Path, options: &AccessLogOptions) -> Option<Self> {
+        options.enabled.then(|| Self {
+            path: data_folder
+                .join(LOGS_DIR_NAME)
+                .join(format!("{ACCESS_LOG_FILE_STEM}.jsonl")),
+            capture_bodies: options.capture_bodies,
+            rotate_at: ROTATE_AT_BYTES,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    pub fn captures_bodies(&self) -> bool {
+        self.capture_bodies
+    }
+
+    fn rotated_path(&self, generation: usize) -> PathBuf {
+        self.path
+            .with_file_name(format!("{ACCESS_LOG_FILE_STEM}.{generation}.jsonl"))
+    }
+
+    pub fn write(&self, record: &AccessRecord) {
+        let Ok(mut line) = serde_json::to_vec(record) else {
+            return;
+        };
+        line.push(b'\n');
+
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(err) = self.append(&line) {
+            log::warn!("Failed to write access log {}: {err}", self.path.display());
+        }
+    }
+
+    fn append(&self, line: &[u8]) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.rotate_at {
+            self.rotate()?;
+        }
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&self.path)?.write_all(line)
+    }
+
+    /// Shift `server_access.jsonl` to `.1`, `.1` to `.2`, ..., dropping the
+    /// oldest generation.
+    fn rotate(&self) -> std::io::Result<()> {
+        let _ = fs::remove_file(self.rotated_path(KEEP_ROTATED));
+        for generation in (1..KEEP_ROTATED).rev() {
+            let from = self.rotated_path(generation);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(generation + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::state::ProviderCustomHeader;
+
+    #[test]
+    fn redacts_every_known_secret() {
+        let provider = ProviderConfig {
+            provider: "openai".into(),
+            api_key: Some("sk-primary-123".into()),
+            api_keys: vec!["sk-primary-123".into(), "sk-backup-456".into()],
+            base_url: None,
+            custom_headers: vec![ProviderCustomHeader {
+                header: "X-Org".into(),
+                value: "org-secret".into(),
+            }],
+            models: vec![],
+            api_type: None,
+        };
+        let redactor = Redactor::for_request("proxy-key", Some("jan-ck-abc"), [&provider]);
+        let text = r#"{"a":"sk-primary-123","b":"sk-backup-456","c":"org-secret","d":"proxy-key jan-ck-abc"}"#;
+        let redacted = redactor.redact(text);
+        for secret in [
+            "sk-primary",
+            "sk-backup",
+            "org-secret",
+            "proxy-key",
+            "jan-ck-abc",
+        ] {
+            assert!(!redacted.contains(secret), "{redacted}");
+        }
+        assert_eq!(redacted.matches(REDACTED).count(), 5);
+
+        let long = vec![b'x'; CAPTURE_LIMIT + 10];
+        assert!(redactor.capture(&long).ends_with("[truncated]"));
+        assert_eq!(Redactor::new([String::new()]).redact("abc"), "abc");
+    }
+
+    #[test]
+    fn appends_jsonl_and_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = AccessLog::new(
+            dir.path(),
+            &AccessLogOptions {
+                enabled: true,
+                capture_bodies: false,
+            },
+        )
+        .unwrap();
+        log.rotate_at = 400;
+
+        let record = AccessRecord {
+            timestamp: "2026-10-18T00:00:00Z".into(),
+            method: "POST".into(),
+            route: "/chat/completions".into(),
+            status: 200,
+            model: Some("qwen3-8b".into()),
+            upstream: Some("llamacpp".into()),
+            latency_ms: 120,
+            input_tokens: 12,
+            output_tokens: 30,
+            ..AccessRecord::default()
+        };
+        for _ in 0..12 {
+            log.write(&record);
+        }
+
+        let live = fs::read_to_string(&log.path).unwrap();
+        let first: serde_json::Value = serde_json::from_str(live.lines().next().unwrap()).unwrap();
+        assert_eq!(first["upstream"], "llamacpp");
+        assert!(first.get("request_body").is_none());
+        assert!(log.rotated_path(1).exists());
+        assert!(!log.rotated_path(KEEP_ROTATED + 1).exists());
+        assert!(fs::metadata(&log.path).unwrap().len() <= 400);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&log.path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
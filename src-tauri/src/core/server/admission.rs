@@ -0,0 +1,402 @@
+//! Admission control for requests to local models.
+//!
+//! llama-server serves a fixed number of slots and queues everything else
+//! internally, out of sight. With `max_concurrent_per_model` set, the proxy
+//! admits at most that many requests per local model at once and holds the
+//! rest in a bounded wait queue. Waiters are served highest priority first
+//! and, within a priority, round-robin across clients, so one busy script
+//! can't starve everyone else. A request that finds the queue full, or waits
+//! longer than the queue timeout, is refused with `429` and a `Retry-After`
+//! estimate.
+//!
+//! Priority comes from the client key's `priority` setting or the
+//! `x-jan-priority` header (`high`, `normal`, `low`); a key's priority is also
+//! the highest its requests may ask for. Batch API work runs at `low`.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+/// Request header naming a priority.
+pub const PRIORITY_HEADER: &str = "x-jan-priority";
+const DEFAULT_MAX_QUEUED: usize = 32;
+const DEFAULT_QUEUE_TIMEOUT_SECS: u64 = 60;
+/// Slot hold time assumed before any request to a model has finished.
+const INITIAL_HOLD_MS: f64 = 5_000.0;
+/// Weight of the newest sample in the moving average of hold times.
+const HOLD_SMOOTHING: f64 = 0.2;
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "normal" => Some(Self::Normal),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    /// The requested priority, capped by the key's, which is also the default.
+    pub fn resolve(requested: Option<Self>, key: Option<Self>) -> Self {
+        match (requested, key) {
+            (Some(requested), Some(key)) => requested.min(key),
+            (requested, key) => requested.or(key).unwrap_or_default(),
+        }
+    }
+}
+
+/// Admission settings from `StartServerConfig`.
+#[derive(Debug, Clone, Default)]
+pub struct AdmissionOptions {
+    /// Requests per local model served at once; `None` disables admission
+    /// control.
+    pub max_concurrent_per_model: Option<usize>,
+    /// Requests per model allowed to wait; defaults to 32.
+    pub max_queued_per_model: Option<usize>,
+    /// How long a request may wait for a slot; defaults to 60 seconds.
+    pub queue_timeout_secs: Option<u64>,
+}
+
+/// Why a request was refused a slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overloaded {
+    pub message: String,
+    pub retry_after_secs: u64,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    grant: oneshot::Sender<()>,
+}
+
+/// Waiters of one priority, per client.
+#[derive(Debug, Default)]
+struct ClientQueues {
+    /// Clients with waiters, in round-robin order.
+    order: VecDeque<String>,
+    waiters: HashMap<String, VecDeque<Waiter>>,
+}
+
+impl ClientQueues {
+    fn push(&mut self, client: &str, waiter: Waiter) {
+        let queue = self.waiters.entry(client.to_string()).or_default();
+        if queue.is_empty() {
+            self.order.push_back(client.to_string());
+        }
+        queue.push_back(waiter);
+    }
+
+    /// The next client's oldest waiter; that client then goes to the back.
+    fn pop(&mut self) -> Option<Waiter> {
+        let client = self.order.pop_front()?;
+        let queue = self.waiters.get_mut(&client)?;
+        let waiter = queue.pop_front();
+        if queue.is_empty() {
+            self.waiters.remove(&client);
+        } else {
+            self.order.push_back(client);
+        }
+        waiter
+    }
+
+    fn remove(&mut self, id: u64) -> bool {
+        let Some(client) = self
+            .waiters
+            .iter()
+            .find(|(_, queue)| queue.iter().any(|w| w.id == id))
+            .map(|(client, _)| client.clone())
+        else {
+            return false;
+        };
+        let queue = self.waiters.get_mut(&client).expect("client just found");
+        queue.retain(|w| w.id != id);
+        if queue.is_empty() {
+            self.waiters.remove(&client);
+            self.order.retain(|c| *c != client);
+        }
+        true
+    }
+
+    fn len(&self) -> usize {
+        self.waiters.values().map(VecDeque::len).sum()
+    }
+}
+
+#[derive(Debug)]
+struct ModelQueue {
+    active: usize,
+    /// Indexed by `Priority as usize`.
+    classes: [ClientQueues; 3],
+    /// Moving average of how long a request holds its slot.
+    avg_hold_ms: f64,
+}
+
+impl Default for ModelQueue {
+    fn default() -> Self {
+        Self {
+            active: 0,
+            classes: Default::default(),
+            avg_hold_ms: INITIAL_HOLD_MS,
+        }
+    }
+}
+
+impl ModelQueue {
+    fn waiting(&self) -> usize {
+        self.classes.iter().map(ClientQueues::len).sum()
+    }
+
+    fn pop(&mut self) -> Option<Waiter> {
+        self.classes.iter_mut().rev().find_map(ClientQueues::pop)
+    }
+
+    fn remove(&mut self, id: u64) -> bool {
+        self.classes.iter_mut().any(|class| class.remove(id))
+    }
+}
+
+/// Per-model slots and wait queues, shared by every request.
+#[derive(Debug)]
+pub struct AdmissionController {
+    limit: usize,
+    max_queued: usize,
+    timeout: Duration,
+    models: Mutex<HashMap<String, ModelQueue>>,
+    next_id: AtomicU64,
+}
+
+/// A slot for one request; dropping it hands the slot to the next waiter.
+#[derive(Debug)]
+pub struct AdmissionPermit {
+    controller: Arc<AdmissionController>,
+    model: String,
+    since: Instant,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.controller.release(&self.model, self.since.elapsed());
+    }
+}
+
+impl AdmissionController {
+    /// The controller, or `None` when admission control is off.
+    pub fn new(options: &AdmissionOptions) -> Option<Arc<Self>> {
+        let limit = options.max_concurrent_per_model.filter(|n| *n > 0)?;
+        Some(Arc::new(Self {
+            limit,
+            max_queued: options.max_queued_per_model.unwrap_or(DEFAULT_MAX_QUEUED),
+            timeout: Duration::from_secs(
+                options
+                    .queue_timeout_secs
+                    .unwrap_or(DEFAULT_QUEUE_TIMEOUT_SECS),
+            ),
+            models: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }))
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ModelQueue>> {
+        self.models.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn permit(self: &Arc<Self>, model: &str) -> AdmissionPermit {
+        AdmissionPermit {
+            controller: self.clone(),
+            model: model.to_string(),
+            since: Instant::now(),
+        }
+    }
+
+    fn overloaded(&self, model: &str, queue: &ModelQueue, reason: &str) -> Overloaded {
+        // Time for everyone ahead, plus us, to get through the slots.
+        let ahead = (queue.waiting() + 1) as f64;
+        let secs = (ahead * queue.avg_hold_ms / self.limit as f64 / 1000.0).ceil();
+        Overloaded {
+            message: format!("Model '{model}' is at capacity: {reason}"),
+            retry_after_secs: (secs as u64).max(1),
+        }
+    }
+
+    /// Wait for a slot on `model`. `client` is the fairness bucket, usually
+    /// the client key name.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        model: &str,
+        client: &str,
+        priority: Priority,
+    ) -> Result<AdmissionPermit, Overloaded> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut granted = {
+            let mut models = self.lock();
+            let queue = models.entry(model.to_string()).or_default();
+            if queue.active < self.limit && queue.waiting() == 0 {
+                queue.active += 1;
+                return Ok(self.permit(model));
+            }
+            if queue.waiting() >= self.max_queued {
+                return Err(self.overloaded(model, queue, "the wait queue is full"));
+            }
+            let (grant, granted) = oneshot::channel();
+            queue.classes[priority as usize].push(client, Waiter { id, grant });
+            granted
+        };
+
+        if let Ok(Ok(())) = tokio::time::timeout(self.timeout, &mut granted).await {
+            return Ok(self.permit(model));
+        }
+        let mut models = self.lock();
+        let queue = models.entry(model.to_string()).or_default();
+        // `release` grants under the lock, so a waiter that is no longer queued
+        // was handed a slot just as the timeout fired.
+        if !queue.remove(id) && granted.try_recv().is_ok() {
+            return Ok(self.permit(model));
+        }
+        Err(self.overloaded(model, queue, "timed out waiting for a slot"))
+    }
+
+    fn release(&self, model: &str, held: Duration) {
+        let mut models = self.lock();
+        let Some(queue) = models.get_mut(model) else {
+            return;
+        };
+        queue.avg_hold_ms =
+            queue.avg_hold_ms * (1.0 - HOLD_SMOOTHING) + held.as_millis() as f64 * HOLD_SMOOTHING;
+        // The slot passes straight to the next waiter still listening.
+        while let Some(waiter) = queue.pop() {
+            if waiter.grant.send(()).is_ok() {
+                return;
+            }
+        }
+        queue.active = queue.active.saturating_sub(1);
+    }
+
+    /// `(model, active, waiting)` for every model seen, for `/metrics`.
+    pub fn snapshot(&self) -> Vec<(String, usize, usize)> {
+        let mut rows: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|(model, queue)| (model.clone(), queue.active, queue.waiting()))
+            .collect();
+        rows.sort();
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(limit: usize, max_queued: usize) -> Arc<AdmissionController> {
+        AdmissionController::new(&AdmissionOptions {
+            max_concurrent_per_model: Some(limit),
+            max_queued_per_model: Some(max_queued),
+            queue_timeout_secs: Some(5),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn priority_is_capped_by_the_key() {
+        assert_eq!(Priority::parse(" HIGH "), Some(Priority::High));
+        assert_eq!(Priority::resolve(None, None), Priority::Normal);
+        assert_eq!(
+            Priority::resolve(Some(Priority::High), None),
+            Priority::High
+        );
+        assert_eq!(
+            Priority::resolve(Some(Priority::High), Some(Priority::Low)),
+            Priority::Low
+        );
+        assert_eq!(
+            Priority::resolve(None, Some(Priority::High)),
+            Priority::High
+        );
+        assert!(AdmissionController::new(&AdmissionOptions::default()).is_none());
+    }
+
+    #[tokio::test]
+    async fn full_queue_is_refused_with_retry_after() {
+        let admission = controller(1, 1);
+        let held = admission.acquire("m", "a", Priority::Normal).await.unwrap();
+        let waiting = tokio::spawn({
+            let admission = admission.clone();
+            async move { admission.acquire("m", "b", Priority::Normal).await }
+        });
+        tokio::task::yield_now().await;
+        while admission.snapshot()[0].2 == 0 {
+            tokio::task::yield_now().await;
+        }
+        let refused = admission
+            .acquire("m", "c", Priority::Normal)
+            .await
+            .unwrap_err();
+        assert!(refused.retry_after_secs >= 1);
+        assert!(refused.message.contains("queue is full"));
+        // Other models have their own slots.
+        assert!(admission
+            .acquire("other", "c", Priority::Normal)
+            .await
+            .is_ok());
+        drop(held);
+        assert!(waiting.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn slots_go_to_higher_priority_then_round_robin_by_client() {
+        let admission = controller(1, 16);
+        let held = admission.acquire("m", "x", Priority::Normal).await.unwrap();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (client, priority) in [
+            ("batch", Priority::Low),
+            ("script", Priority::Normal),
+            ("script", Priority::Normal),
+            ("script", Priority::Normal),
+            ("chat", Priority::Normal),
+            ("ui", Priority::High),
+        ] {
+            let queued = admission.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let permit = queued.acquire("m", client, priority).await.unwrap();
+                order.lock().unwrap().push(client);
+                tokio::task::yield_now().await;
+                drop(permit);
+            }));
+            // Queue them in this order.
+            while admission.snapshot()[0].2 < tasks.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            ["ui", "script", "chat", "script", "script", "batch"]
+        );
+        assert_eq!(admission.snapshot(), [("m".to_string(), 0, 0)]);
+    }
+}
@@ -0,0 +1,959 @@
+//! OpenAI-compatible Batch API backed by the proxy's upstreams.
+//!
+//! `POST /v1/files` stores an uploaded JSONL file of requests,
+//! `POST /v1/batches` queues it and `GET /v1/batches/{id}` reports progress;
+//! once a batch completes its results are read back with
+//! `GET /v1/files/{output_file_id}/content`. Everything lives under
+//! `<jan_data>/batches/`: one `<batch_id>.json` per batch, results appended to
+//! `<batch_id>.output.jsonl` / `<batch_id>.errors.jsonl` as they arrive, and
+//! uploaded and finished files in `files/`. Files and batches belong to the
+//! client key that created them, and other client keys can't see them; a
+//! batch's requests count against its key's quotas and usage.
+//!
+//! A single runner works through queued batches oldest first, sending up to
+//! `batch_concurrency` requests of a batch at once. Batches still queued or
+//! running when the server stops resume when it starts again; requests that
+//! already have a result are not sent twice, and a batch that was finalizing
+//! sends nothing. A batch not done within its 24h completion window expires:
+//! its unsent requests are dropped and the results so far are published.
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const BATCHES_DIR_NAME: &str = "batches";
+const FILES_DIR_NAME: &str = "files";
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+/// The only endpoint batches may target.
+const BATCH_ENDPOINT: &str = "/v1/chat/completions";
+const COMPLETION_WINDOW: &str = "24h";
+const COMPLETION_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/// An uploaded or generated file (OpenAI's file object).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileObject {
+    pub id: String,
+    pub object: String,
+    pub bytes: u64,
+    pub created_at: i64,
+    pub filename: String,
+    /// `batch` for uploads, `batch_output` for results.
+    pub purpose: String,
+    /// Client key that uploaded the file, or whose batch produced it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+impl FileObject {
+    pub fn is_visible_to(&self, client: Option<&str>) -> bool {
+        is_visible(self.owner.as_deref(), client)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Validating,
+    Failed,
+    InProgress,
+    Finalizing,
+    Completed,
+    Expired,
+    Cancelling,
+    Cancelled,
+}
+
+impl BatchStatus {
+    /// Whether the runner still has work to do for the batch.
+    fn is_pending(self) -> bool {
+        matches!(
+            self,
+            Self::Validating | Self::InProgress | Self::Finalizing | Self::Cancelling
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RequestCounts {
+    pub total: u64,
+    pub completed: u64,
+    pub failed: u64,
+}
+
+/// A problem with one line of the input file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchLineError {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+}
+
+/// OpenAI's batch object.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchObject {
+    pub id: String,
+    pub object: String,
+    pub endpoint: String,
+    pub errors: Option<Value>,
+    pub input_file_id: String,
+    pub completion_window: String,
+    pub status: BatchStatus,
+    pub output_file_id: Option<String>,
+    pub error_file_id: Option<String>,
+    pub created_at: i64,
+    pub in_progress_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub finalizing_at: Option<i64>,
+    pub completed_at: Option<i64>,
+    pub failed_at: Option<i64>,
+    pub expired_at: Option<i64>,
+    pub cancelling_at: Option<i64>,
+    pub cancelled_at: Option<i64>,
+    pub request_counts: RequestCounts,
+    pub metadata: Option<Value>,
+    /// Client key that created the batch; its requests are charged to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+impl BatchObject {
+    pub fn is_visible_to(&self, client: Option<&str>) -> bool {
+        is_visible(self.owner.as_deref(), client)
+    }
+
+    fn is_past_window(&self) -> bool {
+        self.expires_at.is_some_and(|at| now() >= at)
+    }
+}
+
+/// Requests without a client key see every file and batch; a client key only
+/// sees the ones it created.
+fn is_visible(owner: Option<&str>, client: Option<&str>) -> bool {
+    client.map_or(true, |client| owner == Some(client))
+}
+
+/// One line of a batch input file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub body: Value,
+}
+
+/// A part of a `multipart/form-data` body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormPart {
+    pub name: String,
+    pub filename: Option<String>,
+    pub data: Vec<u8>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn new_id(prefix: &str) -> String {
+    format!("{prefix}{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Ids name files on disk, so only accept ones we could have issued.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Split a `multipart/form-data` body into its parts.
+pub fn parse_multipart(content_type: &str, body: &[u8]) -> Result<Vec<FormPart>, String> {
+    let boundary = content_type
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("boundary="))
+        .next()
+        .map(|b| b.trim_matches('"'))
+        .filter(|b| !b.is_empty())
+        .ok_or("Expected a multipart/form-data body with a boundary")?;
+
+    // Every delimiter but the first is preceded by CRLF; prepending one lets
+    // the search skip boundary-like text inside part contents.
+    let mut data = b"\r\n".to_vec();
+    data.extend_from_slice(body);
+    let delimiter = format!("\r\n--{boundary}").into_bytes();
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while let Some(pos) = find(&data[i..], &delimiter) {
+        starts.push(i + pos);
+        i += pos + delimiter.len();
+    }
+
+    let mut parts = Vec::new();
+    for window in starts.windows(2) {
+        let segment = &data[window[0] + delimiter.len()..window[1]];
+        let segment = segment.strip_prefix(b"\r\n").unwrap_or(segment);
+        let header_end = find(segment, b"\r\n\r\n").ok_or("Malformed multipart part")?;
+        let headers = String::from_utf8_lossy(&segment[..header_end]);
+        let disposition = headers
+            .lines()
+            .find(|line| {
+                line.to_ascii_lowercase()
+                    .starts_with("content-disposition:")
+            })
+            .ok_or("Multipart part without Content-Disposition")?;
+        let param = |key: &str| {
+            disposition.split(';').find_map(|p| {
+                p.trim()
+                    .strip_prefix(key)
+                    .and_then(|v| v.strip_prefix('='))
+                    .map(|v| v.trim_matches('"').to_string())
+            })
+        };
+        parts.push(FormPart {
+            name: param("name").ok_or("Multipart part without a name")?,
+            filename: param("filename"),
+            data: segment[header_end + 4..].to_vec(),
+        });
+    }
+    if starts.is_empty() {
+        return Err("Multipart body has no parts".to_string());
+    }
+    Ok(parts)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Parse a batch input file, collecting every problem rather than stopping at
+/// the first.
+pub fn parse_input(
+    content: &str,
+    endpoint: &str,
+) -> Result<Vec<BatchRequest>, Vec<BatchLineError>> {
+    let mut requests = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_no = Some(i as u64 + 1);
+        let error = |code: &str, message: String| BatchLineError {
+            code: code.to_string(),
+            message,
+            line: line_no,
+        };
+        let Ok(value) = serde_json::from_str::<Value>(line) else {
+            errors.push(error("invalid_json_line", "Line is not valid JSON".into()));
+            continue;
+        };
+        let Some(custom_id) = value.get("custom_id").and_then(Value::as_str) else {
+            errors.push(error(
+                "missing_custom_id",
+                "Missing string 'custom_id'".into(),
+            ));
+            continue;
+        };
+        if !seen.insert(custom_id.to_string()) {
+            errors.push(error(
+                "duplicate_custom_id",
+                format!("custom_id '{custom_id}' appears more than once"),
+            ));
+            continue;
+        }
+        if value.get("method").and_then(Value::as_str) != Some("POST") {
+            errors.push(error(
+                "invalid_method",
+                "Only POST requests are supported".into(),
+            ));
+            continue;
+        }
+        let url = value.get("url").and_then(Value::as_str).unwrap_or("");
+        if url != endpoint {
+            errors.push(error(
+                "mismatched_url",
+                format!("Request url '{url}' does not match the batch endpoint '{endpoint}'"),
+            ));
+            continue;
+        }
+        match value.get("body").filter(|b| b.is_object()) {
+            Some(body) => requests.push(BatchRequest {
+                custom_id: custom_id.to_string(),
+                body: body.clone(),
+            }),
+            None => errors.push(error("missing_body", "Missing object 'body'".into())),
+        }
+    }
+    if requests.is_empty() && errors.is_empty() {
+        errors.push(BatchLineError {
+            code: "empty_file".to_string(),
+            message: "The input file has no requests".to_string(),
+            line: None,
+        });
+    }
+    if errors.is_empty() {
+        Ok(requests)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Batches and files under `<jan_data>/batches/`.
+#[derive(Debug)]
+pub struct BatchStore {
+    dir: PathBuf,
+    concurrency: usize,
+    /// Serializes read-modify-write of batch records between the runner and
+    /// request handlers.
+    write_lock: Mutex<()>,
+    wake: tokio::sync::Notify,
+}
+
+impl BatchStore {
+    pub fn new(data_folder: &Path, concurrency: Option<usize>) -> Self {
+        Self {
+            dir: data_folder.join(BATCHES_DIR_NAME),
+            concurrency: concurrency.unwrap_or(DEFAULT_BATCH_CONCURRENCY).max(1),
+            write_lock: Mutex::new(()),
+            wake: tokio::sync::Notify::new(),
+        }
+    }
+
+    fn files_dir(&self) -> PathBuf {
+        self.dir.join(FILES_DIR_NAME)
+    }
+
+    fn file_meta_path(&self, id: &str) -> PathBuf {
+        self.files_dir().join(format!("{id}.json"))
+    }
+
+    fn file_content_path(&self, id: &str) -> PathBuf {
+        self.files_dir().join(format!("{id}.jsonl"))
+    }
+
+    fn batch_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    fn partial_path(&self, id: &str, kind: &str) -> PathBuf {
+        self.dir.join(format!("{id}.{kind}.jsonl"))
+    }
+
+    fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let bytes = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+        // Write then rename, so a crash never leaves a torn record.
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+
+    fn store_file(
+        &self,
+        filename: &str,
+        purpose: &str,
+        content: &[u8],
+        owner: Option<&str>,
+    ) -> Result<FileObject, String> {
+        let file = FileObject {
+            id: new_id("file-"),
+            object: "file".to_string(),
+            bytes: content.len() as u64,
+            created_at: now(),
+            filename: filename.to_string(),
+            purpose: purpose.to_string(),
+            owner: owner.map(str::to_string),
+        };
+        fs::create_dir_all(self.files_dir()).map_err(|e| e.to_string())?;
+        fs::write(self.file_content_path(&file.id), content).map_err(|e| e.to_string())?;
+        Self::write_json(&self.file_meta_path(&file.id), &file)?;
+        Ok(file)
+    }
+
+    /// Store an upload from `POST /v1/files` by client key `owner`.
+    pub fn create_file(
+        &self,
+        filename: &str,
+        purpose: &str,
+        content: &[u8],
+        owner: Option<&str>,
+    ) -> Result<FileObject, String> {
+        if purpose != "batch" {
+            return Err(format!(
+                "Unsupported purpose '{purpose}'; only 'batch' files are accepted"
+            ));
+        }
+        self.store_file(filename, purpose, content, owner)
+    }
+
+    pub fn file(&self, id: &str) -> Option<FileObject> {
+        if !is_valid_id(id) {
+            return None;
+        }
+        let bytes = fs::read(self.file_meta_path(id)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    pub fn file_content(&self, id: &str) -> Option<Vec<u8>> {
+        self.file(id)?;
+        fs::read(self.file_content_path(id)).ok()
+    }
+
+    /// Models named by the requests in an input file, for client-key scopes.
+    pub fn input_models(&self, file_id: &str) -> Vec<String> {
+        let content = self.file_content(file_id).unwrap_or_default();
+        let mut models: Vec<String> = String::from_utf8_lossy(&content)
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .filter_map(|v| {
+                v.pointer("/body/model")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            })
+            .collect();
+        models.sort();
+        models.dedup();
+        models
+    }
+
+    /// Queue a batch from a `POST /v1/batches` body sent by client key
+    /// `owner`.
+    pub fn create_batch(
+        &self,
+        request: &Value,
+        owner: Option<&str>,
+    ) -> Result<BatchObject, String> {
+        let input_file_id = request
+            .get("input_file_id")
+            .and_then(Value::as_str)
+            .ok_or("Missing required field 'input_file_id'")?;
+        let input = self
+            .file(input_file_id)
+            .ok_or_else(|| format!("No such file: '{input_file_id}'"))?;
+        if input.purpose != "batch" {
+            return Err(format!(
+                "File '{input_file_id}' was not uploaded for batch use"
+            ));
+        }
+        let endpoint = request
+            .get("endpoint")
+            .and_then(Value::as_str)
+            .ok_or("Missing required field 'endpoint'")?;
+        if endpoint != BATCH_ENDPOINT {
+            return Err(format!(
+                "Unsupported endpoint '{endpoint}'; batches support {BATCH_ENDPOINT}"
+            ));
+        }
+        let window = request
+            .get("completion_window")
+            .and_then(Value::as_str)
+            .unwrap_or(COMPLETION_WINDOW);
+        if window != COMPLETION_WINDOW {
+            return Err(format!(
+                "Unsupported completion_window '{window}'; only '{COMPLETION_WINDOW}' is supported"
+            ));
+        }
+
+        let created_at = now();
+        let batch = BatchObject {
+            id: new_id("batch_"),
+            object: "batch".to_string(),
+            endpoint: endpoint.to_string(),
+            errors: None,
+            input_file_id: input_file_id.to_string(),
+            completion_window: window.to_string(),
+            status: BatchStatus::Validating,
+            output_file_id: None,
+            error_file_id: None,
+            created_at,
+            in_progress_at: None,
+            expires_at: Some(created_at + COMPLETION_WINDOW_SECS),
+            finalizing_at: None,
+            completed_at: None,
+            failed_at: None,
+            expired_at: None,
+            cancelling_at: None,
+            cancelled_at: None,
+            request_counts: RequestCounts::default(),
+            metadata: request.get("metadata").filter(|m| m.is_object()).cloned(),
+            owner: owner.map(str::to_string),
+        };
+        Self::write_json(&self.batch_path(&batch.id), &batch)?;
+        self.wake.notify_one();
+        Ok(batch)
+    }
+
+    pub fn batch(&self, id: &str) -> Option<BatchObject> {
+        if !is_valid_id(id) {
+            return None;
+        }
+        let bytes = fs::read(self.batch_path(id)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    fn update_batch(&self, id: &str, update: impl FnOnce(&mut BatchObject)) -> Option<BatchObject> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut batch = self.batch(id)?;
+        update(&mut batch);
+        if let Err(err) = Self::write_json(&self.batch_path(id), &batch) {
+            log::warn!("Failed to save batch {id}: {err}");
+        }
+        Some(batch)
+    }
+
+    /// Ask the runner to stop a batch; results so far are kept.
+    pub fn cancel_batch(&self, id: &str) -> Option<BatchObject> {
+        let batch = self.update_batch(id, |batch| {
+            if matches!(
+                batch.status,
+                BatchStatus::Validating | BatchStatus::InProgress
+            ) {
+                batch.status = BatchStatus::Cancelling;
+                batch.cancelling_at = Some(now());
+            }
+        })?;
+        self.wake.notify_one();
+        Some(batch)
+    }
+
+    /// Ids of batches the runner still has to work on, oldest first.
+    fn pending(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut batches: Vec<BatchObject> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                self.batch(name.strip_suffix(".json")?)
+            })
+            .filter(|batch| batch.status.is_pending())
+            .collect();
+        batches.sort_by_key(|batch| batch.created_at);
+        batches.into_iter().map(|batch| batch.id).collect()
+    }
+
+    /// `custom_id`s that already have a result, from a previous run.
+    fn answered(&self, id: &str) -> HashSet<String> {
+        ["output", "errors"]
+            .iter()
+            .filter_map(|kind| fs::read_to_string(self.partial_path(id, kind)).ok())
+            .flat_map(|content| {
+                content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+                    .filter_map(|v| v.get("custom_id")?.as_str().map(str::to_string))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn append_result(&self, id: &str, kind: &str, line: &Value) -> Result<(), String> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.partial_path(id, kind))
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{line}").map_err(|e| e.to_string())
+    }
+
+    /// Record the result of one request in the output or error file.
+    fn record_result(&self, id: &str, custom_id: &str, result: Result<Value, String>) {
+        let request_id = new_id("batch_req_");
+        let (kind, line) = match result {
+            Ok(body) => (
+                "output",
+                json!({
+                    "id": request_id,
+                    "custom_id": custom_id,
+                    "response": {"status_code": 200, "request_id": request_id, "body": body},
+                    "error": null,
+                }),
+            ),
+            Err(message) => (
+                "errors",
+                json!({
+                    "id": request_id,
+                    "custom_id": custom_id,
+                    "response": null,
+                    "error": {"code": "request_failed", "message": message},
+                }),
+            ),
+        };
+        if let Err(err) = self.append_result(id, kind, &line) {
+            log::warn!("Failed to record result for batch {id}: {err}");
+            return;
+        }
+        self.update_batch(id, |batch| {
+            if kind == "output" {
+                batch.request_counts.completed += 1;
+            } else {
+                batch.request_counts.failed += 1;
+            }
+        });
+    }
+
+    /// Copy a partial result file into `files/`, returning its id.
+    fn publish_partial(&self, id: &str, kind: &str, owner: Option<&str>) -> Option<String> {
+        let content = fs::read(self.partial_path(id, kind))
+            .ok()
+            .filter(|c| !c.is_empty())?;
+        let file = self
+            .store_file(
+                &format!("{id}_{kind}.jsonl"),
+                "batch_output",
+                &content,
+                owner,
+            )
+            .map_err(|err| log::warn!("Failed to store {kind} file for batch {id}: {err}"))
+            .ok()?;
+        Some(file.id)
+    }
+
+    fn finalize(&self, id: &str) {
+        let Some(batch) = self.update_batch(id, |batch| {
+            batch.status = BatchStatus::Finalizing;
+            batch.finalizing_at.get_or_insert_with(now);
+        }) else {
+            return;
+        };
+        let output_file_id = self.publish_partial(id, "output", batch.owner.as_deref());
+        let error_file_id = self.publish_partial(id, "errors", batch.owner.as_deref());
+        self.update_batch(id, |batch| {
+            batch.output_file_id = output_file_id;
+            batch.error_file_id = error_file_id;
+            let counts = &batch.request_counts;
+            if batch.cancelling_at.is_some() {
+                batch.status = BatchStatus::Cancelled;
+                batch.cancelled_at = Some(now());
+            } else if counts.completed + counts.failed < counts.total && batch.is_past_window() {
+                batch.status = BatchStatus::Expired;
+                batch.expired_at = Some(now());
+            } else {
+                batch.status = BatchStatus::Completed;
+                batch.completed_at = Some(now());
+            }
+        });
+        // The partials go only once the batch points at their published
+        // copies, so a restart in between still knows what was answered.
+        for kind in ["output", "errors"] {
+            let _ = fs::remove_file(self.partial_path(id, kind));
+        }
+    }
+
+    /// Validate a batch's input and move it to `in_progress`, returning the
+    /// requests still to send.
+    fn start(&self, id: &str) -> Option<Vec<BatchRequest>> {
+        let batch = self.batch(id)?;
+        let content = self.file_content(&batch.input_file_id).unwrap_or_default();
+        let requests = match parse_input(&String::from_utf8_lossy(&content), &batch.endpoint) {
+            Ok(requests) => requests,
+            Err(errors) => {
+                log::warn!("Batch {id} failed validation with {} errors", errors.len());
+                self.update_batch(id, |batch| {
+                    batch.status = BatchStatus::Failed;
+                    batch.failed_at = Some(now());
+                    batch.errors = Some(json!({"object": "list", "data": errors}));
+                });
+                return None;
+            }
+        };
+        let total = requests.len() as u64;
+        self.update_batch(id, |batch| {
+            batch.request_counts.total = total;
+            if batch.status == BatchStatus::Validating {
+                batch.status = BatchStatus::InProgress;
+                batch.in_progress_at = Some(now());
+            }
+        });
+        let answered = self.answered(id);
+        Some(
+            requests
+                .into_iter()
+                .filter(|request| !answered.contains(&request.custom_id))
+                .collect(),
+        )
+    }
+
+    /// Whether to stop sending a batch's requests: it is finalizing already,
+    /// was cancelled, or its completion window ran out.
+    fn is_stopping(&self, id: &str) -> bool {
+        self.batch(id).map_or(true, |batch| {
+            matches!(
+                batch.status,
+                BatchStatus::Finalizing | BatchStatus::Cancelling
+            ) || batch.is_past_window()
+        })
+    }
+
+    async fn run_batch<F, Fut>(&self, id: &str, send: &F)
+    where
+        F: Fn(Value, Option<String>) -> Fut,
+        Fut: Future<Output = Result<Value, String>>,
+    {
+        let Some(requests) = self.start(id) else {
+            return;
+        };
+        if !self.is_stopping(id) {
+            log::info!("Running batch {id}: {} requests to send", requests.len());
+            let owner = self.batch(id).and_then(|batch| batch.owner);
+            let owner = &owner;
+            let mut results = futures_util::stream::iter(requests)
+                .map(|request| async move {
+                    let result = send(request.body, owner.clone()).await;
+                    (request.custom_id, result)
+                })
+                .buffer_unordered(self.concurrency);
+            while let Some((custom_id, result)) = results.next().await {
+                self.record_result(id, &custom_id, result);
+                // Dropping the stream abandons the requests still in flight.
+                if self.is_stopping(id) {
+                    break;
+                }
+            }
+        }
+        self.finalize(id);
+        log::info!("Batch {id} finished");
+    }
+}
+
+/// Aborts the batch runner when dropped, i.e. when the server stops.
+pub struct BatchRunner(tokio::task::JoinHandle<()>);
+
+impl Drop for BatchRunner {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Start the runner, which works through pending batches (including ones left
+/// over from a previous run) and then waits for new ones. `send` answers one
+/// chat/completions request body on behalf of the batch's owner.
+pub fn spawn_runner<F, Fut>(store: Arc<BatchStore>, send: F) -> BatchRunner
+where
+    F: Fn(Value, Option<String>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Value, String>> + Send,
+{
+    BatchRunner(tokio::spawn(async move {
+        loop {
+            for id in store.pending() {
+                store.run_batch(&id, &send).await;
+            }
+            store.wake.notified().await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(custom_id: &str, model: &str) -> String {
+        json!({
+            "custom_id": custom_id,
+            "method": "POST",
+            "url": BATCH_ENDPOINT,
+            "body": {"model": model, "messages": [{"role": "user", "content": custom_id}]},
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn parses_multipart_uploads() {
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"purpose\"\r\n\r\nbatch\r\n\
+--xyz\r\nContent-Disposition: form-data; name=\"file\"; filename=\"in.jsonl\"\r\n\
+Content-Type: application/jsonl\r\n\r\n{\"a\":1}\n--xy\n\r\n--xyz--\r\n";
+        let parts = parse_multipart("multipart/form-data; boundary=xyz", body).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "purpose");
+        assert_eq!(parts[0].data, b"batch");
+        assert_eq!(parts[1].filename.as_deref(), Some("in.jsonl"));
+        assert_eq!(parts[1].data, b"{\"a\":1}\n--xy\n");
+        assert!(parse_multipart("application/json", body).is_err());
+    }
+
+    #[test]
+    fn validation_collects_every_bad_line() {
+        let content = format!(
+            "{}\n\nnot json\n{}\n{}\n",
+            line("a", "m"),
+            line("a", "m"),
+            json!({"custom_id": "b", "method": "POST", "url": "/v1/embeddings", "body": {}}),
+        );
+        let errors = parse_input(&content, BATCH_ENDPOINT).unwrap_err();
+        let codes: Vec<_> = errors.iter().map(|e| e.code.as_str()).collect();
+        assert_eq!(
+            codes,
+            ["invalid_json_line", "duplicate_custom_id", "mismatched_url"]
+        );
+        assert_eq!(errors[0].line, Some(3));
+        assert_eq!(
+            parse_input("", BATCH_ENDPOINT).unwrap_err()[0].code,
+            "empty_file"
+        );
+        assert_eq!(
+            parse_input(&line("a", "m"), BATCH_ENDPOINT).unwrap().len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn runs_a_batch_and_resumes_without_resending() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BatchStore::new(dir.path(), Some(2));
+        let content = ["ok-1", "fail", "ok-2"]
+            .iter()
+            .map(|id| line(id, "qwen3-8b"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let file = store
+            .create_file("in.jsonl", "batch", content.as_bytes(), Some("alice"))
+            .unwrap();
+        assert!(store.create_file("x", "fine-tune", b"", None).is_err());
+        assert!(file.is_visible_to(Some("alice")) && file.is_visible_to(None));
+        assert!(!file.is_visible_to(Some("bob")));
+        assert_eq!(store.input_models(&file.id), ["qwen3-8b"]);
+        let batch = store
+            .create_batch(
+                &json!({
+                    "input_file_id": file.id,
+                    "endpoint": BATCH_ENDPOINT,
+                    "completion_window": "24h",
+                }),
+                Some("alice"),
+            )
+            .unwrap();
+
+        // A previous run already answered "ok-1" before the app quit.
+        store.record_result(&batch.id, "ok-1", Ok(json!({"id": "earlier"})));
+        let sent = Mutex::new(Vec::new());
+        let send = |body: Value, owner: Option<String>| {
+            assert_eq!(owner.as_deref(), Some("alice"));
+            let prompt = body["messages"][0]["content"].as_str().unwrap().to_string();
+            sent.lock().unwrap().push(prompt.clone());
+            async move {
+                if prompt == "fail" {
+                    Err("Upstream returned HTTP 500".to_string())
+                } else {
+                    Ok(json!({"object": "chat.completion", "prompt": prompt}))
+                }
+            }
+        };
+        for id in store.pending() {
+            store.run_batch(&id, &send).await;
+        }
+
+        let mut sent = sent.into_inner().unwrap();
+        sent.sort();
+        assert_eq!(sent, ["fail", "ok-2"]);
+        let done = store.batch(&batch.id).unwrap();
+        assert_eq!(done.status, BatchStatus::Completed);
+        assert_eq!(
+            done.request_counts,
+            RequestCounts {
+                total: 3,
+                completed: 2,
+                failed: 1
+            }
+        );
+        assert!(store.pending().is_empty());
+
+        let output = store
+            .file_content(done.output_file_id.as_deref().unwrap())
+            .unwrap();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["custom_id"], "ok-2");
+        assert_eq!(lines[1]["response"]["status_code"], 200);
+        let errors = store
+            .file_content(done.error_file_id.as_deref().unwrap())
+            .unwrap();
+        assert!(String::from_utf8_lossy(&errors).contains("\"custom_id\":\"fail\""));
+        let output_file = store.file(done.output_file_id.as_deref().unwrap()).unwrap();
+        assert_eq!(output_file.purpose, "batch_output");
+        // Results belong to whoever created the batch.
+        assert_eq!(output_file.owner.as_deref(), Some("alice"));
+        assert!(!done.is_visible_to(Some("bob")));
+        assert!(store.file("../batches").is_none());
+    }
+
+    fn queued(store: &BatchStore, ids: &[&str]) -> BatchObject {
+        let content = ids
+            .iter()
+            .map(|id| line(id, "qwen3-8b"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let file = store
+            .create_file("in.jsonl", "batch", content.as_bytes(), None)
+            .unwrap();
+        store
+            .create_batch(
+                &json!({"input_file_id": file.id, "endpoint": BATCH_ENDPOINT}),
+                None,
+            )
+            .unwrap()
+    }
+
+    async fn never_sent(_: Value, _: Option<String>) -> Result<Value, String> {
+        panic!("no request should be sent");
+    }
+
+    #[tokio::test]
+    async fn batches_past_their_window_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BatchStore::new(dir.path(), None);
+        let batch = queued(&store, &["a", "b"]);
+        store.record_result(&batch.id, "a", Ok(json!({})));
+        store.update_batch(&batch.id, |batch| batch.expires_at = Some(now() - 1));
+
+        store.run_batch(&batch.id, &never_sent).await;
+        let expired = store.batch(&batch.id).unwrap();
+        assert_eq!(expired.status, BatchStatus::Expired);
+        assert!(expired.expired_at.is_some() && expired.completed_at.is_none());
+        // What was answered in time is still published.
+        assert!(expired.output_file_id.is_some());
+        assert!(store.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn finalizing_batches_resume_without_sending() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BatchStore::new(dir.path(), None);
+        let batch = queued(&store, &["a", "b"]);
+        // The app stopped while the batch was finalizing.
+        store.record_result(&batch.id, "a", Ok(json!({})));
+        store.record_result(
+            &batch.id,
+            "b",
+            Err("Upstream returned HTTP 500".to_string()),
+        );
+        store.update_batch(&batch.id, |batch| {
+            batch.status = BatchStatus::Finalizing;
+        });
+
+        assert_eq!(store.pending(), std::slice::from_ref(&batch.id));
+        store.run_batch(&batch.id, &never_sent).await;
+        let done = store.batch(&batch.id).unwrap();
+        assert_eq!(done.status, BatchStatus::Completed);
+        let output = store
+            .file_content(done.output_file_id.as_deref().unwrap())
+            .unwrap();
+        assert!(String::from_utf8_lossy(&output).contains("\"custom_id\":\"a\""));
+        assert!(done.error_file_id.is_some());
+        assert!(!store.partial_path(&batch.id, "output").exists());
+    }
+}
@@ -0,0 +1,635 @@
+//! Named per-client API keys for the local API server.
+//!
+//! The shared `proxy_api_key` grants everything. Client keys are issued per
+//! consumer (a teammate, a script, an editor plugin) and each carries its own
+//! scopes: which models and routes it may use, whether it may trigger
+//! server-side MCP tool execution, and request/token quotas.
+//!
+//! Keys live next to the provider secrets in the Jan data folder
+//! (`<jan_data>/client_keys.json`, `0600` on unix). Only a SHA-256 digest of
+//! each secret is stored; the plaintext is returned once, at creation. Quota
+//! windows are tracked in memory and reset when the app restarts.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::State;
+
+use crate::core::app::commands::resolve_jan_data_folder;
+use crate::core::server::admission::Priority;
+use crate::core::state::AppState;
+
+const CLIENT_KEYS_FILE_NAME: &str = "client_keys.json";
+const SECRET_PREFIX: &str = "jan-ck-";
+/// Characters of the secret kept in clear for display (`jan-ck-1a2b3c`).
+const DISPLAY_PREFIX_LEN: usize = 13;
+
+/// Serializes read-modify-write on the keys file.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Per-key request and token limits. `None` means unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClientKeyQuota {
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    #[serde(default)]
+    pub requests_per_day: Option<u32>,
+    /// Prompt + completion tokens per UTC day, as reported by the upstream.
+    #[serde(default)]
+    pub tokens_per_day: Option<u64>,
+}
+
+/// The editable part of a client key: its scopes and limits.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClientKeySettings {
+    /// Model ids this key may request. Empty allows every model; a trailing
+    /// `*` matches by prefix (`openai/*`).
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+    /// Routes (prefix stripped, e.g. `/chat/completions`) this key may call.
+    /// Empty allows every route; a trailing `*` matches by prefix.
+    #[serde(default)]
+    pub allowed_routes: Vec<String>,
+    /// Whether requests made with this key may execute MCP tools server-side
+    /// (`/orchestrations` and `enable_server_tool_execution`) or use MCP
+    /// resources and prompts (`/mcp/*`).
+    #[serde(default)]
+    pub allow_server_tools: bool,
+    /// Whether tool calls the approval policy asks about in this key's
+    /// `/orchestrations` runs are handed back to the client to approve,
+    /// rather than asked in the app.
+    #[serde(default)]
+    pub approve_tool_calls: bool,
+    #[serde(default)]
+    pub quota: ClientKeyQuota,
+    /// Queue priority for this key's requests to local models, and the
+    /// highest an `x-jan-priority` header may ask for. Unset means `normal`
+    /// with no cap.
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// A stored client key. Never carries the plaintext secret.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientKey {
+    pub name: String,
+    /// Leading characters of the secret, for recognising a key in the UI.
+    pub key_prefix: String,
+    /// Hex SHA-256 of the secret.
+    pub key_hash: String,
+    pub created_at: i64,
+    #[serde(flatten)]
+    pub settings: ClientKeySettings,
+}
+
+/// Returned once from `create_client_key`; the only time `secret` is exposed.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedClientKey {
+    pub key: ClientKey,
+    pub secret: String,
+}
+
+/// Current quota windows for one key.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ClientKeyUsage {
+    pub minute_requests: u32,
+    pub day_requests: u32,
+    pub day_tokens: u64,
+    pub total_requests: u64,
+    pub total_tokens: u64,
+    #[serde(skip)]
+    minute: i64,
+    #[serde(skip)]
+    day: i64,
+}
+
+impl ClientKeyUsage {
+    fn roll(&mut self, now: i64) {
+        let minute = now.div_euclid(60);
+        if minute != self.minute {
+            self.minute = minute;
+            self.minute_requests = 0;
+        }
+        let day = now.div_euclid(86_400);
+        if day != self.day {
+            self.day = day;
+            self.day_requests = 0;
+            self.day_tokens = 0;
+        }
+    }
+}
+
+/// A key with its usage, as listed to the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientKeyInfo {
+    #[serde(flatten)]
+    pub key: ClientKey,
+    pub usage: ClientKeyUsage,
+}
+
+/// Why a request was refused admission under a key's quota.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaExceeded {
+    pub message: String,
+    pub retry_after_secs: u64,
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{SECRET_PREFIX}{}", hex::encode(bytes))
+}
+
+/// Exact match, `*` for everything, or a trailing `*` for a prefix match.
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    }
+}
+
+impl ClientKey {
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.settings.allowed_models.is_empty()
+            || self
+                .settings
+                .allowed_models
+                .iter()
+                .any(|p| matches_pattern(p, model))
+    }
+
+    pub fn allows_route(&self, route: &str) -> bool {
+        self.settings.allowed_routes.is_empty()
+            || self
+                .settings
+                .allowed_routes
+                .iter()
+                .any(|p| matches_pattern(p, route))
+    }
+}
+
+fn client_keys_file_path() -> PathBuf {
+    resolve_jan_data_folder().join(CLIENT_KEYS_FILE_NAME)
+}
+
+fn read_keys_file(path: &Path) -> Vec<ClientKey> {
+    let Ok(bytes) = fs::read(path) else {
+        return Vec::new();
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(keys) => keys,
+        Err(err) => {
+            log::warn!("Failed to parse {}: {err}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+fn write_keys_file(path: &Path, keys: &[ClientKey]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let bytes = serde_json::to_vec_pretty(keys).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    write_private(&tmp, &bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Write `bytes` to a file only its owner can read. The file is created that
+/// way, so the contents are never readable by others in between.
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // The mode only applies to new files; tighten one left behind earlier
+    // before anything is written to it.
+    restrict_permissions(path);
+    file.write_all(bytes)
+}
+
+#[cfg(unix)]
+pub(crate) fn restrict_permissions(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Err(err) = fs::set_permissions(path, fs::Permissions::from_mode(0o600)) {
+        log::warn!("Failed to restrict permissions on {}: {err}", path.display());
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn restrict_permissions(_path: &Path) {}
+
+/// In-memory view of the client keys plus their quota windows. Keys are read
+/// from disk on first use and written through on every change.
+#[derive(Debug, Default)]
+pub struct ClientKeyStore {
+    /// Overrides the data-folder location (tests).
+    path: Option<PathBuf>,
+    keys: Mutex<Option<Vec<ClientKey>>>,
+    usage: Mutex<HashMap<String, ClientKeyUsage>>,
+}
+
+impl ClientKeyStore {
+    #[cfg(test)]
+    fn at(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            ..Self::default()
+        }
+    }
+
+    fn file_path(&self) -> PathBuf {
+        self.path.clone().unwrap_or_else(client_keys_file_path)
+    }
+
+    fn with_keys<T>(&self, f: impl FnOnce(&mut Vec<ClientKey>) -> T) -> T {
+        let mut guard = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let keys = guard.get_or_insert_with(|| read_keys_file(&self.file_path()));
+        f(keys)
+    }
+
+    /// Apply `f` to the key list and persist the result if it succeeds.
+    fn modify<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<ClientKey>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _file_guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+        self.with_keys(|keys| {
+            let mut next = keys.clone();
+            let out = f(&mut next)?;
+            write_keys_file(&self.file_path(), &next)?;
+            *keys = next;
+            Ok(out)
+        })
+    }
+
+    /// Whether any enabled key exists. When one does, the server requires
+    /// authentication even if no shared proxy key is set.
+    pub fn has_keys(&self) -> bool {
+        self.with_keys(|keys| keys.iter().any(|k| !k.settings.disabled))
+    }
+
+    /// Resolve a presented secret to its enabled key.
+    pub fn authenticate(&self, secret: &str) -> Option<ClientKey> {
+        if !secret.starts_with(SECRET_PREFIX) {
+            return None;
+        }
+        let hash = hash_secret(secret);
+        self.with_keys(|keys| {
+            keys.iter()
+                .find(|k| k.key_hash == hash && !k.settings.disabled)
+                .cloned()
+        })
+    }
+
+    /// The enabled key named `name`, e.g. the owner of a batch.
+    pub fn get(&self, name: &str) -> Option<ClientKey> {
+        self.with_keys(|keys| {
+            keys.iter()
+                .find(|k| k.name == name && !k.settings.disabled)
+                .cloned()
+        })
+    }
+
+    pub fn list(&self) -> Vec<ClientKeyInfo> {
+        let keys = self.with_keys(|keys| keys.clone());
+        let now = chrono::Utc::now().timestamp();
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        keys.into_iter()
+            .map(|key| {
+                let mut u = usage.get(&key.name).cloned().unwrap_or_default();
+                u.roll(now);
+                usage.insert(key.name.clone(), u.clone());
+                ClientKeyInfo { key, usage: u }
+            })
+            .collect()
+    }
+
+    pub fn create(&self, name: &str, settings: ClientKeySettings) -> Result<CreatedClientKey, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Client key name must not be empty".to_string());
+        }
+        let secret = generate_secret();
+        let key = ClientKey {
+            name: name.to_string(),
+            key_prefix: secret.chars().take(DISPLAY_PREFIX_LEN).collect(),
+            key_hash: hash_secret(&secret),
+            created_at: chrono::Utc::now().timestamp(),
+            settings,
+        };
+        let stored = key.clone();
+        self.modify(move |keys| {
+            if keys.iter().any(|k| k.name == stored.name) {
+                return Err(format!("A client key named '{}' already exists", stored.name));
+            }
+            keys.push(stored);
+            Ok(())
+        })?;
+        Ok(CreatedClientKey { key, secret })
+    }
+
+    pub fn update(&self, name: &str, settings: ClientKeySettings) -> Result<ClientKey, String> {
+        self.modify(|keys| {
+            let key = keys
+                .iter_mut()
+                .find(|k| k.name == name)
+                .ok_or_else(|| format!("Client key '{name}' not found"))?;
+            key.settings = settings;
+            Ok(key.clone())
+        })
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        self.modify(|keys| {
+            let before = keys.len();
+            keys.retain(|k| k.name != name);
+            if keys.len() == before {
+                return Err(format!("Client key '{name}' not found"));
+            }
+            Ok(())
+        })?;
+        self.usage
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name);
+        Ok(())
+    }
+
+    /// Count a request against `key`'s quota, or refuse it.
+    pub fn admit(&self, key: &ClientKey) -> Result<(), QuotaExceeded> {
+        self.admit_at(key, chrono::Utc::now().timestamp())
+    }
+
+    fn admit_at(&self, key: &ClientKey, now: i64) -> Result<(), QuotaExceeded> {
+        let quota = &key.settings.quota;
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let u = usage.entry(key.name.clone()).or_default();
+        u.roll(now);
+
+        let until_next_minute = (60 - now.rem_euclid(60)) as u64;
+        let until_next_day = (86_400 - now.rem_euclid(86_400)) as u64;
+        if let Some(limit) = quota.requests_per_minute {
+            if u.minute_requests >= limit {
+                return Err(QuotaExceeded {
+                    message: format!("Client key '{}' exceeded {limit} requests per minute", key.name),
+                    retry_after_secs: until_next_minute,
+                });
+            }
+        }
+        if let Some(limit) = quota.requests_per_day {
+            if u.day_requests >= limit {
+                return Err(QuotaExceeded {
+                    message: format!("Client key '{}' exceeded {limit} requests per day", key.name),
+                    retry_after_secs: until_next_day,
+                });
+            }
+        }
+        if let Some(limit) = quota.tokens_per_day {
+            if u.day_tokens >= limit {
+                return Err(QuotaExceeded {
+                    message: format!("Client key '{}' exceeded {limit} tokens per day", key.name),
+                    retry_after_secs: until_next_day,
+                });
+            }
+        }
+
+        u.minute_requests += 1;
+        u.day_requests += 1;
+        u.total_requests += 1;
+        Ok(())
+    }
+
+    /// Charge tokens reported by the upstream to `name`'s daily window.
+    pub fn record_tokens(&self, name: &str, tokens: u64) {
+        self.record_tokens_at(name, tokens, chrono::Utc::now().timestamp());
+    }
+
+    fn record_tokens_at(&self, name: &str, tokens: u64, now: i64) {
+        if tokens == 0 {
+            return;
+        }
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let u = usage.entry(name.to_string()).or_default();
+        u.roll(now);
+        u.day_tokens += tokens;
+        u.total_tokens += tokens;
+    }
+}
+
+/// List client keys with their current quota usage.
+#[tauri::command]
+pub async fn list_client_keys(state: State<'_, AppState>) -> Result<Vec<ClientKeyInfo>, String> {
+    let store = state.client_keys.clone();
+    tauri::async_runtime::spawn_blocking(move || store.list())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Issue a new client key. The returned `secret` is not retrievable later.
+#[tauri::command]
+pub async fn create_client_key(
+    state: State<'_, AppState>,
+    name: String,
+    settings: ClientKeySettings,
+) -> Result<CreatedClientKey, String> {
+    let store = state.client_keys.clone();
+    tauri::async_runtime::spawn_blocking(move || store.create(&name, settings))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Replace a client key's scopes and quotas. The secret is unchanged.
+#[tauri::command]
+pub async fn update_client_key(
+    state: State<'_, AppState>,
+    name: String,
+    settings: ClientKeySettings,
+) -> Result<ClientKey, String> {
+    let store = state.client_keys.clone();
+    tauri::async_runtime::spawn_blocking(move || store.update(&name, settings))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Revoke a client key.
+#[tauri::command]
+pub async fn delete_client_key(state: State<'_, AppState>, name: String) -> Result<(), String> {
+    let store = state.client_keys.clone();
+    tauri::async_runtime::spawn_blocking(move || store.delete(&name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> (tempfile::TempDir, ClientKeyStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ClientKeyStore::at(dir.path().join(CLIENT_KEYS_FILE_NAME));
+        (dir, store)
+    }
+
+    #[test]
+    fn create_authenticate_and_persist_only_the_hash() {
+        let (dir, store) = store();
+        assert!(!store.has_keys());
+        let created = store.create("ci", ClientKeySettings::default()).unwrap();
+        assert!(created.secret.starts_with(SECRET_PREFIX));
+        assert!(created.secret.starts_with(&created.key.key_prefix));
+        assert!(store.has_keys());
+
+        assert_eq!(store.authenticate(&created.secret).unwrap().name, "ci");
+        assert!(store.authenticate("jan-ck-wrong").is_none());
+        assert!(store.authenticate("sk-not-a-client-key").is_none());
+
+        let on_disk = fs::read_to_string(dir.path().join(CLIENT_KEYS_FILE_NAME)).unwrap();
+        assert!(!on_disk.contains(&created.secret));
+
+        // A fresh store (app restart) reads the same keys back.
+        let reloaded = ClientKeyStore::at(dir.path().join(CLIENT_KEYS_FILE_NAME));
+        assert!(reloaded.authenticate(&created.secret).is_some());
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected_and_delete_revokes() {
+        let (_dir, store) = store();
+        let created = store.create("alice", ClientKeySettings::default()).unwrap();
+        assert!(store.create("alice", ClientKeySettings::default()).is_err());
+        assert!(store.create("  ", ClientKeySettings::default()).is_err());
+
+        store.delete("alice").unwrap();
+        assert!(store.authenticate(&created.secret).is_none());
+        assert!(store.delete("alice").is_err());
+    }
+
+    #[test]
+    fn disabled_keys_do_not_authenticate() {
+        let (_dir, store) = store();
+        let created = store.create("bob", ClientKeySettings::default()).unwrap();
+        store
+            .update(
+                "bob",
+                ClientKeySettings {
+                    disabled: true,
+                    ..ClientKeySettings::default()
+                },
+            )
+            .unwrap();
+        assert!(store.authenticate(&created.secret).is_none());
+        assert!(!store.has_keys());
+    }
+
+    #[test]
+    fn model_and_route_scopes_support_prefix_wildcards() {
+        let (_dir, store) = store();
+        let key = store
+            .create(
+                "scoped",
+                ClientKeySettings {
+                    allowed_models: vec!["openai/*".into(), "qwen3-8b".into()],
+                    allowed_routes: vec!["/chat/completions".into(), "/models*".into()],
+                    ..ClientKeySettings::default()
+                },
+            )
+            .unwrap()
+            .key;
+        assert!(key.allows_model("openai/gpt-4o"));
+        assert!(key.allows_model("qwen3-8b"));
+        assert!(!key.allows_model("qwen3-8b-instruct"));
+        assert!(key.allows_route("/chat/completions"));
+        assert!(key.allows_route("/models"));
+        assert!(!key.allows_route("/orchestrations"));
+
+        let open = store.create("open", ClientKeySettings::default()).unwrap().key;
+        assert!(open.allows_model("anything"));
+        assert!(open.allows_route("/messages"));
+    }
+
+    #[test]
+    fn request_quotas_reset_with_their_window() {
+        let (_dir, store) = store();
+        let key = store
+            .create(
+                "limited",
+                ClientKeySettings {
+                    quota: ClientKeyQuota {
+                        requests_per_minute: Some(2),
+                        ..ClientKeyQuota::default()
+                    },
+                    ..ClientKeySettings::default()
+                },
+            )
+            .unwrap()
+            .key;
+        let t0 = 1_700_000_020; // 40s into a minute
+        assert!(store.admit_at(&key, t0).is_ok());
+        assert!(store.admit_at(&key, t0 + 1).is_ok());
+        let err = store.admit_at(&key, t0 + 2).unwrap_err();
+        assert_eq!(err.retry_after_secs, 18);
+        assert!(store.admit_at(&key, t0 + 20).is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn key_file_is_private_from_the_start() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stale.json.tmp");
+        fs::write(&path, b"old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, b"new").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+
+        let (_dir, store) = store();
+        store.create("ci", ClientKeySettings::default()).unwrap();
+        let mode = fs::metadata(store.file_path())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn token_quota_blocks_after_usage_is_recorded() {
+        let (_dir, store) = store();
+        let key = store
+            .create(
+                "tokens",
+                ClientKeySettings {
+                    quota: ClientKeyQuota {
+                        tokens_per_day: Some(100),
+                        ..ClientKeyQuota::default()
+                    },
+                    ..ClientKeySettings::default()
+                },
+            )
+            .unwrap()
+            .key;
+        let now = 1_700_000_000;
+        assert!(store.admit_at(&key, now).is_ok());
+        store.record_tokens_at("tokens", 120, now);
+        assert!(store.admit_at(&key, now + 5).is_err());
+        // Next UTC day.
+        assert!(store.admit_at(&key, now + 86_400).is_ok());
+    }
+}
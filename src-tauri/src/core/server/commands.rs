@@ -1,10 +1,16 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use tauri::{AppHandle, Manager, Runtime, State};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
 use tauri_plugin_llamacpp::state::LlamacppState;
 
+use crate::core::server::access_log::AccessLogOptions;
+use crate::core::server::admission::AdmissionOptions;
 use crate::core::server::proxy;
+use crate::core::server::response_cache::ResponseCacheOptions;
+use crate::core::server::tls::TlsOptions;
 use crate::core::app::commands::get_jan_data_folder_path;
+use crate::core::mcp::approval::{tool_approvals, ApprovalRequest, MCP_TOOL_APPROVAL_EVENT};
 use crate::core::state::AppState;
 
 
@@ -17,6 +23,33 @@ pub struct StartServerConfig {
     pub trusted_hosts: Vec<String>,
     pub proxy_timeout: u64,
     pub enable_server_tool_execution: Option<bool>,
+    pub enable_metrics: Option<bool>,
+    /// Serve the Ollama-compatible `/api/*` routes.
+    pub enable_ollama_api: Option<bool>,
+    /// Emulate tool calling for local models without native tool support.
+    pub enable_tool_emulation: Option<bool>,
+    /// PEM certificate chain and private key; both or neither.
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    /// Serve HTTPS with a self-signed certificate kept in the data folder.
+    pub tls_self_signed: Option<bool>,
+    /// CA bundle for verifying client certificates (mTLS).
+    pub tls_client_ca_path: Option<String>,
+    /// Answer repeated deterministic requests from an on-disk cache.
+    pub response_cache: Option<bool>,
+    pub response_cache_max_mb: Option<u64>,
+    pub response_cache_ttl_secs: Option<u64>,
+    /// Write one JSONL record per request to `logs/server_access.jsonl`.
+    pub access_log: Option<bool>,
+    /// Include redacted request and response bodies in the access log.
+    pub access_log_capture_bodies: Option<bool>,
+    /// Requests of a batch sent at once; defaults to 4.
+    pub batch_concurrency: Option<usize>,
+    /// Requests served at once per local model; unset leaves admission
+    /// control off.
+    pub max_concurrent_per_model: Option<usize>,
+    pub max_queued_per_model: Option<usize>,
+    pub queue_timeout_secs: Option<u64>,
 }
 
 #[tauri::command]
@@ -33,7 +66,43 @@ pub async fn start_server<R: Runtime>(
         trusted_hosts,
         proxy_timeout,
         enable_server_tool_execution,
+        enable_metrics,
+        enable_ollama_api,
+        enable_tool_emulation,
+        tls_cert_path,
+        tls_key_path,
+        tls_self_signed,
+        tls_client_ca_path,
+        response_cache,
+        response_cache_max_mb,
+        response_cache_ttl_secs,
+        access_log,
+        access_log_capture_bodies,
+        batch_concurrency,
+        max_concurrent_per_model,
+        max_queued_per_model,
+        queue_timeout_secs,
     } = config;
+    let tls = TlsOptions {
+        cert_path: tls_cert_path.map(PathBuf::from),
+        key_path: tls_key_path.map(PathBuf::from),
+        self_signed: tls_self_signed.unwrap_or(false),
+        client_ca_path: tls_client_ca_path.map(PathBuf::from),
+    };
+    let response_cache = ResponseCacheOptions {
+        enabled: response_cache.unwrap_or(false),
+        max_bytes: response_cache_max_mb.map(|mb| mb.saturating_mul(1024 * 1024)),
+        ttl_secs: response_cache_ttl_secs,
+    };
+    let access_log = AccessLogOptions {
+        enabled: access_log.unwrap_or(false),
+        capture_bodies: access_log_capture_bodies.unwrap_or(false),
+    };
+    let admission = AdmissionOptions {
+        max_concurrent_per_model,
+        max_queued_per_model,
+        queue_timeout_secs,
+    };
     let server_handle = state.server_handle.clone();
     let llama_state: State<Arc<LlamacppState>> = app_handle.state();
     let llama_state_arc = llama_state.inner().clone();
@@ -51,6 +120,14 @@ pub async fn start_server<R: Runtime>(
         >,
     > = Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new()));
 
+    // Tool calls the approval policy holds for the user are shown by the app.
+    let approval_app = app_handle.clone();
+    tool_approvals().set_sink(Arc::new(move |request: &ApprovalRequest| {
+        if let Err(e) = approval_app.emit(MCP_TOOL_APPROVAL_EVENT, request) {
+            log::warn!("Failed to emit tool approval request: {e}");
+        }
+    }));
+
     let actual_port = proxy::start_server(
         server_handle,
         llama_state_arc,
@@ -67,6 +144,17 @@ pub async fn start_server<R: Runtime>(
         state.mcp_settings.clone(),
         get_jan_data_folder_path(app_handle.clone()).to_string_lossy().into_owned(),
         enable_server_tool_execution.unwrap_or(false),
+        enable_metrics.unwrap_or(false),
+        enable_ollama_api.unwrap_or(false),
+        enable_tool_emulation.unwrap_or(false),
+        tls,
+        response_cache,
+        access_log,
+        state.client_keys.clone(),
+        state.usage.clone(),
+        state.model_aliases.clone(),
+        batch_concurrency,
+        admission,
     )
     .await
     .map_err(|e| e.to_string())?;
@@ -3,8 +3,9 @@
 //! Jan's proxy accepts OpenAI chat/completions and, for OpenAI-compatible
 //! upstreams, forwards verbatim. To also front providers with a different
 //! native wire API (OpenAI `/v1/responses`, Google `generateContent`,
-//! Anthropic `/v1/messages`), each such provider gets a converter that rewrites
-//! the request and translates the response back to chat/completions.
+//! Anthropic `/v1/messages`, Ollama `/api/chat`, Azure OpenAI deployments),
+//! each such provider gets a converter that rewrites the request and
+//! translates the response back to chat/completions.
 //!
 //! This module currently provides the shared primitive every converter needs:
 //! an [`SseAccumulator`] that reassembles complete Server-Sent Events across
@@ -29,11 +30,25 @@ pub struct SseEvent {
 #[derive(Debug, Default)]
 pub struct SseAccumulator {
     buf: String,
+    /// Newline-delimited JSON rather than SSE: every non-empty line is one
+    /// event, carried verbatim as its `data`.
+    ndjson: bool,
 }
 
 impl SseAccumulator {
     pub fn new() -> Self {
-        Self { buf: String::new() }
+        Self {
+            buf: String::new(),
+            ndjson: false,
+        }
+    }
+
+    /// An accumulator for NDJSON streams (Ollama's native API).
+    pub fn ndjson() -> Self {
+        Self {
+            buf: String::new(),
+            ndjson: true,
+        }
     }
 
     /// Feed a chunk of the response body; returns every event completed by it.
@@ -41,6 +56,13 @@ impl SseAccumulator {
         // Normalize CRLF so boundary detection only has to look for "\n\n".
         self.buf.push_str(&chunk.replace("\r\n", "\n"));
         let mut events = Vec::new();
+        if self.ndjson {
+            while let Some(idx) = self.buf.find('\n') {
+                let line: String = self.buf.drain(..idx + 1).collect();
+                events.extend(ndjson_event(&line));
+            }
+            return events;
+        }
         while let Some(idx) = self.buf.find("\n\n") {
             let raw: String = self.buf.drain(..idx + 2).collect();
             if let Some(ev) = parse_event(&raw) {
@@ -54,10 +76,21 @@ impl SseAccumulator {
     /// servers omit it before closing the connection).
     pub fn finish(&mut self) -> Option<SseEvent> {
         let raw = std::mem::take(&mut self.buf);
+        if self.ndjson {
+            return ndjson_event(&raw);
+        }
         parse_event(&raw)
     }
 }
 
+fn ndjson_event(line: &str) -> Option<SseEvent> {
+    let line = line.trim();
+    (!line.is_empty()).then(|| SseEvent {
+        event: String::new(),
+        data: line.to_string(),
+    })
+}
+
 fn parse_event(raw: &str) -> Option<SseEvent> {
     let mut ev = SseEvent::default();
     let mut data_lines: Vec<&str> = Vec::new();
@@ -86,11 +119,14 @@ fn parse_event(raw: &str) -> Option<SseEvent> {
 use serde_json::{json, Value};
 use std::collections::HashMap;
 
+use super::media::{self, MediaKind, MediaPart, MediaSource};
+use super::structured_output::{requested_schema, STRUCTURED_OUTPUT_TOOL};
+
 /// Translates an OpenAI chat/completions request to a provider's native wire
 /// API and its response back. Implementors front a provider whose native API is
-/// not chat/completions (OpenAI `/v1/responses`, Google `generateContent`,
-/// Anthropic `/v1/messages`); the proxy selects one by `ProviderConfig.api_type`
-/// and otherwise forwards verbatim.
+/// not plain chat/completions (OpenAI `/v1/responses`, Google `generateContent`,
+/// Anthropic `/v1/messages`, Ollama `/api/chat`, Azure OpenAI); the proxy
+/// selects one by `ProviderConfig.api_type` and otherwise forwards verbatim.
 pub trait UpstreamConverter: Send + Sync {
     /// Path suffix appended to the provider `base_url`. Derived from the request
     /// body because some APIs encode the model and action in the URL (Google:
@@ -118,6 +154,29 @@ pub trait UpstreamConverter: Send + Sync {
     /// Translate one native SSE event into zero or more chat/completions SSE
     /// `data:` payloads (each a JSON chunk string, or the literal `[DONE]`).
     fn convert_stream_event(&self, event: &SseEvent, state: &mut StreamState) -> Vec<String>;
+
+    /// Splits the native stream into events. SSE by default; Ollama streams
+    /// newline-delimited JSON.
+    fn stream_accumulator(&self) -> SseAccumulator {
+        SseAccumulator::new()
+    }
+}
+
+/// Translates a chat/completions chunk stream into another client-facing wire
+/// format (Anthropic `/messages` events, Responses `response.*` events). The
+/// inverse of [`UpstreamConverter`]: it composes after one, so any ingress can
+/// reach any provider. Each returned value is one SSE event whose `type` field
+/// doubles as its `event:` name.
+pub trait ChatStreamTranslator: Send {
+    /// Translate one parsed `chat.completion.chunk`.
+    fn push_chunk(&mut self, chunk: &Value) -> Vec<Value>;
+
+    /// Close anything still open and emit the terminal events. Called on
+    /// `[DONE]` and again when the upstream closes, so it must be idempotent.
+    fn finish(&mut self) -> Vec<Value>;
+
+    /// Emit a terminal error event for an upstream failure mid-stream.
+    fn fail(&mut self, message: &str) -> Vec<Value>;
 }
 
 /// Select the converter for a provider's `api_type`. `None`, `"openai"`, and
@@ -127,6 +186,8 @@ pub fn converter_for(api_type: Option<&str>) -> Option<Box<dyn UpstreamConverter
         Some("openai-responses") => Some(Box::new(OpenAIResponsesConverter::new())),
         Some("google") => Some(Box::new(GoogleGenerateContentConverter::new())),
         Some("anthropic") => Some(Box::new(AnthropicMessagesConverter::new())),
+        Some("ollama") => Some(Box::new(OllamaChatConverter::new())),
+        Some("azure-openai") => Some(Box::new(AzureOpenAIConverter::new())),
         _ => None,
     }
 }
@@ -145,6 +206,17 @@ pub struct StreamState {
     pub finished: bool,
     /// Prompt tokens captured early (Anthropic sends them in `message_start`).
     pub input_tokens: i64,
+    /// Prompt-cache counts, also from `message_start`.
+    pub cache_tokens: CacheTokens,
+    /// Assistant text held back while it may still be an emulated tool call.
+    pub held_text: String,
+    /// The held text opened a tool call; it is parsed when the reply ends.
+    pub holding_tool_call: bool,
+    /// Some assistant text has already been passed on.
+    pub text_sent: bool,
+    /// Native block index of the forced structured-output tool call, whose
+    /// arguments stream out as content.
+    pub structured_block: Option<String>,
 }
 
 /// Fronts OpenAI's `/v1/responses` API, exposing it as chat/completions so the
@@ -160,7 +232,7 @@ impl OpenAIResponsesConverter {
 }
 
 /// Extract plain text from a chat message `content` (string or content-part array).
-fn message_text(content: &Value) -> String {
+pub(crate) fn message_text(content: &Value) -> String {
     match content {
         Value::String(s) => s.clone(),
         Value::Array(parts) => parts
@@ -172,6 +244,43 @@ fn message_text(content: &Value) -> String {
     }
 }
 
+fn log_dropped_media(part: &MediaPart, upstream: &str) {
+    log::warn!("Dropping {:?} attachment the {upstream} upstream cannot take", part.kind);
+}
+
+/// Map each part of a user message's content with `text` or `media`, the
+/// latter returning `None` for attachments the upstream cannot take.
+fn map_user_parts(
+    content: &Value,
+    upstream: &str,
+    text: impl Fn(&str) -> Value,
+    media: impl Fn(&MediaPart) -> Option<Value>,
+) -> Vec<Value> {
+    let Some(parts) = content.as_array() else {
+        return vec![text(&message_text(content))];
+    };
+    parts
+        .iter()
+        .filter_map(|part| match media::from_chat_part(part) {
+            Some(attachment) => {
+                let mapped = media(&attachment);
+                if mapped.is_none() {
+                    log_dropped_media(&attachment, upstream);
+                }
+                mapped
+            }
+            None => part.get("text").and_then(|t| t.as_str()).map(&text),
+        })
+        .collect()
+}
+
+/// Whether chat `content` holds any image, audio or file part.
+fn has_media(content: &Value) -> bool {
+    content
+        .as_array()
+        .is_some_and(|parts| parts.iter().any(|p| media::from_chat_part(p).is_some()))
+}
+
 impl UpstreamConverter for OpenAIResponsesConverter {
     fn upstream_path(&self, _body: &Value) -> String {
         "/responses".to_string()
@@ -218,6 +327,15 @@ impl UpstreamConverter for OpenAIResponsesConverter {
                             input_items.push(json!({"role": "assistant", "content": text}));
                         }
                     }
+                    "user" if has_media(&content) => {
+                        let parts = map_user_parts(
+                            &content,
+                            "Responses",
+                            |text| json!({"type": "input_text", "text": text}),
+                            media::to_responses_part,
+                        );
+                        input_items.push(json!({"role": role, "content": parts}));
+                    }
                     _ => input_items.push(json!({"role": role, "content": message_text(&content)})),
                 }
             }
@@ -244,6 +362,21 @@ impl UpstreamConverter for OpenAIResponsesConverter {
         if let Some(effort) = body.get("reasoning_effort").and_then(|e| e.as_str()) {
             out["reasoning"] = json!({"effort": effort, "summary": "auto"});
         }
+        // Responses takes the json_schema fields flattened into `text.format`.
+        if let Some(format) = body.get("response_format") {
+            match format.get("type").and_then(|t| t.as_str()) {
+                Some("json_schema") => {
+                    let mut flattened = format.get("json_schema").cloned().unwrap_or_else(|| json!({}));
+                    flattened["type"] = json!("json_schema");
+                    if flattened.get("name").is_none() {
+                        flattened["name"] = json!("response");
+                    }
+                    out["text"] = json!({"format": flattened});
+                }
+                Some("json_object") => out["text"] = json!({"format": {"type": "json_object"}}),
+                _ => {}
+            }
+        }
 
         if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
             let flattened: Vec<Value> = tools
@@ -584,6 +717,15 @@ impl UpstreamConverter for GoogleGenerateContentConverter {
                             "parts": [{"functionResponse": {"name": name, "response": response}}]
                         }));
                     }
+                    _ if has_media(&content) => {
+                        let parts = map_user_parts(
+                            &content,
+                            "Gemini",
+                            |text| json!({"text": text}),
+                            |attachment| Some(media::to_gemini_part(attachment)),
+                        );
+                        contents.push(json!({"role": "user", "parts": parts}));
+                    }
                     _ => contents.push(json!({"role": "user", "parts": [{"text": message_text(&content)}]})),
                 }
             }
@@ -607,6 +749,10 @@ impl UpstreamConverter for GoogleGenerateContentConverter {
         if body.get("reasoning_effort").and_then(|e| e.as_str()).is_some() {
             gen_config["thinkingConfig"] = json!({"thinkingBudget": -1, "includeThoughts": true});
         }
+        if let Some(schema) = requested_schema(body) {
+            gen_config["responseMimeType"] = json!("application/json");
+            gen_config["responseJsonSchema"] = schema;
+        }
         if gen_config.as_object().is_some_and(|o| !o.is_empty()) {
             out["generationConfig"] = gen_config;
         }
@@ -812,12 +958,97 @@ fn map_anthropic_finish(reason: &str, saw_tool: bool) -> &'static str {
     }
 }
 
-fn anthropic_usage(input_tokens: i64, output_tokens: i64) -> Value {
-    json!({
-        "prompt_tokens": input_tokens,
+/// A turn ended by the structured-output call is a finished answer.
+fn structured_stop_reason(reason: &str, structured: bool) -> &str {
+    if structured && reason == "tool_use" {
+        "end_turn"
+    } else {
+        reason
+    }
+}
+
+/// Anthropic prompt-cache token counts: written to and read from the cache.
+/// Anthropic's `input_tokens` leaves both out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheTokens {
+    pub creation: i64,
+    pub read: i64,
+}
+
+impl CacheTokens {
+    /// Read from an Anthropic `usage` object, keeping `self` for absent fields.
+    fn update(&mut self, usage: Option<&Value>) {
+        let field = |name: &str| usage.and_then(|u| u.get(name)).and_then(|v| v.as_i64());
+        if let Some(n) = field("cache_creation_input_tokens") {
+            self.creation = n;
+        }
+        if let Some(n) = field("cache_read_input_tokens") {
+            self.read = n;
+        }
+    }
+}
+
+/// chat/completions usage for Anthropic counts. Cached tokens count toward
+/// `prompt_tokens` as OpenAI reports them, and the Anthropic cache fields are
+/// kept beside it.
+fn anthropic_usage(input_tokens: i64, output_tokens: i64, cache: CacheTokens) -> Value {
+    let prompt_tokens = input_tokens + cache.creation + cache.read;
+    let mut usage = json!({
+        "prompt_tokens": prompt_tokens,
         "completion_tokens": output_tokens,
-        "total_tokens": input_tokens + output_tokens,
-    })
+        "total_tokens": prompt_tokens + output_tokens,
+    });
+    if cache != CacheTokens::default() {
+        usage["prompt_tokens_details"] = json!({"cached_tokens": cache.read});
+        usage["cache_creation_input_tokens"] = json!(cache.creation);
+        usage["cache_read_input_tokens"] = json!(cache.read);
+    }
+    usage
+}
+
+/// Anthropic blocks for a chat message's content: text, plus `image` and
+/// `document` blocks for attachments. Each part keeps its `cache_control`; a
+/// message-level `cache_control` marks the last block.
+fn anthropic_content_blocks(msg: &Value) -> Vec<Value> {
+    let mut blocks: Vec<Value> = match msg.get("content") {
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| {
+                let mut block = match media::from_chat_part(part) {
+                    Some(attachment) => {
+                        let block = media::to_anthropic_block(&attachment);
+                        if block.is_none() {
+                            log_dropped_media(&attachment, "Anthropic");
+                        }
+                        block?
+                    }
+                    None => json!({"type": "text", "text": part.get("text")?.as_str()?}),
+                };
+                if let Some(cache) = part.get("cache_control") {
+                    block["cache_control"] = cache.clone();
+                }
+                Some(block)
+            })
+            .collect(),
+        Some(content) => {
+            let text = message_text(content);
+            if text.is_empty() {
+                Vec::new()
+            } else {
+                vec![json!({"type": "text", "text": text})]
+            }
+        }
+        None => Vec::new(),
+    };
+    mark_cached(msg, &mut blocks);
+    blocks
+}
+
+/// Copy a chat message's own `cache_control` onto the last of its blocks.
+fn mark_cached(msg: &Value, blocks: &mut [Value]) {
+    if let (Some(cache), Some(last)) = (msg.get("cache_control"), blocks.last_mut()) {
+        last["cache_control"] = cache.clone();
+    }
 }
 
 /// Append `blocks` to the last message when it shares `role`, else start a new
@@ -868,20 +1099,21 @@ impl UpstreamConverter for AnthropicMessagesConverter {
             }
         }
 
-        let mut system: Vec<String> = Vec::new();
+        let mut system: Vec<Value> = Vec::new();
         let mut messages: Vec<Value> = Vec::new();
         if let Some(msgs) = body.get("messages").and_then(|m| m.as_array()) {
             for msg in msgs {
                 let role = msg.get("role").and_then(|r| r.as_str()).unwrap_or("user");
                 let content = msg.get("content").cloned().unwrap_or(Value::Null);
                 match role {
-                    "system" | "developer" => system.push(message_text(&content)),
+                    // Anthropic system prompts are text only.
+                    "system" | "developer" => system.extend(
+                        anthropic_content_blocks(msg)
+                            .into_iter()
+                            .filter(|b| b["type"] == "text"),
+                    ),
                     "assistant" => {
-                        let mut blocks: Vec<Value> = Vec::new();
-                        let text = message_text(&content);
-                        if !text.is_empty() {
-                            blocks.push(json!({"type": "text", "text": text}));
-                        }
+                        let mut blocks = anthropic_content_blocks(msg);
                         if let Some(calls) = msg.get("tool_calls").and_then(|c| c.as_array()) {
                             for call in calls {
                                 let func = call.get("function");
@@ -898,30 +1130,35 @@ impl UpstreamConverter for AnthropicMessagesConverter {
                                 }));
                             }
                         }
+                        mark_cached(msg, &mut blocks);
                         push_merged(&mut messages, "assistant", blocks);
                     }
                     "tool" => {
-                        push_merged(
-                            &mut messages,
-                            "user",
-                            vec![json!({
-                                "type": "tool_result",
-                                "tool_use_id": msg.get("tool_call_id").cloned().unwrap_or(Value::Null),
-                                "content": message_text(&content),
-                            })],
-                        );
+                        let mut blocks = vec![json!({
+                            "type": "tool_result",
+                            "tool_use_id": msg.get("tool_call_id").cloned().unwrap_or(Value::Null),
+                            "content": message_text(&content),
+                        })];
+                        mark_cached(msg, &mut blocks);
+                        push_merged(&mut messages, "user", blocks);
+                    }
+                    _ => {
+                        let mut blocks = anthropic_content_blocks(msg);
+                        if blocks.is_empty() {
+                            blocks.push(json!({"type": "text", "text": ""}));
+                        }
+                        push_merged(&mut messages, "user", blocks);
                     }
-                    _ => push_merged(
-                        &mut messages,
-                        "user",
-                        vec![json!({"type": "text", "text": message_text(&content)})],
-                    ),
                 }
             }
         }
         out["messages"] = json!(messages);
-        if !system.is_empty() {
-            out["system"] = json!(system.join("\n\n"));
+        // A cache breakpoint inside the system prompt needs the block form.
+        if system.iter().any(|b| b.get("cache_control").is_some()) {
+            out["system"] = json!(system);
+        } else if !system.is_empty() {
+            let texts: Vec<&str> = system.iter().filter_map(|b| b["text"].as_str()).collect();
+            out["system"] = json!(texts.join("\n\n"));
         }
 
         if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
@@ -929,11 +1166,15 @@ impl UpstreamConverter for AnthropicMessagesConverter {
                 .iter()
                 .filter_map(|t| {
                     let func = t.get("function")?;
-                    Some(json!({
+                    let mut tool = json!({
                         "name": func.get("name").cloned().unwrap_or(Value::Null),
                         "description": func.get("description").cloned().unwrap_or(Value::Null),
                         "input_schema": func.get("parameters").cloned().unwrap_or(json!({"type": "object"})),
-                    }))
+                    });
+                    if let Some(cache) = t.get("cache_control").or_else(|| func.get("cache_control")) {
+                        tool["cache_control"] = cache.clone();
+                    }
+                    Some(tool)
                 })
                 .collect();
             if !mapped.is_empty() {
@@ -955,6 +1196,28 @@ impl UpstreamConverter for AnthropicMessagesConverter {
             };
         }
 
+        // No native structured output: the answer must be a call to a tool
+        // taking the schema, unwrapped again in the response. With tools still
+        // on offer the model may call one of those first instead.
+        if let Some(schema) = requested_schema(body) {
+            let tools_usable = out.get("tools").is_some()
+                && out["tool_choice"].get("type").and_then(|t| t.as_str()) != Some("none");
+            let structured = json!({
+                "name": STRUCTURED_OUTPUT_TOOL,
+                "description": "Give the final answer as this tool's input.",
+                "input_schema": schema,
+            });
+            match out.get_mut("tools").and_then(|t| t.as_array_mut()) {
+                Some(tools) => tools.push(structured),
+                None => out["tools"] = json!([structured]),
+            }
+            if !tools_usable {
+                out["tool_choice"] = json!({"type": "tool", "name": STRUCTURED_OUTPUT_TOOL});
+            } else if out["tool_choice"].get("type").and_then(|t| t.as_str()) != Some("tool") {
+                out["tool_choice"] = json!({"type": "any"});
+            }
+        }
+
         out
     }
 
@@ -962,6 +1225,7 @@ impl UpstreamConverter for AnthropicMessagesConverter {
         let mut content = String::new();
         let mut reasoning = String::new();
         let mut tool_calls: Vec<Value> = Vec::new();
+        let mut structured = false;
 
         if let Some(blocks) = upstream.get("content").and_then(|c| c.as_array()) {
             for block in blocks {
@@ -978,6 +1242,11 @@ impl UpstreamConverter for AnthropicMessagesConverter {
                     }
                     Some("tool_use") => {
                         let input = block.get("input").cloned().unwrap_or_else(|| json!({}));
+                        if block.get("name").and_then(|n| n.as_str()) == Some(STRUCTURED_OUTPUT_TOOL) {
+                            structured = true;
+                            content.push_str(&serde_json::to_string(&input).unwrap_or_default());
+                            continue;
+                        }
                         tool_calls.push(json!({
                             "id": block.get("id").cloned().unwrap_or(Value::Null),
                             "type": "function",
@@ -1006,12 +1275,17 @@ impl UpstreamConverter for AnthropicMessagesConverter {
             message["tool_calls"] = json!(tool_calls);
         }
         let finish_reason = map_anthropic_finish(
-            upstream.get("stop_reason").and_then(|r| r.as_str()).unwrap_or("end_turn"),
+            structured_stop_reason(
+                upstream.get("stop_reason").and_then(|r| r.as_str()).unwrap_or("end_turn"),
+                structured,
+            ),
             saw_tool,
         );
         let usage = upstream.get("usage");
         let input_tokens = usage.and_then(|u| u.get("input_tokens")).and_then(|v| v.as_i64()).unwrap_or(0);
         let output_tokens = usage.and_then(|u| u.get("output_tokens")).and_then(|v| v.as_i64()).unwrap_or(0);
+        let mut cache = CacheTokens::default();
+        cache.update(usage);
 
         json!({
             "id": upstream.get("id").cloned().unwrap_or_else(|| json!("chatcmpl-proxy")),
@@ -1023,7 +1297,7 @@ impl UpstreamConverter for AnthropicMessagesConverter {
                 "message": message,
                 "finish_reason": finish_reason,
             }],
-            "usage": anthropic_usage(input_tokens, output_tokens),
+            "usage": anthropic_usage(input_tokens, output_tokens, cache),
         })
     }
 
@@ -1054,13 +1328,18 @@ impl UpstreamConverter for AnthropicMessagesConverter {
                     if let Some(t) = msg.get("usage").and_then(|u| u.get("input_tokens")).and_then(|v| v.as_i64()) {
                         state.input_tokens = t;
                     }
+                    state.cache_tokens.update(msg.get("usage"));
                 }
             }
             "content_block_start" => {
                 let block = data.get("content_block");
                 let block_type = block.and_then(|b| b.get("type")).and_then(|t| t.as_str());
                 push_role_chunk(state, &mut out);
-                if block_type == Some("tool_use") {
+                let name = block.and_then(|b| b.get("name")).and_then(|n| n.as_str());
+                if block_type == Some("tool_use") && name == Some(STRUCTURED_OUTPUT_TOOL) {
+                    let block_index = data.get("index").and_then(|v| v.as_i64()).unwrap_or(0).to_string();
+                    state.structured_block = Some(block_index);
+                } else if block_type == Some("tool_use") {
                     let block_index = data.get("index").and_then(|v| v.as_i64()).unwrap_or(0).to_string();
                     let idx = state.next_tool_index;
                     state.next_tool_index += 1;
@@ -1099,7 +1378,12 @@ impl UpstreamConverter for AnthropicMessagesConverter {
                     Some("input_json_delta") => {
                         let block_index = data.get("index").and_then(|v| v.as_i64()).unwrap_or(0).to_string();
                         let idx = *state.tool_index.get(&block_index).unwrap_or(&0);
-                        if let Some(partial) = delta.and_then(|d| d.get("partial_json")).and_then(|t| t.as_str()) {
+                        let partial = delta.and_then(|d| d.get("partial_json")).and_then(|t| t.as_str());
+                        if state.structured_block.as_ref() == Some(&block_index) {
+                            if let Some(partial) = partial.filter(|p| !p.is_empty()) {
+                                out.push(chunk_str(state, json!({"content": partial}), None));
+                            }
+                        } else if let Some(partial) = partial {
                             out.push(chunk_str(
                                 state,
                                 json!({"tool_calls": [{"index": idx, "function": {"arguments": partial}}]}),
@@ -1117,13 +1401,15 @@ impl UpstreamConverter for AnthropicMessagesConverter {
                     .and_then(|d| d.get("stop_reason"))
                     .and_then(|r| r.as_str())
                     .unwrap_or("end_turn");
+                let reason = structured_stop_reason(reason, state.structured_block.is_some());
                 let finish = map_anthropic_finish(reason, state.saw_tool_call);
                 let output_tokens = data
                     .get("usage")
                     .and_then(|u| u.get("output_tokens"))
                     .and_then(|v| v.as_i64())
                     .unwrap_or(0);
-                let usage = anthropic_usage(state.input_tokens, output_tokens);
+                state.cache_tokens.update(data.get("usage"));
+                let usage = anthropic_usage(state.input_tokens, output_tokens, state.cache_tokens);
                 out.push(chunk_str_with_usage(state, json!({}), Some(finish), Some(&usage)));
                 out.push("[DONE]".to_string());
                 state.finished = true;
@@ -1134,6 +1420,375 @@ impl UpstreamConverter for AnthropicMessagesConverter {
     }
 }
 
+/// Fronts Ollama's native `/api/chat`. Streams are newline-delimited JSON
+/// rather than SSE, tool-call arguments are objects rather than JSON strings,
+/// and images travel as bare base64 beside the message text. The registered
+/// provider `base_url` is the server root, e.g. `http://ollama.lan:11434`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OllamaChatConverter;
+
+impl OllamaChatConverter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Map Ollama's `prompt_eval_count`/`eval_count` to chat/completions usage.
+fn ollama_usage(upstream: &Value) -> Value {
+    let prompt = upstream.get("prompt_eval_count").and_then(|v| v.as_i64()).unwrap_or(0);
+    let completion = upstream.get("eval_count").and_then(|v| v.as_i64()).unwrap_or(0);
+    json!({
+        "prompt_tokens": prompt,
+        "completion_tokens": completion,
+        "total_tokens": prompt + completion,
+    })
+}
+
+/// Map an Ollama `done_reason` to a chat/completions `finish_reason`.
+fn map_ollama_finish(reason: &str, saw_tool: bool) -> &'static str {
+    if saw_tool {
+        return "tool_calls";
+    }
+    match reason {
+        "length" => "length",
+        _ => "stop",
+    }
+}
+
+/// Ollama tool calls as chat `tool_calls`, numbered from `first_index`.
+fn ollama_calls_to_chat(calls: &[Value], first_index: usize) -> Vec<Value> {
+    calls
+        .iter()
+        .enumerate()
+        .map(|(i, call)| {
+            let func = call.get("function");
+            let args = func.and_then(|f| f.get("arguments")).cloned().unwrap_or_else(|| json!({}));
+            let arguments = match args {
+                Value::String(s) => s,
+                other => serde_json::to_string(&other).unwrap_or_default(),
+            };
+            let idx = first_index + i;
+            json!({
+                "index": idx,
+                "id": format!("call_{idx}"),
+                "type": "function",
+                "function": {
+                    "name": func.and_then(|f| f.get("name")).cloned().unwrap_or(Value::Null),
+                    "arguments": arguments,
+                }
+            })
+        })
+        .collect()
+}
+
+impl UpstreamConverter for OllamaChatConverter {
+    fn upstream_path(&self, _body: &Value) -> String {
+        "/api/chat".to_string()
+    }
+
+    fn convert_request(&self, body: &Value) -> Value {
+        // tool_call_id -> function name; Ollama pairs tool results by name.
+        let mut call_names: HashMap<String, String> = HashMap::new();
+        let mut messages: Vec<Value> = Vec::new();
+        if let Some(msgs) = body.get("messages").and_then(|m| m.as_array()) {
+            for msg in msgs {
+                let role = match msg.get("role").and_then(|r| r.as_str()).unwrap_or("user") {
+                    "developer" => "system",
+                    other => other,
+                };
+                let content = msg.get("content").cloned().unwrap_or(Value::Null);
+                let mut out = json!({"role": role, "content": message_text(&content)});
+
+                // Ollama only takes inline base64 images; remote URLs, audio and
+                // documents are dropped.
+                let images: Vec<String> = content
+                    .as_array()
+                    .map(|parts| {
+                        parts
+                            .iter()
+                            .filter_map(media::from_chat_part)
+                            .filter_map(|attachment| match attachment.source {
+                                MediaSource::Inline { data, .. } if attachment.kind == MediaKind::Image => Some(data),
+                                _ => {
+                                    log_dropped_media(&attachment, "Ollama");
+                                    None
+                                }
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if !images.is_empty() {
+                    out["images"] = json!(images);
+                }
+
+                if let Some(calls) = msg.get("tool_calls").and_then(|c| c.as_array()) {
+                    let mapped: Vec<Value> = calls
+                        .iter()
+                        .map(|call| {
+                            let func = call.get("function");
+                            let name = func.and_then(|f| f.get("name")).cloned().unwrap_or(Value::Null);
+                            if let (Some(id), Some(n)) = (call.get("id").and_then(|v| v.as_str()), name.as_str()) {
+                                call_names.insert(id.to_string(), n.to_string());
+                            }
+                            let args_str = func
+                                .and_then(|f| f.get("arguments"))
+                                .and_then(|a| a.as_str())
+                                .unwrap_or("{}");
+                            let args: Value = serde_json::from_str(args_str).unwrap_or_else(|_| json!({}));
+                            json!({"function": {"name": name, "arguments": args}})
+                        })
+                        .collect();
+                    if !mapped.is_empty() {
+                        out["tool_calls"] = json!(mapped);
+                    }
+                }
+                if role == "tool" {
+                    if let Some(name) = msg
+                        .get("tool_call_id")
+                        .and_then(|v| v.as_str())
+                        .and_then(|id| call_names.get(id))
+                    {
+                        out["tool_name"] = json!(name);
+                    }
+                }
+                messages.push(out);
+            }
+        }
+
+        let mut out = json!({
+            "model": body.get("model").cloned().unwrap_or(Value::Null),
+            "messages": messages,
+            // Ollama streams unless told otherwise; chat/completions does not.
+            "stream": body.get("stream").and_then(|s| s.as_bool()).unwrap_or(false),
+        });
+
+        let mut options = json!({});
+        for key in [
+            "temperature",
+            "top_p",
+            "top_k",
+            "min_p",
+            "seed",
+            "stop",
+            "repeat_penalty",
+            "presence_penalty",
+            "frequency_penalty",
+        ] {
+            if let Some(v) = body.get(key) {
+                options[key] = v.clone();
+            }
+        }
+        if let Some(v) = body.get("max_tokens").or_else(|| body.get("max_completion_tokens")) {
+            options["num_predict"] = v.clone();
+        }
+        if options.as_object().is_some_and(|o| !o.is_empty()) {
+            out["options"] = options;
+        }
+
+        if let Some(format) = body.get("response_format") {
+            match format.get("type").and_then(|t| t.as_str()) {
+                Some("json_object") => out["format"] = json!("json"),
+                Some("json_schema") => {
+                    if let Some(schema) = format.get("json_schema").and_then(|s| s.get("schema")) {
+                        out["format"] = schema.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+        if body.get("reasoning_effort").and_then(|e| e.as_str()).is_some() {
+            out["think"] = json!(true);
+        }
+        // Ollama takes OpenAI-shaped function tools as they are.
+        if let Some(tools) = body.get("tools").filter(|t| t.is_array()) {
+            out["tools"] = tools.clone();
+        }
+
+        out
+    }
+
+    fn convert_response(&self, upstream: &Value) -> Value {
+        let msg = upstream.get("message");
+        let content = msg.and_then(|m| m.get("content")).and_then(|c| c.as_str()).unwrap_or("");
+        let tool_calls: Vec<Value> = msg
+            .and_then(|m| m.get("tool_calls"))
+            .and_then(|c| c.as_array())
+            .map(|calls| {
+                ollama_calls_to_chat(calls, 0)
+                    .into_iter()
+                    .map(|mut call| {
+                        // `index` only belongs on stream deltas.
+                        if let Some(obj) = call.as_object_mut() {
+                            obj.remove("index");
+                        }
+                        call
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut message = json!({"role": "assistant"});
+        let saw_tool = !tool_calls.is_empty();
+        message["content"] = if content.is_empty() && saw_tool {
+            Value::Null
+        } else {
+            json!(content)
+        };
+        if let Some(thinking) = msg
+            .and_then(|m| m.get("thinking"))
+            .and_then(|t| t.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            message["reasoning_content"] = json!(thinking);
+        }
+        if saw_tool {
+            message["tool_calls"] = json!(tool_calls);
+        }
+        let finish_reason = map_ollama_finish(
+            upstream.get("done_reason").and_then(|r| r.as_str()).unwrap_or("stop"),
+            saw_tool,
+        );
+
+        json!({
+            "id": "chatcmpl-proxy",
+            "object": "chat.completion",
+            "created": 0,
+            "model": upstream.get("model").cloned().unwrap_or(Value::Null),
+            "choices": [{
+                "index": 0,
+                "message": message,
+                "finish_reason": finish_reason,
+            }],
+            "usage": ollama_usage(upstream),
+        })
+    }
+
+    fn convert_stream_event(&self, event: &SseEvent, state: &mut StreamState) -> Vec<String> {
+        if state.finished {
+            return Vec::new();
+        }
+        let data: Value = match serde_json::from_str(&event.data) {
+            Ok(v) => v,
+            Err(_) => return Vec::new(),
+        };
+        if let Some(err) = data.get("error") {
+            state.finished = true;
+            let message = err.as_str().map(str::to_string).unwrap_or_else(|| err.to_string());
+            return vec![json!({"error": {"message": message}}).to_string(), "[DONE]".to_string()];
+        }
+        if let Some(model) = data.get("model").and_then(|v| v.as_str()) {
+            if state.model.is_empty() {
+                state.model = model.to_string();
+            }
+        }
+
+        let mut out: Vec<String> = Vec::new();
+        let msg = data.get("message");
+        if let Some(thinking) = msg
+            .and_then(|m| m.get("thinking"))
+            .and_then(|t| t.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            push_role_chunk(state, &mut out);
+            out.push(chunk_str(state, json!({"reasoning_content": thinking}), None));
+        }
+        if let Some(text) = msg
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            push_role_chunk(state, &mut out);
+            out.push(chunk_str(state, json!({"content": text}), None));
+        }
+        // Ollama sends each tool call whole, never as argument fragments.
+        if let Some(calls) = msg
+            .and_then(|m| m.get("tool_calls"))
+            .and_then(|c| c.as_array())
+            .filter(|c| !c.is_empty())
+        {
+            push_role_chunk(state, &mut out);
+            let mapped = ollama_calls_to_chat(calls, state.next_tool_index);
+            state.next_tool_index += mapped.len();
+            state.saw_tool_call = true;
+            out.push(chunk_str(state, json!({"tool_calls": mapped}), None));
+        }
+
+        if data.get("done").and_then(|d| d.as_bool()).unwrap_or(false) {
+            let reason = data.get("done_reason").and_then(|r| r.as_str()).unwrap_or("stop");
+            let finish = map_ollama_finish(reason, state.saw_tool_call);
+            let usage = ollama_usage(&data);
+            out.push(chunk_str_with_usage(state, json!({}), Some(finish), Some(&usage)));
+            out.push("[DONE]".to_string());
+            state.finished = true;
+        }
+        out
+    }
+
+    fn stream_accumulator(&self) -> SseAccumulator {
+        SseAccumulator::ndjson()
+    }
+}
+
+/// Azure OpenAI REST API version sent with every request.
+const AZURE_OPENAI_API_VERSION: &str = "2024-10-21";
+
+/// Fronts an Azure OpenAI resource. The wire format is chat/completions, but
+/// the model is addressed as a deployment in the path, every request carries
+/// an `api-version` query and auth is an `api-key` header. The registered
+/// provider `base_url` is the resource's `/openai` root, e.g.
+/// `https://my-resource.openai.azure.com/openai`, and its models are
+/// deployment names.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AzureOpenAIConverter;
+
+impl AzureOpenAIConverter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl UpstreamConverter for AzureOpenAIConverter {
+    fn upstream_path(&self, body: &Value) -> String {
+        let deployment = body.get("model").and_then(|m| m.as_str()).unwrap_or("");
+        format!("/deployments/{deployment}/chat/completions?api-version={AZURE_OPENAI_API_VERSION}")
+    }
+
+    fn auth_header(&self, key: &str) -> (&'static str, String) {
+        ("api-key", key.to_string())
+    }
+
+    fn convert_request(&self, body: &Value) -> Value {
+        body.clone()
+    }
+
+    fn convert_response(&self, upstream: &Value) -> Value {
+        upstream.clone()
+    }
+
+    fn convert_stream_event(&self, event: &SseEvent, state: &mut StreamState) -> Vec<String> {
+        if state.finished {
+            return Vec::new();
+        }
+        if event.data == "[DONE]" {
+            state.finished = true;
+            return vec![event.data.clone()];
+        }
+        let Ok(data) = serde_json::from_str::<Value>(&event.data) else {
+            return Vec::new();
+        };
+        // Azure opens with a content-filter chunk that has no choices; chat
+        // clients expect every chunk to carry one (or the usage).
+        let no_choices = data
+            .get("choices")
+            .and_then(|c| c.as_array())
+            .map_or(true, |c| c.is_empty());
+        if no_choices && data.get("usage").map_or(true, |u| u.is_null()) {
+            return Vec::new();
+        }
+        vec![event.data.clone()]
+    }
+}
+
 fn push_role_chunk(state: &mut StreamState, out: &mut Vec<String>) {
     if !state.role_sent {
         state.role_sent = true;
@@ -1141,7 +1796,7 @@ fn push_role_chunk(state: &mut StreamState, out: &mut Vec<String>) {
     }
 }
 
-fn chunk_str(state: &StreamState, delta: Value, finish: Option<&str>) -> String {
+pub(crate) fn chunk_str(state: &StreamState, delta: Value, finish: Option<&str>) -> String {
     json!({
         "id": non_empty(&state.id, "chatcmpl-proxy"),
         "object": "chat.completion.chunk",
@@ -1269,6 +1924,16 @@ mod tests {
         assert_eq!(last.map(|e| e.data), Some("tail".to_string()));
     }
 
+    #[test]
+    fn ndjson_mode_yields_one_event_per_line() {
+        let mut acc = SseAccumulator::ndjson();
+        let events = acc.push("{\"a\":1}\n\n{\"b\":");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "{\"a\":1}");
+        assert!(acc.push("2}").is_empty());
+        assert_eq!(acc.finish().unwrap().data, "{\"b\":2}");
+    }
+
     #[test]
     fn finish_is_empty_when_buffer_has_no_fields() {
         let mut acc = SseAccumulator::new();
@@ -1285,6 +1950,24 @@ mod openai_responses_tests {
         OpenAIResponsesConverter::new()
     }
 
+    #[test]
+    fn request_carries_user_images_and_files_as_input_parts() {
+        let body = json!({"messages": [{"role": "user", "content": [
+            {"type": "text", "text": "compare"},
+            {"type": "image_url", "image_url": {"url": "https://x.test/a.png", "detail": "high"}},
+            {"type": "file", "file": {"filename": "r.pdf", "file_data": "data:application/pdf;base64,JVBE"}}
+        ]}]});
+        let out = conv().convert_request(&body);
+        assert_eq!(
+            out["input"][0]["content"],
+            json!([
+                {"type": "input_text", "text": "compare"},
+                {"type": "input_image", "image_url": "https://x.test/a.png", "detail": "high"},
+                {"type": "input_file", "filename": "r.pdf", "file_data": "data:application/pdf;base64,JVBE"}
+            ])
+        );
+    }
+
     #[test]
     fn request_splits_system_into_instructions_and_maps_input() {
         let body = json!({
@@ -1308,6 +1991,20 @@ mod openai_responses_tests {
         assert_eq!(out["reasoning"], json!({"effort": "high", "summary": "auto"}));
     }
 
+    #[test]
+    fn request_flattens_json_schema_into_text_format() {
+        let body = json!({
+            "model": "gpt-5",
+            "messages": [],
+            "response_format": {"type": "json_schema", "json_schema": {"name": "out", "schema": {"type": "object"}, "strict": true}}
+        });
+        let out = conv().convert_request(&body);
+        assert_eq!(
+            out["text"],
+            json!({"format": {"type": "json_schema", "name": "out", "schema": {"type": "object"}, "strict": true}})
+        );
+    }
+
     #[test]
     fn request_flattens_tools_and_maps_tool_messages() {
         let body = json!({
@@ -1516,6 +2213,24 @@ mod google_generate_content_tests {
         GoogleGenerateContentConverter::new()
     }
 
+    #[test]
+    fn request_maps_attachments_to_inline_and_file_data() {
+        let body = json!({"messages": [{"role": "user", "content": [
+            {"type": "text", "text": "transcribe"},
+            {"type": "input_audio", "input_audio": {"data": "UklG", "format": "wav"}},
+            {"type": "image_url", "image_url": {"url": "https://x.test/a.jpg"}}
+        ]}]});
+        let out = conv().convert_request(&body);
+        assert_eq!(
+            out["contents"][0]["parts"],
+            json!([
+                {"text": "transcribe"},
+                {"inlineData": {"mimeType": "audio/wav", "data": "UklG"}},
+                {"fileData": {"mimeType": "image/jpeg", "fileUri": "https://x.test/a.jpg"}}
+            ])
+        );
+    }
+
     #[test]
     fn path_encodes_model_and_action() {
         let c = conv();
@@ -1534,6 +2249,18 @@ mod google_generate_content_tests {
         assert_eq!(conv().auth_header("k"), ("x-goog-api-key", "k".to_string()));
     }
 
+    #[test]
+    fn request_maps_json_schema_to_response_json_schema() {
+        let body = json!({
+            "model": "gemini-2.5-pro",
+            "messages": [],
+            "response_format": {"type": "json_schema", "json_schema": {"name": "out", "schema": {"type": "object"}}}
+        });
+        let out = conv().convert_request(&body);
+        assert_eq!(out["generationConfig"]["responseMimeType"], json!("application/json"));
+        assert_eq!(out["generationConfig"]["responseJsonSchema"], json!({"type": "object"}));
+    }
+
     #[test]
     fn request_maps_roles_system_and_thinking() {
         let body = json!({
@@ -1742,6 +2469,25 @@ mod anthropic_messages_tests {
         assert_eq!(c.upstream_path(&json!({})), "/messages");
     }
 
+    #[test]
+    fn request_maps_images_and_pdfs_to_blocks_and_drops_audio() {
+        let body = json!({"messages": [{"role": "user", "content": [
+            {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}},
+            {"type": "file", "file": {"file_data": "data:application/pdf;base64,JVBE"}, "cache_control": {"type": "ephemeral"}},
+            {"type": "input_audio", "input_audio": {"data": "UklG", "format": "wav"}},
+            {"type": "text", "text": "summarize"}
+        ]}]});
+        let out = conv().convert_request(&body);
+        assert_eq!(
+            out["messages"][0]["content"],
+            json!([
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}},
+                {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBE"}, "cache_control": {"type": "ephemeral"}},
+                {"type": "text", "text": "summarize"}
+            ])
+        );
+    }
+
     #[test]
     fn request_maps_system_messages_and_default_max_tokens() {
         let body = json!({
@@ -1924,4 +2670,276 @@ mod anthropic_messages_tests {
         assert_eq!(atc["index"], json!(0));
         assert_eq!(atc["function"]["arguments"], json!("{\"a\""));
     }
+
+    #[test]
+    fn request_keeps_cache_control_on_system_parts_messages_and_tools() {
+        let body = json!({
+            "model": "c",
+            "messages": [
+                {"role": "system", "content": [
+                    {"type": "text", "text": "long rules", "cache_control": {"type": "ephemeral"}}
+                ]},
+                {"role": "user", "content": "hi", "cache_control": {"type": "ephemeral"}}
+            ],
+            "tools": [{"type": "function", "cache_control": {"type": "ephemeral"},
+                       "function": {"name": "f", "parameters": {"type": "object"}}}]
+        });
+        let out = conv().convert_request(&body);
+        assert_eq!(
+            out["system"],
+            json!([{"type": "text", "text": "long rules", "cache_control": {"type": "ephemeral"}}])
+        );
+        assert_eq!(
+            out["messages"][0]["content"],
+            json!([{"type": "text", "text": "hi", "cache_control": {"type": "ephemeral"}}])
+        );
+        assert_eq!(out["tools"][0]["cache_control"], json!({"type": "ephemeral"}));
+    }
+
+    #[test]
+    fn usage_reports_cache_reads_and_writes() {
+        let upstream = json!({
+            "content": [{"type": "text", "text": "ok"}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 2,
+                      "cache_creation_input_tokens": 100, "cache_read_input_tokens": 900}
+        });
+        let out = conv().convert_response(&upstream);
+        assert_eq!(
+            out["usage"],
+            json!({
+                "prompt_tokens": 1010,
+                "completion_tokens": 2,
+                "total_tokens": 1012,
+                "prompt_tokens_details": {"cached_tokens": 900},
+                "cache_creation_input_tokens": 100,
+                "cache_read_input_tokens": 900
+            })
+        );
+
+        let c = conv();
+        let mut state = StreamState::default();
+        c.convert_stream_event(
+            &ev("message_start", json!({"message": {"usage": {"input_tokens": 10, "cache_read_input_tokens": 900}}})),
+            &mut state,
+        );
+        let done = c.convert_stream_event(
+            &ev("message_delta", json!({"delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 2}})),
+            &mut state,
+        );
+        let finish: Value = serde_json::from_str(&done[0]).unwrap();
+        assert_eq!(finish["usage"]["prompt_tokens"], json!(910));
+        assert_eq!(finish["usage"]["cache_read_input_tokens"], json!(900));
+    }
+
+    #[test]
+    fn request_forces_structured_output_tool_for_json_schema() {
+        let schema = json!({"type": "object", "properties": {"n": {"type": "integer"}}});
+        let body = json!({
+            "model": "c",
+            "messages": [{"role": "user", "content": "count"}],
+            "response_format": {"type": "json_schema", "json_schema": {"name": "count", "schema": schema}}
+        });
+        let out = conv().convert_request(&body);
+        assert_eq!(out["tools"][0]["name"], json!(STRUCTURED_OUTPUT_TOOL));
+        assert_eq!(out["tools"][0]["input_schema"], schema);
+        assert_eq!(out["tool_choice"], json!({"type": "tool", "name": STRUCTURED_OUTPUT_TOOL}));
+
+        // Offered tools stay callable; some tool call is still required.
+        let body = json!({
+            "model": "c",
+            "messages": [],
+            "tools": [{"type": "function", "function": {"name": "f", "parameters": {"type": "object"}}}],
+            "response_format": {"type": "json_object"}
+        });
+        let out = conv().convert_request(&body);
+        assert_eq!(out["tools"].as_array().unwrap().len(), 2);
+        assert_eq!(out["tool_choice"], json!({"type": "any"}));
+    }
+
+    #[test]
+    fn structured_output_call_is_unwrapped_into_content() {
+        let upstream = json!({
+            "content": [{"type": "tool_use", "id": "tu_1", "name": STRUCTURED_OUTPUT_TOOL, "input": {"n": 3}}],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 1, "output_tokens": 1}
+        });
+        let out = conv().convert_response(&upstream);
+        let choice = &out["choices"][0];
+        assert_eq!(choice["message"]["content"], json!("{\"n\":3}"));
+        assert!(choice["message"].get("tool_calls").is_none());
+        assert_eq!(choice["finish_reason"], json!("stop"));
+
+        let c = conv();
+        let mut state = StreamState::default();
+        let start = c.convert_stream_event(
+            &ev("content_block_start", json!({"index": 0, "content_block": {"type": "tool_use", "id": "tu_1", "name": STRUCTURED_OUTPUT_TOOL}})),
+            &mut state,
+        );
+        assert_eq!(start.len(), 1);
+        let arg = c.convert_stream_event(
+            &ev("content_block_delta", json!({"index": 0, "delta": {"type": "input_json_delta", "partial_json": "{\"n\":"}})),
+            &mut state,
+        );
+        let chunk: Value = serde_json::from_str(&arg[0]).unwrap();
+        assert_eq!(chunk["choices"][0]["delta"]["content"], json!("{\"n\":"));
+        let done = c.convert_stream_event(
+            &ev("message_delta", json!({"delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 5}})),
+            &mut state,
+        );
+        let finish: Value = serde_json::from_str(&done[0]).unwrap();
+        assert_eq!(finish["choices"][0]["finish_reason"], json!("stop"));
+    }
+}
+
+#[cfg(test)]
+mod ollama_chat_tests {
+    use super::*;
+
+    fn conv() -> OllamaChatConverter {
+        OllamaChatConverter::new()
+    }
+
+    fn ev(data: Value) -> SseEvent {
+        SseEvent { event: String::new(), data: data.to_string() }
+    }
+
+    #[test]
+    fn request_maps_options_images_tools_and_tool_names() {
+        let body = json!({
+            "model": "llama3.2",
+            "messages": [
+                {"role": "developer", "content": "be brief"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "what is this?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBOR"}}
+                ]},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "c1", "type": "function", "function": {"name": "lookup", "arguments": "{\"q\":\"cat\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "c1", "content": "a cat"}
+            ],
+            "max_tokens": 64,
+            "temperature": 0.3,
+            "response_format": {"type": "json_object"},
+            "tools": [{"type": "function", "function": {"name": "lookup", "parameters": {"type": "object"}}}]
+        });
+        let c = conv();
+        assert_eq!(c.upstream_path(&body), "/api/chat");
+        let out = c.convert_request(&body);
+        assert_eq!(out["stream"], json!(false));
+        assert_eq!(out["options"], json!({"temperature": 0.3, "num_predict": 64}));
+        assert_eq!(out["format"], json!("json"));
+        assert_eq!(out["tools"], body["tools"]);
+        let msgs = out["messages"].as_array().unwrap();
+        assert_eq!(msgs[0]["role"], json!("system"));
+        assert_eq!(msgs[1]["content"], json!("what is this?"));
+        assert_eq!(msgs[1]["images"], json!(["iVBOR"]));
+        assert_eq!(msgs[2]["tool_calls"][0]["function"]["arguments"], json!({"q": "cat"}));
+        assert_eq!(msgs[3]["tool_name"], json!("lookup"));
+    }
+
+    #[test]
+    fn response_maps_thinking_tool_calls_and_usage() {
+        let upstream = json!({
+            "model": "llama3.2",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "thinking": "hmm",
+                "tool_calls": [{"function": {"name": "lookup", "arguments": {"q": "cat"}}}]
+            },
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 12,
+            "eval_count": 5
+        });
+        let out = conv().convert_response(&upstream);
+        let msg = &out["choices"][0]["message"];
+        assert_eq!(msg["content"], Value::Null);
+        assert_eq!(msg["reasoning_content"], json!("hmm"));
+        assert_eq!(msg["tool_calls"][0]["id"], json!("call_0"));
+        assert_eq!(msg["tool_calls"][0]["function"]["arguments"], json!("{\"q\":\"cat\"}"));
+        assert!(msg["tool_calls"][0].get("index").is_none());
+        assert_eq!(out["choices"][0]["finish_reason"], json!("tool_calls"));
+        assert_eq!(out["usage"]["total_tokens"], json!(17));
+    }
+
+    #[test]
+    fn stream_emits_content_tool_calls_and_finish_from_ndjson_lines() {
+        let c = conv();
+        let mut acc = c.stream_accumulator();
+        let mut state = StreamState::default();
+        let body = format!(
+            "{}\n{}\n{}\n",
+            json!({"model": "llama3.2", "message": {"role": "assistant", "content": "Hi"}, "done": false}),
+            json!({"message": {"role": "assistant", "content": "", "tool_calls": [
+                {"function": {"name": "lookup", "arguments": {"q": "cat"}}}
+            ]}, "done": false}),
+            json!({"message": {"role": "assistant", "content": ""}, "done": true,
+                "done_reason": "stop", "prompt_eval_count": 3, "eval_count": 4}),
+        );
+        let out: Vec<String> = acc
+            .push(&body)
+            .iter()
+            .flat_map(|e| c.convert_stream_event(e, &mut state))
+            .collect();
+        // role + content + tool_calls + finish + [DONE]
+        assert_eq!(out.len(), 5);
+        let content: Value = serde_json::from_str(&out[1]).unwrap();
+        assert_eq!(content["choices"][0]["delta"]["content"], json!("Hi"));
+        assert_eq!(content["model"], json!("llama3.2"));
+        let tool: Value = serde_json::from_str(&out[2]).unwrap();
+        assert_eq!(tool["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"], json!("{\"q\":\"cat\"}"));
+        let finish: Value = serde_json::from_str(&out[3]).unwrap();
+        assert_eq!(finish["choices"][0]["finish_reason"], json!("tool_calls"));
+        assert_eq!(finish["usage"]["completion_tokens"], json!(4));
+        assert_eq!(out[4], "[DONE]");
+
+        let mut failed = StreamState::default();
+        let err = c.convert_stream_event(&ev(json!({"error": "model not found"})), &mut failed);
+        assert_eq!(err.len(), 2);
+        assert!(err[0].contains("model not found"));
+    }
+}
+
+#[cfg(test)]
+mod azure_openai_tests {
+    use super::*;
+
+    fn conv() -> AzureOpenAIConverter {
+        AzureOpenAIConverter::new()
+    }
+
+    fn ev(data: &str) -> SseEvent {
+        SseEvent { event: String::new(), data: data.to_string() }
+    }
+
+    #[test]
+    fn path_names_the_deployment_and_api_version_and_auth_uses_api_key() {
+        let c = conv();
+        assert_eq!(
+            c.upstream_path(&json!({"model": "gpt-4o-prod"})),
+            format!("/deployments/gpt-4o-prod/chat/completions?api-version={AZURE_OPENAI_API_VERSION}")
+        );
+        assert_eq!(c.auth_header("k"), ("api-key", "k".to_string()));
+        let body = json!({"model": "gpt-4o-prod", "messages": [], "tools": []});
+        assert_eq!(c.convert_request(&body), body);
+    }
+
+    #[test]
+    fn stream_drops_content_filter_preamble_and_passes_chunks_through() {
+        let c = conv();
+        let mut state = StreamState::default();
+        let preamble = json!({"choices": [], "prompt_filter_results": [{"prompt_index": 0}]}).to_string();
+        assert!(c.convert_stream_event(&ev(&preamble), &mut state).is_empty());
+
+        let chunk = json!({"choices": [{"index": 0, "delta": {"tool_calls": [{"index": 0, "function": {"arguments": "{}"}}]}}]})
+            .to_string();
+        assert_eq!(c.convert_stream_event(&ev(&chunk), &mut state), vec![chunk.clone()]);
+        let usage = json!({"choices": [], "usage": {"prompt_tokens": 1, "completion_tokens": 2}}).to_string();
+        assert_eq!(c.convert_stream_event(&ev(&usage), &mut state), vec![usage.clone()]);
+        assert_eq!(c.convert_stream_event(&ev("[DONE]"), &mut state), vec!["[DONE]".to_string()]);
+        assert!(state.finished);
+    }
 }
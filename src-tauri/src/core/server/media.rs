@@ -0,0 +1,391 @@
+//! Images, audio and documents across API dialects.
+//!
+//! Chat/completions is the proxy's internal format, so each dialect's media
+//! part is read into a [`MediaPart`] and written back out in another's terms:
+//! OpenAI chat `image_url` / `input_audio` / `file` parts, Responses
+//! `input_image` / `input_audio` / `input_file`, Anthropic `image` /
+//! `document` blocks and Gemini `inlineData` / `fileData` parts. A writer
+//! returns `None` when its dialect cannot carry the part (Anthropic has no
+//! audio input), so callers can log the drop instead of losing it silently.
+
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Audio,
+    Document,
+}
+
+/// Where a part's bytes live: inline base64 or a URL the upstream fetches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaSource {
+    Inline { mime_type: String, data: String },
+    Url(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaPart {
+    pub kind: MediaKind,
+    pub source: MediaSource,
+    pub filename: Option<String>,
+    /// OpenAI image `detail` (`low` / `high` / `auto`).
+    pub detail: Option<String>,
+}
+
+impl MediaPart {
+    fn new(kind: MediaKind, source: MediaSource) -> Self {
+        Self {
+            kind,
+            source,
+            filename: None,
+            detail: None,
+        }
+    }
+
+    /// The part's MIME type, guessed from a URL's extension when not inline.
+    pub fn mime_type(&self) -> String {
+        match &self.source {
+            MediaSource::Inline { mime_type, .. } => mime_type.clone(),
+            MediaSource::Url(url) => mime_from_url(url, self.kind).to_string(),
+        }
+    }
+
+    fn data_url(&self) -> String {
+        match &self.source {
+            MediaSource::Inline { mime_type, data } => format!("data:{mime_type};base64,{data}"),
+            MediaSource::Url(url) => url.clone(),
+        }
+    }
+}
+
+/// Split a `data:<mime>;base64,<data>` URL.
+pub fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    let (mime_type, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+    Some((mime_type, data))
+}
+
+fn source_from_url(url: &str) -> MediaSource {
+    match parse_data_url(url) {
+        Some((mime_type, data)) => MediaSource::Inline {
+            mime_type: mime_type.to_string(),
+            data: data.to_string(),
+        },
+        None => MediaSource::Url(url.to_string()),
+    }
+}
+
+fn mime_from_url(url: &str, kind: MediaKind) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let ext = path.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("wav") => "audio/wav",
+        Some("mp3") => "audio/mpeg",
+        Some("pdf") => "application/pdf",
+        Some("txt") => "text/plain",
+        _ => match kind {
+            MediaKind::Image => "image/jpeg",
+            MediaKind::Audio => "audio/wav",
+            MediaKind::Document => "application/pdf",
+        },
+    }
+}
+
+/// OpenAI `input_audio.format` for a MIME type and back.
+fn audio_format(mime_type: &str) -> &str {
+    match mime_type {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+        other => other.strip_prefix("audio/").unwrap_or(other),
+    }
+}
+
+fn audio_mime(format: &str) -> String {
+    match format {
+        "mp3" => "audio/mpeg".to_string(),
+        other => format!("audio/{other}"),
+    }
+}
+
+fn audio_part(input_audio: &Value) -> Option<MediaPart> {
+    let data = input_audio.get("data").and_then(|d| d.as_str())?;
+    let format = input_audio
+        .get("format")
+        .and_then(|f| f.as_str())
+        .unwrap_or("wav");
+    Some(MediaPart::new(
+        MediaKind::Audio,
+        MediaSource::Inline {
+            mime_type: audio_mime(format),
+            data: data.to_string(),
+        },
+    ))
+}
+
+fn inline_audio(part: &MediaPart) -> Option<Value> {
+    match &part.source {
+        MediaSource::Inline { mime_type, data } => Some(json!({
+            "data": data,
+            "format": audio_format(mime_type),
+        })),
+        MediaSource::Url(_) => None,
+    }
+}
+
+/// Read an OpenAI chat content part: `image_url`, `input_audio` or `file`.
+pub fn from_chat_part(part: &Value) -> Option<MediaPart> {
+    match part.get("type").and_then(|t| t.as_str())? {
+        "image_url" => {
+            let image_url = part.get("image_url")?;
+            // Some clients send the URL as a bare string.
+            let url = image_url
+                .as_str()
+                .or_else(|| image_url.get("url")?.as_str())?;
+            let mut media = MediaPart::new(MediaKind::Image, source_from_url(url));
+            media.detail = image_url
+                .get("detail")
+                .and_then(|d| d.as_str())
+                .map(String::from);
+            Some(media)
+        }
+        "input_audio" => audio_part(part.get("input_audio")?),
+        "file" => {
+            let file = part.get("file")?;
+            let url = file
+                .get("file_data")
+                .or_else(|| file.get("file_url"))
+                .and_then(|u| u.as_str())?;
+            let mut media = MediaPart::new(MediaKind::Document, source_from_url(url));
+            media.filename = file
+                .get("filename")
+                .and_then(|f| f.as_str())
+                .map(String::from);
+            Some(media)
+        }
+        _ => None,
+    }
+}
+
+/// Write an OpenAI chat content part. Audio must be inline; a document URL
+/// goes in `file.file_url`, which the proxy's own converters read back.
+pub fn to_chat_part(part: &MediaPart) -> Option<Value> {
+    match part.kind {
+        MediaKind::Image => {
+            let mut image_url = json!({"url": part.data_url()});
+            if let Some(detail) = &part.detail {
+                image_url["detail"] = json!(detail);
+            }
+            Some(json!({"type": "image_url", "image_url": image_url}))
+        }
+        MediaKind::Audio => {
+            Some(json!({"type": "input_audio", "input_audio": inline_audio(part)?}))
+        }
+        MediaKind::Document => {
+            let mut file = match &part.source {
+                MediaSource::Inline { .. } => json!({"file_data": part.data_url()}),
+                MediaSource::Url(url) => json!({"file_url": url}),
+            };
+            if let Some(name) = &part.filename {
+                file["filename"] = json!(name);
+            }
+            Some(json!({"type": "file", "file": file}))
+        }
+    }
+}
+
+/// Read a Responses input part: `input_image`, `input_audio` or `input_file`.
+pub fn from_responses_part(part: &Value) -> Option<MediaPart> {
+    match part.get("type").and_then(|t| t.as_str())? {
+        "input_image" => {
+            let url = part.get("image_url").and_then(|u| u.as_str())?;
+            let mut media = MediaPart::new(MediaKind::Image, source_from_url(url));
+            media.detail = part
+                .get("detail")
+                .and_then(|d| d.as_str())
+                .map(String::from);
+            Some(media)
+        }
+        "input_audio" => audio_part(part.get("input_audio")?),
+        "input_file" => {
+            let url = part
+                .get("file_data")
+                .or_else(|| part.get("file_url"))
+                .and_then(|u| u.as_str())?;
+            let mut media = MediaPart::new(MediaKind::Document, source_from_url(url));
+            media.filename = part
+                .get("filename")
+                .and_then(|f| f.as_str())
+                .map(String::from);
+            Some(media)
+        }
+        _ => None,
+    }
+}
+
+/// Write a Responses input part.
+pub fn to_responses_part(part: &MediaPart) -> Option<Value> {
+    match part.kind {
+        MediaKind::Image => {
+            let mut out = json!({"type": "input_image", "image_url": part.data_url()});
+            if let Some(detail) = &part.detail {
+                out["detail"] = json!(detail);
+            }
+            Some(out)
+        }
+        MediaKind::Audio => {
+            Some(json!({"type": "input_audio", "input_audio": inline_audio(part)?}))
+        }
+        MediaKind::Document => {
+            let mut out = match &part.source {
+                MediaSource::Inline { .. } => {
+                    json!({"type": "input_file", "file_data": part.data_url()})
+                }
+                MediaSource::Url(url) => json!({"type": "input_file", "file_url": url}),
+            };
+            // Inline files must be named.
+            out["filename"] = json!(part.filename.as_deref().unwrap_or("document.pdf"));
+            Some(out)
+        }
+    }
+}
+
+/// Read an Anthropic `image` or `document` block with a `base64` or `url`
+/// source.
+pub fn from_anthropic_block(block: &Value) -> Option<MediaPart> {
+    let kind = match block.get("type").and_then(|t| t.as_str())? {
+        "image" => MediaKind::Image,
+        "document" => MediaKind::Document,
+        _ => return None,
+    };
+    let source = block.get("source")?;
+    let source = match source.get("type").and_then(|t| t.as_str()) {
+        Some("url") => MediaSource::Url(source.get("url")?.as_str()?.to_string()),
+        _ => MediaSource::Inline {
+            mime_type: source
+                .get("media_type")
+                .or(block.get("media_type"))?
+                .as_str()?
+                .to_string(),
+            data: source.get("data")?.as_str()?.to_string(),
+        },
+    };
+    let mut media = MediaPart::new(kind, source);
+    media.filename = block
+        .get("title")
+        .and_then(|t| t.as_str())
+        .map(String::from);
+    Some(media)
+}
+
+/// Write an Anthropic `image` or `document` block. Anthropic takes no audio.
+pub fn to_anthropic_block(part: &MediaPart) -> Option<Value> {
+    let kind = match part.kind {
+        MediaKind::Image => "image",
+        MediaKind::Document => "document",
+        MediaKind::Audio => return None,
+    };
+    let source = match &part.source {
+        MediaSource::Inline { mime_type, data } => {
+            json!({"type": "base64", "media_type": mime_type, "data": data})
+        }
+        MediaSource::Url(url) => json!({"type": "url", "url": url}),
+    };
+    let mut block = json!({"type": kind, "source": source});
+    if let (MediaKind::Document, Some(name)) = (part.kind, &part.filename) {
+        block["title"] = json!(name);
+    }
+    Some(block)
+}
+
+/// Write a Gemini part: `inlineData` for base64, `fileData` for URLs.
+pub fn to_gemini_part(part: &MediaPart) -> Value {
+    match &part.source {
+        MediaSource::Inline { mime_type, data } => {
+            json!({"inlineData": {"mimeType": mime_type, "data": data}})
+        }
+        MediaSource::Url(url) => {
+            json!({"fileData": {"mimeType": part.mime_type(), "fileUri": url}})
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_parts_round_trip_images_audio_and_files() {
+        let parts = [
+            json!({"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA", "detail": "low"}}),
+            json!({"type": "image_url", "image_url": {"url": "https://x.test/cat.webp"}}),
+            json!({"type": "input_audio", "input_audio": {"data": "UklG", "format": "wav"}}),
+            json!({"type": "file", "file": {"filename": "a.pdf", "file_data": "data:application/pdf;base64,JVBE"}}),
+        ];
+        for part in &parts {
+            let media = from_chat_part(part).unwrap();
+            assert_eq!(to_chat_part(&media).as_ref(), Some(part));
+        }
+        assert_eq!(from_chat_part(&json!({"type": "text", "text": "hi"})), None);
+    }
+
+    #[test]
+    fn anthropic_and_responses_parts_map_through_chat() {
+        let doc = json!({
+            "type": "document",
+            "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBE"}
+        });
+        let media = from_anthropic_block(&doc).unwrap();
+        assert_eq!(to_anthropic_block(&media), Some(doc));
+        assert_eq!(
+            to_responses_part(&media),
+            Some(
+                json!({"type": "input_file", "file_data": "data:application/pdf;base64,JVBE", "filename": "document.pdf"})
+            )
+        );
+
+        let image = from_responses_part(
+            &json!({"type": "input_image", "image_url": "https://x.test/a.png"}),
+        )
+        .unwrap();
+        assert_eq!(
+            to_anthropic_block(&image),
+            Some(
+                json!({"type": "image", "source": {"type": "url", "url": "https://x.test/a.png"}})
+            )
+        );
+
+        let audio = from_chat_part(
+            &json!({"type": "input_audio", "input_audio": {"data": "SUQz", "format": "mp3"}}),
+        )
+        .unwrap();
+        assert_eq!(to_anthropic_block(&audio), None);
+        assert_eq!(
+            to_responses_part(&audio),
+            Some(json!({"type": "input_audio", "input_audio": {"data": "SUQz", "format": "mp3"}}))
+        );
+    }
+
+    #[test]
+    fn gemini_parts_inline_base64_and_reference_urls() {
+        let inline = from_chat_part(
+            &json!({"type": "input_audio", "input_audio": {"data": "SUQz", "format": "mp3"}}),
+        )
+        .unwrap();
+        assert_eq!(
+            to_gemini_part(&inline),
+            json!({"inlineData": {"mimeType": "audio/mpeg", "data": "SUQz"}})
+        );
+        let url = from_chat_part(
+            &json!({"type": "image_url", "image_url": {"url": "https://x.test/p.PNG?s=1"}}),
+        )
+        .unwrap();
+        assert_eq!(
+            to_gemini_part(&url),
+            json!({"fileData": {"mimeType": "image/png", "fileUri": "https://x.test/p.PNG?s=1"}})
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Prometheus metrics for the local API server.
+//!
+//! Counters and histograms are collected for every request the server
+//! handles; `GET /metrics` renders them in the Prometheus text exposition
+//! format when the server was started with `enable_metrics`. Router and MCP
+//! gauges are sampled at scrape time rather than tracked.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Upper bounds (seconds) shared by the latency and time-to-first-token
+/// histograms. Local generations routinely run for minutes.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Routes reported under their own label; anything else (typos, scanners,
+/// passthrough paths) is folded into `other` to keep label cardinality fixed.
+const KNOWN_ROUTES: &[&str] = &[
+    "/",
+    "/openapi.json",
+    "/metrics",
+    "/models",
+    "/usage",
+    "/files",
+    "/batches",
+    "/chat/completions",
+    "/completions",
+    "/embeddings",
+    "/messages",
+    "/responses",
+    "/orchestrations",
+    "/api/chat",
+    "/api/generate",
+    "/api/embeddings",
+    "/api/embed",
+];
+
+/// Metrics label for a request path (with the API prefix already removed).
+pub(crate) fn route_label(path: &str) -> &'static str {
+    KNOWN_ROUTES
+        .iter()
+        .find(|route| **route == path)
+        .copied()
+        .unwrap_or("other")
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    /// Per-bucket (non-cumulative) counts; the last slot is `+Inf`.
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, secs: f64) {
+        let slot = LATENCY_BUCKETS
+            .iter()
+            .position(|le| secs <= *le)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[slot] += 1;
+        self.sum += secs;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, route: &str) {
+        let mut cumulative = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            cumulative += count;
+            let le = LATENCY_BUCKETS
+                .get(i)
+                .map_or_else(|| "+Inf".to_string(), |b| b.to_string());
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{route=\"{route}\",le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(out, "{name}_sum{{route=\"{route}\"}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{route=\"{route}\"}} {}", self.count);
+    }
+}
+
+/// Request counters and latency histograms for one server run.
+#[derive(Debug, Default)]
+pub struct ServerMetrics {
+    requests: Mutex<BTreeMap<(&'static str, u16), u64>>,
+    latency: Mutex<BTreeMap<&'static str, Histogram>>,
+    ttft: Mutex<BTreeMap<&'static str, Histogram>>,
+    streams_in_flight: AtomicI64,
+}
+
+/// Holds one slot of the in-flight streams gauge until dropped.
+pub struct StreamGuard(Arc<ServerMetrics>);
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.0.streams_in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ServerMetrics {
+    pub fn record_request(&self, route: &'static str, status: u16) {
+        let mut requests = self.requests.lock().unwrap_or_else(|e| e.into_inner());
+        *requests.entry((route, status)).or_default() += 1;
+    }
+
+    /// Record a finished reply's total latency and, when it produced any
+    /// bytes, its time to first byte.
+    pub fn observe_latency(&self, route: &'static str, latency_ms: u64, ttft_ms: Option<u64>) {
+        self.latency
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(route)
+            .or_default()
+            .observe(latency_ms as f64 / 1000.0);
+        if let Some(ttft_ms) = ttft_ms {
+            self.ttft
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(route)
+                .or_default()
+                .observe(ttft_ms as f64 / 1000.0);
+        }
+    }
+
+    /// Count a streaming reply as in flight until the guard is dropped.
+    pub fn track_stream(self: &Arc<Self>) -> StreamGuard {
+        self.streams_in_flight.fetch_add(1, Ordering::Relaxed);
+        StreamGuard(self.clone())
+    }
+
+    /// Append the request metrics in Prometheus text format.
+    pub fn render(&self, out: &mut String) {
+        out.push_str("# HELP jan_http_requests_total Requests handled by the local API server.\n");
+        out.push_str("# TYPE jan_http_requests_total counter\n");
+        for ((route, status), count) in self
+            .requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "jan_http_requests_total{{route=\"{route}\",status=\"{status}\"}} {count}"
+            );
+        }
+
+        for (name, help, histograms) in [
+            (
+                "jan_http_request_duration_seconds",
+                "Time from request to the last byte of the reply.",
+                &self.latency,
+            ),
+            (
+                "jan_http_time_to_first_token_seconds",
+                "Time from request to the first byte of the reply.",
+                &self.ttft,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} histogram");
+            for (route, histogram) in histograms.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+                histogram.render(out, name, route);
+            }
+        }
+
+        out.push_str("# HELP jan_http_streams_in_flight Streaming replies currently being sent.\n");
+        out.push_str("# TYPE jan_http_streams_in_flight gauge\n");
+        let _ = writeln!(
+            out,
+            "jan_http_streams_in_flight {}",
+            self.streams_in_flight.load(Ordering::Relaxed)
+        );
+    }
+}
+
+/// llama.cpp router state sampled at scrape time.
+#[derive(Debug, Default)]
+pub struct RouterSnapshot {
+    /// Whether `router_pid` names a live process.
+    pub up: bool,
+    /// `(model id, status)` pairs as reported by the router's `/models`.
+    pub models: Vec<(String, String)>,
+}
+
+/// Append router and MCP gauges in Prometheus text format.
+pub fn render_runtime(out: &mut String, router: &RouterSnapshot, mcp_servers: &[String]) {
+    out.push_str("# HELP jan_llamacpp_router_up Whether the llama.cpp router process is alive.\n");
+    out.push_str("# TYPE jan_llamacpp_router_up gauge\n");
+    let _ = writeln!(out, "jan_llamacpp_router_up {}", u8::from(router.up));
+
+    out.push_str("# HELP jan_llamacpp_model_loaded Models the router currently has loaded.\n");
+    out.push_str("# TYPE jan_llamacpp_model_loaded gauge\n");
+    for (model, status) in &router.models {
+        if status == "loaded" {
+            let _ = writeln!(
+                out,
+                "jan_llamacpp_model_loaded{{model=\"{}\"}} 1",
+                escape_label(model)
+            );
+        }
+    }
+
+    out.push_str("# HELP jan_llamacpp_models Router models by status.\n");
+    out.push_str("# TYPE jan_llamacpp_models gauge\n");
+    let mut by_status: BTreeMap<&str, u64> = BTreeMap::new();
+    for (_, status) in &router.models {
+        *by_status.entry(status.as_str()).or_default() += 1;
+    }
+    for (status, count) in by_status {
+        let _ = writeln!(
+            out,
+            "jan_llamacpp_models{{status=\"{}\"}} {count}",
+            escape_label(status)
+        );
+    }
+
+    out.push_str("# HELP jan_mcp_server_connected MCP servers with a live connection.\n");
+    out.push_str("# TYPE jan_mcp_server_connected gauge\n");
+    for server in mcp_servers {
+        let _ = writeln!(
+            out,
+            "jan_mcp_server_connected{{server=\"{}\"}} 1",
+            escape_label(server)
+        );
+    }
+}
+
+/// Append per-model admission gauges: `(model, active, waiting)` rows.
+pub fn render_admission(out: &mut String, queues: &[(String, usize, usize)]) {
+    out.push_str("# HELP jan_admission_active Requests holding a slot on a local model.\n");
+    out.push_str("# TYPE jan_admission_active gauge\n");
+    for (model, active, _) in queues {
+        let _ = writeln!(
+            out,
+            "jan_admission_active{{model=\"{}\"}} {active}",
+            escape_label(model)
+        );
+    }
+
+    out.push_str("# HELP jan_admission_queued Requests waiting for a slot on a local model.\n");
+    out.push_str("# TYPE jan_admission_queued gauge\n");
+    for (model, _, waiting) in queues {
+        let _ = writeln!(
+            out,
+            "jan_admission_queued{{model=\"{}\"}} {waiting}",
+            escape_label(model)
+        );
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_paths_fold_into_other() {
+        assert_eq!(route_label("/chat/completions"), "/chat/completions");
+        assert_eq!(route_label("/wp-login.php"), "other");
+    }
+
+    #[test]
+    fn renders_counters_cumulative_buckets_and_stream_gauge() {
+        let metrics = Arc::new(ServerMetrics::default());
+        metrics.record_request("/chat/completions", 200);
+        metrics.record_request("/chat/completions", 200);
+        metrics.record_request("/models", 401);
+        metrics.observe_latency("/chat/completions", 300, Some(40));
+        metrics.observe_latency("/chat/completions", 7_000, None);
+        let guard = metrics.track_stream();
+
+        let mut out = String::new();
+        metrics.render(&mut out);
+        assert!(
+            out.contains("jan_http_requests_total{route=\"/chat/completions\",status=\"200\"} 2")
+        );
+        assert!(out.contains("jan_http_requests_total{route=\"/models\",status=\"401\"} 1"));
+        assert!(out.contains(
+            "jan_http_request_duration_seconds_bucket{route=\"/chat/completions\",le=\"0.5\"} 1"
+        ));
+        assert!(out.contains(
+            "jan_http_request_duration_seconds_bucket{route=\"/chat/completions\",le=\"10\"} 2"
+        ));
+        assert!(out.contains(
+            "jan_http_request_duration_seconds_bucket{route=\"/chat/completions\",le=\"+Inf\"} 2"
+        ));
+        assert!(out
+            .contains("jan_http_time_to_first_token_seconds_count{route=\"/chat/completions\"} 1"));
+        assert!(out.contains("jan_http_streams_in_flight 1"));
+
+        drop(guard);
+        let mut out = String::new();
+        metrics.render(&mut out);
+        assert!(out.contains("jan_http_streams_in_flight 0"));
+    }
+
+    #[test]
+    fn renders_router_and_mcp_state() {
+        let router = RouterSnapshot {
+            up: true,
+            models: vec![
+                ("qwen3-8b".into(), "loaded".into()),
+                ("gemma\"3".into(), "loaded".into()),
+                ("llama-3.2-1b".into(), "unloaded".into()),
+            ],
+        };
+        let mut out = String::new();
+        render_runtime(&mut out, &router, &["filesystem".to_string()]);
+        assert!(out.contains("jan_llamacpp_router_up 1"));
+        assert!(out.contains("jan_llamacpp_model_loaded{model=\"qwen3-8b\"} 1"));
+        assert!(out.contains("jan_llamacpp_model_loaded{model=\"gemma\\\"3\"} 1"));
+        assert!(!out.contains("llama-3.2-1b"));
+        assert!(out.contains("jan_llamacpp_models{status=\"loaded\"} 2"));
+        assert!(out.contains("jan_mcp_server_connected{server=\"filesystem\"} 1"));
+
+        let mut out = String::new();
+        render_admission(&mut out, &[("qwen3-8b".into(), 2, 5)]);
+        assert!(out.contains("jan_admission_active{model=\"qwen3-8b\"} 2"));
+        assert!(out.contains("jan_admission_queued{model=\"qwen3-8b\"} 5"));
+    }
+}
@@ -1,10 +1,29 @@
+pub mod access_log;
+pub mod admission;
+pub mod batches;
+pub mod client_keys;
 pub mod commands;
 pub mod converters;
+pub mod media;
+pub mod metrics;
+pub mod model_aliases;
+pub mod model_catalog;
+pub mod ollama;
+pub mod orchestration_stream;
+pub mod orchestration_threads;
+pub mod prompt_cache;
 pub mod provider_secrets;
 pub mod proxy;
 pub mod remote_provider_commands;
+pub mod response_cache;
+pub mod responses;
+pub mod structured_output;
 #[cfg(test)]
 pub mod tests;
+pub mod tls;
+pub mod tokens;
+pub mod tool_emulation;
+pub mod usage;
 
 // MLX session types used by the proxy. MLX is macOS-only, so on other platforms
 // we expose a field-compatible stub: the session map is always empty there, so
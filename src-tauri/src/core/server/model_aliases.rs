@@ -0,0 +1,416 @@
+//! Model aliases with fallback chains for the local API server.
+//!
+//! An alias such as `team-default` names an ordered (or weighted) list of
+//! real model ids — a local router model, then one remote provider, then
+//! another. A request naming the alias is proxied to the first target; when
+//! that fails before any bytes reach the client (connection error, 5xx, or no
+//! response headers within `first_byte_timeout_secs`) the next target is
+//! tried. API-key rotation within a provider still happens per target.
+//!
+//! Aliases are stored in `<jan_data>/model_aliases.json`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::core::app::commands::resolve_jan_data_folder;
+use crate::core::state::AppState;
+
+const MODEL_ALIASES_FILE_NAME: &str = "model_aliases.json";
+
+/// Serializes read-modify-write on the aliases file.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// How an alias orders its targets for each request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AliasStrategy {
+    /// Always try targets in the listed order.
+    #[default]
+    Ordered,
+    /// Pick the first target at random in proportion to its weight; the
+    /// remaining targets follow as fallbacks, also drawn by weight.
+    Weighted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AliasTarget {
+    /// Model id as accepted by `/v1/chat/completions` (a router model, an MLX
+    /// session or a remote provider model).
+    pub model: String,
+    /// Share of traffic under the weighted strategy. Zero-weight targets are
+    /// only used as fallbacks, after every weighted target.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelAlias {
+    pub name: String,
+    #[serde(default)]
+    pub strategy: AliasStrategy,
+    pub targets: Vec<AliasTarget>,
+    /// Give up on a target that has not sent response headers within this
+    /// many seconds. `None` waits as long as the upstream does.
+    #[serde(default)]
+    pub first_byte_timeout_secs: Option<u64>,
+}
+
+/// Targets to try for one request, in order.
+#[derive(Debug, Clone)]
+pub struct AliasPlan {
+    pub alias: String,
+    pub models: Vec<String>,
+    pub first_byte_timeout: Option<Duration>,
+}
+
+impl ModelAlias {
+    fn validate(&self, existing: &[ModelAlias]) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Model alias name must not be empty".to_string());
+        }
+        if self.targets.is_empty() {
+            return Err(format!(
+                "Model alias '{}' needs at least one target",
+                self.name
+            ));
+        }
+        for target in &self.targets {
+            if target.model.trim().is_empty() {
+                return Err(format!(
+                    "Model alias '{}' has a target without a model",
+                    self.name
+                ));
+            }
+            // Chains are one level deep so a failover can't loop.
+            if target.model == self.name
+                || existing
+                    .iter()
+                    .any(|a| a.name == target.model && a.name != self.name)
+            {
+                return Err(format!(
+                    "Model alias '{}' cannot target another alias ('{}')",
+                    self.name, target.model
+                ));
+            }
+        }
+        if self.first_byte_timeout_secs == Some(0) {
+            return Err("first_byte_timeout_secs must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+
+    /// Order the targets for one request.
+    fn plan_with(&self, rng: &mut impl Rng) -> AliasPlan {
+        let models = match self.strategy {
+            AliasStrategy::Ordered => self.targets.iter().map(|t| t.model.clone()).collect(),
+            AliasStrategy::Weighted => {
+                // Weighted sampling without replacement (Efraimidis–Spirakis):
+                // sort by u^(1/w) descending. Zero weights sort last, stably.
+                let mut keyed: Vec<(f64, &AliasTarget)> = self
+                    .targets
+                    .iter()
+                    .map(|t| {
+                        let key = if t.weight == 0 {
+                            -1.0
+                        } else {
+                            rng.gen::<f64>().powf(1.0 / f64::from(t.weight))
+                        };
+                        (key, t)
+                    })
+                    .collect();
+                keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+                keyed.into_iter().map(|(_, t)| t.model.clone()).collect()
+            }
+        };
+        AliasPlan {
+            alias: self.name.clone(),
+            models,
+            first_byte_timeout: self.first_byte_timeout_secs.map(Duration::from_secs),
+        }
+    }
+}
+
+fn model_aliases_file_path() -> PathBuf {
+    resolve_jan_data_folder().join(MODEL_ALIASES_FILE_NAME)
+}
+
+fn read_aliases_file(path: &Path) -> Vec<ModelAlias> {
+    let Ok(bytes) = fs::read(path) else {
+        return Vec::new();
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(aliases) => aliases,
+        Err(err) => {
+            log::warn!("Failed to parse {}: {err}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+fn write_aliases_file(path: &Path, aliases: &[ModelAlias]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let bytes = serde_json::to_vec_pretty(aliases).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, &bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// In-memory view of the model aliases, read from disk on first use and
+/// written through on every change.
+#[derive(Debug, Default)]
+pub struct ModelAliasStore {
+    /// Overrides the data-folder location (tests).
+    path: Option<PathBuf>,
+    aliases: Mutex<Option<Vec<ModelAlias>>>,
+}
+
+impl ModelAliasStore {
+    #[cfg(test)]
+    fn at(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            ..Self::default()
+        }
+    }
+
+    fn file_path(&self) -> PathBuf {
+        self.path.clone().unwrap_or_else(model_aliases_file_path)
+    }
+
+    fn with_aliases<T>(&self, f: impl FnOnce(&mut Vec<ModelAlias>) -> T) -> T {
+        let mut guard = self.aliases.lock().unwrap_or_else(|e| e.into_inner());
+        let aliases = guard.get_or_insert_with(|| read_aliases_file(&self.file_path()));
+        f(aliases)
+    }
+
+    fn modify<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<ModelAlias>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _file_guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+        self.with_aliases(|aliases| {
+            let mut next = aliases.clone();
+            let out = f(&mut next)?;
+            write_aliases_file(&self.file_path(), &next)?;
+            *aliases = next;
+            Ok(out)
+        })
+    }
+
+    pub fn list(&self) -> Vec<ModelAlias> {
+        self.with_aliases(|aliases| aliases.clone())
+    }
+
+    /// Create `alias`, or replace the alias with the same name.
+    pub fn set(&self, mut alias: ModelAlias) -> Result<ModelAlias, String> {
+        alias.name = alias.name.trim().to_string();
+        self.modify(|aliases| {
+            alias.validate(aliases)?;
+            // Existing aliases may not point at the name being (re)defined.
+            if let Some(user) = aliases
+                .iter()
+                .find(|a| a.name != alias.name && a.targets.iter().any(|t| t.model == alias.name))
+            {
+                return Err(format!(
+                    "'{}' is a target of model alias '{}' and cannot itself be an alias",
+                    alias.name, user.name
+                ));
+            }
+            match aliases.iter_mut().find(|a| a.name == alias.name) {
+                Some(existing) => *existing = alias.clone(),
+                None => aliases.push(alias.clone()),
+            }
+            Ok(alias)
+        })
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        self.modify(|aliases| {
+            let before = aliases.len();
+            aliases.retain(|a| a.name != name);
+            if aliases.len() == before {
+                return Err(format!("Model alias '{name}' not found"));
+            }
+            Ok(())
+        })
+    }
+
+    /// The targets to try for a request naming `model`, or `None` when it is
+    /// not an alias.
+    pub fn plan(&self, model: &str) -> Option<AliasPlan> {
+        self.with_aliases(|aliases| {
+            aliases
+                .iter()
+                .find(|a| a.name == model)
+                .map(|a| a.plan_with(&mut rand::thread_rng()))
+        })
+    }
+}
+
+/// List the configured model aliases.
+#[tauri::command]
+pub async fn list_model_aliases(state: State<'_, AppState>) -> Result<Vec<ModelAlias>, String> {
+    let store = state.model_aliases.clone();
+    tauri::async_runtime::spawn_blocking(move || store.list())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Create or replace a model alias.
+#[tauri::command]
+pub async fn set_model_alias(
+    state: State<'_, AppState>,
+    alias: ModelAlias,
+) -> Result<ModelAlias, String> {
+    let store = state.model_aliases.clone();
+    tauri::async_runtime::spawn_blocking(move || store.set(alias))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Remove a model alias.
+#[tauri::command]
+pub async fn delete_model_alias(state: State<'_, AppState>, name: String) -> Result<(), String> {
+    let store = state.model_aliases.clone();
+    tauri::async_runtime::spawn_blocking(move || store.delete(&name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn target(model: &str, weight: u32) -> AliasTarget {
+        AliasTarget {
+            model: model.to_string(),
+            weight,
+        }
+    }
+
+    fn alias(name: &str, strategy: AliasStrategy, targets: Vec<AliasTarget>) -> ModelAlias {
+        ModelAlias {
+            name: name.to_string(),
+            strategy,
+            targets,
+            first_byte_timeout_secs: None,
+        }
+    }
+
+    #[test]
+    fn set_persists_replaces_and_plans_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(MODEL_ALIASES_FILE_NAME);
+        let store = ModelAliasStore::at(path.clone());
+        assert!(store.plan("team-default").is_none());
+
+        store
+            .set(alias(
+                " team-default ",
+                AliasStrategy::Ordered,
+                vec![target("qwen3-8b", 1), target("openai/gpt-4o-mini", 1)],
+            ))
+            .unwrap();
+        let mut replaced = alias(
+            "team-default",
+            AliasStrategy::Ordered,
+            vec![
+                target("qwen3-8b", 1),
+                target("gpt-4o-mini", 1),
+                target("claude-haiku-4-5", 1),
+            ],
+        );
+        replaced.first_byte_timeout_secs = Some(20);
+        store.set(replaced).unwrap();
+
+        let plan = ModelAliasStore::at(path).plan("team-default").unwrap();
+        assert_eq!(plan.models, ["qwen3-8b", "gpt-4o-mini", "claude-haiku-4-5"]);
+        assert_eq!(plan.first_byte_timeout, Some(Duration::from_secs(20)));
+        assert_eq!(store.list().len(), 1);
+
+        store.delete("team-default").unwrap();
+        assert!(store.plan("team-default").is_none());
+        assert!(store.delete("team-default").is_err());
+    }
+
+    #[test]
+    fn rejects_empty_and_nested_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ModelAliasStore::at(dir.path().join(MODEL_ALIASES_FILE_NAME));
+        assert!(store
+            .set(alias("empty", AliasStrategy::Ordered, vec![]))
+            .is_err());
+        assert!(store
+            .set(alias(
+                "self",
+                AliasStrategy::Ordered,
+                vec![target("self", 1)]
+            ))
+            .is_err());
+
+        store
+            .set(alias(
+                "fast",
+                AliasStrategy::Ordered,
+                vec![target("qwen3-8b", 1)],
+            ))
+            .unwrap();
+        assert!(store
+            .set(alias(
+                "team",
+                AliasStrategy::Ordered,
+                vec![target("fast", 1)]
+            ))
+            .is_err());
+        store
+            .set(alias(
+                "team",
+                AliasStrategy::Ordered,
+                vec![target("gpt-4o", 1)],
+            ))
+            .unwrap();
+        assert!(store
+            .set(alias(
+                "gpt-4o",
+                AliasStrategy::Ordered,
+                vec![target("qwen3-8b", 1)]
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn weighted_plans_follow_weights_and_keep_every_fallback() {
+        let balanced = alias(
+            "balanced",
+            AliasStrategy::Weighted,
+            vec![target("a", 3), target("b", 1), target("backup", 0)],
+        );
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut first_a = 0;
+        for _ in 0..4000 {
+            let plan = balanced.plan_with(&mut rng);
+            assert_eq!(plan.models.len(), 3);
+            assert_eq!(plan.models[2], "backup");
+            if plan.models[0] == "a" {
+                first_a += 1;
+            }
+        }
+        // Expect ~75% of first picks on `a`.
+        assert!((2800..3200).contains(&first_a), "{first_a}");
+    }
+}
@@ -0,0 +1,407 @@
+//! Model descriptions for `GET /models` and `GET /models/{id}`.
+//!
+//! Besides the OpenAI fields, each entry says what serves the model, whether
+//! it is loaded, how much context it was trained for and what it can do.
+//! Local models are described from their `model.yml` plus the weights'
+//! header: the GGUF metadata for llama.cpp (and the mmproj's for vision or
+//! audio), the Hugging Face `config.json` and `tokenizer_config.json` next to
+//! the weights for MLX. Headers are cached per file until its mtime changes,
+//! so listing stays cheap. Remote models only carry their provider, and
+//! aliases their targets.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+/// `created` reported when a model's install time is unknown.
+const UNKNOWN_CREATED: u64 = 1;
+
+/// The parts of `model.yml` the listing reads.
+#[derive(Debug, Default, Deserialize)]
+struct ModelYml {
+    model_path: Option<String>,
+    name: Option<String>,
+    #[serde(default)]
+    size_bytes: u64,
+    #[serde(default)]
+    embedding: bool,
+    mmproj_path: Option<String>,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+/// What a weights header reveals about the model.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct HeaderFacts {
+    architecture: Option<String>,
+    context_length: Option<u64>,
+    /// The chat template handles `tools`.
+    tools: bool,
+    /// mmproj encoders (`clip.has_vision_encoder` / `clip.has_audio_encoder`).
+    vision: bool,
+    audio: bool,
+}
+
+fn gguf_header_facts(meta: &HashMap<String, String>) -> HeaderFacts {
+    let architecture = meta.get("general.architecture").cloned();
+    let context_length = architecture
+        .as_ref()
+        .and_then(|arch| meta.get(&format!("{arch}.context_length")))
+        .and_then(|v| v.parse().ok());
+    let flag = |key: &str| {
+        meta.get(key)
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    };
+    HeaderFacts {
+        architecture,
+        context_length,
+        tools: meta
+            .get("tokenizer.chat_template")
+            .is_some_and(|t| t.contains("tools")),
+        vision: flag("clip.has_vision_encoder"),
+        audio: flag("clip.has_audio_encoder"),
+    }
+}
+
+/// Facts from a Hugging Face `config.json`; multimodal configs nest the
+/// language model's settings under `text_config`.
+fn hf_header_facts(config: &Value, chat_template: Option<&str>) -> HeaderFacts {
+    let field = |key: &str| {
+        config
+            .get(key)
+            .or_else(|| config.get("text_config").and_then(|t| t.get(key)))
+    };
+    HeaderFacts {
+        architecture: field("model_type")
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        context_length: field("max_position_embeddings").and_then(Value::as_u64),
+        tools: chat_template.is_some_and(|t| t.contains("tools")),
+        vision: config.get("vision_config").is_some(),
+        audio: false,
+    }
+}
+
+fn header_cache() -> &'static Mutex<HashMap<PathBuf, (SystemTime, HeaderFacts)>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, (SystemTime, HeaderFacts)>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// `read` applied to `path`, reusing the cached result while the file's mtime
+/// is unchanged. Unreadable files yield `None` and are retried next time.
+fn cached_header(
+    path: &Path,
+    read: impl FnOnce(&Path) -> Option<HeaderFacts>,
+) -> Option<HeaderFacts> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    if let Some((at, facts)) = header_cache().lock().ok()?.get(path) {
+        if *at == modified {
+            return Some(facts.clone());
+        }
+    }
+    let facts = read(path)?;
+    if let Ok(mut cache) = header_cache().lock() {
+        cache.insert(path.to_path_buf(), (modified, facts.clone()));
+    }
+    Some(facts)
+}
+
+fn read_gguf(path: &Path) -> Option<HeaderFacts> {
+    let file = std::fs::File::open(path).ok()?;
+    match tauri_plugin_llamacpp::gguf::helpers::read_gguf_metadata(file) {
+        Ok(header) => Some(gguf_header_facts(&header.metadata)),
+        Err(e) => {
+            log::debug!("Failed to read GGUF metadata from {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Reads an MLX model's `config.json`, with the chat template from the
+/// sibling `tokenizer_config.json` or `chat_template.jinja` when present.
+fn read_hf_config(config_path: &Path) -> Option<HeaderFacts> {
+    let config: Value = serde_json::from_slice(&std::fs::read(config_path).ok()?).ok()?;
+    let dir = config_path.parent()?;
+    let template = std::fs::read(dir.join("tokenizer_config.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_slice::<Value>(&raw).ok())
+        .and_then(|t| t.get("chat_template").map(Value::to_string))
+        .or_else(|| std::fs::read_to_string(dir.join("chat_template.jinja")).ok());
+    Some(hf_header_facts(&config, template.as_deref()))
+}
+
+/// What a local model is and can do, from its `model.yml` and weights.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ModelFacts {
+    pub name: Option<String>,
+    pub architecture: Option<String>,
+    pub context_length: Option<u64>,
+    pub capabilities: Vec<&'static str>,
+    pub size_bytes: Option<u64>,
+    /// Install time: the `model.yml` mtime, in Unix seconds.
+    pub created: Option<u64>,
+}
+
+/// `embedding` for embedders, else `completion` plus whatever the weights or
+/// `model.yml` add. An mmproj without encoder flags is taken as vision, as the
+/// llama.cpp extension does.
+fn capabilities(
+    yml: &ModelYml,
+    weights: &HeaderFacts,
+    mmproj: Option<&HeaderFacts>,
+) -> Vec<&'static str> {
+    let listed = |cap: &str| yml.capabilities.iter().any(|c| c == cap);
+    if yml.embedding || listed("embedding") || listed("embeddings") {
+        return vec!["embedding"];
+    }
+    let mut caps = vec!["completion"];
+    if weights.tools || listed("tools") {
+        caps.push("tools");
+    }
+    let (vision, audio) =
+        mmproj.map_or((weights.vision, false), |m| (m.vision || !m.audio, m.audio));
+    if vision || listed("vision") {
+        caps.push("vision");
+    }
+    if audio || listed("audio") {
+        caps.push("audio");
+    }
+    caps
+}
+
+/// Describes the `engine` (`llamacpp` or `mlx`) model installed as
+/// `<data_folder>/<engine>/models/<model_id>/model.yml`. `weights_hint` is the
+/// weights path to fall back on when there is no readable `model.yml`.
+pub(crate) fn local_model_facts(
+    data_folder: &Path,
+    engine: &str,
+    model_id: &str,
+    weights_hint: Option<&str>,
+) -> Option<ModelFacts> {
+    // Ids come from the router or MLX sessions, but never leave `models/`
+    if !Path::new(model_id)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    let yml_path = data_folder
+        .join(engine)
+        .join("models")
+        .join(model_id)
+        .join("model.yml");
+    let created = std::fs::metadata(&yml_path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let yml: ModelYml = std::fs::read_to_string(&yml_path)
+        .ok()
+        .and_then(|content| serde_yaml::from_str(&content).ok())
+        .unwrap_or_default();
+    let resolve = |p: &str| {
+        let path = PathBuf::from(p);
+        if path.is_absolute() {
+            path
+        } else {
+            data_folder.join(path)
+        }
+    };
+    let weights_path = yml.model_path.as_deref().or(weights_hint).map(resolve);
+    if created.is_none() && weights_path.is_none() {
+        return None;
+    }
+
+    let weights = match &weights_path {
+        Some(path) if engine == "mlx" => {
+            let dir = if path.is_dir() {
+                path.as_path()
+            } else {
+                path.parent()?
+            };
+            cached_header(&dir.join("config.json"), read_hf_config)
+        }
+        Some(path) => cached_header(path, read_gguf),
+        None => None,
+    }
+    .unwrap_or_default();
+    let mmproj = yml
+        .mmproj_path
+        .as_deref()
+        .map(|p| cached_header(&resolve(p), read_gguf).unwrap_or_default());
+
+    Some(ModelFacts {
+        name: yml.name.clone(),
+        architecture: weights.architecture.clone(),
+        context_length: weights.context_length,
+        capabilities: capabilities(&yml, &weights, mmproj.as_ref()),
+        size_bytes: (yml.size_bytes > 0).then_some(yml.size_bytes),
+        created,
+    })
+}
+
+/// `state` of a router-served model from the router's `status.value`.
+pub(crate) fn router_state(status: Option<&str>) -> &'static str {
+    match status {
+        Some("loaded") => "loaded",
+        Some("loading") => "loading",
+        _ => "installed",
+    }
+}
+
+/// One `/models` entry. `state` is `loaded`, `loading` or `installed` for
+/// local models and `available` for remote models and aliases.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ModelDescription {
+    pub id: String,
+    pub owned_by: &'static str,
+    pub state: &'static str,
+    pub facts: ModelFacts,
+    /// Registered provider serving a remote model.
+    pub provider: Option<String>,
+    /// Models an alias dispatches to.
+    pub targets: Vec<String>,
+}
+
+impl ModelDescription {
+    pub(crate) fn to_json(&self) -> Value {
+        let mut entry = Map::new();
+        entry.insert("id".into(), json!(self.id));
+        entry.insert("object".into(), json!("model"));
+        entry.insert(
+            "created".into(),
+            json!(self.facts.created.unwrap_or(UNKNOWN_CREATED)),
+        );
+        entry.insert("owned_by".into(), json!(self.owned_by));
+        entry.insert("state".into(), json!(self.state));
+        let facts = &self.facts;
+        let optional = [
+            ("name", facts.name.as_ref().map(|v| json!(v))),
+            (
+                "architecture",
+                facts.architecture.as_ref().map(|v| json!(v)),
+            ),
+            ("context_length", facts.context_length.map(|v| json!(v))),
+            ("size_bytes", facts.size_bytes.map(|v| json!(v))),
+            ("provider", self.provider.as_ref().map(|v| json!(v))),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                entry.insert(key.into(), value);
+            }
+        }
+        if !facts.capabilities.is_empty() {
+            entry.insert("capabilities".into(), json!(facts.capabilities));
+        }
+        if !self.targets.is_empty() {
+            entry.insert("targets".into(), json!(self.targets));
+        }
+        Value::Object(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn gguf_and_hf_headers_yield_context_and_tool_support() {
+        let gguf = gguf_header_facts(&meta(&[
+            ("general.architecture", "qwen3"),
+            ("qwen3.context_length", "40960"),
+            ("tokenizer.chat_template", "{% if tools %}...{% endif %}"),
+        ]));
+        assert_eq!(gguf.architecture.as_deref(), Some("qwen3"));
+        assert_eq!(gguf.context_length, Some(40960));
+        assert!(gguf.tools && !gguf.vision);
+
+        let hf = hf_header_facts(
+            &json!({
+                "model_type": "gemma3",
+                "text_config": { "max_position_embeddings": 131072 },
+                "vision_config": {}
+            }),
+            None,
+        );
+        assert_eq!(hf.architecture.as_deref(), Some("gemma3"));
+        assert_eq!(hf.context_length, Some(131072));
+        assert!(hf.vision && !hf.tools);
+    }
+
+    #[test]
+    fn capabilities_combine_weights_mmproj_and_model_yml() {
+        let tools = HeaderFacts {
+            tools: true,
+            ..Default::default()
+        };
+        let yml = ModelYml::default();
+        assert_eq!(
+            capabilities(&yml, &tools, None),
+            vec!["completion", "tools"]
+        );
+        // An mmproj without encoder flags is a vision projector
+        let bare = HeaderFacts::default();
+        assert_eq!(
+            capabilities(&yml, &bare, Some(&bare)),
+            vec!["completion", "vision"]
+        );
+        let audio_only = HeaderFacts {
+            audio: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            capabilities(&yml, &bare, Some(&audio_only)),
+            vec!["completion", "audio"]
+        );
+        let embedder = ModelYml {
+            embedding: true,
+            ..Default::default()
+        };
+        assert_eq!(capabilities(&embedder, &tools, None), vec!["embedding"]);
+    }
+
+    #[test]
+    fn local_facts_read_model_yml_and_entries_omit_unknowns() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_dir = dir.path().join("llamacpp/models/org/tiny");
+        std::fs::create_dir_all(&model_dir).unwrap();
+        std::fs::write(
+            model_dir.join("model.yml"),
+            "model_path: llamacpp/models/org/tiny/model.gguf\nname: Tiny\nsize_bytes: 42\ncapabilities:\n  - tools\n",
+        )
+        .unwrap();
+
+        let facts = local_model_facts(dir.path(), "llamacpp", "org/tiny", None).unwrap();
+        assert_eq!(facts.name.as_deref(), Some("Tiny"));
+        assert_eq!(facts.size_bytes, Some(42));
+        assert_eq!(facts.capabilities, vec!["completion", "tools"]);
+        assert!(facts.created.is_some());
+        assert!(local_model_facts(dir.path(), "llamacpp", "../escape", None).is_none());
+        assert!(local_model_facts(dir.path(), "llamacpp", "missing", None).is_none());
+
+        let entry = ModelDescription {
+            id: "org/tiny".into(),
+            owned_by: "llama.cpp",
+            state: router_state(Some("unloaded")),
+            facts,
+            provider: None,
+            targets: Vec::new(),
+        }
+        .to_json();
+        assert_eq!(entry["state"], "installed");
+        assert_eq!(entry["capabilities"], json!(["completion", "tools"]));
+        assert!(entry.get("context_length").is_none());
+        assert!(entry.get("provider").is_none());
+    }
+}
@@ -0,0 +1,707 @@
+//! Ollama API ingress (`/api/chat`, `/api/generate`, `/api/embeddings`,
+//! `/api/embed`, `/api/tags`, `/api/show`, `/api/ps`, `/api/version`).
+//!
+//! Like the Responses ingress, inference requests are rewritten into
+//! chat/completions (or `/embeddings`) so they reach whichever backend the
+//! proxy already routes the model to — llama.cpp router, MLX or a registered
+//! provider — and the chat-shaped reply is translated back. Streaming replies
+//! are newline-delimited JSON objects rather than SSE, ending with a
+//! `"done": true` object that carries the token counts.
+//!
+//! Model management endpoints (`pull`, `push`, `create`, `delete`, `copy`)
+//! are not served; models are managed in Jan.
+
+use serde_json::{json, Value};
+
+use super::converters::ChatStreamTranslator;
+
+/// Ollama version reported by `/api/version`; clients gate features on it.
+pub(crate) const OLLAMA_COMPAT_VERSION: &str = "0.6.0";
+
+/// Content type of streamed Ollama replies.
+pub(crate) const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Ollama inference endpoints served through chat/completions or `/embeddings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OllamaRoute {
+    Chat,
+    Generate,
+    /// Legacy single-prompt `/api/embeddings`.
+    Embeddings,
+    /// Batch `/api/embed`.
+    Embed,
+}
+
+impl OllamaRoute {
+    pub(crate) fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/api/chat" => Some(Self::Chat),
+            "/api/generate" => Some(Self::Generate),
+            "/api/embeddings" => Some(Self::Embeddings),
+            "/api/embed" => Some(Self::Embed),
+            _ => None,
+        }
+    }
+
+    /// The OpenAI-compatible path the request is forwarded to.
+    pub(crate) fn upstream_path(self) -> &'static str {
+        match self {
+            Self::Chat | Self::Generate => "/chat/completions",
+            Self::Embeddings | Self::Embed => "/embeddings",
+        }
+    }
+}
+
+fn now_rfc3339() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Ollama images are bare base64; chat/completions wants a data URL. The
+/// MIME type is sniffed from the first bytes, defaulting to PNG.
+fn image_data_url(base64: &str) -> String {
+    let mime = if base64.starts_with("/9j/") {
+        "image/jpeg"
+    } else if base64.starts_with("R0lGOD") {
+        "image/gif"
+    } else if base64.starts_with("UklGR") {
+        "image/webp"
+    } else {
+        "image/png"
+    };
+    format!("data:{mime};base64,{base64}")
+}
+
+/// Chat content for `text` plus any attached `images`.
+fn content_with_images(text: &str, images: Option<&Value>) -> Value {
+    let images: Vec<&str> = images
+        .and_then(|i| i.as_array())
+        .map(|arr| arr.iter().filter_map(|i| i.as_str()).collect())
+        .unwrap_or_default();
+    if images.is_empty() {
+        return json!(text);
+    }
+    let mut parts = Vec::with_capacity(images.len() + 1);
+    if !text.is_empty() {
+        parts.push(json!({"type": "text", "text": text}));
+    }
+    for image in images {
+        parts.push(json!({"type": "image_url", "image_url": {"url": image_data_url(image)}}));
+    }
+    Value::Array(parts)
+}
+
+/// Map Ollama `messages` to chat messages. Ollama tool calls carry no ids, so
+/// each assistant call gets one and the `tool` replies that follow are paired
+/// with them by `tool_name`, else in order.
+fn messages_to_chat(messages: &[Value]) -> Vec<Value> {
+    let mut out = Vec::with_capacity(messages.len());
+    let mut pending_calls: Vec<(String, String)> = Vec::new();
+    let mut next_call = 0;
+    for message in messages {
+        let role = message
+            .get("role")
+            .and_then(|r| r.as_str())
+            .unwrap_or("user");
+        let text = message
+            .get("content")
+            .and_then(|c| c.as_str())
+            .unwrap_or("");
+        match role {
+            "assistant" => {
+                let mut msg = json!({"role": "assistant", "content": text});
+                if let Some(calls) = message.get("tool_calls").and_then(|c| c.as_array()) {
+                    pending_calls.clear();
+                    let calls: Vec<Value> = calls
+                        .iter()
+                        .map(|call| {
+                            let func = call.get("function");
+                            let name = func
+                                .and_then(|f| f.get("name"))
+                                .and_then(|n| n.as_str())
+                                .unwrap_or("")
+                                .to_string();
+                            let arguments = match func.and_then(|f| f.get("arguments")) {
+                                Some(Value::String(s)) => s.clone(),
+                                Some(v) => v.to_string(),
+                                None => "{}".to_string(),
+                            };
+                            let id = format!("call_{next_call}");
+                            next_call += 1;
+                            pending_calls.push((id.clone(), name.clone()));
+                            json!({
+                                "id": id,
+                                "type": "function",
+                                "function": {"name": name, "arguments": arguments},
+                            })
+                        })
+                        .collect();
+                    if !calls.is_empty() {
+                        msg["tool_calls"] = Value::Array(calls);
+                    }
+                }
+                out.push(msg);
+            }
+            "tool" => {
+                let name = message.get("tool_name").and_then(|n| n.as_str());
+                let pos = name
+                    .and_then(|n| pending_calls.iter().position(|(_, call)| call == n))
+                    .or_else(|| (!pending_calls.is_empty()).then_some(0));
+                let id = match pos {
+                    Some(pos) => pending_calls.remove(pos).0,
+                    None => {
+                        next_call += 1;
+                        format!("call_{}", next_call - 1)
+                    }
+                };
+                out.push(json!({"role": "tool", "tool_call_id": id, "content": text}));
+            }
+            _ => out.push(json!({
+                "role": role,
+                "content": content_with_images(text, message.get("images")),
+            })),
+        }
+    }
+    out
+}
+
+/// Copy Ollama `options` onto a chat body. `num_predict` of -1 (unbounded)
+/// and model-load options such as `num_ctx` are dropped.
+fn apply_options(options: &Value, out: &mut Value) {
+    for key in [
+        "temperature",
+        "top_p",
+        "top_k",
+        "min_p",
+        "seed",
+        "stop",
+        "repeat_penalty",
+        "presence_penalty",
+        "frequency_penalty",
+    ] {
+        if let Some(v) = options.get(key) {
+            out[key] = v.clone();
+        }
+    }
+    if let Some(n) = options
+        .get("num_predict")
+        .and_then(|n| n.as_i64())
+        .filter(|n| *n >= 0)
+    {
+        out["max_tokens"] = json!(n);
+    }
+}
+
+/// Rewrite an Ollama inference request into its OpenAI-compatible body.
+pub(crate) fn ollama_request_to_openai(route: OllamaRoute, body: &Value) -> Result<Value, String> {
+    let model = body
+        .get("model")
+        .and_then(|m| m.as_str())
+        .ok_or("Request body must contain a 'model' field")?;
+
+    let messages = match route {
+        OllamaRoute::Embeddings => {
+            let prompt = body.get("prompt").cloned().unwrap_or_else(|| json!(""));
+            return Ok(json!({"model": model, "input": prompt}));
+        }
+        OllamaRoute::Embed => {
+            let input = body
+                .get("input")
+                .cloned()
+                .ok_or("Request body must contain an 'input' field")?;
+            let mut out = json!({"model": model, "input": input});
+            if let Some(dimensions) = body.get("dimensions") {
+                out["dimensions"] = dimensions.clone();
+            }
+            return Ok(out);
+        }
+        OllamaRoute::Chat => body
+            .get("messages")
+            .and_then(|m| m.as_array())
+            .map(|m| messages_to_chat(m))
+            .unwrap_or_default(),
+        OllamaRoute::Generate => {
+            if body.get("suffix").is_some_and(|s| !s.is_null()) {
+                return Err("'suffix' (fill-in-the-middle) is not supported".to_string());
+            }
+            let mut messages = Vec::new();
+            if let Some(system) = body
+                .get("system")
+                .and_then(|s| s.as_str())
+                .filter(|s| !s.is_empty())
+            {
+                messages.push(json!({"role": "system", "content": system}));
+            }
+            let prompt = body.get("prompt").and_then(|p| p.as_str()).unwrap_or("");
+            messages.push(json!({
+                "role": "user",
+                "content": content_with_images(prompt, body.get("images")),
+            }));
+            messages
+        }
+    };
+
+    // Ollama streams unless told otherwise.
+    let stream = body.get("stream").and_then(|s| s.as_bool()).unwrap_or(true);
+    let mut out = json!({"model": model, "messages": messages, "stream": stream});
+    if stream {
+        // The final `done` object reports token counts.
+        out["stream_options"] = json!({"include_usage": true});
+    }
+    if let Some(options) = body.get("options") {
+        apply_options(options, &mut out);
+    }
+    match body.get("format") {
+        Some(Value::String(s)) if s == "json" => {
+            out["response_format"] = json!({"type": "json_object"});
+        }
+        Some(schema @ Value::Object(_)) => {
+            out["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": {"name": "response", "schema": schema},
+            });
+        }
+        _ => {}
+    }
+    if let Some(tools) = body.get("tools").filter(|t| t.is_array()) {
+        out["tools"] = tools.clone();
+    }
+    Ok(out)
+}
+
+/// The reply to a load-only request (`/api/generate` without a prompt, or
+/// `/api/chat` without messages), which Ollama clients use to warm a model.
+/// Nothing is forwarded; the backend loads models on first use.
+pub(crate) fn preload_reply(route: OllamaRoute, body: &Value) -> Option<Value> {
+    let empty = match route {
+        OllamaRoute::Chat => body
+            .get("messages")
+            .and_then(|m| m.as_array())
+            .map_or(true, |m| m.is_empty()),
+        OllamaRoute::Generate => {
+            body.get("prompt")
+                .and_then(|p| p.as_str())
+                .map_or(true, str::is_empty)
+                && body.get("images").is_none()
+        }
+        OllamaRoute::Embeddings | OllamaRoute::Embed => false,
+    };
+    if !empty {
+        return None;
+    }
+    let mut reply = json!({
+        "model": body.get("model").cloned().unwrap_or(Value::Null),
+        "created_at": now_rfc3339(),
+        "done": true,
+        "done_reason": "load",
+    });
+    match route {
+        OllamaRoute::Chat => reply["message"] = json!({"role": "assistant", "content": ""}),
+        _ => reply["response"] = json!(""),
+    }
+    Some(reply)
+}
+
+fn done_reason(finish_reason: Option<&str>) -> &'static str {
+    match finish_reason {
+        Some("length") => "length",
+        _ => "stop",
+    }
+}
+
+/// Ollama tool calls: `arguments` is an object, not a JSON string.
+fn ollama_tool_calls(calls: &[(String, String)]) -> Value {
+    calls
+        .iter()
+        .map(|(name, arguments)| {
+            let arguments: Value = serde_json::from_str(arguments).unwrap_or_else(|_| json!({}));
+            json!({"function": {"name": name, "arguments": arguments}})
+        })
+        .collect()
+}
+
+/// Set `prompt_eval_count` / `eval_count` from chat/completions usage.
+fn apply_usage(usage: Option<&Value>, out: &mut Value) {
+    let Some(usage) = usage.filter(|u| !u.is_null()) else {
+        return;
+    };
+    if let Some(n) = usage.get("prompt_tokens") {
+        out["prompt_eval_count"] = n.clone();
+    }
+    if let Some(n) = usage.get("completion_tokens") {
+        out["eval_count"] = n.clone();
+    }
+}
+
+/// Translate a non-streaming OpenAI-compatible reply into the Ollama shape.
+/// `model` is the name the client asked for.
+pub(crate) fn openai_response_to_ollama(route: OllamaRoute, reply: &Value, model: &str) -> Value {
+    let data = reply.get("data").and_then(|d| d.as_array());
+    let embedding = |item: &Value| item.get("embedding").cloned().unwrap_or_else(|| json!([]));
+    match route {
+        OllamaRoute::Embeddings => {
+            let first = data.and_then(|d| d.first());
+            return json!({"embedding": first.map(embedding).unwrap_or_else(|| json!([]))});
+        }
+        OllamaRoute::Embed => {
+            let embeddings: Vec<Value> = data
+                .map(|d| d.iter().map(embedding).collect())
+                .unwrap_or_default();
+            let mut out = json!({"model": model, "embeddings": embeddings});
+            apply_usage(reply.get("usage"), &mut out);
+            return out;
+        }
+        OllamaRoute::Chat | OllamaRoute::Generate => {}
+    }
+
+    let choice = reply
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .and_then(|c| c.first());
+    let message = choice.and_then(|c| c.get("message"));
+    let text = message
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_str())
+        .unwrap_or("");
+    let thinking = message
+        .and_then(|m| m.get("reasoning_content"))
+        .and_then(|r| r.as_str())
+        .filter(|r| !r.is_empty());
+    let calls: Vec<(String, String)> = message
+        .and_then(|m| m.get("tool_calls"))
+        .and_then(|c| c.as_array())
+        .map(|calls| {
+            calls
+                .iter()
+                .map(|call| {
+                    let func = call.get("function");
+                    let field = |name: &str| {
+                        func.and_then(|f| f.get(name))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("")
+                            .to_string()
+                    };
+                    (field("name"), field("arguments"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut out = json!({
+        "model": model,
+        "created_at": now_rfc3339(),
+        "done": true,
+        "done_reason": done_reason(
+            choice
+                .and_then(|c| c.get("finish_reason"))
+                .and_then(|f| f.as_str()),
+        ),
+    });
+    match route {
+        OllamaRoute::Chat => {
+            let mut msg = json!({"role": "assistant", "content": text});
+            if let Some(thinking) = thinking {
+                msg["thinking"] = json!(thinking);
+            }
+            if !calls.is_empty() {
+                msg["tool_calls"] = ollama_tool_calls(&calls);
+            }
+            out["message"] = msg;
+        }
+        _ => {
+            out["response"] = json!(text);
+            if let Some(thinking) = thinking {
+                out["thinking"] = json!(thinking);
+            }
+        }
+    }
+    apply_usage(reply.get("usage"), &mut out);
+    out
+}
+
+/// `/api/tags` and `/api/ps` entry for a model the server answers for;
+/// `served_by` is its `/models` owner. Jan does not expose sizes or digests,
+/// so those are left empty.
+pub(crate) fn model_entry(id: &str, served_by: &str) -> Value {
+    let format = match served_by {
+        "llama.cpp" => "gguf",
+        other => other,
+    };
+    json!({
+        "name": id,
+        "model": id,
+        "modified_at": now_rfc3339(),
+        "size": 0,
+        "digest": "",
+        "details": {
+            "format": format,
+            "family": "",
+            "families": null,
+            "parameter_size": "",
+            "quantization_level": "",
+        },
+    })
+}
+
+/// `/api/show` reply for a served model.
+pub(crate) fn show_reply(id: &str, served_by: &str) -> Value {
+    let entry = model_entry(id, served_by);
+    json!({
+        "modelfile": "",
+        "parameters": "",
+        "template": "",
+        "details": entry["details"],
+        "model_info": {},
+        "capabilities": ["completion", "tools"],
+        "modified_at": entry["modified_at"],
+    })
+}
+
+/// Turns chat/completions stream chunks into Ollama NDJSON objects: one per
+/// content or thinking delta, tool calls once their arguments are complete,
+/// then a closing `"done": true` object.
+pub(crate) struct OllamaStreamTranslator {
+    route: OllamaRoute,
+    model: String,
+    /// `(name, arguments)` per chat tool-call index, in order.
+    tool_calls: Vec<(String, String)>,
+    usage: Value,
+    finish_reason: Option<String>,
+    finished: bool,
+}
+
+impl OllamaStreamTranslator {
+    pub(crate) fn new(route: OllamaRoute, model: &str) -> Self {
+        Self {
+            route,
+            model: model.to_string(),
+            tool_calls: Vec::new(),
+            usage: Value::Null,
+            finish_reason: None,
+            finished: false,
+        }
+    }
+
+    /// A partial object; `message` holds the delta for `/api/chat`.
+    fn object(&self, content: &str, thinking: Option<&str>, done: bool) -> Value {
+        let mut out = json!({
+            "model": self.model,
+            "created_at": now_rfc3339(),
+            "done": done,
+        });
+        if self.route == OllamaRoute::Chat {
+            let mut msg = json!({"role": "assistant", "content": content});
+            if let Some(thinking) = thinking {
+                msg["thinking"] = json!(thinking);
+            }
+            out["message"] = msg;
+        } else {
+            out["response"] = json!(content);
+            if let Some(thinking) = thinking {
+                out["thinking"] = json!(thinking);
+            }
+        }
+        out
+    }
+
+    fn flush_tool_calls(&mut self, events: &mut Vec<Value>) {
+        if self.tool_calls.is_empty() || self.route != OllamaRoute::Chat {
+            return;
+        }
+        let mut ev = self.object("", None, false);
+        ev["message"]["tool_calls"] = ollama_tool_calls(&self.tool_calls);
+        self.tool_calls.clear();
+        events.push(ev);
+    }
+}
+
+impl ChatStreamTranslator for OllamaStreamTranslator {
+    fn push_chunk(&mut self, chunk: &Value) -> Vec<Value> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.usage = usage.clone();
+        }
+        let choice = chunk
+            .get("choices")
+            .and_then(|c| c.as_array())
+            .and_then(|c| c.first());
+        let Some(delta) = choice.and_then(|c| c.get("delta")) else {
+            return events;
+        };
+
+        let thinking = delta
+            .get("reasoning_content")
+            .and_then(|r| r.as_str())
+            .filter(|t| !t.is_empty());
+        let content = delta.get("content").and_then(|c| c.as_str()).unwrap_or("");
+        if thinking.is_some() || !content.is_empty() {
+            events.push(self.object(content, thinking, false));
+        }
+
+        if let Some(calls) = delta.get("tool_calls").and_then(|c| c.as_array()) {
+            for call in calls {
+                let index = call.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
+                if self.tool_calls.len() <= index {
+                    self.tool_calls.resize(index + 1, Default::default());
+                }
+                let func = call.get("function");
+                if let Some(name) = func.and_then(|f| f.get("name")).and_then(|n| n.as_str()) {
+                    self.tool_calls[index].0.push_str(name);
+                }
+                if let Some(args) = func
+                    .and_then(|f| f.get("arguments"))
+                    .and_then(|a| a.as_str())
+                {
+                    self.tool_calls[index].1.push_str(args);
+                }
+            }
+        }
+
+        if let Some(reason) = choice
+            .and_then(|c| c.get("finish_reason"))
+            .and_then(|f| f.as_str())
+        {
+            self.finish_reason = Some(reason.to_string());
+            self.flush_tool_calls(&mut events);
+        }
+        events
+    }
+
+    fn finish(&mut self) -> Vec<Value> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+        self.flush_tool_calls(&mut events);
+        self.finished = true;
+        let mut done = self.object("", None, true);
+        done["done_reason"] = json!(done_reason(self.finish_reason.as_deref()));
+        apply_usage(Some(&self.usage), &mut done);
+        events.push(done);
+        events
+    }
+
+    fn fail(&mut self, message: &str) -> Vec<Value> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.finished = true;
+        vec![json!({"error": message})]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_request_maps_options_format_images_and_tool_turns() {
+        let body = json!({
+            "model": "qwen3",
+            "messages": [
+                {"role": "system", "content": "be brief"},
+                {"role": "user", "content": "what is this?", "images": ["/9j/4AAQ"]},
+                {"role": "assistant", "content": "", "tool_calls": [
+                    {"function": {"name": "lookup", "arguments": {"q": "cat"}}}
+                ]},
+                {"role": "tool", "content": "a cat", "tool_name": "lookup"}
+            ],
+            "options": {"temperature": 0.1, "num_predict": 32, "num_ctx": 8192},
+            "format": "json"
+        });
+        let out = ollama_request_to_openai(OllamaRoute::Chat, &body).unwrap();
+        assert_eq!(out["stream"], true);
+        assert_eq!(out["stream_options"], json!({"include_usage": true}));
+        assert_eq!(out["temperature"], 0.1);
+        assert_eq!(out["max_tokens"], 32);
+        assert!(out.get("num_ctx").is_none());
+        assert_eq!(out["response_format"], json!({"type": "json_object"}));
+
+        let messages = out["messages"].as_array().unwrap();
+        assert_eq!(
+            messages[1]["content"][1]["image_url"]["url"],
+            "data:image/jpeg;base64,/9j/4AAQ"
+        );
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"],
+            r#"{"q":"cat"}"#
+        );
+        assert_eq!(
+            messages[3]["tool_call_id"],
+            messages[2]["tool_calls"][0]["id"]
+        );
+
+        let generate = json!({"model": "qwen3", "system": "s", "prompt": "hi", "stream": false});
+        let out = ollama_request_to_openai(OllamaRoute::Generate, &generate).unwrap();
+        assert_eq!(out["messages"].as_array().unwrap().len(), 2);
+        assert!(out.get("stream_options").is_none());
+        assert!(preload_reply(OllamaRoute::Generate, &generate).is_none());
+        let warm = preload_reply(OllamaRoute::Generate, &json!({"model": "qwen3"})).unwrap();
+        assert_eq!(warm["done_reason"], "load");
+    }
+
+    #[test]
+    fn non_streaming_replies_translate_per_route() {
+        let chat = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{"id": "c1", "type": "function",
+                        "function": {"name": "lookup", "arguments": "{\"q\":\"cat\"}"}}]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 4}
+        });
+        let out = openai_response_to_ollama(OllamaRoute::Chat, &chat, "qwen3");
+        assert_eq!(out["model"], "qwen3");
+        assert_eq!(out["done_reason"], "stop");
+        assert_eq!(
+            out["message"]["tool_calls"][0]["function"]["arguments"]["q"],
+            "cat"
+        );
+        assert_eq!(out["prompt_eval_count"], 9);
+        assert_eq!(out["eval_count"], 4);
+
+        let embeddings = json!({"data": [{"embedding": [0.1, 0.2]}, {"embedding": [0.3]}]});
+        let legacy = openai_response_to_ollama(OllamaRoute::Embeddings, &embeddings, "e5");
+        assert_eq!(legacy, json!({"embedding": [0.1, 0.2]}));
+        let batch = openai_response_to_ollama(OllamaRoute::Embed, &embeddings, "e5");
+        assert_eq!(batch["embeddings"], json!([[0.1, 0.2], [0.3]]));
+    }
+
+    #[test]
+    fn stream_emits_deltas_tool_calls_and_a_single_done() {
+        let mut t = OllamaStreamTranslator::new(OllamaRoute::Chat, "qwen3");
+        let mut events = t.push_chunk(&json!({"choices": [{"delta": {"content": "Hel"}}]}));
+        events.extend(t.push_chunk(&json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 0, "function": {"name": "lookup", "arguments": "{\"q\":"}}
+        ]}}]})));
+        events.extend(t.push_chunk(&json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 0, "function": {"arguments": "\"cat\"}"}}
+        ]}, "finish_reason": "tool_calls"}]})));
+        events.extend(t.push_chunk(
+            &json!({"choices": [], "usage": {"prompt_tokens": 5, "completion_tokens": 7}}),
+        ));
+        events.extend(t.finish());
+        assert!(t.finish().is_empty());
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["message"]["content"], "Hel");
+        assert_eq!(events[0]["done"], false);
+        assert_eq!(
+            events[1]["message"]["tool_calls"][0]["function"]["arguments"]["q"],
+            "cat"
+        );
+        assert_eq!(events[2]["done"], true);
+        assert_eq!(events[2]["eval_count"], 7);
+
+        let mut g = OllamaStreamTranslator::new(OllamaRoute::Generate, "qwen3");
+        let events = g.push_chunk(&json!({"choices": [{"delta": {"content": "hi"}}]}));
+        assert_eq!(events[0]["response"], "hi");
+        assert_eq!(g.fail("boom"), vec![json!({"error": "boom"})]);
+    }
+}
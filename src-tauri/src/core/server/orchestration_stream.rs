@@ -0,0 +1,342 @@
+//! Server-sent events for `/orchestrations` with `stream: true`.
+//!
+//! A streamed orchestration reports each step of the model → MCP tools →
+//! model loop as it happens instead of one JSON reply at the end. Events are
+//! JSON objects framed as `event: <type>` SSE messages:
+//!
+//! - `turn.started`: a model turn begins (`turn` counts from 0).
+//! - `message.delta`: assistant `content` / `reasoning_content` text.
+//! - `tool_call.approval_required`: the approval policy is holding a call
+//!   until it is approved in the app.
+//! - `tool_call.started`: a tool call with its parsed arguments and server.
+//! - `tool_call.progress`: an MCP `notifications/progress` update for it.
+//! - `tool_call.completed`: its result text, with `is_error`.
+//! - `orchestration.completed`: the final chat completion, as the
+//!   non-streamed endpoint would have returned it.
+//! - `orchestration.requires_approval`: the run stopped on tool calls held
+//!   for the client; `response` is the reply the non-streamed endpoint gives.
+//! - `error`: the orchestration failed; nothing follows.
+
+use serde_json::{json, Value};
+
+/// Prefix the MCP executor gives failed tool results.
+const TOOL_ERROR_PREFIX: &str = "ERROR: ";
+
+/// `object` of an orchestration reply stopped on held tool calls, and the
+/// type of the event carrying it.
+const REQUIRES_APPROVAL: &str = "orchestration.requires_approval";
+
+/// Folds the chat/completions chunks of one streamed turn back into the
+/// completion a non-streamed request would have returned.
+#[derive(Debug, Default)]
+pub struct TurnAssembler {
+    id: String,
+    model: String,
+    content: String,
+    reasoning: String,
+    tool_calls: Vec<Value>,
+    finish_reason: Option<String>,
+    usage: Option<Value>,
+}
+
+impl TurnAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold one chunk in; returns the `message.delta` event for its text,
+    /// if it carried any. Tool-call fragments are only reported whole, by
+    /// [`tool_call_started`].
+    pub fn push_chunk(&mut self, turn: usize, chunk: &Value) -> Option<Value> {
+        for (field, slot) in [("id", &mut self.id), ("model", &mut self.model)] {
+            if slot.is_empty() {
+                if let Some(v) = chunk.get(field).and_then(|v| v.as_str()) {
+                    *slot = v.to_string();
+                }
+            }
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.usage = Some(usage.clone());
+        }
+        let choice = chunk.get("choices").and_then(|c| c.get(0))?;
+        if let Some(reason) = choice.get("finish_reason").and_then(|r| r.as_str()) {
+            self.finish_reason = Some(reason.to_string());
+        }
+        let delta = choice.get("delta")?;
+        for call in delta
+            .get("tool_calls")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+        {
+            self.push_tool_call(call);
+        }
+
+        let mut out = json!({});
+        if let Some(text) = delta
+            .get("content")
+            .and_then(|c| c.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            self.content.push_str(text);
+            out["content"] = json!(text);
+        }
+        if let Some(text) = delta
+            .get("reasoning_content")
+            .or_else(|| delta.get("reasoning"))
+            .and_then(|c| c.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            self.reasoning.push_str(text);
+            out["reasoning_content"] = json!(text);
+        }
+        if out.as_object().is_some_and(|o| o.is_empty()) {
+            return None;
+        }
+        Some(json!({"type": "message.delta", "turn": turn, "delta": out}))
+    }
+
+    fn push_tool_call(&mut self, fragment: &Value) {
+        let index = fragment
+            .get("index")
+            .and_then(|i| i.as_u64())
+            .map_or(self.tool_calls.len(), |i| i as usize);
+        while self.tool_calls.len() <= index {
+            self.tool_calls.push(json!({
+                "id": "",
+                "type": "function",
+                "function": {"name": "", "arguments": ""},
+            }));
+        }
+        let call = &mut self.tool_calls[index];
+        if let Some(id) = fragment.get("id").and_then(|v| v.as_str()) {
+            call["id"] = json!(id);
+        }
+        let function = fragment.get("function");
+        if let Some(name) = function
+            .and_then(|f| f.get("name"))
+            .and_then(|v| v.as_str())
+        {
+            call["function"]["name"] = json!(name);
+        }
+        if let Some(args) = function
+            .and_then(|f| f.get("arguments"))
+            .and_then(|v| v.as_str())
+        {
+            let mut joined = call["function"]["arguments"]
+                .as_str()
+                .unwrap_or("")
+                .to_string();
+            joined.push_str(args);
+            call["function"]["arguments"] = json!(joined);
+        }
+    }
+
+    /// The assembled `chat.completion`.
+    pub fn finish(self) -> Value {
+        let mut message = json!({"role": "assistant", "content": self.content});
+        if !self.reasoning.is_empty() {
+            message["reasoning_content"] = json!(self.reasoning);
+        }
+        if !self.tool_calls.is_empty() {
+            if self.content.is_empty() {
+                message["content"] = Value::Null;
+            }
+            message["tool_calls"] = json!(self.tool_calls);
+        }
+        let finish_reason = self.finish_reason.unwrap_or_else(|| {
+            if self.tool_calls.is_empty() {
+                "stop"
+            } else {
+                "tool_calls"
+            }
+            .to_string()
+        });
+        let mut completion = json!({
+            "id": self.id,
+            "object": "chat.completion",
+            "model": self.model,
+            "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}],
+        });
+        if let Some(usage) = self.usage {
+            completion["usage"] = usage;
+        }
+        completion
+    }
+}
+
+/// The `message.delta` for a whole, non-streamed completion, for upstreams
+/// that answered a streamed turn with plain JSON.
+pub fn completion_delta(turn: usize, completion: &Value) -> Option<Value> {
+    let message = completion.get("choices")?.get(0)?.get("message")?;
+    let mut assembler = TurnAssembler::new();
+    assembler.push_chunk(turn, &json!({"choices": [{"delta": message}]}))
+}
+
+pub fn turn_started(turn: usize) -> Value {
+    json!({"type": "turn.started", "turn": turn})
+}
+
+/// `tool_call.started` for a chat tool call, with its arguments parsed when
+/// they are valid JSON.
+pub fn tool_call_started(call: &Value, server: Option<&str>) -> Value {
+    let function = call.get("function");
+    let raw = function
+        .and_then(|f| f.get("arguments"))
+        .and_then(|a| a.as_str())
+        .unwrap_or("{}");
+    json!({
+        "type": "tool_call.started",
+        "id": call.get("id").cloned().unwrap_or(Value::Null),
+        "name": function.and_then(|f| f.get("name")).cloned().unwrap_or(Value::Null),
+        "arguments": serde_json::from_str::<Value>(raw).unwrap_or_else(|_| json!(raw)),
+        "server": server,
+    })
+}
+
+fn with_fields(mut event: Value, fields: Value) -> Value {
+    if let (Some(event), Value::Object(fields)) = (event.as_object_mut(), fields) {
+        for (key, value) in fields {
+            event.entry(key).or_insert(value);
+        }
+    }
+    event
+}
+
+/// `tool_call.approval_required` carrying a serialized approval request.
+pub fn tool_call_approval_required(request: Value) -> Value {
+    with_fields(json!({"type": "tool_call.approval_required"}), request)
+}
+
+/// `tool_call.progress` carrying a serialized MCP progress update.
+pub fn tool_call_progress(id: &str, name: &str, progress: Value) -> Value {
+    with_fields(
+        json!({"type": "tool_call.progress", "id": id, "name": name}),
+        progress,
+    )
+}
+
+pub fn tool_call_completed(id: &str, name: &str, result: &str) -> Value {
+    json!({
+        "type": "tool_call.completed",
+        "id": id,
+        "name": name,
+        "result": result,
+        "is_error": result.starts_with(TOOL_ERROR_PREFIX),
+    })
+}
+
+/// The reply for a run stopped on tool calls held for the client: the
+/// conversation so far, to send back with `approvals`, and the calls to
+/// decide on.
+pub fn requires_approval(model: &str, messages: &[Value], pending: Value) -> Value {
+    json!({
+        "object": REQUIRES_APPROVAL,
+        "model": model,
+        "messages": messages,
+        "pending_approvals": pending,
+    })
+}
+
+/// The last event of a run that ended without error.
+pub fn completed(response: &Value) -> Value {
+    let kind = match response.get("object").and_then(|o| o.as_str()) {
+        Some(REQUIRES_APPROVAL) => REQUIRES_APPROVAL,
+        _ => "orchestration.completed",
+    };
+    json!({"type": kind, "response": response})
+}
+
+pub fn failed(message: &str) -> Value {
+    json!({"type": "error", "error": {"message": message}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(delta: Value, finish: Option<&str>) -> Value {
+        json!({"id": "c1", "model": "m", "choices": [{"index": 0, "delta": delta, "finish_reason": finish}]})
+    }
+
+    #[test]
+    fn assembler_streams_text_and_rebuilds_tool_calls() {
+        let mut turn = TurnAssembler::new();
+        assert_eq!(
+            turn.push_chunk(
+                1,
+                &chunk(json!({"role": "assistant", "content": "Look"}), None)
+            ),
+            Some(json!({"type": "message.delta", "turn": 1, "delta": {"content": "Look"}}))
+        );
+        let call = json!({"index": 0, "id": "call_1", "function": {"name": "search", "arguments": "{\"q\":"}});
+        assert_eq!(
+            turn.push_chunk(1, &chunk(json!({"tool_calls": [call]}), None)),
+            None
+        );
+        let rest = json!({"index": 0, "function": {"arguments": "\"rust\"}"}});
+        turn.push_chunk(1, &chunk(json!({"tool_calls": [rest]}), Some("tool_calls")));
+        turn.push_chunk(1, &json!({"choices": [], "usage": {"total_tokens": 9}}));
+
+        let completion = turn.finish();
+        assert_eq!(completion["id"], "c1");
+        assert_eq!(completion["usage"]["total_tokens"], 9);
+        let choice = &completion["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(choice["message"]["content"], "Look");
+        assert_eq!(
+            choice["message"]["tool_calls"][0],
+            json!({"id": "call_1", "type": "function", "function": {"name": "search", "arguments": "{\"q\":\"rust\"}"}})
+        );
+    }
+
+    #[test]
+    fn tool_events_parse_arguments_and_flag_errors() {
+        let call = json!({"id": "call_1", "function": {"name": "search", "arguments": "{\"q\":\"rust\"}"}});
+        assert_eq!(
+            tool_call_started(&call, Some("web")),
+            json!({"type": "tool_call.started", "id": "call_1", "name": "search", "arguments": {"q": "rust"}, "server": "web"})
+        );
+        assert_eq!(
+            tool_call_progress(
+                "call_1",
+                "search",
+                json!({"server": "web", "progress": 1.0, "percent": 50.0})
+            ),
+            json!({"type": "tool_call.progress", "id": "call_1", "name": "search", "server": "web", "progress": 1.0, "percent": 50.0})
+        );
+        assert_eq!(
+            tool_call_completed("call_1", "search", "ok")["is_error"],
+            false
+        );
+        assert_eq!(
+            tool_call_completed("call_1", "search", "ERROR: boom")["is_error"],
+            true
+        );
+    }
+
+    #[test]
+    fn held_calls_end_the_stream_with_requires_approval() {
+        let pending = json!([{"id": "call_1", "server": "shell", "tool": "run", "arguments": {}}]);
+        let reply = requires_approval("m", &[json!({"role": "user", "content": "hi"})], pending);
+        assert_eq!(reply["pending_approvals"][0]["id"], "call_1");
+        assert_eq!(completed(&reply)["type"], "orchestration.requires_approval");
+        assert_eq!(
+            completed(&json!({"object": "chat.completion"}))["type"],
+            "orchestration.completed"
+        );
+        assert_eq!(
+            tool_call_approval_required(json!({"id": "call_1", "tool": "run"})),
+            json!({"type": "tool_call.approval_required", "id": "call_1", "tool": "run"})
+        );
+    }
+
+    #[test]
+    fn whole_completion_becomes_one_delta() {
+        let completion = json!({"choices": [{"message": {"role": "assistant", "content": "Hi"}}]});
+        assert_eq!(
+            completion_delta(0, &completion),
+            Some(json!({"type": "message.delta", "turn": 0, "delta": {"content": "Hi"}}))
+        );
+    }
+}
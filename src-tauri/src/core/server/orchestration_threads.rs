@@ -0,0 +1,515 @@
+//! Saving `/orchestrations` runs as Jan threads.
+//!
+//! With `persist: true` a run is written to a new thread. With `thread_id` it
+//! continues a stored thread: the thread's conversation goes to the model
+//! ahead of `messages`, and the run is added to the thread afterwards.
+//!
+//! Messages are stored the way a chat in the app stores them, so the run can
+//! be reviewed and continued there and with `jan threads messages`. Each user
+//! turn is one message. The assistant turns of one reply, with their tool
+//! calls and tool results, fold into a single assistant message of `text`,
+//! `reasoning` and `tool_call` content items.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+use crate::core::threads::db;
+use crate::core::threads::helpers::{
+    get_lock_for_thread, read_messages_from_file, should_use_sqlite, update_thread_metadata,
+    write_messages_to_file,
+};
+use crate::core::threads::utils::{
+    ensure_thread_dir_exists, get_messages_path, get_thread_metadata_path,
+};
+
+use super::converters::message_text;
+
+/// Longest thread title taken from the run's first user message.
+const TITLE_CHARS: usize = 50;
+
+/// The thread a run is saved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThreadTarget {
+    /// A thread created for this run.
+    New(String),
+    /// A stored thread the run continues.
+    Existing(String),
+}
+
+impl ThreadTarget {
+    pub fn id(&self) -> &str {
+        match self {
+            ThreadTarget::New(id) | ThreadTarget::Existing(id) => id,
+        }
+    }
+}
+
+/// The thread an `/orchestrations` body asks to be saved to, if any.
+pub fn thread_target(body: &Value) -> Result<Option<ThreadTarget>, String> {
+    if let Some(id) = body.get("thread_id").filter(|v| !v.is_null()) {
+        let id = id.as_str().ok_or("'thread_id' must be a string")?;
+        // Thread ids name directories in the data folder.
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!("Invalid thread_id '{id}'"));
+        }
+        return Ok(Some(ThreadTarget::Existing(id.to_string())));
+    }
+    let persist = body
+        .get("persist")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    Ok(persist.then(|| ThreadTarget::New(Uuid::new_v4().to_string())))
+}
+
+/// Put `history` ahead of the body's `messages`, which may be omitted when
+/// continuing a thread.
+pub fn prepend_history(body: &mut Value, mut history: Vec<Value>) {
+    let Some(body) = body.as_object_mut() else {
+        return;
+    };
+    match body.get_mut("messages") {
+        Some(Value::Array(messages)) => {
+            history.append(messages);
+            *messages = history;
+        }
+        // Left for request validation to reject.
+        Some(other) if !other.is_null() => {}
+        _ => {
+            body.insert("messages".to_string(), Value::Array(history));
+        }
+    }
+}
+
+fn text_item(kind: &str, value: &str) -> Value {
+    json!({"type": kind, "text": {"value": value, "annotations": []}})
+}
+
+fn item_type(item: &Value) -> Option<&str> {
+    item.get("type").and_then(|t| t.as_str())
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A stored thread as chat messages, to send ahead of the run's own. A reply
+/// whose tool calls were still held for approval ends with those calls
+/// unanswered, so the run resumes them.
+pub fn thread_history(stored: &[Value]) -> Vec<Value> {
+    let mut history = Vec::new();
+    for message in stored {
+        let items = message
+            .get("content")
+            .and_then(|c| c.as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let text = items
+            .iter()
+            .filter(|i| item_type(i) == Some("text"))
+            .filter_map(|i| i.pointer("/text/value").and_then(|v| v.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match message.get("role").and_then(|r| r.as_str()) {
+            Some("user") => history.push(json!({"role": "user", "content": text})),
+            Some("assistant") => {
+                let calls: Vec<&Value> = items
+                    .iter()
+                    .filter(|i| item_type(i) == Some("tool_call"))
+                    .collect();
+                let mut assistant = json!({"role": "assistant", "content": text});
+                if !calls.is_empty() {
+                    if text.is_empty() {
+                        assistant["content"] = Value::Null;
+                    }
+                    assistant["tool_calls"] = calls
+                        .iter()
+                        .map(|call| {
+                            json!({
+                                "id": call.get("tool_call_id"),
+                                "type": "function",
+                                "function": {
+                                    "name": call.get("tool_name"),
+                                    "arguments": call.get("input").map(as_text).unwrap_or_default(),
+                                },
+                            })
+                        })
+                        .collect();
+                }
+                history.push(assistant);
+                for call in calls {
+                    if let Some(output) = call.get("output").filter(|o| !o.is_null()) {
+                        history.push(json!({
+                            "role": "tool",
+                            "tool_call_id": call.get("tool_call_id"),
+                            "content": as_text(output),
+                        }));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    history
+}
+
+fn thread_message(
+    thread_id: &str,
+    role: &str,
+    content: Vec<Value>,
+    assistant_id: Option<&str>,
+    now_ms: i64,
+) -> Value {
+    let mut message = json!({
+        "id": Uuid::new_v4().to_string(),
+        "object": "thread.message",
+        "thread_id": thread_id,
+        "role": role,
+        "content": content,
+        "status": "ready",
+        "created_at": now_ms,
+        "completed_at": now_ms,
+        "metadata": {},
+    });
+    if let Some(assistant_id) = assistant_id {
+        message["assistant_id"] = json!(assistant_id);
+    }
+    message
+}
+
+fn assistant_items(message: &Value) -> Vec<Value> {
+    let mut items = Vec::new();
+    if let Some(reasoning) = message
+        .get("reasoning_content")
+        .and_then(|r| r.as_str())
+        .filter(|r| !r.is_empty())
+    {
+        items.push(text_item("reasoning", reasoning));
+    }
+    let text = message_text(message.get("content").unwrap_or(&Value::Null));
+    if !text.is_empty() {
+        items.push(text_item("text", &text));
+    }
+    for call in message
+        .get("tool_calls")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let function = call.get("function");
+        let arguments = function
+            .and_then(|f| f.get("arguments"))
+            .and_then(|a| a.as_str())
+            .unwrap_or("{}");
+        items.push(json!({
+            "type": "tool_call",
+            "tool_call_id": call.get("id"),
+            "tool_name": function.and_then(|f| f.get("name")),
+            "input": serde_json::from_str::<Value>(arguments).unwrap_or_else(|_| json!(arguments)),
+        }));
+    }
+    items
+}
+
+/// Add a run's chat messages to a stored thread. Returns the index of the
+/// first stored message that changed.
+pub fn fold_run(
+    stored: &mut Vec<Value>,
+    run: &[Value],
+    thread_id: &str,
+    assistant_id: Option<&str>,
+    now_ms: i64,
+) -> usize {
+    let mut first_changed = stored.len();
+    for message in run {
+        let continues_assistant = stored
+            .last()
+            .is_some_and(|m| m.get("role").and_then(|r| r.as_str()) == Some("assistant"));
+        match message.get("role").and_then(|r| r.as_str()) {
+            Some("user") => {
+                let text = message_text(message.get("content").unwrap_or(&Value::Null));
+                stored.push(thread_message(
+                    thread_id,
+                    "user",
+                    vec![text_item("text", &text)],
+                    assistant_id,
+                    now_ms,
+                ));
+            }
+            Some("assistant") if continues_assistant => {
+                first_changed = first_changed.min(stored.len() - 1);
+                let last = stored.last_mut().expect("checked above");
+                if let Some(content) = last.get_mut("content").and_then(|c| c.as_array_mut()) {
+                    content.extend(assistant_items(message));
+                }
+                last["completed_at"] = json!(now_ms);
+            }
+            Some("assistant") => {
+                let mut items = assistant_items(message);
+                if items.is_empty() {
+                    items.push(text_item("text", ""));
+                }
+                stored.push(thread_message(
+                    thread_id,
+                    "assistant",
+                    items,
+                    assistant_id,
+                    now_ms,
+                ));
+            }
+            Some("tool") if continues_assistant => {
+                let id = message.get("tool_call_id");
+                let index = stored.len() - 1;
+                let call = stored[index]
+                    .get_mut("content")
+                    .and_then(|c| c.as_array_mut())
+                    .and_then(|items| {
+                        items.iter_mut().find(|i| {
+                            item_type(i) == Some("tool_call") && i.get("tool_call_id") == id
+                        })
+                    });
+                if let Some(call) = call {
+                    call["output"] =
+                        json!(message_text(message.get("content").unwrap_or(&Value::Null)));
+                    first_changed = first_changed.min(index);
+                }
+            }
+            // System prompts belong to the assistant, not the thread.
+            _ => {}
+        }
+    }
+    first_changed
+}
+
+/// Metadata for a thread created for a run, titled after its first user
+/// message.
+pub fn new_thread(
+    thread_id: &str,
+    run: &[Value],
+    model: &str,
+    engine: &str,
+    assistant_id: Option<&str>,
+    now_secs: i64,
+) -> Value {
+    let first_user = run
+        .iter()
+        .find(|m| m.get("role").and_then(|r| r.as_str()) == Some("user"))
+        .map(|m| message_text(m.get("content").unwrap_or(&Value::Null)))
+        .unwrap_or_default();
+    let title = match first_user.trim() {
+        "" => "Orchestration".to_string(),
+        text => text.chars().take(TITLE_CHARS).collect(),
+    };
+    let mut assistant = match assistant_id {
+        Some(id) => json!({"id": id, "name": id}),
+        None => json!({"id": "model-only", "name": "Model"}),
+    };
+    assistant["model"] = json!({"id": model, "engine": engine});
+    json!({
+        "id": thread_id,
+        "object": "thread",
+        "title": title,
+        "assistants": [assistant],
+        "created": now_secs,
+        "updated": now_secs,
+        "metadata": {"source": "orchestration"},
+    })
+}
+
+async fn stored_thread(data_folder: &Path, thread_id: &str) -> Result<Option<Value>, String> {
+    if should_use_sqlite() {
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        return db::db_get_thread(thread_id).await;
+    }
+
+    let path = get_thread_metadata_path(data_folder, thread_id);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&data)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+async fn stored_messages(data_folder: &Path, thread_id: &str) -> Result<Vec<Value>, String> {
+    if should_use_sqlite() {
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        return db::db_thread_messages(thread_id).await;
+    }
+
+    read_messages_from_file(data_folder, thread_id)
+}
+
+/// The stored messages of a thread a run continues.
+pub async fn load_thread(data_folder: &Path, thread_id: &str) -> Result<Vec<Value>, String> {
+    if stored_thread(data_folder, thread_id).await?.is_none() {
+        return Err(format!("Thread '{thread_id}' not found"));
+    }
+    stored_messages(data_folder, thread_id).await
+}
+
+/// Add a run's messages to its thread, creating the thread for
+/// [`ThreadTarget::New`].
+pub async fn save_run(
+    data_folder: &Path,
+    target: &ThreadTarget,
+    run: &[Value],
+    model: &str,
+    engine: &str,
+    assistant_id: Option<&str>,
+) -> Result<(), String> {
+    let thread_id = target.id();
+    let now = chrono::Utc::now();
+    let lock = get_lock_for_thread(thread_id).await;
+    let _guard = lock.lock().await;
+
+    let mut thread = match target {
+        ThreadTarget::New(_) => {
+            new_thread(thread_id, run, model, engine, assistant_id, now.timestamp())
+        }
+        ThreadTarget::Existing(_) => stored_thread(data_folder, thread_id)
+            .await?
+            .ok_or_else(|| format!("Thread '{thread_id}' not found"))?,
+    };
+    thread["updated"] = json!(now.timestamp());
+    let mut messages = stored_messages(data_folder, thread_id).await?;
+
+    if should_use_sqlite() {
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        {
+            let first_changed = fold_run(
+                &mut messages,
+                run,
+                thread_id,
+                assistant_id,
+                now.timestamp_millis(),
+            );
+            db::db_upsert_thread(&thread).await?;
+            return db::db_upsert_messages(&messages[first_changed..]).await;
+        }
+    }
+
+    fold_run(
+        &mut messages,
+        run,
+        thread_id,
+        assistant_id,
+        now.timestamp_millis(),
+    );
+    ensure_thread_dir_exists(data_folder, thread_id)?;
+    write_messages_to_file(&messages, &get_messages_path(data_folder, thread_id))?;
+    update_thread_metadata(data_folder, thread_id, &thread)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(id: &str, args: &str) -> Value {
+        json!({"id": id, "type": "function", "function": {"name": "search", "arguments": args}})
+    }
+
+    #[test]
+    fn run_folds_into_one_assistant_message_per_reply() {
+        let run = vec![
+            json!({"role": "system", "content": "be brief"}),
+            json!({"role": "user", "content": "find rust"}),
+            json!({"role": "assistant", "content": null, "tool_calls": [call("c1", "{\"q\":\"rust\"}")]}),
+            json!({"role": "tool", "tool_call_id": "c1", "content": "rust-lang.org"}),
+            json!({"role": "assistant", "content": "Found it.", "reasoning_content": "easy"}),
+        ];
+        let mut stored = Vec::new();
+        assert_eq!(fold_run(&mut stored, &run, "t1", None, 7), 0);
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0]["role"], "user");
+        assert_eq!(stored[0]["content"][0]["text"]["value"], "find rust");
+        let items = stored[1]["content"].as_array().unwrap();
+        assert_eq!(
+            items[0],
+            json!({"type": "tool_call", "tool_call_id": "c1", "tool_name": "search", "input": {"q": "rust"}, "output": "rust-lang.org"})
+        );
+        assert_eq!(items[1]["type"], "reasoning");
+        assert_eq!(items[2]["text"]["value"], "Found it.");
+        assert_eq!(stored[1]["thread_id"], "t1");
+    }
+
+    #[test]
+    fn held_calls_round_trip_through_the_thread() {
+        let run = vec![
+            json!({"role": "user", "content": "search twice"}),
+            json!({"role": "assistant", "content": "", "tool_calls": [call("c1", "{}"), call("c2", "{}")]}),
+            json!({"role": "tool", "tool_call_id": "c1", "content": "one"}),
+        ];
+        let mut stored = Vec::new();
+        fold_run(&mut stored, &run, "t1", None, 1);
+
+        let history = thread_history(&stored);
+        assert_eq!(history.len(), 3);
+        assert!(history[1]["content"].is_null());
+        assert_eq!(history[1]["tool_calls"][1]["id"], "c2");
+        assert_eq!(history[1]["tool_calls"][0]["function"]["arguments"], "{}");
+        assert_eq!(
+            history[2],
+            json!({"role": "tool", "tool_call_id": "c1", "content": "one"})
+        );
+
+        // Resuming answers c2 and finishes the same reply.
+        let resumed = vec![
+            json!({"role": "tool", "tool_call_id": "c2", "content": "two"}),
+            json!({"role": "assistant", "content": "done"}),
+        ];
+        assert_eq!(fold_run(&mut stored, &resumed, "t1", None, 2), 1);
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[1]["content"][1]["output"], "two");
+        assert_eq!(stored[1]["content"][2]["text"]["value"], "done");
+    }
+
+    #[tokio::test]
+    async fn saved_runs_continue_the_same_thread() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = thread_target(&json!({"persist": true})).unwrap().unwrap();
+        let first = [
+            json!({"role": "user", "content": "hello"}),
+            json!({"role": "assistant", "content": "hi"}),
+        ];
+        save_run(dir.path(), &target, &first, "m", "llamacpp", None)
+            .await
+            .unwrap();
+
+        let id = target.id().to_string();
+        let continued = thread_target(&json!({"thread_id": id})).unwrap().unwrap();
+        assert_eq!(continued, ThreadTarget::Existing(id.clone()));
+        let mut body = json!({"messages": [{"role": "user", "content": "again"}]});
+        prepend_history(
+            &mut body,
+            thread_history(&load_thread(dir.path(), &id).await.unwrap()),
+        );
+        assert_eq!(body["messages"][1]["content"], "hi");
+        assert_eq!(body["messages"][2]["content"], "again");
+
+        let second = [
+            json!({"role": "user", "content": "again"}),
+            json!({"role": "assistant", "content": "hi again"}),
+        ];
+        save_run(dir.path(), &continued, &second, "m", "llamacpp", None)
+            .await
+            .unwrap();
+        let stored = load_thread(dir.path(), &id).await.unwrap();
+        assert_eq!(stored.len(), 4);
+        assert_eq!(stored[3]["content"][0]["text"]["value"], "hi again");
+
+        assert!(load_thread(dir.path(), "missing").await.is_err());
+        assert!(thread_target(&json!({"thread_id": "../etc"})).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Prompt-cache hints for local llama.cpp models.
+//!
+//! Clients mark reusable prefixes with Anthropic-style `cache_control`
+//! breakpoints, on content parts, messages, tools or system blocks. Remote
+//! Anthropic providers get them as they are. llama-server has no breakpoints,
+//! but it keeps each slot's last prompt in its KV cache. A hinted request
+//! therefore asks for `cache_prompt` and is pinned (`id_slot`) to a slot chosen
+//! from a hash of its cached prefix. Requests sharing a prefix, from any
+//! session or client, land on the slot that already holds it.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use serde_json::{json, Value};
+
+/// Whether `v` carries a `cache_control` marker at any depth.
+fn has_marker(v: &Value) -> bool {
+    match v {
+        Value::Object(map) => map.contains_key("cache_control") || map.values().any(has_marker),
+        Value::Array(items) => items.iter().any(has_marker),
+        _ => false,
+    }
+}
+
+fn strip_markers(v: &mut Value) {
+    match v {
+        Value::Object(map) => {
+            map.remove("cache_control");
+            map.values_mut().for_each(strip_markers);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_markers),
+        _ => {}
+    }
+}
+
+/// Whether a chat/completions or `/messages` body marks any cache breakpoint.
+pub fn has_cache_hints(body: &Value) -> bool {
+    ["system", "messages", "tools"]
+        .iter()
+        .filter_map(|key| body.get(*key))
+        .any(has_marker)
+}
+
+/// A hash of the prompt up to its last breakpoint: the model, tools, system
+/// prompt and messages through the last one holding a marker. Markers
+/// themselves are left out, so a moved breakpoint over the same text still
+/// matches.
+pub fn cached_prefix_key(body: &Value) -> Option<u64> {
+    let messages = body.get("messages").and_then(|m| m.as_array());
+    let last_marked = messages.and_then(|m| m.iter().rposition(has_marker));
+    let marked_elsewhere = ["system", "tools"]
+        .iter()
+        .filter_map(|key| body.get(*key))
+        .any(has_marker);
+    if last_marked.is_none() && !marked_elsewhere {
+        return None;
+    }
+    let mut prefix = json!({
+        "model": body.get("model"),
+        "tools": body.get("tools"),
+        "system": body.get("system"),
+        "messages": messages.map(|m| &m[..last_marked.map_or(0, |i| i + 1)]),
+    });
+    strip_markers(&mut prefix);
+    let mut hasher = DefaultHasher::new();
+    prefix.to_string().hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Turn a hinted body for a local llama.cpp model into llama-server's
+/// terms: `cache_prompt`, an `id_slot` out of `total_slots` when the model
+/// has more than one, and no `cache_control` markers left. Bodies without
+/// hints, or already naming a slot, keep their own choice.
+pub fn apply_local_cache_hints(body: &mut Value, total_slots: Option<u64>) {
+    let Some(key) = cached_prefix_key(body) else {
+        return;
+    };
+    for field in ["system", "messages", "tools"] {
+        if let Some(v) = body.get_mut(field) {
+            strip_markers(v);
+        }
+    }
+    let Some(obj) = body.as_object_mut() else {
+        return;
+    };
+    obj.entry("cache_prompt").or_insert(json!(true));
+    if let Some(slots) = total_slots.filter(|n| *n > 1) {
+        obj.entry("id_slot").or_insert(json!(key % slots));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(question: &str) -> Value {
+        json!({
+            "model": "m",
+            "messages": [
+                {"role": "system", "content": [
+                    {"type": "text", "text": "long rules", "cache_control": {"type": "ephemeral"}}
+                ]},
+                {"role": "user", "content": question}
+            ]
+        })
+    }
+
+    #[test]
+    fn prefix_key_covers_only_the_marked_prefix() {
+        assert!(has_cache_hints(&body("a")));
+        assert_eq!(cached_prefix_key(&body("a")), cached_prefix_key(&body("b")));
+        let mut other = body("a");
+        other["messages"][0]["content"][0]["text"] = json!("other rules");
+        assert_ne!(cached_prefix_key(&body("a")), cached_prefix_key(&other));
+        assert_eq!(
+            cached_prefix_key(&json!({"messages": [{"role": "user", "content": "x"}]})),
+            None
+        );
+    }
+
+    #[test]
+    fn local_hints_pin_a_slot_and_drop_markers() {
+        let mut hinted = body("a");
+        apply_local_cache_hints(&mut hinted, Some(4));
+        assert_eq!(hinted["cache_prompt"], json!(true));
+        assert!(hinted["id_slot"].as_u64().unwrap() < 4);
+        assert!(!has_cache_hints(&hinted));
+
+        let mut single = body("a");
+        apply_local_cache_hints(&mut single, Some(1));
+        assert!(single.get("id_slot").is_none());
+
+        let mut plain = json!({"messages": [{"role": "user", "content": "x"}]});
+        apply_local_cache_hints(&mut plain, Some(4));
+        assert!(plain.get("cache_prompt").is_none());
+    }
+}
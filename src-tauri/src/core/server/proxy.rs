@@ -10,22 +10,67 @@ use hyper_util::rt::TokioIo;
 use jan_utils::{extract_host_from_origin, is_cors_header, is_valid_host, remove_prefix};
 use reqwest::Client;
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use std::fs;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tauri_plugin_llamacpp::state::LlamacppState;
 use tokio::sync::Mutex;
 
-use crate::core::server::converters::{converter_for, SseAccumulator, StreamState, UpstreamConverter};
+use crate::core::server::access_log::{
+    AccessLog, AccessLogOptions, AccessRecord, Redactor, CAPTURE_LIMIT,
+};
+use crate::core::server::admission::{
+    AdmissionController, AdmissionOptions, AdmissionPermit, Overloaded, Priority, PRIORITY_HEADER,
+};
+use crate::core::server::batches::{self, parse_multipart, BatchStore};
+use crate::core::server::client_keys::{ClientKey, ClientKeyStore};
+use crate::core::server::metrics::{self, RouterSnapshot, ServerMetrics};
+use crate::core::server::model_aliases::{AliasPlan, ModelAliasStore};
+use crate::core::server::model_catalog::{self, ModelDescription, ModelFacts};
+use crate::core::server::ollama::{
+    self, ollama_request_to_openai, openai_response_to_ollama, OllamaRoute, OllamaStreamTranslator,
+    NDJSON_CONTENT_TYPE, OLLAMA_COMPAT_VERSION,
+};
+use crate::core::server::orchestration_stream::{self, TurnAssembler};
+use crate::core::server::orchestration_threads::{self, ThreadTarget};
+use crate::core::server::response_cache::{
+    self, bypasses_cache, CachedResponse, ResponseCache, ResponseCacheOptions,
+    RESPONSE_CACHE_HEADER,
+};
+use crate::core::server::media;
+use crate::core::server::prompt_cache;
+use crate::core::server::structured_output;
+use crate::core::server::tls::{self, TlsOptions};
+use crate::core::server::tokens;
+use crate::core::server::tool_emulation::ToolEmulationConverter;
+use crate::core::server::converters::{
+    converter_for, AnthropicMessagesConverter, ChatStreamTranslator, SseAccumulator, SseEvent,
+    StreamState, UpstreamConverter,
+};
+use crate::core::server::responses::{
+    chat_response_to_responses, responses_request_to_chat, ResponsesStreamTranslator,
+};
+use crate::core::server::usage::{
+    is_metered_route, TokenCounts, UsageEvent, UsageLedger, UsageQuery, UsageScanner,
+};
 use crate::core::{
-    mcp::models::McpSettings,
+    mcp::{
+        approval::{tool_approvals, ApprovalMode, ApprovalRequest, Verdict},
+        helpers::with_timeout,
+        models::{McpSettings, ToolApproval},
+        progress::subscribe_progress,
+        resources,
+    },
     state::{ProviderConfig, ServerHandle, SharedMcpServers},
 };
 
 type ResBody = BoxBody<Bytes, Infallible>;
+/// Request body handed to `proxy_request`: the live `Incoming` stream, or a
+/// buffer when `serve_request` already had to read it.
+type ReqBody = BoxBody<Bytes, hyper::Error>;
 
 fn full<B: Into<Bytes>>(chunk: B) -> ResBody {
     Full::new(chunk.into()).boxed()
@@ -38,12 +83,29 @@ fn empty() -> ResBody {
 /// hyper 1.0 dropped `Body::channel`; this mpsc-backed `StreamBody` restores a
 /// sender handle for the streaming/passthrough paths. `send_data` returns
 /// `Result<(), ()>` so existing `.is_err()` disconnect checks compile unchanged.
+#[derive(Clone)]
 struct BodySender(tokio::sync::mpsc::Sender<Result<Frame<Bytes>, Infallible>>);
 
 impl BodySender {
     async fn send_data(&mut self, data: Bytes) -> Result<(), ()> {
         self.0.send(Ok(Frame::data(data))).await.map_err(|_| ())
     }
+
+    /// Await `fut` unless the client goes away first (hyper drops the body
+    /// when the connection closes), in which case `None` is returned.
+    ///
+    /// Forwarders wait on the upstream through this so a disconnect is seen
+    /// immediately rather than at the next send. Dropping the upstream stream
+    /// closes that connection, which is what makes llama-server cancel the
+    /// generation and free its slot; each tee layer dropping its inner body
+    /// passes the disconnect on to the layer below.
+    async fn unless_closed<F: std::future::Future>(&self, fut: F) -> Option<F::Output> {
+        tokio::select! {
+            biased;
+            out = fut => Some(out),
+            _ = self.0.closed() => None,
+        }
+    }
 }
 
 fn body_channel() -> (BodySender, ResBody) {
@@ -252,14 +314,18 @@ pub(crate) fn transform_anthropic_to_openai(body: &serde_json::Value) -> Option<
                     .cloned()
                     .unwrap_or(serde_json::json!({}));
 
-                Some(serde_json::json!({
+                let mut openai_tool = serde_json::json!({
                     "type": "function",
                     "function": {
                         "name": name,
                         "description": description,
                         "parameters": input_schema
                     }
-                }))
+                });
+                if let Some(cache) = tool.get("cache_control") {
+                    openai_tool["cache_control"] = cache.clone();
+                }
+                Some(openai_tool)
             })
             .collect();
 
@@ -284,6 +350,20 @@ pub(crate) fn transform_anthropic_to_openai(body: &serde_json::Value) -> Option<
     if let Some(stop) = body.get("stop_sequences") {
         result["stop"] = stop.clone();
     }
+    if let Some(choice) = body.get("tool_choice") {
+        let mapped = match choice.get("type").and_then(|t| t.as_str()) {
+            Some("auto") => Some(serde_json::json!("auto")),
+            Some("any") => Some(serde_json::json!("required")),
+            Some("none") => Some(serde_json::json!("none")),
+            Some("tool") => choice.get("name").map(|name| {
+                serde_json::json!({"type": "function", "function": {"name": name}})
+            }),
+            _ => None,
+        };
+        if let Some(mapped) = mapped {
+            result["tool_choice"] = mapped;
+        }
+    }
 
     Some(result)
 }
@@ -404,7 +484,17 @@ pub(crate) fn convert_messages(
                 .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
                 .collect::<Vec<_>>()
                 .join("\n");
-            if !text.is_empty() {
+            // Cache breakpoints survive only in the part form.
+            if blocks.iter().any(|b| b.get("cache_control").is_some()) {
+                let mut parts = Vec::new();
+                for block in blocks {
+                    convert_media_block(block, &mut parts);
+                }
+                openai_messages.push(serde_json::json!({
+                    "role": "system",
+                    "content": parts
+                }));
+            } else if !text.is_empty() {
                 openai_messages.push(serde_json::json!({
                     "role": "system",
                     "content": text
@@ -443,14 +533,6 @@ pub(crate) fn convert_messages(
                 for block in content_array {
                     let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or("");
                     match block_type {
-                        "text" => {
-                            if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-                                text_parts.push(serde_json::json!({
-                                    "type": "text",
-                                    "text": text
-                                }));
-                            }
-                        }
                         "tool_use" => {
                             if let (Some(id), Some(name), Some(input)) = (
                                 block.get("id").and_then(|v| v.as_str()),
@@ -493,7 +575,7 @@ pub(crate) fn convert_messages(
             "user" => {
                 // Separate tool_result blocks from regular content
                 let mut text_parts: Vec<serde_json::Value> = Vec::new();
-                let mut tool_results: Vec<(String, String)> = Vec::new();
+                let mut tool_results: Vec<(String, String, Option<serde_json::Value>)> = Vec::new();
 
                 for block in content_array {
                     let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or("");
@@ -505,15 +587,8 @@ pub(crate) fn convert_messages(
                                 .unwrap_or("")
                                 .to_string();
                             let result_content = extract_tool_result_content(block.get("content"));
-                            tool_results.push((tool_use_id, result_content));
-                        }
-                        "text" => {
-                            if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-                                text_parts.push(serde_json::json!({
-                                    "type": "text",
-                                    "text": text
-                                }));
-                            }
+                            let cache = block.get("cache_control").cloned();
+                            tool_results.push((tool_use_id, result_content, cache));
                         }
                         _ => {
                             convert_media_block(block, &mut text_parts);
@@ -522,12 +597,16 @@ pub(crate) fn convert_messages(
                 }
 
                 // Tool results become role:"tool" messages (must come before user text)
-                for (tool_call_id, result) in tool_results {
-                    openai_messages.push(serde_json::json!({
+                for (tool_call_id, result, cache) in tool_results {
+                    let mut tool_message = serde_json::json!({
                         "role": "tool",
                         "tool_call_id": tool_call_id,
                         "content": result
-                    }));
+                    });
+                    if let Some(cache) = cache {
+                        tool_message["cache_control"] = cache;
+                    }
+                    openai_messages.push(tool_message);
                 }
 
                 // Remaining user content
@@ -556,11 +635,15 @@ pub(crate) fn convert_messages(
     Some(serde_json::Value::Array(openai_messages))
 }
 
-/// Convert text parts to OpenAI content value (string for single text, array for mixed)
+/// Convert text parts to OpenAI content value (string for single text, array for mixed).
+/// A part carrying a `cache_control` breakpoint keeps the array form.
 pub(crate) fn text_parts_to_content(parts: &[serde_json::Value]) -> serde_json::Value {
     if parts.is_empty() {
         serde_json::Value::String(String::new())
-    } else if parts.len() == 1 && parts[0].get("type").and_then(|t| t.as_str()) == Some("text") {
+    } else if parts.len() == 1
+        && parts[0].get("type").and_then(|t| t.as_str()) == Some("text")
+        && parts[0].get("cache_control").is_none()
+    {
         serde_json::Value::String(
             parts[0]
                 .get("text")
@@ -573,26 +656,19 @@ pub(crate) fn text_parts_to_content(parts: &[serde_json::Value]) -> serde_json::
     }
 }
 
-/// Convert image/media blocks to OpenAI format
+/// Convert text, image and document blocks to OpenAI parts, keeping any
+/// `cache_control`.
 pub(crate) fn convert_media_block(block: &serde_json::Value, parts: &mut Vec<serde_json::Value>) {
-    let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or("");
-    if block_type == "image" {
-        if let Some(source) = block.get("source") {
-            if let (Some(data), Some(media_type)) = (
-                source.get("data").and_then(|v| v.as_str()),
-                source
-                    .get("media_type")
-                    .or(block.get("media_type"))
-                    .and_then(|v| v.as_str()),
-            ) {
-                parts.push(serde_json::json!({
-                    "type": "image_url",
-                    "image_url": {
-                        "url": format!("data:{media_type};base64,{data}")
-                    }
-                }));
-            }
-        }
+    let pushed = parts.len();
+    push_media_part(block, parts);
+    if let (Some(cache), Some(part)) = (block.get("cache_control"), parts.get_mut(pushed)) {
+        part["cache_control"] = cache.clone();
+    }
+}
+
+fn push_media_part(block: &serde_json::Value, parts: &mut Vec<serde_json::Value>) {
+    if let Some(attachment) = media::from_anthropic_block(block) {
+        parts.extend(media::to_chat_part(&attachment));
     } else if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
         parts.push(serde_json::json!({
             "type": "text",
@@ -696,13 +772,285 @@ pub(crate) fn transform_openai_response_to_anthropic(response: &serde_json::Valu
         "model": response.get("model").unwrap_or(&serde_json::json!("")).clone(),
         "stop_reason": stop_reason,
         "stop_sequence": serde_json::Value::Null,
-        "usage": response.get("usage").cloned().unwrap_or(serde_json::json!({
-            "input_tokens": 0,
-            "output_tokens": 0
-        }))
+        "usage": anthropic_usage_from_chat(response.get("usage"))
     })
 }
 
+/// Map chat/completions `usage` (prompt/completion tokens) to Anthropic's
+/// input/output token counts. Already-Anthropic usage passes through.
+fn anthropic_usage_from_chat(usage: Option<&serde_json::Value>) -> serde_json::Value {
+    match usage {
+        Some(u) if u.get("prompt_tokens").is_some() || u.get("completion_tokens").is_some() => {
+            let (read, creation) = chat_cache_tokens(u);
+            let prompt = u.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+            let mut out = serde_json::json!({
+                // Anthropic counts cache reads and writes apart from input.
+                "input_tokens": prompt.saturating_sub(read.unwrap_or(0) + creation.unwrap_or(0)),
+                "output_tokens": u.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+            });
+            if let Some(read) = read {
+                out["cache_read_input_tokens"] = serde_json::json!(read);
+            }
+            if let Some(creation) = creation {
+                out["cache_creation_input_tokens"] = serde_json::json!(creation);
+            }
+            out
+        }
+        Some(u) if u.is_object() => u.clone(),
+        _ => serde_json::json!({"input_tokens": 0, "output_tokens": 0}),
+    }
+}
+
+/// Prompt-cache reads and writes in chat/completions `usage`, from the
+/// Anthropic fields a converter keeps or OpenAI's `cached_tokens`.
+fn chat_cache_tokens(usage: &serde_json::Value) -> (Option<u64>, Option<u64>) {
+    let read = usage
+        .get("cache_read_input_tokens")
+        .or_else(|| {
+            usage
+                .get("prompt_tokens_details")
+                .and_then(|d| d.get("cached_tokens"))
+        })
+        .and_then(|v| v.as_u64());
+    let creation = usage
+        .get("cache_creation_input_tokens")
+        .and_then(|v| v.as_u64());
+    (read, creation)
+}
+
+/// Translates a chat/completions chunk stream into Anthropic `/messages` SSE
+/// events. `message_delta` / `message_stop` are held until `[DONE]` (or the
+/// end of the stream) so a trailing usage-only chunk still reports real token
+/// counts; without one, output tokens are estimated from the streamed text.
+#[derive(Debug, Default)]
+pub(crate) struct AnthropicStreamTranslator {
+    started: bool,
+    finished: bool,
+    text_block_index: Option<usize>,
+    /// OpenAI tool-call index -> Anthropic content block index, for open blocks.
+    tool_blocks: HashMap<usize, usize>,
+    next_block_index: usize,
+    saw_tool_use: bool,
+    accumulated_content: String,
+    stop_reason: Option<String>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    cache_read_tokens: Option<u64>,
+    cache_creation_tokens: Option<u64>,
+}
+
+impl AnthropicStreamTranslator {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn start(&mut self, chunk: &serde_json::Value, role: &str, events: &mut Vec<serde_json::Value>) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        events.push(serde_json::json!({
+            "type": "message_start",
+            "message": {
+                "id": chunk.get("id").cloned().unwrap_or(serde_json::json!("")),
+                "type": "message",
+                "role": role,
+                "content": [],
+                "model": chunk.get("model").cloned().unwrap_or(serde_json::json!("")),
+                "stop_reason": serde_json::Value::Null,
+                "stop_sequence": serde_json::Value::Null,
+                "usage": {
+                    "input_tokens": self.input_tokens.unwrap_or(0),
+                    "output_tokens": 0
+                }
+            }
+        }));
+    }
+
+    fn close_text(&mut self, events: &mut Vec<serde_json::Value>) {
+        if let Some(idx) = self.text_block_index.take() {
+            events.push(serde_json::json!({"type": "content_block_stop", "index": idx}));
+        }
+    }
+
+    fn close_blocks(&mut self, events: &mut Vec<serde_json::Value>) {
+        self.close_text(events);
+        let mut tool_indices: Vec<usize> = self.tool_blocks.drain().map(|(_, idx)| idx).collect();
+        tool_indices.sort();
+        for idx in tool_indices {
+            events.push(serde_json::json!({"type": "content_block_stop", "index": idx}));
+        }
+    }
+}
+
+impl ChatStreamTranslator for AnthropicStreamTranslator {
+    fn push_chunk(&mut self, chunk: &serde_json::Value) -> Vec<serde_json::Value> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            if let Some(n) = usage.get("prompt_tokens").and_then(|v| v.as_u64()) {
+                self.input_tokens = Some(n);
+            }
+            if let Some(n) = usage.get("completion_tokens").and_then(|v| v.as_u64()) {
+                self.output_tokens = Some(n);
+            }
+            let (read, creation) = chat_cache_tokens(usage);
+            self.cache_read_tokens = read.or(self.cache_read_tokens);
+            self.cache_creation_tokens = creation.or(self.cache_creation_tokens);
+        }
+
+        let choice = chunk
+            .get("choices")
+            .and_then(|c| c.as_array())
+            .and_then(|c| c.first());
+        let Some(delta) = choice.and_then(|c| c.get("delta")) else {
+            return events;
+        };
+        let role = delta
+            .get("role")
+            .and_then(|r| r.as_str())
+            .unwrap_or("assistant");
+        self.start(chunk, role, &mut events);
+
+        if let Some(text) = delta.get("content").and_then(|c| c.as_str()) {
+            if !text.is_empty() {
+                let idx = match self.text_block_index {
+                    Some(idx) => idx,
+                    None => {
+                        let idx = self.next_block_index;
+                        self.next_block_index += 1;
+                        self.text_block_index = Some(idx);
+                        events.push(serde_json::json!({
+                            "type": "content_block_start",
+                            "index": idx,
+                            "content_block": { "type": "text", "text": "" }
+                        }));
+                        idx
+                    }
+                };
+                self.accumulated_content.push_str(text);
+                events.push(serde_json::json!({
+                    "type": "content_block_delta",
+                    "index": idx,
+                    "delta": { "type": "text_delta", "text": text }
+                }));
+            }
+        }
+
+        if let Some(tool_calls) = delta.get("tool_calls").and_then(|tc| tc.as_array()) {
+            // Anthropic blocks are sequential: text closes before tool_use opens.
+            self.close_text(&mut events);
+            for tc in tool_calls {
+                let tc_index = tc.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
+                if let Some(id) = tc.get("id").and_then(|v| v.as_str()) {
+                    let name = tc
+                        .get("function")
+                        .and_then(|f| f.get("name"))
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("");
+                    let idx = self.next_block_index;
+                    self.next_block_index += 1;
+                    self.tool_blocks.insert(tc_index, idx);
+                    self.saw_tool_use = true;
+                    events.push(serde_json::json!({
+                        "type": "content_block_start",
+                        "index": idx,
+                        "content_block": {
+                            "type": "tool_use",
+                            "id": id,
+                            "name": name,
+                            "input": {}
+                        }
+                    }));
+                }
+                if let Some(args) = tc
+                    .get("function")
+                    .and_then(|f| f.get("arguments"))
+                    .and_then(|a| a.as_str())
+                {
+                    if let Some(&idx) = self.tool_blocks.get(&tc_index).filter(|_| !args.is_empty()) {
+                        events.push(serde_json::json!({
+                            "type": "content_block_delta",
+                            "index": idx,
+                            "delta": { "type": "input_json_delta", "partial_json": args }
+                        }));
+                    }
+                }
+            }
+        }
+
+        if let Some(reason) = choice
+            .and_then(|c| c.get("finish_reason"))
+            .and_then(|fr| fr.as_str())
+        {
+            self.close_blocks(&mut events);
+            self.stop_reason = Some(
+                match reason {
+                    "stop" => "end_turn",
+                    "length" => "max_tokens",
+                    "tool_calls" => "tool_use",
+                    other => other,
+                }
+                .to_string(),
+            );
+        }
+        events
+    }
+
+    fn finish(&mut self) -> Vec<serde_json::Value> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+        self.start(&serde_json::Value::Null, "assistant", &mut events);
+        self.close_blocks(&mut events);
+        self.finished = true;
+
+        let stop_reason = self.stop_reason.clone().unwrap_or_else(|| {
+            if self.saw_tool_use { "tool_use" } else { "end_turn" }.to_string()
+        });
+        let output_tokens = self
+            .output_tokens
+            .unwrap_or_else(|| self.accumulated_content.split_whitespace().count() as u64);
+        let mut usage = serde_json::json!({ "output_tokens": output_tokens });
+        // Cache counts usually arrive after `message_start`; Anthropic repeats
+        // the input side here cumulatively.
+        if self.cache_read_tokens.is_some() || self.cache_creation_tokens.is_some() {
+            let read = self.cache_read_tokens.unwrap_or(0);
+            let creation = self.cache_creation_tokens.unwrap_or(0);
+            usage["input_tokens"] = serde_json::json!(self
+                .input_tokens
+                .unwrap_or(0)
+                .saturating_sub(read + creation));
+            usage["cache_read_input_tokens"] = serde_json::json!(read);
+            usage["cache_creation_input_tokens"] = serde_json::json!(creation);
+        }
+        events.push(serde_json::json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": stop_reason,
+                "stop_sequence": serde_json::Value::Null
+            },
+            "usage": usage
+        }));
+        events.push(serde_json::json!({"type": "message_stop"}));
+        events
+    }
+
+    fn fail(&mut self, message: &str) -> Vec<serde_json::Value> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.finished = true;
+        vec![serde_json::json!({
+            "type": "error",
+            "error": { "type": "api_error", "message": message }
+        })]
+    }
+}
+
 /// Configuration for the proxy server
 #[derive(Clone)]
 pub struct ProxyConfig {
@@ -712,6 +1060,15 @@ pub struct ProxyConfig {
     pub host: String,
     pub port: u16,
     pub enable_server_tool_execution: bool,
+    /// Serve Prometheus metrics at `/metrics`.
+    pub enable_metrics: bool,
+    /// Serve the Ollama-compatible `/api/*` routes.
+    pub enable_ollama_api: bool,
+    /// Emulate tool calling in the prompt for local models whose chat
+    /// template has no tool support.
+    pub enable_tool_emulation: bool,
+    /// Connections are TLS-terminated (advertise `https` URLs).
+    pub tls: bool,
 }
 
 /// Determines the final destination path based on the original request path
@@ -719,6 +1076,7 @@ pub fn get_destination_path(original_path: &str, prefix: &str) -> String {
     remove_prefix(original_path, prefix)
 }
 
+use crate::core::mcp::lockfile::is_process_alive;
 use crate::core::server::MlxBackendSession;
 
 use rmcp::model::{CallToolRequestParam, CallToolResult};
@@ -765,20 +1123,84 @@ pub(crate) fn parse_openai_messages(messages: &serde_json::Value) -> Result<Vec<
             .get("role")
             .and_then(|v| v.as_str())
             .ok_or("Each message must include a string 'role'")?;
-        let content = msg
-            .get("content")
-            .and_then(|v| v.as_str())
-            .ok_or("Each message must include 'content' as a string")?;
+        let tool_calls = msg.get("tool_calls").filter(|v| v.is_array());
+        let content = match msg.get("content") {
+            Some(serde_json::Value::String(text)) => serde_json::json!(text),
+            // An assistant turn that only calls tools, as an orchestration
+            // held for approval hands it back.
+            None | Some(serde_json::Value::Null) if tool_calls.is_some() => serde_json::Value::Null,
+            _ => return Err("Each message must include 'content' as a string".to_string()),
+        };
 
         // Keep upstream format minimal and predictable.
-        out.push(serde_json::json!({
+        let mut message = serde_json::json!({
             "role": role,
             "content": content
-        }));
+        });
+        if let Some(tool_calls) = tool_calls {
+            message["tool_calls"] = tool_calls.clone();
+        }
+        if let Some(id) = msg.get("tool_call_id").and_then(|v| v.as_str()) {
+            message["tool_call_id"] = serde_json::json!(id);
+        }
+        out.push(message);
     }
     Ok(out)
 }
 
+/// Tool calls of the last assistant turn that have no tool result yet, when
+/// only tool results follow it: the calls a held orchestration resumes with.
+pub(crate) fn unanswered_tool_calls(messages: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    fn role(message: &serde_json::Value) -> Option<&str> {
+        message.get("role").and_then(|r| r.as_str())
+    }
+    let Some(last_assistant) = messages.iter().rposition(|m| role(m) == Some("assistant")) else {
+        return Vec::new();
+    };
+    let replies = &messages[last_assistant + 1..];
+    if replies.iter().any(|m| role(m) != Some("tool")) {
+        return Vec::new();
+    }
+    let answered: HashSet<&str> = replies
+        .iter()
+        .filter_map(|m| m.get("tool_call_id").and_then(|v| v.as_str()))
+        .collect();
+    messages[last_assistant]
+        .get("tool_calls")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|call| {
+            call.get("id")
+                .and_then(|v| v.as_str())
+                .is_some_and(|id| !answered.contains(id))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Who approves `ask` tool calls for an `/orchestrations` request: the API
+/// client when its key is set to approve tool calls, the desktop app otherwise.
+pub(crate) fn orchestration_approval_mode(client_key: Option<&ClientKey>) -> ApprovalMode {
+    match client_key {
+        Some(key) if key.settings.approve_tool_calls => ApprovalMode::Client(key.name.clone()),
+        _ => ApprovalMode::Desktop,
+    }
+}
+
+/// The client's `approvals`: held tool call id to approved or denied.
+fn client_tool_approvals(body: &serde_json::Value) -> HashMap<String, bool> {
+    body.get("approvals")
+        .and_then(|v| v.as_object())
+        .map(|approvals| {
+            approvals
+                .iter()
+                .filter_map(|(id, v)| v.as_bool().map(|approved| (id.clone(), approved)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 pub(crate) fn set_system_prompt(messages: &mut Vec<serde_json::Value>, system_prompt: &str) {
     messages.retain(|m| m.get("role").and_then(|r| r.as_str()) != Some("system"));
     messages.insert(
@@ -882,81 +1304,644 @@ async fn router_list_models(llama_state: &LlamacppState, client: &Client) -> Vec
         .unwrap_or_default()
 }
 
+/// Router liveness and per-model status (`status.value` from the router's
+/// `/models`) for the metrics endpoint.
+async fn router_snapshot(llama_state: &LlamacppState, client: &Client) -> RouterSnapshot {
+    let pid = llama_state
+        .router_pid
+        .load(std::sync::atomic::Ordering::SeqCst);
+    let mut snapshot = RouterSnapshot {
+        up: pid != 0 && is_process_alive(pid),
+        models: Vec::new(),
+    };
+    let (url, key) = {
+        let guard = llama_state.router.lock().await;
+        match guard.as_ref() {
+            Some(h) => (
+                format!("http://127.0.0.1:{}/models", h.port),
+                h.api_key.clone(),
+            ),
+            None => return snapshot,
+        }
+    };
+    let json = match client
+        .get(&url)
+        .bearer_auth(key)
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => resp.json::<serde_json::Value>().await.ok(),
+        Ok(_) => None,
+        Err(e) => {
+            log::debug!("Failed to query router /models for metrics: {e}");
+            None
+        }
+    };
+    if let Some(models) = json
+        .as_ref()
+        .and_then(|v| v.get("data"))
+        .and_then(|d| d.as_array())
+    {
+        snapshot.models = models
+            .iter()
+            .filter_map(|m| {
+                let id = m.get("id")?.as_str()?;
+                let status = m
+                    .get("status")
+                    .and_then(|s| s.get("value"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown");
+                Some((id.to_string(), status.to_string()))
+            })
+            .collect();
+    }
+    snapshot
+}
+
 async fn router_first_model(llama_state: &LlamacppState, client: &Client) -> Option<String> {
     router_list_models(llama_state, client).await.into_iter().next()
 }
 
-async fn resolve_upstream_for_model(
-    model_id: &str,
-    provider_configs: Arc<Mutex<HashMap<String, ProviderConfig>>>,
-    llama_state: Arc<LlamacppState>,
-    mlx_sessions: Arc<Mutex<HashMap<i32, MlxBackendSession>>>,
-) -> Result<(String, Vec<String>), String> {
-    let destination_path = "/chat/completions";
+/// POST `body` to one of llama-server's native (non-`/v1`) endpoints through
+/// the router, which picks the model from the body's `model`.
+async fn router_post(
+    client: &Client,
+    llama_state: &LlamacppState,
+    path: &str,
+    body: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let (url, key) = {
+        let guard = llama_state.router.lock().await;
+        match guard.as_ref() {
+            Some(h) => (
+                format!("http://127.0.0.1:{}{path}", h.port),
+                h.api_key.clone(),
+            ),
+            None => return Err("No llama.cpp models are running".to_string()),
+        }
+    };
+    let resp = client
+        .post(&url)
+        .bearer_auth(key)
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| format!("Router request failed: {e}"))?;
+    let status = resp.status();
+    let text = resp.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Router {path} returned HTTP {status}: {text}"));
+    }
+    serde_json::from_str(&text).map_err(|e| format!("Failed to parse router JSON: {e}"))
+}
 
-    let pc = provider_configs.lock().await;
-    let provider_name = pc
-        .iter()
-        .find(|(_, config)| config.models.iter().any(|m| m == model_id))
-        .map(|(_, config)| config.provider.clone())
-        .or_else(|| {
-            if let Some(sep_pos) = model_id.find('/') {
-                let potential_provider: &str = &model_id[..sep_pos];
-                if pc.contains_key(potential_provider) {
-                    return Some(potential_provider.to_string());
+/// Token ids llama-server's tokenizer gives a request for `model`. With
+/// `messages`, the model's chat template renders them (and any `tools`) first,
+/// as a chat request would; otherwise `prompt` is tokenized as it is.
+async fn local_tokenize(
+    client: &Client,
+    llama_state: &LlamacppState,
+    model: &str,
+    body: &serde_json::Value,
+    add_special: bool,
+) -> Result<Vec<u64>, String> {
+    let content = match body.get("messages") {
+        Some(messages) => {
+            let mut template = serde_json::json!({"model": model, "messages": messages});
+            for key in ["tools", "add_generation_prompt", "chat_template_kwargs"] {
+                if let Some(v) = body.get(key) {
+                    template[key] = v.clone();
                 }
             }
-            pc.get(model_id).map(|c| c.provider.clone())
-        });
-    drop(pc);
-
-    if let Some(provider) = provider_name {
-        let pc2 = provider_configs.lock().await;
-        if let Some(provider_cfg) = pc2.get(provider.as_str()).cloned() {
-            let api_url = provider_cfg
-                .base_url
-                .clone()
-                .ok_or_else(|| format!("Missing base_url for provider '{provider}'"))?;
-            let url = format!("{}{}", api_url, destination_path);
-            return Ok((url, provider_cfg.bearer_key_chain()));
+            let rendered = router_post(client, llama_state, "/apply-template", &template).await?;
+            rendered
+                .get("prompt")
+                .and_then(|p| p.as_str())
+                .ok_or("Router /apply-template returned no prompt")?
+                .to_string()
         }
-    }
-
-    let mlx_guard = mlx_sessions.lock().await;
-    if let Some(info) = mlx_guard.values().find(|s| s.info.model_id == model_id) {
-        let target_port = info.info.port;
-        return Ok((
-            format!("http://127.0.0.1:{target_port}/v1{destination_path}"),
-            vec![info.info.api_key.clone()],
-        ));
-    }
-    drop(mlx_guard);
+        None => body
+            .get("prompt")
+            .and_then(|p| p.as_str())
+            .ok_or("Request must include 'prompt' or 'messages'")?
+            .to_string(),
+    };
+    let request = serde_json::json!({
+        "model": model,
+        "content": content,
+        "add_special": add_special,
+        "parse_special": true,
+    });
+    let reply = router_post(client, llama_state, "/tokenize", &request).await?;
+    tokens::token_ids(&reply).ok_or_else(|| "Router /tokenize returned no tokens".to_string())
+}
 
-    if let Some((url, key)) = router_upstream(&llama_state, destination_path).await {
-        return Ok((url, vec![key]));
+/// Relay a `count_tokens` body to an Anthropic provider, trying each key.
+async fn forward_anthropic_count_tokens(
+    client: &Client,
+    base_url: &str,
+    api_keys: &[String],
+    body: &serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let converter = AnthropicMessagesConverter::new();
+    let url = format!("{base_url}/messages/count_tokens");
+    let attempts: Vec<Option<&str>> = if api_keys.is_empty() {
+        vec![None]
+    } else {
+        api_keys.iter().map(|s| Some(s.as_str())).collect()
+    };
+    let mut last = (StatusCode::BAD_GATEWAY, serde_json::Value::Null);
+    for (i, key) in attempts.iter().enumerate() {
+        let mut req = client.post(&url).json(body);
+        for (name, value) in converter.extra_headers() {
+            req = req.header(name, value);
+        }
+        if let Some(key) = key {
+            let (name, value) = converter.auth_header(key);
+            req = req.header(name, value);
+        }
+        let (status, text) = match req.send().await {
+            Ok(resp) => (resp.status(), resp.text().await.unwrap_or_default()),
+            Err(e) => {
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    anthropic_error("api_error", &format!("Upstream request failed: {e}")),
+                )
+            }
+        };
+        last = (
+            status,
+            serde_json::from_str(&text).unwrap_or_else(|_| anthropic_error("api_error", &text)),
+        );
+        if !(http_status_indicates_api_key_retry(status) && i + 1 < attempts.len()) {
+            break;
+        }
     }
+    last
+}
 
-    Err(format!("No upstream session found for model '{model_id}'"))
+fn anthropic_error(kind: &str, message: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "error",
+        "error": { "type": kind, "message": message }
+    })
 }
 
-pub(crate) fn copy_optional_chat_params(from: &serde_json::Value, into: &mut serde_json::Map<String, serde_json::Value>) {
-    for key in [
-        "temperature",
-        "top_p",
-        "top_k",
-        "max_tokens",
-        "stop_sequences",
-        "stop",
-        "frequency_penalty",
-        "presence_penalty",
-    ] {
-        if let Some(v) = from.get(key) {
-            into.insert(key.to_string(), v.clone());
+fn openai_error(message: &str, param: Option<&str>) -> serde_json::Value {
+    serde_json::json!({
+        "error": {
+            "message": message,
+            "type": "invalid_request_error",
+            "param": param,
+            "code": serde_json::Value::Null
         }
-    }
+    })
 }
 
-async fn collect_mcp_openai_tools(
+/// Count the input tokens of an Anthropic `/messages/count_tokens` body:
+/// exactly for local llama.cpp models, natively for Anthropic providers, and
+/// as an estimate for every other backend.
+async fn count_message_tokens(
+    body: &serde_json::Value,
+    client: &Client,
+    provider_configs: &Mutex<HashMap<String, ProviderConfig>>,
+    llama_state: &LlamacppState,
+    mlx_sessions: &Mutex<HashMap<i32, MlxBackendSession>>,
+) -> (StatusCode, serde_json::Value) {
+    let Some(chat_body) = transform_anthropic_to_openai(body) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            anthropic_error(
+                "invalid_request_error",
+                "count_tokens requires 'model' and 'messages'",
+            ),
+        );
+    };
+    let model = chat_body
+        .get("model")
+        .and_then(|m| m.as_str())
+        .unwrap_or("")
+        .to_string();
+    let provider = {
+        let pc = provider_configs.lock().await;
+        find_provider_for_model(&pc, &model).and_then(|name| pc.get(&name).cloned())
+    };
+    let estimate = || {
+        (
+            StatusCode::OK,
+            tokens::count_tokens_reply(tokens::estimate_chat_tokens(&chat_body), true),
+        )
+    };
+    if let Some(provider) = provider {
+        return match (provider.api_type.as_deref(), provider.base_url.as_deref()) {
+            (Some("anthropic"), Some(base_url)) => {
+                forward_anthropic_count_tokens(client, base_url, &provider.bearer_key_chain(), body)
+                    .await
+            }
+            _ => estimate(),
+        };
+    }
+    if mlx_sessions
+        .lock()
+        .await
+        .values()
+        .any(|s| s.info.model_id == model)
+    {
+        return estimate();
+    }
+    match local_tokenize(client, llama_state, &model, &chat_body, true).await {
+        Ok(ids) => (
+            StatusCode::OK,
+            tokens::count_tokens_reply(ids.len() as u64, false),
+        ),
+        Err(e) => (StatusCode::BAD_GATEWAY, anthropic_error("api_error", &e)),
+    }
+}
+
+/// Serve `/tokenize` or `/detokenize` with a local llama.cpp model's own
+/// tokenizer. Token ids mean nothing across models, so other backends are
+/// refused rather than estimated.
+async fn tokenize_locally(
+    path: &str,
+    body: &serde_json::Value,
+    client: &Client,
+    provider_configs: &Mutex<HashMap<String, ProviderConfig>>,
+    llama_state: &LlamacppState,
+    mlx_sessions: &Mutex<HashMap<i32, MlxBackendSession>>,
+    jan_data_folder: &str,
+) -> (StatusCode, serde_json::Value) {
+    let Some(model) = body.get("model").and_then(|m| m.as_str()) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            openai_error("Request body is missing 'model'", Some("model")),
+        );
+    };
+    let remote = find_provider_for_model(&*provider_configs.lock().await, model).is_some();
+    let mlx = mlx_sessions
+        .lock()
+        .await
+        .values()
+        .any(|s| s.info.model_id == model);
+    if remote || mlx {
+        return (
+            StatusCode::BAD_REQUEST,
+            openai_error(
+                &format!(
+                    "Tokenization is only available for local llama.cpp models, not '{model}'"
+                ),
+                Some("model"),
+            ),
+        );
+    }
+
+    if path == "/detokenize" {
+        let Some(ids) = body.get("tokens").filter(|t| t.is_array()) else {
+            return (
+                StatusCode::BAD_REQUEST,
+                openai_error("Request body is missing 'tokens'", Some("tokens")),
+            );
+        };
+        let request = serde_json::json!({"model": model, "tokens": ids});
+        return match router_post(client, llama_state, "/detokenize", &request).await {
+            Ok(reply) => (
+                StatusCode::OK,
+                serde_json::json!({"prompt": reply.get("content").cloned().unwrap_or_default()}),
+            ),
+            Err(e) => (StatusCode::BAD_GATEWAY, openai_error(&e, None)),
+        };
+    }
+
+    let add_special = body
+        .get("add_special_tokens")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    match local_tokenize(client, llama_state, model, body, add_special).await {
+        Ok(ids) => {
+            let (data_folder, id) = (PathBuf::from(jan_data_folder), model.to_string());
+            let max_model_len = tokio::task::spawn_blocking(move || {
+                model_catalog::local_model_facts(&data_folder, "llamacpp", &id, None)
+            })
+            .await
+            .ok()
+            .flatten()
+            .and_then(|facts| facts.context_length);
+            (StatusCode::OK, tokens::tokenize_reply(&ids, max_model_len))
+        }
+        Err(e) => (StatusCode::BAD_GATEWAY, openai_error(&e, None)),
+    }
+}
+
+/// Every model id the server answers for and what serves it (`llama.cpp`,
+/// `mlx`, `remote` or `alias`), in `/models` order.
+async fn served_models(
+    llama_state: &LlamacppState,
+    client: &Client,
+    mlx_sessions: &Mutex<HashMap<i32, MlxBackendSession>>,
+    provider_configs: &Mutex<HashMap<String, ProviderConfig>>,
+    model_aliases: &ModelAliasStore,
+) -> Vec<(String, &'static str)> {
+    let mut models: Vec<(String, &'static str)> = router_list_models(llama_state, client)
+        .await
+        .into_iter()
+        .map(|id| (id, "llama.cpp"))
+        .collect();
+    models.extend(
+        mlx_sessions
+            .lock()
+            .await
+            .values()
+            .map(|session| (session.info.model_id.clone(), "mlx")),
+    );
+    models.extend(
+        provider_configs
+            .lock()
+            .await
+            .values()
+            .flat_map(|provider_cfg| provider_cfg.models.clone())
+            .map(|id| (id, "remote")),
+    );
+    // Model aliases are requested like any other model id
+    models.extend(
+        model_aliases
+            .list()
+            .into_iter()
+            .map(|alias| (alias.name, "alias")),
+    );
+    models
+}
+
+/// `served_models` described for `/models`: load state from the router and
+/// MLX sessions, facts from `model.yml` and the weights, the provider of
+/// remote models and the targets of aliases.
+async fn describe_models(
+    llama_state: &LlamacppState,
+    client: &Client,
+    mlx_sessions: &Mutex<HashMap<i32, MlxBackendSession>>,
+    provider_configs: &Mutex<HashMap<String, ProviderConfig>>,
+    model_aliases: &ModelAliasStore,
+    jan_data_folder: &str,
+) -> Vec<ModelDescription> {
+    let served = served_models(
+        llama_state,
+        client,
+        mlx_sessions,
+        provider_configs,
+        model_aliases,
+    )
+    .await;
+    let router_status: HashMap<String, String> = router_snapshot(llama_state, client)
+        .await
+        .models
+        .into_iter()
+        .collect();
+    let mlx_paths: HashMap<String, String> = mlx_sessions
+        .lock()
+        .await
+        .values()
+        .map(|session| {
+            (
+                session.info.model_id.clone(),
+                session.info.model_path.clone(),
+            )
+        })
+        .collect();
+    let providers = provider_configs.lock().await.clone();
+    let alias_targets: HashMap<String, Vec<String>> = model_aliases
+        .list()
+        .into_iter()
+        .map(|alias| {
+            (
+                alias.name,
+                alias.targets.into_iter().map(|t| t.model).collect(),
+            )
+        })
+        .collect();
+    let data_folder = PathBuf::from(jan_data_folder);
+
+    // model.yml and weight headers are read from disk
+    tokio::task::spawn_blocking(move || {
+        let local_facts = |engine: &str, id: &str, weights: Option<&str>| {
+            model_catalog::local_model_facts(&data_folder, engine, id, weights).unwrap_or_default()
+        };
+        served
+            .into_iter()
+            .map(|(id, owned_by)| {
+                let mut model = ModelDescription {
+                    id,
+                    owned_by,
+                    state: "available",
+                    facts: ModelFacts::default(),
+                    provider: None,
+                    targets: Vec::new(),
+                };
+                match owned_by {
+                    "llama.cpp" => {
+                        let status = router_status.get(&model.id).map(String::as_str);
+                        model.state = model_catalog::router_state(status);
+                        model.facts = local_facts("llamacpp", &model.id, None);
+                    }
+                    "mlx" => {
+                        model.state = "loaded";
+                        let weights = mlx_paths.get(&model.id).map(String::as_str);
+                        model.facts = local_facts("mlx", &model.id, weights);
+                    }
+                    "remote" => model.provider = find_provider_for_model(&providers, &model.id),
+                    _ => model.targets = alias_targets.get(&model.id).cloned().unwrap_or_default(),
+                }
+                model
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Name of the registered provider serving `model_id`: one that lists the model,
+/// else one named by its `provider/` prefix, else one named exactly `model_id`.
+pub(crate) fn find_provider_for_model(
+    configs: &HashMap<String, ProviderConfig>,
+    model_id: &str,
+) -> Option<String> {
+    configs
+        .values()
+        .find(|config| config.models.iter().any(|m| m == model_id))
+        .map(|config| config.provider.clone())
+        .or_else(|| {
+            if let Some(sep_pos) = model_id.find('/') {
+                let potential_provider: &str = &model_id[..sep_pos];
+                if configs.contains_key(potential_provider) {
+                    return Some(potential_provider.to_string());
+                }
+            }
+            configs.get(model_id).map(|c| c.provider.clone())
+        })
+}
+
+async fn resolve_upstream_for_model(
+    model_id: &str,
+    provider_configs: Arc<Mutex<HashMap<String, ProviderConfig>>>,
+    llama_state: Arc<LlamacppState>,
+    mlx_sessions: Arc<Mutex<HashMap<i32, MlxBackendSession>>>,
+) -> Result<(String, Vec<String>), String> {
+    let destination_path = "/chat/completions";
+
+    let provider_name = find_provider_for_model(&*provider_configs.lock().await, model_id);
+
+    if let Some(provider) = provider_name {
+        let pc2 = provider_configs.lock().await;
+        if let Some(provider_cfg) = pc2.get(provider.as_str()).cloned() {
+            let api_url = provider_cfg
+                .base_url
+                .clone()
+                .ok_or_else(|| format!("Missing base_url for provider '{provider}'"))?;
+            let url = format!("{}{}", api_url, destination_path);
+            return Ok((url, provider_cfg.bearer_key_chain()));
+        }
+    }
+
+    let mlx_guard = mlx_sessions.lock().await;
+    if let Some(info) = mlx_guard.values().find(|s| s.info.model_id == model_id) {
+        let target_port = info.info.port;
+        return Ok((
+            format!("http://127.0.0.1:{target_port}/v1{destination_path}"),
+            vec![info.info.api_key.clone()],
+        ));
+    }
+    drop(mlx_guard);
+
+    if let Some((url, key)) = router_upstream(&llama_state, destination_path).await {
+        return Ok((url, vec![key]));
+    }
+
+    Err(format!("No upstream session found for model '{model_id}'"))
+}
+
+/// The tool-call emulation for a chat/completions `body` sent to `model_id`,
+/// when it is enabled, the body offers tools and the model is a local one
+/// whose chat template cannot take them (per `model.yml` and its weights).
+async fn tool_emulation_for(
+    enabled: bool,
+    body: &serde_json::Value,
+    model_id: &str,
+    provider_configs: &Mutex<HashMap<String, ProviderConfig>>,
+    mlx_sessions: &Mutex<HashMap<i32, MlxBackendSession>>,
+    jan_data_folder: &str,
+) -> Option<ToolEmulationConverter> {
+    if !enabled {
+        return None;
+    }
+    let converter = ToolEmulationConverter::for_request(body)?;
+    if find_provider_for_model(&*provider_configs.lock().await, model_id).is_some() {
+        return None;
+    }
+    let mlx_weights = mlx_sessions
+        .lock()
+        .await
+        .values()
+        .find(|s| s.info.model_id == model_id)
+        .map(|s| s.info.model_path.clone());
+    let engine = if mlx_weights.is_some() {
+        "mlx"
+    } else {
+        "llamacpp"
+    };
+    let (data_folder, id) = (PathBuf::from(jan_data_folder), model_id.to_string());
+    let facts = tokio::task::spawn_blocking(move || {
+        model_catalog::local_model_facts(&data_folder, engine, &id, mlx_weights.as_deref())
+    })
+    .await
+    .ok()
+    .flatten()?;
+    if facts.capabilities.contains(&"tools") {
+        return None;
+    }
+    log::info!("Emulating tool calls for '{model_id}', whose chat template has no tool support");
+    Some(converter)
+}
+
+/// Constrain a llama.cpp model's chat/completions body to its requested
+/// `response_format` schema through llama-server's `json_schema`. Remote
+/// providers and MLX models are left to handle `response_format` themselves.
+async fn constrain_local_response_format(
+    body: &mut serde_json::Value,
+    provider_configs: &Mutex<HashMap<String, ProviderConfig>>,
+    mlx_sessions: &Mutex<HashMap<i32, MlxBackendSession>>,
+) {
+    if body.get("response_format").is_none() {
+        return;
+    }
+    let model_id = body.get("model").and_then(|v| v.as_str()).unwrap_or("");
+    if is_llamacpp_model(model_id, provider_configs, mlx_sessions).await {
+        structured_output::constrain_with_json_schema(body);
+        // Same converter as for tool schemas, so the same fixes apply.
+        if let Some(schema) = body.get_mut("json_schema") {
+            normalize_openai_tool_parameters_schema(schema);
+        }
+    }
+}
+
+/// Whether `model_id` is served by the llama.cpp router: neither a remote
+/// provider's model nor a running MLX session.
+async fn is_llamacpp_model(
+    model_id: &str,
+    provider_configs: &Mutex<HashMap<String, ProviderConfig>>,
+    mlx_sessions: &Mutex<HashMap<i32, MlxBackendSession>>,
+) -> bool {
+    find_provider_for_model(&*provider_configs.lock().await, model_id).is_none()
+        && !mlx_sessions
+            .lock()
+            .await
+            .values()
+            .any(|s| s.info.model_id == model_id)
+}
+
+/// Number of slots (`--parallel`) the router's instance of `model` runs, from
+/// llama-server's `/props`.
+async fn router_total_slots(
+    client: &Client,
+    llama_state: &LlamacppState,
+    model: &str,
+) -> Option<u64> {
+    let (url, key) = {
+        let guard = llama_state.router.lock().await;
+        let h = guard.as_ref()?;
+        (
+            format!("http://127.0.0.1:{}/props", h.port),
+            h.api_key.clone(),
+        )
+    };
+    let resp = client
+        .get(&url)
+        .query(&[("model", model)])
+        .bearer_auth(key)
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .await
+        .ok()
+        .filter(|r| r.status().is_success())?;
+    let props: serde_json::Value = resp.json().await.ok()?;
+    props.get("total_slots").and_then(|n| n.as_u64())
+}
+
+pub(crate) fn copy_optional_chat_params(from: &serde_json::Value, into: &mut serde_json::Map<String, serde_json::Value>) {
+    for key in [
+        "temperature",
+        "top_p",
+        "top_k",
+        "max_tokens",
+        "stop_sequences",
+        "stop",
+        "frequency_penalty",
+        "presence_penalty",
+    ] {
+        if let Some(v) = from.get(key) {
+            into.insert(key.to_string(), v.clone());
+        }
+    }
+}
+
+async fn collect_mcp_openai_tools(
     mcp_servers: &SharedMcpServers,
     mcp_settings: &Arc<Mutex<McpSettings>>,
 ) -> Result<(Vec<serde_json::Value>, HashMap<String, String>), String> {
@@ -1012,18 +1997,88 @@ async fn collect_mcp_openai_tools(
     Ok((openai_tools, tool_to_server))
 }
 
+/// Tool calls of one assistant turn, once the approval policy had its say.
+struct ToolRun {
+    /// `(tool_call_id, result)` for every call that ran or was refused.
+    results: Vec<(String, String)>,
+    /// Calls held for the API client to approve.
+    pending: Vec<ApprovalRequest>,
+}
+
+/// Drive `calls` with at most `limit` running at once and return their
+/// outputs in call order. The first error abandons the rest.
+pub(crate) async fn run_bounded<T, F>(
+    calls: impl IntoIterator<Item = F>,
+    limit: usize,
+) -> Result<Vec<T>, String>
+where
+    F: std::future::Future<Output = Result<T, String>>,
+{
+    let mut running = futures_util::stream::iter(calls).buffered(limit.max(1));
+    let mut outputs = Vec::new();
+    while let Some(output) = running.next().await {
+        outputs.push(output?);
+    }
+    Ok(outputs)
+}
+
+/// Report a tool call that was answered without running.
+async fn report_unrun_tool_call(
+    events: &mut Option<&mut BodySender>,
+    tool_call: &serde_json::Value,
+    server: Option<&str>,
+    result: &str,
+) -> Result<(), String> {
+    let id = tool_call.get("id").and_then(|v| v.as_str()).unwrap_or("");
+    let name = tool_call
+        .pointer("/function/name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    emit_orchestration_event(
+        events,
+        orchestration_stream::tool_call_started(tool_call, server),
+    )
+    .await?;
+    emit_orchestration_event(
+        events,
+        orchestration_stream::tool_call_completed(id, name, result),
+    )
+    .await
+}
+
+/// Run the tool calls of one assistant turn, subject to the approval policy.
+/// With `resumed`, the API client's answers by call id, the calls are ones a
+/// held orchestration resumes with rather than the model's. Approval is asked
+/// call by call, then the approved calls run concurrently, at most
+/// `max_parallel_tool_calls` at once and each under the tool call timeout. A
+/// call that fails or times out becomes an error result for the model rather
+/// than ending the orchestration. With `events`, each call is reported to a
+/// streamed orchestration as it starts, progresses and ends; progress from a
+/// server is reported on each of its calls that is running.
 async fn execute_mcp_tool_calls(
     tool_calls: &[serde_json::Value],
     tool_to_server: &HashMap<String, String>,
     mcp_servers: &SharedMcpServers,
     mcp_settings: &Arc<Mutex<McpSettings>>,
-) -> Result<Vec<(String, String)>, String> {
-    let timeout_duration = mcp_settings.lock().await.tool_call_timeout_duration();
-    let servers = mcp_servers.lock().await;
+    approval: &ApprovalMode,
+    resumed: Option<&HashMap<String, bool>>,
+    mut events: Option<&mut BodySender>,
+) -> Result<ToolRun, String> {
+    let (timeout_duration, max_parallel, policy) = {
+        let settings = mcp_settings.lock().await;
+        (
+            settings.tool_call_timeout_duration(),
+            settings.max_parallel_tool_calls,
+            settings.tool_approval.clone(),
+        )
+    };
 
-    let mut results = Vec::with_capacity(tool_calls.len());
+    // Results stay in call order whatever order the calls finish in.
+    let mut results: Vec<Option<(String, String)>> = vec![None; tool_calls.len()];
+    let mut pending = Vec::new();
+    let mut approved = Vec::new();
 
-    for tc in tool_calls {
+    for (index, tc) in tool_calls.iter().enumerate() {
         let tool_call_id = tc.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
         let tool_name = tc
             .get("function")
@@ -1047,56 +2102,281 @@ async fn execute_mcp_tool_calls(
             serde_json::Map::new()
         };
 
-        let server_name = tool_to_server
-            .get(&tool_name)
-            .ok_or_else(|| format!("No MCP server registered for tool '{tool_name}'"))?;
-
-        let service = servers
-            .get(server_name)
-            .ok_or_else(|| format!("MCP server '{server_name}' not found in runtime state"))?;
-
-        let tool_call = service.call_tool(CallToolRequestParam {
-            name: tool_name.clone().into(),
-            arguments: Some(args_map),
-        });
-
-        let result = match tokio::time::timeout(timeout_duration, tool_call).await {
-            Ok(call_result) => call_result.map_err(|e| e.to_string()),
-            Err(_) => Err(format!(
-                "Tool call '{tool_name}' timed out after {} seconds",
-                timeout_duration.as_secs()
-            )),
+        let Some(server_name) = tool_to_server.get(&tool_name) else {
+            let error = format!("ERROR: No MCP server registered for tool '{tool_name}'");
+            report_unrun_tool_call(&mut events, tc, None, &error).await?;
+            results[index] = Some((tool_call_id, error));
+            continue;
         };
 
-        let tool_result_string = match result {
-            Ok(res) => mcp_call_result_to_string(&res),
-            Err(e) => format!("ERROR: {e}"),
+        let request = ApprovalRequest {
+            id: tool_call_id.clone(),
+            server: server_name.clone(),
+            tool: tool_name.clone(),
+            arguments: args_value,
+        };
+        if *approval == ApprovalMode::Desktop
+            && policy.decide(server_name, &tool_name) == ToolApproval::Ask
+        {
+            emit_orchestration_event(
+                &mut events,
+                orchestration_stream::tool_call_approval_required(
+                    serde_json::to_value(&request).unwrap_or_default(),
+                ),
+            )
+            .await?;
+        }
+        let verdict = match (approval, resumed) {
+            (ApprovalMode::Client(client), Some(decisions)) => tool_approvals().resume(
+                &policy,
+                client,
+                &request,
+                decisions.get(&tool_call_id).copied(),
+            ),
+            _ => tool_approvals().check(&policy, approval, &request).await,
         };
+        match verdict {
+            Verdict::Pending => pending.push(request),
+            Verdict::Denied(reason) => {
+                let error = format!("ERROR: Tool call '{tool_name}' was not approved: {reason}");
+                report_unrun_tool_call(&mut events, tc, Some(server_name), &error).await?;
+                results[index] = Some((tool_call_id, error));
+            }
+            Verdict::Allowed => {
+                approved.push((index, tc, server_name, tool_call_id, tool_name, args_map))
+            }
+        }
+    }
+
+    if !approved.is_empty() {
+        let servers = mcp_servers.lock().await;
+        let calls = approved.into_iter().map(
+            |(index, tc, server_name, tool_call_id, tool_name, args_map)| {
+                let service = servers.get(server_name);
+                // Each call reports to the stream through its own handle.
+                let mut sender = events.as_deref().cloned();
+                async move {
+                    let mut events = sender.as_mut();
+                    emit_orchestration_event(
+                        &mut events,
+                        orchestration_stream::tool_call_started(tc, Some(server_name.as_str())),
+                    )
+                    .await?;
+
+                    let result = match service {
+                        Some(service) => {
+                            let what = format!("Tool call '{tool_name}'");
+                            let tool_call = with_timeout(
+                                service.call_tool(CallToolRequestParam {
+                                    name: tool_name.clone().into(),
+                                    arguments: Some(args_map),
+                                }),
+                                timeout_duration,
+                                &what,
+                            );
+                            match events.as_deref_mut() {
+                                Some(sender) => {
+                                    relay_tool_progress(
+                                        tool_call,
+                                        sender,
+                                        server_name,
+                                        &tool_call_id,
+                                        &tool_name,
+                                    )
+                                    .await
+                                }
+                                None => tool_call.await,
+                            }
+                        }
+                        None => Err(format!(
+                            "MCP server '{server_name}' not found in runtime state"
+                        )),
+                    };
+                    let result = match result {
+                        Ok(res) => mcp_call_result_to_string(&res),
+                        Err(e) => format!("ERROR: {e}"),
+                    };
 
-        results.push((tool_call_id, tool_result_string));
+                    emit_orchestration_event(
+                        &mut events,
+                        orchestration_stream::tool_call_completed(
+                            &tool_call_id,
+                            &tool_name,
+                            &result,
+                        ),
+                    )
+                    .await?;
+                    Ok((index, tool_call_id, result))
+                }
+            },
+        );
+        for (index, tool_call_id, result) in run_bounded(calls, max_parallel).await? {
+            results[index] = Some((tool_call_id, result));
+        }
     }
 
-    Ok(results)
+    Ok(ToolRun {
+        results: results.into_iter().flatten().collect(),
+        pending,
+    })
 }
 
-async fn call_openai_chat_completions(
-    client: &Client,
-    upstream_url: &str,
-    api_keys: &[String],
-    body: &serde_json::Value,
-) -> Result<serde_json::Value, String> {
-    let attempts: Vec<Option<&str>> = if api_keys.is_empty() {
-        vec![None]
-    } else {
-        api_keys.iter().map(|s| Some(s.as_str())).collect()
-    };
-
-    let mut last_err = String::new();
-    for (i, key_ref) in attempts.iter().enumerate() {
-        let mut req = client
-            .post(upstream_url)
-            .header("Content-Type", "application/json")
-            .header("Accept-Encoding", "identity");
+/// Append a tool run's results to the conversation. Returns the
+/// `orchestration.requires_approval` reply when calls are held for the client.
+fn record_tool_run(
+    conversation_messages: &mut Vec<serde_json::Value>,
+    run: ToolRun,
+    model_id: &str,
+) -> Option<serde_json::Value> {
+    for (tool_call_id, result_text) in run.results {
+        conversation_messages.push(serde_json::json!({
+            "role": "tool",
+            "tool_call_id": tool_call_id,
+            "content": result_text
+        }));
+    }
+    if run.pending.is_empty() {
+        return None;
+    }
+    Some(orchestration_stream::requires_approval(
+        model_id,
+        conversation_messages,
+        serde_json::to_value(&run.pending).unwrap_or_default(),
+    ))
+}
+
+/// The final assistant message of a completion, as a saved thread keeps it.
+fn final_assistant_turn(completion: &serde_json::Value) -> serde_json::Value {
+    let message = extract_choice_message(completion);
+    let mut turn = serde_json::json!({
+        "role": "assistant",
+        "content": message
+            .and_then(|m| m.get("content"))
+            .cloned()
+            .unwrap_or(serde_json::Value::Null),
+    });
+    if let Some(reasoning) = message
+        .and_then(|m| m.get("reasoning_content"))
+        .filter(|r| !r.is_null())
+    {
+        turn["reasoning_content"] = reasoning.clone();
+    }
+    turn
+}
+
+/// Save an `/orchestrations` run to its Jan thread and tag the reply with the
+/// thread id. `history_len` messages of the transcript came from the thread
+/// already. A run that cannot be saved is still returned.
+async fn save_orchestration_thread(
+    target: &ThreadTarget,
+    jan_data_folder: &str,
+    provider_configs: &Arc<Mutex<HashMap<String, ProviderConfig>>>,
+    transcript: &[serde_json::Value],
+    history_len: usize,
+    assistant_id: Option<&str>,
+    reply: &mut serde_json::Value,
+) {
+    let run: Vec<serde_json::Value> = transcript
+        .iter()
+        .filter(|m| m.get("role").and_then(|r| r.as_str()) != Some("system"))
+        .skip(history_len)
+        .cloned()
+        .collect();
+    let model = reply
+        .get("model")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let engine = find_provider_for_model(&*provider_configs.lock().await, &model)
+        .unwrap_or_else(|| "llamacpp".to_string());
+    match orchestration_threads::save_run(
+        Path::new(jan_data_folder),
+        target,
+        &run,
+        &model,
+        &engine,
+        assistant_id,
+    )
+    .await
+    {
+        Ok(()) => {
+            if let Some(reply) = reply.as_object_mut() {
+                reply.insert("thread_id".to_string(), serde_json::json!(target.id()));
+            }
+        }
+        Err(e) => log::error!(
+            "Failed to save orchestration to thread {}: {e}",
+            target.id()
+        ),
+    }
+}
+
+const ORCHESTRATION_CLIENT_GONE: &str = "Client disconnected from the orchestration stream";
+
+/// Send one event of a streamed orchestration. Fails once the client is
+/// gone, which ends the orchestration.
+async fn emit_orchestration_event(
+    events: &mut Option<&mut BodySender>,
+    event: serde_json::Value,
+) -> Result<(), String> {
+    match events {
+        Some(sender) => sender
+            .send_data(sse_event(&event))
+            .await
+            .map_err(|_| ORCHESTRATION_CLIENT_GONE.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Await an MCP tool call while relaying its server's progress notifications
+/// to a streamed orchestration. Progress carries no call identity (rmcp keeps
+/// the progress token of a `call_tool` to itself), so updates are matched by
+/// server only: while several calls to one server run at once, each of them
+/// relays every update from that server.
+async fn relay_tool_progress<F: std::future::Future>(
+    call: F,
+    sender: &mut BodySender,
+    server: &str,
+    id: &str,
+    name: &str,
+) -> F::Output {
+    let mut progress = subscribe_progress();
+    tokio::pin!(call);
+    loop {
+        tokio::select! {
+            out = &mut call => return out,
+            Ok(update) = progress.recv() => {
+                if update.server == server {
+                    let payload = serde_json::to_value(&update).unwrap_or_default();
+                    let event = orchestration_stream::tool_call_progress(id, name, payload);
+                    // A gone client is noticed at the call's completion event.
+                    let _ = sender.send_data(sse_event(&event)).await;
+                }
+            }
+        }
+    }
+}
+
+/// POST a chat/completions body, moving on to the next API key when one is
+/// rejected, and return the first successful response.
+async fn send_chat_completions(
+    client: &Client,
+    upstream_url: &str,
+    api_keys: &[String],
+    body: &serde_json::Value,
+) -> Result<reqwest::Response, String> {
+    let attempts: Vec<Option<&str>> = if api_keys.is_empty() {
+        vec![None]
+    } else {
+        api_keys.iter().map(|s| Some(s.as_str())).collect()
+    };
+
+    let mut last_err = String::new();
+    for (i, key_ref) in attempts.iter().enumerate() {
+        let mut req = client
+            .post(upstream_url)
+            .header("Content-Type", "application/json")
+            .header("Accept-Encoding", "identity");
 
         if let Some(key) = key_ref {
             req = req.header("Authorization", format!("Bearer {key}"));
@@ -1109,13 +2389,10 @@ async fn call_openai_chat_completions(
             .map_err(|e| format!("Upstream request failed: {e}"))?;
 
         let status = resp.status();
-        let text = resp.text().await.map_err(|e| e.to_string())?;
-
         if status.is_success() {
-            return serde_json::from_str::<serde_json::Value>(&text).map_err(|e| {
-                format!("Failed to parse upstream JSON: {e}. Body: {text}")
-            });
+            return Ok(resp);
         }
+        let text = resp.text().await.map_err(|e| e.to_string())?;
 
         last_err = format!("Upstream returned HTTP {status}: {text}");
         if http_status_indicates_api_key_retry(status) && i + 1 < attempts.len() {
@@ -1131,6 +2408,118 @@ async fn call_openai_chat_completions(
     Err(last_err)
 }
 
+async fn call_openai_chat_completions(
+    client: &Client,
+    upstream_url: &str,
+    api_keys: &[String],
+    body: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let resp = send_chat_completions(client, upstream_url, api_keys, body).await?;
+    let text = resp.text().await.map_err(|e| e.to_string())?;
+    serde_json::from_str::<serde_json::Value>(&text)
+        .map_err(|e| format!("Failed to parse upstream JSON: {e}. Body: {text}"))
+}
+
+/// [`call_openai_chat_completions`], with tool calls emulated in the prompt
+/// when `emulation` is set.
+async fn call_chat_completions_emulated(
+    client: &Client,
+    upstream_url: &str,
+    api_keys: &[String],
+    body: &serde_json::Value,
+    emulation: Option<&ToolEmulationConverter>,
+) -> Result<serde_json::Value, String> {
+    match emulation {
+        Some(emulation) => {
+            let body = emulation.convert_request(body);
+            let completion =
+                call_openai_chat_completions(client, upstream_url, api_keys, &body).await?;
+            Ok(emulation.convert_response(&completion))
+        }
+        None => call_openai_chat_completions(client, upstream_url, api_keys, body).await,
+    }
+}
+
+/// [`call_chat_completions_emulated`] for a streamed orchestration: the turn
+/// is requested with `stream: true`, its text relayed as `message.delta`
+/// events, and the reply reassembled so the loop carries on unchanged.
+async fn stream_orchestration_turn(
+    client: &Client,
+    upstream_url: &str,
+    api_keys: &[String],
+    body: &serde_json::Value,
+    emulation: Option<&ToolEmulationConverter>,
+    turn: usize,
+    sender: &mut BodySender,
+) -> Result<serde_json::Value, String> {
+    let mut body = match emulation {
+        Some(emulation) => emulation.convert_request(body),
+        None => body.clone(),
+    };
+    body["stream"] = serde_json::json!(true);
+    body["stream_options"] = serde_json::json!({ "include_usage": true });
+    let resp = send_chat_completions(client, upstream_url, api_keys, &body).await?;
+
+    let is_sse = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.contains("event-stream"));
+    if !is_sse {
+        // Some backends ignore `stream`; relay the whole reply as one delta.
+        let text = resp.text().await.map_err(|e| e.to_string())?;
+        let completion: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| format!("Failed to parse upstream JSON: {e}. Body: {text}"))?;
+        let completion = match emulation {
+            Some(emulation) => emulation.convert_response(&completion),
+            None => completion,
+        };
+        if let Some(delta) = orchestration_stream::completion_delta(turn, &completion) {
+            emit_orchestration_event(&mut Some(sender), delta).await?;
+        }
+        return Ok(completion);
+    }
+
+    let mut stream = resp.bytes_stream();
+    let mut acc = SseAccumulator::new();
+    let mut state = StreamState::default();
+    let mut reply = TurnAssembler::new();
+    loop {
+        let next = sender
+            .unless_closed(stream.next())
+            .await
+            .ok_or(ORCHESTRATION_CLIENT_GONE)?;
+        let done = next.is_none();
+        let events = match next {
+            Some(Ok(chunk)) => acc.push(&String::from_utf8_lossy(&chunk)),
+            Some(Err(e)) => return Err(format!("Upstream stream failed: {e}")),
+            None => acc.finish().into_iter().collect(),
+        };
+        for event in events {
+            let payloads = match emulation {
+                Some(emulation) => emulation.convert_stream_event(&event, &mut state),
+                None => vec![event.data],
+            };
+            for payload in payloads {
+                // Skips `[DONE]` too.
+                let Ok(chunk) = serde_json::from_str::<serde_json::Value>(&payload) else {
+                    continue;
+                };
+                if let Some(err) = chunk.get("error") {
+                    return Err(format!("Upstream stream failed: {err}"));
+                }
+                if let Some(delta) = reply.push_chunk(turn, &chunk) {
+                    emit_orchestration_event(&mut Some(&mut *sender), delta).await?;
+                }
+            }
+        }
+        if done {
+            break;
+        }
+    }
+    Ok(reply.finish())
+}
+
 // orchestration coordinator threads state from multiple subsystems
 #[allow(clippy::too_many_arguments)]
 async fn run_server_side_openai_orchestration(
@@ -1142,6 +2531,10 @@ async fn run_server_side_openai_orchestration(
     mcp_servers: SharedMcpServers,
     mcp_settings: Arc<Mutex<McpSettings>>,
     jan_data_folder: &str,
+    enable_tool_emulation: bool,
+    approval: ApprovalMode,
+    mut events: Option<&mut BodySender>,
+    mut transcript: Option<&mut Vec<serde_json::Value>>,
 ) -> Result<serde_json::Value, String> {
     let messages_value = json_body
         .get("messages")
@@ -1195,6 +2588,40 @@ async fn run_server_side_openai_orchestration(
         mlx_sessions.clone(),
     )
     .await?;
+    let emulation = tool_emulation_for(
+        enable_tool_emulation,
+        &serde_json::json!({ "tools": openai_tools }),
+        &model_id,
+        &provider_configs,
+        &mlx_sessions,
+        jan_data_folder,
+    )
+    .await;
+
+    // A client resuming a held orchestration sends its decisions with the
+    // conversation it was handed; run the calls it was waiting on first.
+    if let ApprovalMode::Client(_) = approval {
+        let held = unanswered_tool_calls(&conversation_messages);
+        if !held.is_empty() {
+            let decisions = client_tool_approvals(json_body);
+            let run = execute_mcp_tool_calls(
+                &held,
+                &tool_to_server,
+                &mcp_servers,
+                &mcp_settings,
+                &approval,
+                Some(&decisions),
+                events.as_deref_mut(),
+            )
+            .await?;
+            if let Some(reply) = record_tool_run(&mut conversation_messages, run, &model_id) {
+                if let Some(transcript) = transcript {
+                    *transcript = conversation_messages;
+                }
+                return Ok(reply);
+            }
+        }
+    }
 
     let max_turns = json_body
         .get("max_turns")
@@ -1204,7 +2631,7 @@ async fn run_server_side_openai_orchestration(
 
     let mut last_response: Option<serde_json::Value> = None;
 
-    for _turn in 0..max_turns {
+    for turn in 0..max_turns {
         let mut completion_map = serde_json::Map::new();
         completion_map.insert("model".to_string(), serde_json::json!(model_id));
         completion_map.insert(
@@ -1224,18 +2651,40 @@ async fn run_server_side_openai_orchestration(
         copy_optional_chat_params(json_body, &mut completion_map);
         let request_value = serde_json::Value::Object(completion_map);
 
-        let completion = call_openai_chat_completions(
-            client,
-            &upstream_url,
-            &session_api_keys,
-            &request_value,
-        )
-        .await?;
+        emit_orchestration_event(&mut events, orchestration_stream::turn_started(turn)).await?;
+        let completion = match events.as_deref_mut() {
+            Some(sender) => {
+                stream_orchestration_turn(
+                    client,
+                    &upstream_url,
+                    &session_api_keys,
+                    &request_value,
+                    emulation.as_ref(),
+                    turn,
+                    sender,
+                )
+                .await?
+            }
+            None => {
+                call_chat_completions_emulated(
+                    client,
+                    &upstream_url,
+                    &session_api_keys,
+                    &request_value,
+                    emulation.as_ref(),
+                )
+                .await?
+            }
+        };
 
         let tool_calls = extract_tool_calls(&completion);
         last_response = Some(completion.clone());
 
         if tool_calls.is_empty() {
+            if let Some(transcript) = transcript.as_deref_mut() {
+                conversation_messages.push(final_assistant_turn(&completion));
+                *transcript = conversation_messages;
+            }
             return Ok(completion);
         }
 
@@ -1257,20 +2706,21 @@ async fn run_server_side_openai_orchestration(
             }));
         }
 
-        let tool_results = execute_mcp_tool_calls(
+        let run = execute_mcp_tool_calls(
             &tool_calls,
             &tool_to_server,
             &mcp_servers,
             &mcp_settings,
+            &approval,
+            None,
+            events.as_deref_mut(),
         )
         .await?;
-
-        for (tool_call_id, result_text) in tool_results {
-            conversation_messages.push(serde_json::json!({
-                "role": "tool",
-                "tool_call_id": tool_call_id,
-                "content": result_text
-            }));
+        if let Some(reply) = record_tool_run(&mut conversation_messages, run, &model_id) {
+            if let Some(transcript) = transcript.as_deref_mut() {
+                *transcript = conversation_messages;
+            }
+            return Ok(reply);
         }
     }
 
@@ -1281,9 +2731,436 @@ async fn run_server_side_openai_orchestration(
     ))
 }
 
-/// Handles the proxy request logic
+/// Fairness bucket for requests without a client key.
+const ANONYMOUS_CLIENT: &str = "(anonymous)";
+/// Fairness bucket for Batch API work.
+const BATCH_CLIENT: &str = "(batch)";
+
+/// Wait for a slot on a local model. `None` when admission control is off or
+/// a remote provider serves the model.
+async fn admit_local(
+    admission: Option<&Arc<AdmissionController>>,
+    provider_configs: &Arc<Mutex<HashMap<String, ProviderConfig>>>,
+    model: &str,
+    client: &str,
+    priority: Priority,
+) -> Result<Option<AdmissionPermit>, Overloaded> {
+    let Some(admission) = admission else {
+        return Ok(None);
+    };
+    if find_provider_for_model(&*provider_configs.lock().await, model).is_some() {
+        return Ok(None);
+    }
+    admission.acquire(model, client, priority).await.map(Some)
+}
+
+/// How a request naming a model alias went (see `try_alias_targets`).
+pub(crate) enum AliasAttempt<B> {
+    /// A target replied. Its admission slot, if it took one, is held until
+    /// the reply has been sent.
+    Served {
+        response: Response<B>,
+        target: String,
+        permit: Option<AdmissionPermit>,
+    },
+    /// The last target didn't reply before the first-byte timeout.
+    TimedOut(String),
+    /// The last target is a local model with no free slot.
+    Overloaded(String, Overloaded),
+    NoTargets,
+}
+
+/// Try `plan`'s targets in order until one replies without a server error.
+/// A local target waits for a slot on its own model before it is forwarded
+/// to and gives the slot back when the next target is tried; remote targets
+/// skip admission. The upstream reply is only handed back once its headers
+/// arrive, so nothing has reached the client while we fail over.
+pub(crate) async fn try_alias_targets<B, E, F, Fut>(
+    plan: &AliasPlan,
+    admission: Option<&Arc<AdmissionController>>,
+    provider_configs: &Arc<Mutex<HashMap<String, ProviderConfig>>>,
+    client: &str,
+    priority: Priority,
+    forward: F,
+) -> Result<AliasAttempt<B>, E>
+where
+    F: Fn(&str) -> Fut,
+    Fut: std::future::Future<Output = Result<Response<B>, E>>,
+{
+    for (i, target) in plan.models.iter().enumerate() {
+        let is_last = i + 1 == plan.models.len();
+        let failure = match admit_local(admission, provider_configs, target, client, priority).await
+        {
+            Err(overloaded) if is_last => {
+                return Ok(AliasAttempt::Overloaded(target.clone(), overloaded));
+            }
+            Err(overloaded) => overloaded.message,
+            Ok(permit) => {
+                let attempt = forward(target);
+                let outcome = match plan.first_byte_timeout {
+                    Some(limit) => tokio::time::timeout(limit, attempt).await.ok(),
+                    None => Some(attempt.await),
+                };
+                match outcome {
+                    Some(Ok(response)) if !is_last && response.status().is_server_error() => {
+                        format!("status {}", response.status())
+                    }
+                    Some(response) => {
+                        return Ok(AliasAttempt::Served {
+                            response: response?,
+                            target: target.clone(),
+                            permit,
+                        });
+                    }
+                    None if !is_last => "no response in time".to_string(),
+                    None => return Ok(AliasAttempt::TimedOut(target.clone())),
+                }
+            }
+        };
+        log::warn!(
+            "Model alias '{}': target '{target}' failed ({failure}), falling back to '{}'",
+            plan.alias,
+            plan.models[i + 1]
+        );
+    }
+    Ok(AliasAttempt::NoTargets)
+}
+
+/// Send one Batch API request to `model`, a local model waiting for an
+/// admission slot first. Results are written whole, so never streamed.
 #[allow(clippy::too_many_arguments)]
-async fn proxy_request(
+async fn send_batch_request(
+    mut body: serde_json::Value,
+    model: &str,
+    client: &Client,
+    provider_configs: &Arc<Mutex<HashMap<String, ProviderConfig>>>,
+    llama_state: &Arc<LlamacppState>,
+    mlx_sessions: &Arc<Mutex<HashMap<i32, MlxBackendSession>>>,
+    admission: Option<&Arc<AdmissionController>>,
+    enable_tool_emulation: bool,
+    jan_data_folder: &str,
+) -> Result<serde_json::Value, String> {
+    // Batch work queues behind interactive requests and, rather than failing
+    // when the queue is full, waits to try again.
+    let _permit = loop {
+        match admit_local(
+            admission,
+            provider_configs,
+            model,
+            BATCH_CLIENT,
+            Priority::Low,
+        )
+        .await
+        {
+            Ok(permit) => break permit,
+            Err(overloaded) => {
+                tokio::time::sleep(std::time::Duration::from_secs(overloaded.retry_after_secs))
+                    .await
+            }
+        }
+    };
+    body["model"] = model.into();
+    let emulation = tool_emulation_for(
+        enable_tool_emulation,
+        &body,
+        model,
+        provider_configs,
+        mlx_sessions,
+        jan_data_folder,
+    )
+    .await;
+    if emulation.is_none() {
+        constrain_local_response_format(&mut body, provider_configs, mlx_sessions).await;
+    }
+    let (url, api_keys) = resolve_upstream_for_model(
+        model,
+        provider_configs.clone(),
+        llama_state.clone(),
+        mlx_sessions.clone(),
+    )
+    .await?;
+    body["stream"] = serde_json::Value::Bool(false);
+    call_chat_completions_emulated(client, &url, &api_keys, &body, emulation.as_ref()).await
+}
+
+fn is_batch_api_path(path: &str) -> bool {
+    matches!(path, "/files" | "/batches")
+        || path.starts_with("/files/")
+        || path.starts_with("/batches/")
+}
+
+/// Serve the `/files` and `/batches` routes of the Batch API (see `batches`),
+/// returning the reply's status, content type and body.
+async fn serve_batch_api(
+    method: &hyper::Method,
+    path: &str,
+    headers: &hyper::HeaderMap,
+    body: ReqBody,
+    batches: Arc<BatchStore>,
+    client_key: Option<&ClientKey>,
+) -> (StatusCode, &'static str, Bytes) {
+    let body = if *method == hyper::Method::POST {
+        body.collect().await.map(|c| c.to_bytes()).map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read request body".to_string(),
+            )
+        })
+    } else {
+        Ok(Bytes::new())
+    };
+    let (method, path) = (method.clone(), path.to_string());
+    let content_type = headers
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let client_key = client_key.cloned();
+    let outcome = match body {
+        Ok(body) => tokio::task::spawn_blocking(move || {
+            batch_api_reply(
+                &batches,
+                &method,
+                &path,
+                &content_type,
+                &body,
+                client_key.as_ref(),
+            )
+        })
+        .await
+        .unwrap_or_else(|e| Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))),
+        Err(e) => Err(e),
+    };
+
+    match outcome {
+        Ok((content_type, reply)) => (StatusCode::OK, content_type, reply),
+        Err((status, message)) => {
+            let error = serde_json::json!({
+                "error": {"message": message, "type": "invalid_request_error"}
+            });
+            (status, "application/json", Bytes::from(error.to_string()))
+        }
+    }
+}
+
+fn is_mcp_features_path(path: &str) -> bool {
+    path.starts_with("/mcp/")
+}
+
+/// Serve the `/mcp/*` routes: resources and prompts of the connected MCP
+/// servers (see `mcp::resources`), returning the reply's status and body.
+async fn serve_mcp_features(
+    method: &hyper::Method,
+    path: &str,
+    body: ReqBody,
+    mcp_servers: &SharedMcpServers,
+    mcp_settings: &Arc<Mutex<McpSettings>>,
+) -> (StatusCode, String) {
+    let timeout_duration = mcp_settings.lock().await.tool_call_timeout_duration();
+    match mcp_features_reply(method, path, body, mcp_servers, timeout_duration).await {
+        Ok(reply) => (StatusCode::OK, reply.to_string()),
+        Err((status, message)) => {
+            let error = serde_json::json!({
+                "error": {"message": message, "type": "invalid_request_error"}
+            });
+            (status, error.to_string())
+        }
+    }
+}
+
+/// The `/mcp/*` routes proper. A server that fails a request is reported as
+/// a bad gateway.
+async fn mcp_features_reply(
+    method: &hyper::Method,
+    path: &str,
+    body: ReqBody,
+    mcp_servers: &SharedMcpServers,
+    timeout_duration: std::time::Duration,
+) -> Result<serde_json::Value, (StatusCode, String)> {
+    let request = || async {
+        let bytes = body.collect().await.map(|c| c.to_bytes()).map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read request body".to_string(),
+            )
+        })?;
+        serde_json::from_slice::<serde_json::Value>(&bytes)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON body: {e}")))
+    };
+    let field = |request: &serde_json::Value, name: &str| {
+        request
+            .get(name)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Missing required field '{name}'"),
+                )
+            })
+    };
+    let upstream = |e: String| (StatusCode::BAD_GATEWAY, e);
+    let list = |data| serde_json::json!({"object": "list", "data": data});
+
+    match (method.clone(), path) {
+        (hyper::Method::GET, "/mcp/resources") => {
+            let listed = resources::list_resources(mcp_servers, None, timeout_duration).await;
+            Ok(list(serde_json::to_value(listed).unwrap_or_default()))
+        }
+        (hyper::Method::POST, "/mcp/resources/read") => {
+            let request = request().await?;
+            let (server, uri) = (field(&request, "server")?, field(&request, "uri")?);
+            let read = resources::read_resource(mcp_servers, &server, uri, timeout_duration)
+                .await
+                .map_err(upstream)?;
+            Ok(serde_json::to_value(read).unwrap_or_default())
+        }
+        (hyper::Method::GET, "/mcp/prompts") => {
+            let listed = resources::list_prompts(mcp_servers, None, timeout_duration).await;
+            Ok(list(serde_json::to_value(listed).unwrap_or_default()))
+        }
+        (hyper::Method::POST, "/mcp/prompts/get") => {
+            let request = request().await?;
+            let (server, name) = (field(&request, "server")?, field(&request, "name")?);
+            let arguments = request
+                .get("arguments")
+                .and_then(|a| a.as_object())
+                .cloned();
+            let prompt =
+                resources::get_prompt(mcp_servers, &server, name, arguments, timeout_duration)
+                    .await
+                    .map_err(upstream)?;
+            Ok(serde_json::to_value(prompt).unwrap_or_default())
+        }
+        _ => Err((
+            StatusCode::NOT_FOUND,
+            format!("No route for {method} {path}"),
+        )),
+    }
+}
+
+/// The Batch API routes proper; the store does blocking file IO.
+pub(crate) fn batch_api_reply(
+    batches: &BatchStore,
+    method: &hyper::Method,
+    path: &str,
+    content_type: &str,
+    body: &[u8],
+    client_key: Option<&ClientKey>,
+) -> Result<(&'static str, Bytes), (StatusCode, String)> {
+    let json = |value: serde_json::Value| ("application/json", Bytes::from(value.to_string()));
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, message);
+    let not_found =
+        |what: &str, id: &str| (StatusCode::NOT_FOUND, format!("No such {what}: '{id}'"));
+    // Another client key's files and batches are as good as missing.
+    let owner = client_key.map(|key| key.name.as_str());
+    let file = |id: &str| batches.file(id).filter(|file| file.is_visible_to(owner));
+    let batch = |id: &str| batches.batch(id).filter(|batch| batch.is_visible_to(owner));
+
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    match (method.clone(), segments.as_slice()) {
+        (hyper::Method::POST, ["files"]) => {
+            let form = parse_multipart(content_type, body).map_err(bad_request)?;
+            let field = |name: &str| form.iter().find(|part| part.name == name);
+            let file =
+                field("file").ok_or_else(|| bad_request("Missing 'file' field".to_string()))?;
+            let purpose = field("purpose")
+                .map(|part| String::from_utf8_lossy(&part.data).trim().to_string())
+                .unwrap_or_default();
+            let filename = file.filename.as_deref().unwrap_or("input.jsonl");
+            let stored = batches
+                .create_file(filename, &purpose, &file.data, owner)
+                .map_err(bad_request)?;
+            Ok(json(serde_json::json!(stored)))
+        }
+        (hyper::Method::GET, ["files", id]) => file(id)
+            .map(|file| json(serde_json::json!(file)))
+            .ok_or_else(|| not_found("file", id)),
+        (hyper::Method::GET, ["files", id, "content"]) => file(id)
+            .and_then(|_| batches.file_content(id))
+            .map(|content| ("application/jsonl", Bytes::from(content)))
+            .ok_or_else(|| not_found("file", id)),
+        (hyper::Method::POST, ["batches"]) => {
+            let request: serde_json::Value = serde_json::from_slice(body)
+                .map_err(|e| bad_request(format!("Invalid JSON body: {e}")))?;
+            let input_file_id = request.get("input_file_id").and_then(|v| v.as_str());
+            if let Some(id) = input_file_id.filter(|id| file(id).is_none()) {
+                return Err(bad_request(format!("No such file: '{id}'")));
+            }
+            // A model-scoped client key may only batch the models it could call.
+            if let Some(key) = client_key.filter(|key| !key.settings.allowed_models.is_empty()) {
+                let models = batches.input_models(input_file_id.unwrap_or(""));
+                if let Some(model) = models.iter().find(|m| !key.allows_model(m)) {
+                    return Err((
+                        StatusCode::FORBIDDEN,
+                        format!(
+                            "API key '{}' is not allowed to use model '{model}'",
+                            key.name
+                        ),
+                    ));
+                }
+            }
+            let batch = batches.create_batch(&request, owner).map_err(bad_request)?;
+            Ok(json(serde_json::json!(batch)))
+        }
+        (hyper::Method::GET, ["batches", id]) => batch(id)
+            .map(|batch| json(serde_json::json!(batch)))
+            .ok_or_else(|| not_found("batch", id)),
+        (hyper::Method::POST, ["batches", id, "cancel"]) => batch(id)
+            .and_then(|_| batches.cancel_batch(id))
+            .map(|batch| json(serde_json::json!(batch)))
+            .ok_or_else(|| not_found("batch", id)),
+        _ => Err((
+            StatusCode::NOT_FOUND,
+            format!("Unknown route: {method} {path}"),
+        )),
+    }
+}
+
+/// Paths served without host validation or authentication (docs, landing page
+/// and the opt-in metrics endpoint, which scrapers reach from other hosts).
+const WHITELISTED_PATHS: [&str; 7] = [
+    "/",
+    "/openapi.json",
+    "/favicon.ico",
+    "/docs/swagger-ui.css",
+    "/docs/swagger-ui-bundle.js",
+    "/docs/swagger-ui-standalone-preset.js",
+    "/metrics",
+];
+
+/// Bearer token or `X-Api-Key` value presented by the caller.
+fn presented_api_key(headers: &hyper::HeaderMap) -> Option<&str> {
+    headers
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|auth| auth.strip_prefix("Bearer "))
+        .or_else(|| headers.get("X-Api-Key").and_then(|v| v.to_str().ok()))
+}
+
+/// Routes whose body names the model it runs on, which a model-scoped client
+/// key must name and be allowed. Files, batches and MCP routes carry none.
+pub(crate) fn names_model(route: &str) -> bool {
+    is_metered_route(route)
+        || matches!(
+            route,
+            "/messages/count_tokens" | "/tokenize" | "/detokenize"
+        )
+}
+
+/// Entry point for every connection. Resolves a per-client key (see
+/// `client_keys`) and enforces its route, model and quota scopes before
+/// `proxy_request` runs, then observes the reply so its token usage and
+/// latency land in the usage ledger (and are charged to the client key).
+/// Requests without a client key fall through to the shared proxy-key check,
+/// except that once client keys exist, anonymous access is refused. Requests
+/// naming a model alias are tried against each of its targets in turn (see
+/// `model_aliases`), and deterministic requests may be answered from the
+/// `response_cache`. Requests to local models, including alias targets, wait
+/// for a slot when `admission` control is on. Every request gets an `access_log` record when
+/// enabled.
+#[allow(clippy::too_many_arguments)]
+async fn serve_request(
     req: Request<Incoming>,
     client: Client,
     config: ProxyConfig,
@@ -1294,22 +3171,586 @@ async fn proxy_request(
     mcp_servers: SharedMcpServers,
     mcp_settings: Arc<Mutex<McpSettings>>,
     jan_data_folder: String,
+    client_keys: Arc<ClientKeyStore>,
+    usage: Arc<UsageLedger>,
+    model_aliases: Arc<ModelAliasStore>,
+    response_cache: Option<Arc<ResponseCache>>,
+    access_log: Option<Arc<AccessLog>>,
+    server_metrics: Arc<ServerMetrics>,
+    batches: Arc<BatchStore>,
+    admission: Option<Arc<AdmissionController>>,
 ) -> Result<Response<ResBody>, hyper::Error> {
-    if req.method() == hyper::Method::OPTIONS {
-        log::debug!(
-            "Handling CORS preflight request from {:?} {:?}",
-            req.headers().get(hyper::header::HOST),
-            req.headers()
-                .get(hyper::header::ACCESS_CONTROL_REQUEST_METHOD)
-        );
-
-        let host = req
-            .headers()
-            .get(hyper::header::HOST)
+    let received_at = chrono::Utc::now();
+    let path = get_destination_path(req.uri().path(), &config.prefix);
+    let is_public =
+        req.method() == hyper::Method::OPTIONS || WHITELISTED_PATHS.contains(&path.as_str());
+
+    let client_key = match presented_api_key(req.headers()) {
+        Some(secret) if !is_public => client_keys.authenticate(secret),
+        _ => None,
+    };
+    let capture_bodies = access_log.as_ref().is_some_and(|log| log.captures_bodies());
+    let presented_secret = presented_api_key(req.headers())
+        .filter(|_| capture_bodies)
+        .map(str::to_string);
+
+    let header = |name: hyper::header::HeaderName| {
+        req.headers()
+            .get(name)
             .and_then(|v| v.to_str().ok())
-            .unwrap_or("");
+            .unwrap_or("")
+            .to_string()
+    };
+    let host_header = header(hyper::header::HOST);
+    let origin_header = header(hyper::header::ORIGIN);
+    let reply = |status: StatusCode| {
+        let mut error_response = Response::builder().status(status);
+        error_response = add_cors_headers_with_host_and_origin(
+            error_response,
+            &host_header,
+            &origin_header,
+            &config.trusted_hosts,
+        );
+        error_response
+    };
 
-        let origin = req
+    match &client_key {
+        None if !is_public && config.proxy_api_key.is_empty() && client_keys.has_keys() => {
+            return Ok(reply(StatusCode::UNAUTHORIZED)
+                .body(full("Invalid or missing authorization token"))
+                .unwrap());
+        }
+        Some(key)
+            if !key.allows_route(&path)
+                || ((path == "/orchestrations" || is_mcp_features_path(&path))
+                    && !key.settings.allow_server_tools) =>
+        {
+            log::warn!("Client key '{}' denied route {path}", key.name);
+            return Ok(reply(StatusCode::FORBIDDEN)
+                .body(full(format!(
+                    "API key '{}' is not allowed to call {path}",
+                    key.name
+                )))
+                .unwrap());
+        }
+        _ => {}
+    }
+
+    let (parts, body) = req.into_parts();
+    let metered = parts.method == hyper::Method::POST && is_metered_route(&path);
+    let checks_model = parts.method == hyper::Method::POST
+        && names_model(&path)
+        && client_key
+            .as_ref()
+            .is_some_and(|key| !key.settings.allowed_models.is_empty());
+    let mut model: Option<String> = None;
+    let mut buffered: Option<Bytes> = None;
+    let buffers_body =
+        parts.method == hyper::Method::POST && (metered || checks_model || capture_bodies);
+    let body: ReqBody = if buffers_body {
+        let bytes = match body.collect().await {
+            Ok(c) => c.to_bytes(),
+            Err(_) => {
+                return Ok(reply(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(full("Failed to read request body"))
+                    .unwrap());
+            }
+        };
+        model = serde_json::from_slice::<serde_json::Value>(&bytes)
+            .ok()
+            .and_then(|v| v.get("model").and_then(|m| m.as_str()).map(str::to_string));
+        if let Some(key) = client_key.as_ref().filter(|_| checks_model) {
+            let refusal = match &model {
+                Some(m) if key.allows_model(m) => None,
+                Some(m) => Some(format!(
+                    "API key '{}' is not allowed to use model '{m}'",
+                    key.name
+                )),
+                None => Some(format!(
+                    "API key '{}' is restricted to specific models; the request must name one",
+                    key.name
+                )),
+            };
+            if let Some(message) = refusal {
+                log::warn!("{message}");
+                return Ok(reply(StatusCode::FORBIDDEN).body(full(message)).unwrap());
+            }
+        }
+        buffered = Some(bytes.clone());
+        Full::new(bytes).map_err(|never| match never {}).boxed()
+    } else {
+        body.boxed()
+    };
+
+    if let Some(key) = &client_key {
+        if let Err(exceeded) = client_keys.admit(key) {
+            log::warn!("{}", exceeded.message);
+            return Ok(reply(StatusCode::TOO_MANY_REQUESTS)
+                .header(
+                    hyper::header::RETRY_AFTER,
+                    exceeded.retry_after_secs.to_string(),
+                )
+                .body(full(exceeded.message))
+                .unwrap());
+        }
+        log::info!(
+            "{} {path} authorized by client key '{}'",
+            parts.method,
+            key.name
+        );
+    }
+
+    let started = std::time::Instant::now();
+    let key_name = client_key.as_ref().map(|key| key.name.clone());
+    let cache_key = match (&response_cache, &buffered) {
+        (Some(_), Some(bytes)) if metered && !bypasses_cache(&parts.headers) => {
+            serde_json::from_slice::<serde_json::Value>(bytes)
+                .ok()
+                .and_then(|body| response_cache::cache_key(&path, &body))
+        }
+        _ => None,
+    };
+    let cached = match (&response_cache, &cache_key) {
+        (Some(cache), Some(key)) => {
+            let (cache, key) = (cache.clone(), key.clone());
+            tokio::task::spawn_blocking(move || cache.get(&key))
+                .await
+                .ok()
+                .flatten()
+        }
+        _ => None,
+    };
+    let cache_hit = cached.is_some();
+    let method = parts.method.to_string();
+    let request_capture = buffered.clone().filter(|_| capture_bodies);
+    let alias_plan = match (&model, &buffered) {
+        (Some(m), Some(_)) if metered && !cache_hit => model_aliases.plan(m),
+        _ => None,
+    };
+    let priority = Priority::resolve(
+        parts
+            .headers
+            .get(PRIORITY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(Priority::parse),
+        client_key.as_ref().and_then(|key| key.settings.priority),
+    );
+    let admission_client = key_name.as_deref().unwrap_or(ANONYMOUS_CLIENT);
+    let overloaded_reply = |overloaded: Overloaded| {
+        log::warn!("{}", overloaded.message);
+        reply(StatusCode::TOO_MANY_REQUESTS)
+            .header(
+                hyper::header::RETRY_AFTER,
+                overloaded.retry_after_secs.to_string(),
+            )
+            .body(full(overloaded.message))
+            .unwrap()
+    };
+    // Held until the reply has been sent, so streams keep their slot. An
+    // alias takes the slot of whichever target serves it.
+    let mut permit = match &model {
+        Some(m) if metered && !cache_hit && alias_plan.is_none() => {
+            match admit_local(
+                admission.as_ref(),
+                &provider_configs,
+                m,
+                admission_client,
+                priority,
+            )
+            .await
+            {
+                Ok(permit) => permit,
+                Err(overloaded) => return Ok(overloaded_reply(overloaded)),
+            }
+        }
+        _ => None,
+    };
+    let forward = |req: Request<ReqBody>, client_key: Option<ClientKey>| {
+        proxy_request(
+            req,
+            client.clone(),
+            config.clone(),
+            llama_state.clone(),
+            mlx_sessions.clone(),
+            provider_configs.clone(),
+            model_param_defaults.clone(),
+            mcp_servers.clone(),
+            mcp_settings.clone(),
+            jan_data_folder.clone(),
+            client_key,
+            usage.clone(),
+            model_aliases.clone(),
+            server_metrics.clone(),
+            batches.clone(),
+            admission.clone(),
+        )
+    };
+
+    let mut response = match (cached, alias_plan.zip(buffered)) {
+        (Some(entry), _) => {
+            log::debug!("Serving {path} from the response cache");
+            reply(StatusCode::OK)
+                .header(hyper::header::CONTENT_TYPE, entry.content_type.clone())
+                .body(replay_cached(entry))
+                .unwrap()
+        }
+        (None, Some((plan, bytes))) => {
+            let attempt = try_alias_targets(
+                &plan,
+                admission.as_ref(),
+                &provider_configs,
+                admission_client,
+                priority,
+                |target: &str| {
+                    let body = Full::new(with_model(&bytes, target))
+                        .map_err(|never| match never {})
+                        .boxed();
+                    forward(Request::from_parts(parts.clone(), body), client_key.clone())
+                },
+            )
+            .await?;
+            let (mut response, target) = match attempt {
+                AliasAttempt::Served {
+                    response,
+                    target,
+                    permit: slot,
+                } => {
+                    permit = slot;
+                    (response, target)
+                }
+                AliasAttempt::TimedOut(target) => {
+                    let message =
+                        format!("Model alias '{}': no target responded in time", plan.alias);
+                    (
+                        reply(StatusCode::GATEWAY_TIMEOUT)
+                            .body(full(message))
+                            .unwrap(),
+                        target,
+                    )
+                }
+                AliasAttempt::Overloaded(target, overloaded) => {
+                    (overloaded_reply(overloaded), target)
+                }
+                AliasAttempt::NoTargets => {
+                    return Ok(reply(StatusCode::BAD_GATEWAY)
+                        .body(full(format!("Model alias '{}' has no targets", plan.alias)))
+                        .unwrap());
+                }
+            };
+            if let Ok(value) = hyper::header::HeaderValue::from_str(&target) {
+                response.headers_mut().insert(UPSTREAM_MODEL_HEADER, value);
+            }
+            model = Some(target);
+            response
+        }
+        (None, None) => forward(Request::from_parts(parts, body), client_key).await?,
+    };
+    if let Some(key) = cache_key {
+        let status = if cache_hit { "hit" } else { "miss" };
+        response.headers_mut().insert(
+            RESPONSE_CACHE_HEADER,
+            hyper::header::HeaderValue::from_static(status),
+        );
+        if let Some(cache) = response_cache.filter(|_| !cache_hit) {
+            if response.status() == StatusCode::OK {
+                response = store_in_cache(response, cache, key);
+            }
+        }
+    }
+
+    let provider = if metered {
+        Some(match &model {
+            // Hits are reported separately so they don't inflate model stats.
+            _ if cache_hit => "cache".to_string(),
+            Some(m) => usage_provider_label(m, &provider_configs, &mlx_sessions).await,
+            None => "unknown".to_string(),
+        })
+    } else {
+        None
+    };
+    if provider.is_none() && key_name.is_none() && access_log.is_none() {
+        return Ok(response);
+    }
+
+    let redactor = if capture_bodies {
+        Some(Redactor::for_request(
+            &config.proxy_api_key,
+            presented_secret.as_deref(),
+            provider_configs.lock().await.values(),
+        ))
+    } else {
+        None
+    };
+    response.extensions_mut().insert(AccessLogged);
+    let route = metrics::route_label(&path);
+    let stream_guard = is_event_stream(&response).then(|| server_metrics.track_stream());
+    Ok(observe_response(
+        response,
+        started,
+        capture_bodies,
+        move |outcome| {
+            drop(stream_guard);
+            drop(permit);
+            if let Some(access_log) = access_log {
+                let capture = |body: &[u8]| redactor.as_ref().map(|r| r.capture(body));
+                let record = AccessRecord {
+                    timestamp: received_at.to_rfc3339(),
+                    method,
+                    route: path,
+                    status: outcome.status,
+                    model: model.clone(),
+                    upstream: provider.clone(),
+                    client: key_name.clone(),
+                    latency_ms: outcome.latency_ms,
+                    ttft_ms: outcome.ttft_ms,
+                    input_tokens: outcome.tokens.input_tokens,
+                    output_tokens: outcome.tokens.output_tokens,
+                    request_body: request_capture.as_deref().and_then(capture),
+                    response_body: outcome.body.as_deref().and_then(capture),
+                };
+                tokio::task::spawn_blocking(move || access_log.write(&record));
+            }
+            if let Some(name) = key_name.as_ref().filter(|_| !cache_hit) {
+                client_keys.record_tokens(name, outcome.tokens.total());
+            }
+            if provider.is_some() {
+                server_metrics.observe_latency(route, outcome.latency_ms, outcome.ttft_ms);
+            }
+            // Auth failures never reached a model; don't let them skew the stats.
+            let Some(provider) = provider.filter(|_| !matches!(outcome.status, 401 | 403)) else {
+                return;
+            };
+            let event = UsageEvent {
+                model: model.unwrap_or_else(|| "unknown".to_string()),
+                provider,
+                client: key_name,
+                status: outcome.status,
+                tokens: outcome.tokens,
+                latency_ms: outcome.latency_ms,
+                ttft_ms: outcome.ttft_ms,
+            };
+            tokio::task::spawn_blocking(move || usage.record(event));
+        },
+    ))
+}
+
+/// Marks replies that `serve_request` logged itself; the connection handler
+/// logs the rest (requests refused before proxying).
+#[derive(Clone, Copy)]
+struct AccessLogged;
+
+/// Reply header naming the alias target that served the request.
+const UPSTREAM_MODEL_HEADER: &str = "x-jan-upstream-model";
+
+/// `body` (a JSON object) with its `model` replaced by `model`.
+fn with_model(body: &Bytes, model: &str) -> Bytes {
+    let Ok(mut json) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return body.clone();
+    };
+    let Some(object) = json.as_object_mut() else {
+        return body.clone();
+    };
+    object.insert("model".to_string(), model.into());
+    serde_json::to_vec(&json)
+        .map(Bytes::from)
+        .unwrap_or_else(|_| body.clone())
+}
+
+/// Usage-ledger provider label for `model`: the remote provider's name,
+/// `mlx` for an MLX session, otherwise `llamacpp`.
+async fn usage_provider_label(
+    model: &str,
+    provider_configs: &Arc<Mutex<HashMap<String, ProviderConfig>>>,
+    mlx_sessions: &Arc<Mutex<HashMap<i32, MlxBackendSession>>>,
+) -> String {
+    if let Some(provider) = find_provider_for_model(&*provider_configs.lock().await, model) {
+        return provider;
+    }
+    if mlx_sessions
+        .lock()
+        .await
+        .values()
+        .any(|s| s.info.model_id == model)
+    {
+        return "mlx".to_string();
+    }
+    "llamacpp".to_string()
+}
+
+/// Body for a cached reply; streams are replayed one SSE event per frame.
+fn replay_cached(entry: CachedResponse) -> ResBody {
+    if !entry.is_event_stream() {
+        return full(entry.body);
+    }
+    let (mut sender, body) = body_channel();
+    tokio::spawn(async move {
+        for event in entry.body.split_inclusive("\n\n") {
+            if sender
+                .send_data(Bytes::copy_from_slice(event.as_bytes()))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+    body
+}
+
+/// Pass `response` through unchanged, saving a copy of its body in `cache`
+/// under `key` once the client has received all of it.
+fn store_in_cache(
+    response: Response<ResBody>,
+    cache: Arc<ResponseCache>,
+    key: String,
+) -> Response<ResBody> {
+    let content_type = response
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json")
+        .to_string();
+    let (parts, mut inner) = response.into_parts();
+    let (mut sender, body) = body_channel();
+    tokio::spawn(async move {
+        let limit = cache.max_entry_bytes();
+        let mut copy = Some(Vec::new());
+        loop {
+            let Some(next) = sender.unless_closed(inner.frame()).await else {
+                // The client went away; the reply may be cut short.
+                return;
+            };
+            let Some(frame) = next else {
+                break;
+            };
+            let frame = match frame {
+                Ok(frame) => frame,
+                Err(never) => match never {},
+            };
+            if let Ok(data) = frame.into_data() {
+                copy = copy
+                    .filter(|copy| copy.len() + data.len() <= limit)
+                    .map(|mut copy| {
+                        copy.extend_from_slice(&data);
+                        copy
+                    });
+                if sender.send_data(data).await.is_err() {
+                    // The client went away; the reply may be cut short.
+                    return;
+                }
+            }
+        }
+        drop(sender);
+        if let Some(body) = copy.and_then(|copy| String::from_utf8(copy).ok()) {
+            tokio::task::spawn_blocking(move || cache.put(&key, &content_type, body));
+        }
+    });
+    Response::from_parts(parts, body)
+}
+
+/// What [`observe_response`] saw of a finished reply.
+struct ResponseOutcome {
+    status: u16,
+    tokens: TokenCounts,
+    latency_ms: u64,
+    /// Time until the first body bytes; `None` when the body was empty.
+    ttft_ms: Option<u64>,
+    /// Start of the body, when captured for the access log.
+    body: Option<Vec<u8>>,
+}
+
+/// Streamed replies: SSE, or NDJSON from the Ollama routes.
+fn is_event_stream(response: &Response<ResBody>) -> bool {
+    response
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.contains("event-stream") || ct.contains(NDJSON_CONTENT_TYPE))
+}
+
+/// Tee the reply body through a [`UsageScanner`], timing the first and last
+/// bytes, and hand the outcome to `on_complete` once the body ends (or the
+/// client goes away). With `capture`, the start of the body is kept too.
+fn observe_response(
+    response: Response<ResBody>,
+    started: std::time::Instant,
+    capture: bool,
+    on_complete: impl FnOnce(ResponseOutcome) + Send + 'static,
+) -> Response<ResBody> {
+    let status = response.status().as_u16();
+    let is_sse = is_event_stream(&response);
+    let (parts, mut inner) = response.into_parts();
+    let (mut sender, body) = body_channel();
+    tokio::spawn(async move {
+        let mut scanner = UsageScanner::new(is_sse);
+        let mut ttft_ms = None;
+        let mut captured = capture.then(Vec::new);
+        while let Some(Some(frame)) = sender.unless_closed(inner.frame()).await {
+            let frame = match frame {
+                Ok(frame) => frame,
+                Err(never) => match never {},
+            };
+            if let Ok(data) = frame.into_data() {
+                if ttft_ms.is_none() && !data.is_empty() {
+                    ttft_ms = Some(started.elapsed().as_millis() as u64);
+                }
+                scanner.push(&data);
+                if let Some(captured) = &mut captured {
+                    // One byte past the limit marks the capture as truncated.
+                    let room = (CAPTURE_LIMIT + 1).saturating_sub(captured.len());
+                    captured.extend_from_slice(&data[..data.len().min(room)]);
+                }
+                if sender.send_data(data).await.is_err() {
+                    break;
+                }
+            }
+        }
+        on_complete(ResponseOutcome {
+            status,
+            tokens: scanner.finish(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            ttft_ms,
+            body: captured,
+        });
+    });
+    Response::from_parts(parts, body)
+}
+
+/// Handles the proxy request logic
+#[allow(clippy::too_many_arguments)]
+async fn proxy_request(
+    req: Request<ReqBody>,
+    client: Client,
+    mut config: ProxyConfig,
+    llama_state: Arc<LlamacppState>,
+    mlx_sessions: Arc<Mutex<HashMap<i32, MlxBackendSession>>>,
+    provider_configs: Arc<Mutex<HashMap<String, ProviderConfig>>>,
+    model_param_defaults: Arc<Mutex<HashMap<String, serde_json::Value>>>,
+    mcp_servers: SharedMcpServers,
+    mcp_settings: Arc<Mutex<McpSettings>>,
+    jan_data_folder: String,
+    client_key: Option<ClientKey>,
+    usage: Arc<UsageLedger>,
+    model_aliases: Arc<ModelAliasStore>,
+    server_metrics: Arc<ServerMetrics>,
+    batches: Arc<BatchStore>,
+    admission: Option<Arc<AdmissionController>>,
+) -> Result<Response<ResBody>, hyper::Error> {
+    if req.method() == hyper::Method::OPTIONS {
+        log::debug!(
+            "Handling CORS preflight request from {:?} {:?}",
+            req.headers().get(hyper::header::HOST),
+            req.headers()
+                .get(hyper::header::ACCESS_CONTROL_REQUEST_METHOD)
+        );
+
+        let host = req
+            .headers()
+            .get(hyper::header::HOST)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+
+        let origin = req
             .headers()
             .get(hyper::header::ORIGIN)
             .and_then(|v| v.to_str().ok())
@@ -1466,14 +3907,7 @@ async fn proxy_request(
     let path = get_destination_path(original_path, &config.prefix);
     let method = parts.method.clone();
 
-    let whitelisted_paths = [
-        "/",
-        "/openapi.json",
-        "/favicon.ico",
-        "/docs/swagger-ui.css",
-        "/docs/swagger-ui-bundle.js",
-        "/docs/swagger-ui-standalone-preset.js",
-    ];
+    let whitelisted_paths = WHITELISTED_PATHS;
     let is_whitelisted_path = whitelisted_paths.contains(&path.as_str());
 
     if !is_whitelisted_path {
@@ -1506,7 +3940,8 @@ async fn proxy_request(
         log::debug!("Bypassing host validation for whitelisted path: {path}");
     }
 
-    if !is_whitelisted_path && !config.proxy_api_key.is_empty() {
+    // Requests already authorized by a client key skip the shared-key check.
+    if !is_whitelisted_path && !config.proxy_api_key.is_empty() && client_key.is_none() {
         // Check Authorization header (Bearer token)
         let auth_valid = parts
             .headers
@@ -1540,6 +3975,13 @@ async fn proxy_request(
         log::debug!("Bypassing authorization check for whitelisted path: {path}");
     }
 
+    if client_key
+        .as_ref()
+        .is_some_and(|key| !key.settings.allow_server_tools)
+    {
+        config.enable_server_tool_execution = false;
+    }
+
     if path.contains("/configs") {
         let mut error_response = Response::builder().status(StatusCode::NOT_FOUND);
         error_response = add_cors_headers_with_host_and_origin(
@@ -1566,6 +4008,9 @@ async fn proxy_request(
     // Model id when the request resolves to an MLX session — MLX has no preset,
     // so sampling defaults are injected into the body before forwarding.
     let mut mlx_model_id: Option<String> = None;
+    // Set when a non-chat ingress is served through chat/completions (or a
+    // converter); the chat-shaped reply is translated back for the client.
+    let mut translated_ingress: Option<TranslatedIngress> = None;
 
     match (method.clone(), destination_path.as_str()) {
         // Anthropic /messages endpoint - tries /messages first, falls back to /chat/completions on error
@@ -1637,6 +4082,10 @@ async fn proxy_request(
                             mcp_servers.clone(),
                             mcp_settings.clone(),
                             &jan_data_folder,
+                            config.enable_tool_emulation,
+                            ApprovalMode::Desktop,
+                            None,
+                            None,
                         )
                         .await
                         {
@@ -1697,10 +4146,50 @@ async fn proxy_request(
                             drop(pc2);
 
                             if let Some(provider_cfg) = provider_config {
-                                target_base_url = provider_cfg.base_url.clone().map(|url| {
-                                    format!("{}{}", url.trim_end_matches('/'), "/messages")
-                                });
                                 session_api_keys = provider_cfg.bearer_key_chain();
+                                // Native Anthropic and OpenAI-compatible providers
+                                // take /messages directly (with the chat fallback
+                                // below); other native APIs go Anthropic -> chat ->
+                                // converter and back.
+                                let converter = match provider_cfg.api_type.as_deref() {
+                                    Some("anthropic") => None,
+                                    other => converter_for(other),
+                                };
+                                if let Some(converter) = converter {
+                                    let Some(mut chat_body) =
+                                        transform_anthropic_to_openai(&json_body)
+                                    else {
+                                        let mut error_response =
+                                            Response::builder().status(StatusCode::BAD_REQUEST);
+                                        error_response = add_cors_headers_with_host_and_origin(
+                                            error_response,
+                                            &host_header,
+                                            &origin_header,
+                                            &config.trusted_hosts,
+                                        );
+                                        return Ok(error_response
+                                            .body(full("Invalid /messages payload"))
+                                            .unwrap());
+                                    };
+                                    // Native APIs enforce their own limits, so keep
+                                    // the client's max_tokens on this path.
+                                    if let Some(max_tokens) = json_body.get("max_tokens") {
+                                        chat_body["max_tokens"] = max_tokens.clone();
+                                    }
+                                    let path = converter.upstream_path(&chat_body);
+                                    target_base_url = provider_cfg
+                                        .base_url
+                                        .clone()
+                                        .map(|url| format!("{}{path}", url.trim_end_matches('/')));
+                                    buffered_body =
+                                        serde_json::to_vec(&chat_body).ok().map(Bytes::from);
+                                    upstream_converter = Some(converter);
+                                    translated_ingress = Some(TranslatedIngress::Anthropic);
+                                } else {
+                                    target_base_url = provider_cfg.base_url.clone().map(|url| {
+                                        format!("{}{}", url.trim_end_matches('/'), "/messages")
+                                    });
+                                }
                             }
                         } else {
                             let mlx_session_info = {
@@ -1790,7 +4279,7 @@ async fn proxy_request(
                 }
             };
 
-            let json_body: serde_json::Value = match serde_json::from_slice(&body_bytes) {
+            let mut json_body: serde_json::Value = match serde_json::from_slice(&body_bytes) {
                 Ok(v) => v,
                 Err(e) => {
                     let mut error_response = Response::builder()
@@ -1814,17 +4303,108 @@ async fn proxy_request(
                 .filter(|v| !v.is_empty())
                 .map(|v| v.to_string());
 
+            // Saved as a Jan thread: `thread_id` continues a stored thread,
+            // whose conversation goes ahead of `messages`; `persist` starts a
+            // new one. Only the run's own messages are added to the thread.
+            let thread = match orchestration_threads::thread_target(&json_body) {
+                Ok(v) => v,
+                Err(e) => {
+                    let mut error_response = Response::builder().status(StatusCode::BAD_REQUEST);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response.body(full(e)).unwrap());
+                }
+            };
+            let mut history_len = 0;
+            if let Some(ThreadTarget::Existing(thread_id)) = &thread {
+                match orchestration_threads::load_thread(Path::new(&jan_data_folder), thread_id)
+                    .await
+                {
+                    Ok(stored) => {
+                        let history = orchestration_threads::thread_history(&stored);
+                        history_len = history.len();
+                        orchestration_threads::prepend_history(&mut json_body, history);
+                    }
+                    Err(e) => {
+                        let mut error_response = Response::builder().status(StatusCode::NOT_FOUND);
+                        error_response = add_cors_headers_with_host_and_origin(
+                            error_response,
+                            &host_header,
+                            &origin_header,
+                            &config.trusted_hosts,
+                        );
+                        return Ok(error_response.body(full(e)).unwrap());
+                    }
+                }
+            }
+
+            // Streamed: the same loop, reporting each step as an SSE event
+            // (see `orchestration_stream`) and ending with the final reply.
             let stream = json_body.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
             if stream {
-                let mut error_response = Response::builder()
-                    .status(StatusCode::BAD_REQUEST);
-                error_response = add_cors_headers_with_host_and_origin(
-                    error_response,
+                let (mut sender, body) = body_channel();
+                let client = client.clone();
+                let provider_configs = provider_configs.clone();
+                let llama_state = llama_state.clone();
+                let mlx_sessions = mlx_sessions.clone();
+                let mcp_servers = mcp_servers.clone();
+                let mcp_settings = mcp_settings.clone();
+                let jan_data_folder = jan_data_folder.clone();
+                let enable_tool_emulation = config.enable_tool_emulation;
+                let approval = orchestration_approval_mode(client_key.as_ref());
+                tokio::spawn(async move {
+                    let mut transcript = Vec::new();
+                    let outcome = run_server_side_openai_orchestration(
+                        &json_body,
+                        &client,
+                        provider_configs.clone(),
+                        llama_state,
+                        mlx_sessions,
+                        mcp_servers,
+                        mcp_settings,
+                        &jan_data_folder,
+                        enable_tool_emulation,
+                        approval,
+                        Some(&mut sender),
+                        thread.as_ref().map(|_| &mut transcript),
+                    )
+                    .await;
+                    let event = match outcome {
+                        Ok(mut response) => {
+                            if let Some(target) = &thread {
+                                save_orchestration_thread(
+                                    target,
+                                    &jan_data_folder,
+                                    &provider_configs,
+                                    &transcript,
+                                    history_len,
+                                    assistant_id.as_deref(),
+                                    &mut response,
+                                )
+                                .await;
+                            }
+                            orchestration_stream::completed(&response)
+                        }
+                        Err(e) if e == ORCHESTRATION_CLIENT_GONE => return,
+                        Err(e) => orchestration_stream::failed(&e),
+                    };
+                    let _ = sender.send_data(sse_event(&event)).await;
+                });
+                let mut response_builder = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(hyper::header::CONTENT_TYPE, "text/event-stream")
+                    .header(hyper::header::CACHE_CONTROL, "no-cache");
+                response_builder = add_cors_headers_with_host_and_origin(
+                    response_builder,
                     &host_header,
                     &origin_header,
                     &config.trusted_hosts,
                 );
-                return Ok(error_response.body(full("stream=true is not supported for /orchestrations")).unwrap());
+                return Ok(response_builder.body(body).unwrap());
             }
 
             let messages_value = match json_body.get("messages") {
@@ -1963,6 +4543,76 @@ async fn proxy_request(
                     return Ok(error_response.body(full(e)).unwrap());
                 }
             };
+            let emulation = tool_emulation_for(
+                config.enable_tool_emulation,
+                &serde_json::json!({ "tools": openai_tools }),
+                &model_id,
+                &provider_configs,
+                &mlx_sessions,
+                &jan_data_folder,
+            )
+            .await;
+
+            // Tool calls the approval policy holds are answered in the app,
+            // or for keys that approve tool calls handed back to the caller,
+            // who resumes by sending the conversation with its `approvals`.
+            let approval = orchestration_approval_mode(client_key.as_ref());
+            let held = if let ApprovalMode::Client(_) = approval {
+                unanswered_tool_calls(&conversation_messages)
+            } else {
+                Vec::new()
+            };
+            if !held.is_empty() {
+                let run = match execute_mcp_tool_calls(
+                    &held,
+                    &tool_to_server,
+                    &mcp_servers,
+                    &mcp_settings,
+                    &approval,
+                    Some(&client_tool_approvals(&json_body)),
+                    None,
+                )
+                .await
+                {
+                    Ok(v) => v,
+                    Err(e) => {
+                        let mut error_response =
+                            Response::builder().status(StatusCode::INTERNAL_SERVER_ERROR);
+                        error_response = add_cors_headers_with_host_and_origin(
+                            error_response,
+                            &host_header,
+                            &origin_header,
+                            &config.trusted_hosts,
+                        );
+                        return Ok(error_response.body(full(e)).unwrap());
+                    }
+                };
+                if let Some(mut reply) = record_tool_run(&mut conversation_messages, run, &model_id)
+                {
+                    if let Some(target) = &thread {
+                        save_orchestration_thread(
+                            target,
+                            &jan_data_folder,
+                            &provider_configs,
+                            &conversation_messages,
+                            history_len,
+                            assistant_id.as_deref(),
+                            &mut reply,
+                        )
+                        .await;
+                    }
+                    let mut response_builder = Response::builder()
+                        .status(StatusCode::OK)
+                        .header(hyper::header::CONTENT_TYPE, "application/json");
+                    response_builder = add_cors_headers_with_host_and_origin(
+                        response_builder,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(response_builder.body(full(reply.to_string())).unwrap());
+                }
+            }
 
             let max_turns = json_body
                 .get("max_turns")
@@ -1991,11 +4641,12 @@ async fn proxy_request(
 
                 let request_value = serde_json::Value::Object(completion_map);
 
-                let completion = match call_openai_chat_completions(
+                let completion = match call_chat_completions_emulated(
                     &client,
                     &upstream_url,
                     &session_api_keys,
                     &request_value,
+                    emulation.as_ref(),
                 )
                 .await
                 {
@@ -2017,6 +4668,20 @@ async fn proxy_request(
                 last_response = Some(completion.clone());
 
                 if tool_calls.is_empty() {
+                    let mut completion = completion;
+                    if let Some(target) = &thread {
+                        conversation_messages.push(final_assistant_turn(&completion));
+                        save_orchestration_thread(
+                            target,
+                            &jan_data_folder,
+                            &provider_configs,
+                            &conversation_messages,
+                            history_len,
+                            assistant_id.as_deref(),
+                            &mut completion,
+                        )
+                        .await;
+                    }
                     let body_str = serde_json::to_string(&completion).unwrap_or_else(|_| "{}".to_string());
                     let mut response_builder = Response::builder()
                         .status(StatusCode::OK)
@@ -2049,11 +4714,14 @@ async fn proxy_request(
                     }));
                 }
 
-                let tool_results = match execute_mcp_tool_calls(
+                let run = match execute_mcp_tool_calls(
                     &tool_calls,
                     &tool_to_server,
                     &mcp_servers,
                     &mcp_settings,
+                    &approval,
+                    None,
+                    None,
                 )
                 .await
                 {
@@ -2070,16 +4738,33 @@ async fn proxy_request(
                         return Ok(error_response.body(full(e)).unwrap());
                     }
                 };
-
-                for (tool_call_id, result_text) in tool_results {
-                    conversation_messages.push(serde_json::json!({
-                        "role": "tool",
-                        "tool_call_id": tool_call_id,
-                        "content": result_text
-                    }));
-                }
-            }
-
+                if let Some(mut reply) = record_tool_run(&mut conversation_messages, run, &model_id)
+                {
+                    if let Some(target) = &thread {
+                        save_orchestration_thread(
+                            target,
+                            &jan_data_folder,
+                            &provider_configs,
+                            &conversation_messages,
+                            history_len,
+                            assistant_id.as_deref(),
+                            &mut reply,
+                        )
+                        .await;
+                    }
+                    let mut response_builder = Response::builder()
+                        .status(StatusCode::OK)
+                        .header(hyper::header::CONTENT_TYPE, "application/json");
+                    response_builder = add_cors_headers_with_host_and_origin(
+                        response_builder,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(response_builder.body(full(reply.to_string())).unwrap());
+                }
+            }
+
             // Safety catch: model kept requesting tools beyond max_turns.
             let body_json = last_response.unwrap_or_else(|| serde_json::json!({}));
             let body_str = serde_json::to_string(&body_json).unwrap_or_else(|_| "{}".to_string());
@@ -2096,10 +4781,68 @@ async fn proxy_request(
             let response = error_response.body(full(payload)).unwrap();
             return Ok(response);
         }
+        (hyper::Method::POST, "/messages/count_tokens")
+        | (hyper::Method::POST, "/tokenize")
+        | (hyper::Method::POST, "/detokenize") => {
+            let body_bytes = match body.collect().await {
+                Ok(c) => c.to_bytes(),
+                Err(_) => {
+                    let mut error_response =
+                        Response::builder().status(StatusCode::INTERNAL_SERVER_ERROR);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response
+                        .body(full("Failed to read request body"))
+                        .unwrap());
+                }
+            };
+            let (status, reply) = match serde_json::from_slice::<serde_json::Value>(&body_bytes) {
+                Ok(mut json_body) if destination_path == "/messages/count_tokens" => {
+                    strip_billing_header_in_body(&mut json_body);
+                    count_message_tokens(
+                        &json_body,
+                        &client,
+                        &provider_configs,
+                        &llama_state,
+                        &mlx_sessions,
+                    )
+                    .await
+                }
+                Ok(json_body) => {
+                    tokenize_locally(
+                        &destination_path,
+                        &json_body,
+                        &client,
+                        &provider_configs,
+                        &llama_state,
+                        &mlx_sessions,
+                        &jan_data_folder,
+                    )
+                    .await
+                }
+                Err(e) => (
+                    StatusCode::BAD_REQUEST,
+                    openai_error(&format!("Invalid JSON body: {e}"), None),
+                ),
+            };
+            let mut response_builder = Response::builder()
+                .status(status)
+                .header(hyper::header::CONTENT_TYPE, "application/json");
+            response_builder = add_cors_headers_with_host_and_origin(
+                response_builder,
+                &host_header,
+                &origin_header,
+                &config.trusted_hosts,
+            );
+            return Ok(response_builder.body(full(reply.to_string())).unwrap());
+        }
         (hyper::Method::POST, "/chat/completions")
         | (hyper::Method::POST, "/completions")
-        | (hyper::Method::POST, "/embeddings")
-        | (hyper::Method::POST, "/messages/count_tokens") => {
+        | (hyper::Method::POST, "/embeddings") => {
             log::info!(
                 "Handling POST request to {destination_path} requiring model lookup in body",
             );
@@ -2152,6 +4895,10 @@ async fn proxy_request(
                             mcp_servers.clone(),
                             mcp_settings.clone(),
                             &jan_data_folder,
+                            config.enable_tool_emulation,
+                            ApprovalMode::Desktop,
+                            None,
+                            None,
                         )
                         .await
                         {
@@ -2339,92 +5086,586 @@ async fn proxy_request(
                 }
             }
         }
+        (hyper::Method::POST, "/responses") => {
+            log::info!("Handling POST request to /responses");
+            let body_bytes = match body.collect().await {
+                Ok(c) => c.to_bytes(),
+                Err(_) => {
+                    let mut error_response =
+                        Response::builder().status(StatusCode::INTERNAL_SERVER_ERROR);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response
+                        .body(full("Failed to read request body"))
+                        .unwrap());
+                }
+            };
+
+            let json_body: serde_json::Value = match serde_json::from_slice(&body_bytes) {
+                Ok(v) => v,
+                Err(e) => {
+                    log::warn!("Failed to parse POST body for /responses as JSON: {e}");
+                    let mut error_response = Response::builder().status(StatusCode::BAD_REQUEST);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response
+                        .body(full(format!("Invalid JSON body: {e}")))
+                        .unwrap());
+                }
+            };
+
+            let model_id = json_body
+                .get("model")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let provider_cfg = {
+                let pc = provider_configs.lock().await;
+                find_provider_for_model(&pc, &model_id).and_then(|p| pc.get(&p).cloned())
+            };
+
+            // A provider that natively speaks Responses gets the request verbatim.
+            if let Some(cfg) = provider_cfg
+                .as_ref()
+                .filter(|c| c.api_type.as_deref() == Some("openai-responses"))
+            {
+                log::info!(
+                    "Forwarding /responses natively to provider '{}' for model '{model_id}'",
+                    cfg.provider
+                );
+                target_base_url = cfg
+                    .base_url
+                    .clone()
+                    .map(|url| format!("{}/responses", url.trim_end_matches('/')));
+                session_api_keys = cfg.bearer_key_chain();
+                buffered_body = Some(body_bytes);
+            } else {
+                let mut chat_body = match responses_request_to_chat(&json_body) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        let mut error_response =
+                            Response::builder().status(StatusCode::BAD_REQUEST);
+                        error_response = add_cors_headers_with_host_and_origin(
+                            error_response,
+                            &host_header,
+                            &origin_header,
+                            &config.trusted_hosts,
+                        );
+                        return Ok(error_response.body(full(e)).unwrap());
+                    }
+                };
+                normalize_openai_tools_in_chat_body(&mut chat_body);
+
+                if let Some(cfg) = provider_cfg {
+                    log::info!("Serving /responses via provider '{}' for model '{model_id}'", cfg.provider);
+                    let converter = converter_for(cfg.api_type.as_deref());
+                    let path = converter
+                        .as_ref()
+                        .map(|c| c.upstream_path(&chat_body))
+                        .unwrap_or_else(|| "/chat/completions".to_string());
+                    target_base_url = cfg.base_url.clone().map(|url| format!("{url}{path}"));
+                    upstream_converter = converter;
+                    session_api_keys = cfg.bearer_key_chain();
+                } else {
+                    let mlx_session_info = {
+                        let mlx_guard = mlx_sessions.lock().await;
+                        mlx_guard
+                            .values()
+                            .find(|s| s.info.model_id == model_id)
+                            .map(|s| s.info.clone())
+                    };
+                    if let Some(info) = mlx_session_info {
+                        session_api_keys = vec![info.api_key.clone()];
+                        mlx_model_id = Some(model_id.clone());
+                        target_base_url =
+                            Some(format!("http://127.0.0.1:{}/v1/chat/completions", info.port));
+                    } else if let Some((url, key)) =
+                        router_upstream(&llama_state, "/chat/completions").await
+                    {
+                        session_api_keys = vec![key];
+                        target_base_url = Some(url);
+                    } else {
+                        log::warn!("Request for model '{model_id}' but no models are running.");
+                        let mut error_response =
+                            Response::builder().status(StatusCode::SERVICE_UNAVAILABLE);
+                        error_response = add_cors_headers_with_host_and_origin(
+                            error_response,
+                            &host_header,
+                            &origin_header,
+                            &config.trusted_hosts,
+                        );
+                        return Ok(error_response
+                            .body(full("No models are available"))
+                            .unwrap());
+                    }
+                }
+
+                buffered_body = serde_json::to_vec(&chat_body).ok().map(Bytes::from);
+                translated_ingress = Some(TranslatedIngress::Responses(json_body));
+            }
+        }
         (hyper::Method::GET, "/models") => {
             log::debug!("Handling GET /v1/models request");
 
-            let local_models: Vec<_> = router_list_models(&llama_state, &client)
+            let mut models = describe_models(
+                &llama_state,
+                &client,
+                &mlx_sessions,
+                &provider_configs,
+                &model_aliases,
+                &jan_data_folder,
+            )
+            .await;
+            if let Some(key) = &client_key {
+                models.retain(|model| key.allows_model(&model.id));
+            }
+            let count = |owner: &str| models.iter().filter(|m| m.owned_by == owner).count();
+            let (local_count, mlx_count, remote_count, alias_count) = (
+                count("llama.cpp"),
+                count("mlx"),
+                count("remote"),
+                count("alias"),
+            );
+
+            let all_models: Vec<_> = models.iter().map(ModelDescription::to_json).collect();
+
+            let response_json = serde_json::json!({
+                "object": "list",
+                "data": all_models
+            });
+
+            let body_str =
+                serde_json::to_string(&response_json).unwrap_or_else(|_| "{}".to_string());
+
+            let mut response_builder = Response::builder()
+                .status(StatusCode::OK)
+                .header(hyper::header::CONTENT_TYPE, "application/json");
+
+            response_builder = add_cors_headers_with_host_and_origin(
+                response_builder,
+                &host_header,
+                &origin_header,
+                &config.trusted_hosts,
+            );
+
+            log::debug!(
+                "Returning {} models ({} llama.cpp, {} MLX, {} remote, {} aliases)",
+                all_models.len(),
+                local_count,
+                mlx_count,
+                remote_count,
+                alias_count
+            );
+
+            return Ok(response_builder.body(full(body_str)).unwrap());
+        }
+
+        (hyper::Method::GET, model_path) if model_path.starts_with("/models/") => {
+            // Ids may contain `/` (nested llama.cpp models, `provider/model`)
+            let model_id = &model_path["/models/".len()..];
+            log::debug!("Handling GET /v1/models/{model_id} request");
+
+            let visible = client_key
+                .as_ref()
+                .map_or(true, |key| key.allows_model(model_id));
+            let model = if visible {
+                describe_models(
+                    &llama_state,
+                    &client,
+                    &mlx_sessions,
+                    &provider_configs,
+                    &model_aliases,
+                    &jan_data_folder,
+                )
                 .await
                 .into_iter()
-                .map(|id| {
+                .find(|model| model.id == model_id)
+            } else {
+                None
+            };
+            let (status, body) = match model {
+                Some(model) => (StatusCode::OK, model.to_json()),
+                None => (
+                    StatusCode::NOT_FOUND,
                     serde_json::json!({
-                        "id": id,
-                        "object": "model",
-                        "created": 1,
-                        "owned_by": "llama.cpp"
-                    })
-                })
-                .collect();
-
-            // Get MLX sessions
-            let mlx_models: Vec<_> = {
-                let mlx_guard = mlx_sessions.lock().await;
-                mlx_guard
-                    .values()
-                    .map(|session| {
-                        serde_json::json!({
-                            "id": session.info.model_id,
-                            "object": "model",
-                            "created": 1,
-                            "owned_by": "mlx"
+                        "error": {
+                            "message": format!("The model '{model_id}' does not exist"),
+                            "type": "invalid_request_error",
+                            "param": "model",
+                            "code": "model_not_found"
+                        }
+                    }),
+                ),
+            };
+
+            let mut response_builder = Response::builder()
+                .status(status)
+                .header(hyper::header::CONTENT_TYPE, "application/json");
+            response_builder = add_cors_headers_with_host_and_origin(
+                response_builder,
+                &host_header,
+                &origin_header,
+                &config.trusted_hosts,
+            );
+            return Ok(response_builder.body(full(body.to_string())).unwrap());
+        }
+
+        (hyper::Method::GET, "/usage") => {
+            log::debug!("Handling GET /v1/usage request");
+
+            let mut query = match UsageQuery::from_query_string(parts.uri.query().unwrap_or("")) {
+                Ok(query) => query,
+                Err(e) => {
+                    let mut error_response = Response::builder().status(StatusCode::BAD_REQUEST);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response.body(full(e)).unwrap());
+                }
+            };
+            // A client key only ever sees its own usage.
+            if let Some(key) = &client_key {
+                query.client = Some(key.name.clone());
+            }
+
+            let report = tokio::task::spawn_blocking(move || usage.report(&query))
+                .await
+                .ok()
+                .and_then(|report| serde_json::to_string(&report).ok())
+                .unwrap_or_else(|| "{}".to_string());
+
+            let mut response_builder = Response::builder()
+                .status(StatusCode::OK)
+                .header(hyper::header::CONTENT_TYPE, "application/json");
+            response_builder = add_cors_headers_with_host_and_origin(
+                response_builder,
+                &host_header,
+                &origin_header,
+                &config.trusted_hosts,
+            );
+            return Ok(response_builder.body(full(report)).unwrap());
+        }
+
+        (_, mcp_path) if is_mcp_features_path(mcp_path) => {
+            let (status, reply) =
+                serve_mcp_features(&method, mcp_path, body, &mcp_servers, &mcp_settings).await;
+            let mut response_builder = Response::builder()
+                .status(status)
+                .header(hyper::header::CONTENT_TYPE, "application/json");
+            response_builder = add_cors_headers_with_host_and_origin(
+                response_builder,
+                &host_header,
+                &origin_header,
+                &config.trusted_hosts,
+            );
+            return Ok(response_builder.body(full(reply)).unwrap());
+        }
+
+        (_, batch_path) if is_batch_api_path(batch_path) => {
+            let (status, content_type, reply) = serve_batch_api(
+                &method,
+                batch_path,
+                &parts.headers,
+                body,
+                batches,
+                client_key.as_ref(),
+            )
+            .await;
+            let mut response_builder = Response::builder()
+                .status(status)
+                .header(hyper::header::CONTENT_TYPE, content_type);
+            response_builder = add_cors_headers_with_host_and_origin(
+                response_builder,
+                &host_header,
+                &origin_header,
+                &config.trusted_hosts,
+            );
+            return Ok(response_builder.body(full(reply)).unwrap());
+        }
+
+        (hyper::Method::GET, "/metrics") => {
+            if !config.enable_metrics {
+                let mut error_response = Response::builder().status(StatusCode::NOT_FOUND);
+                error_response = add_cors_headers_with_host_and_origin(
+                    error_response,
+                    &host_header,
+                    &origin_header,
+                    &config.trusted_hosts,
+                );
+                return Ok(error_response
+                    .body(full("Metrics are disabled for this server"))
+                    .unwrap());
+            }
+
+            let router = router_snapshot(&llama_state, &client).await;
+            let mut connected_mcp: Vec<String> = mcp_servers.lock().await.keys().cloned().collect();
+            connected_mcp.sort();
+
+            let mut body = String::new();
+            server_metrics.render(&mut body);
+            metrics::render_runtime(&mut body, &router, &connected_mcp);
+            if let Some(admission) = &admission {
+                metrics::render_admission(&mut body, &admission.snapshot());
+            }
+
+            let mut response_builder = Response::builder()
+                .status(StatusCode::OK)
+                .header(hyper::header::CONTENT_TYPE, "text/plain; version=0.0.4");
+            response_builder = add_cors_headers_with_host_and_origin(
+                response_builder,
+                &host_header,
+                &origin_header,
+                &config.trusted_hosts,
+            );
+            return Ok(response_builder.body(full(body)).unwrap());
+        }
+
+        (hyper::Method::POST, ollama_path)
+            if config.enable_ollama_api && OllamaRoute::from_path(ollama_path).is_some() =>
+        {
+            let route = OllamaRoute::from_path(ollama_path).unwrap();
+            log::info!("Handling POST request to {destination_path} (Ollama API)");
+            let body_bytes = match body.collect().await {
+                Ok(c) => c.to_bytes(),
+                Err(_) => {
+                    let mut error_response =
+                        Response::builder().status(StatusCode::INTERNAL_SERVER_ERROR);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response
+                        .body(full("Failed to read request body"))
+                        .unwrap());
+                }
+            };
+
+            let json_body: serde_json::Value = match serde_json::from_slice(&body_bytes) {
+                Ok(v) => v,
+                Err(e) => {
+                    log::warn!("Failed to parse POST body for {destination_path} as JSON: {e}");
+                    let mut error_response = Response::builder().status(StatusCode::BAD_REQUEST);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response
+                        .body(full(format!("Invalid JSON body: {e}")))
+                        .unwrap());
+                }
+            };
+
+            // Warm-up requests carry no prompt; backends load models on first use.
+            if let Some(reply) = ollama::preload_reply(route, &json_body) {
+                let mut response_builder = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(hyper::header::CONTENT_TYPE, "application/json");
+                response_builder = add_cors_headers_with_host_and_origin(
+                    response_builder,
+                    &host_header,
+                    &origin_header,
+                    &config.trusted_hosts,
+                );
+                return Ok(response_builder.body(full(reply.to_string())).unwrap());
+            }
+
+            let mut openai_body = match ollama_request_to_openai(route, &json_body) {
+                Ok(v) => v,
+                Err(e) => {
+                    let mut error_response = Response::builder().status(StatusCode::BAD_REQUEST);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response.body(full(e)).unwrap());
+                }
+            };
+            let upstream_path = route.upstream_path();
+            if upstream_path == "/chat/completions" {
+                normalize_openai_tools_in_chat_body(&mut openai_body);
+            }
+
+            let model_id = json_body
+                .get("model")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let provider_cfg = {
+                let pc = provider_configs.lock().await;
+                find_provider_for_model(&pc, &model_id).and_then(|p| pc.get(&p).cloned())
+            };
+
+            if let Some(cfg) = provider_cfg {
+                log::info!(
+                    "Serving {destination_path} via provider '{}' for model '{model_id}'",
+                    cfg.provider
+                );
+                // A converter only applies to chat/completions.
+                let converter = if upstream_path == "/chat/completions" {
+                    converter_for(cfg.api_type.as_deref())
+                } else {
+                    None
+                };
+                let path = converter
+                    .as_ref()
+                    .map(|c| c.upstream_path(&openai_body))
+                    .unwrap_or_else(|| upstream_path.to_string());
+                target_base_url = cfg.base_url.clone().map(|url| format!("{url}{path}"));
+                upstream_converter = converter;
+                session_api_keys = cfg.bearer_key_chain();
+            } else {
+                let mlx_session_info = {
+                    let mlx_guard = mlx_sessions.lock().await;
+                    mlx_guard
+                        .values()
+                        .find(|s| s.info.model_id == model_id)
+                        .map(|s| s.info.clone())
+                };
+                if let Some(info) = mlx_session_info {
+                    session_api_keys = vec![info.api_key.clone()];
+                    mlx_model_id = Some(model_id.clone());
+                    target_base_url =
+                        Some(format!("http://127.0.0.1:{}/v1{upstream_path}", info.port));
+                } else if let Some((url, key)) = router_upstream(&llama_state, upstream_path).await
+                {
+                    session_api_keys = vec![key];
+                    target_base_url = Some(url);
+                } else {
+                    log::warn!("Request for model '{model_id}' but no models are running.");
+                    let mut error_response =
+                        Response::builder().status(StatusCode::SERVICE_UNAVAILABLE);
+                    error_response = add_cors_headers_with_host_and_origin(
+                        error_response,
+                        &host_header,
+                        &origin_header,
+                        &config.trusted_hosts,
+                    );
+                    return Ok(error_response
+                        .body(full("No models are available"))
+                        .unwrap());
+                }
+            }
+
+            buffered_body = serde_json::to_vec(&openai_body).ok().map(Bytes::from);
+            translated_ingress = Some(TranslatedIngress::Ollama {
+                route,
+                model: model_id,
+            });
+        }
+
+        (hyper::Method::GET, "/api/version") if config.enable_ollama_api => {
+            let body = serde_json::json!({ "version": OLLAMA_COMPAT_VERSION });
+            let mut response_builder = Response::builder()
+                .status(StatusCode::OK)
+                .header(hyper::header::CONTENT_TYPE, "application/json");
+            response_builder = add_cors_headers_with_host_and_origin(
+                response_builder,
+                &host_header,
+                &origin_header,
+                &config.trusted_hosts,
+            );
+            return Ok(response_builder.body(full(body.to_string())).unwrap());
+        }
+
+        (hyper::Method::GET, "/api/tags")
+        | (hyper::Method::GET, "/api/ps")
+        | (hyper::Method::POST, "/api/show")
+            if config.enable_ollama_api =>
+        {
+            let mut models = served_models(
+                &llama_state,
+                &client,
+                &mlx_sessions,
+                &provider_configs,
+                &model_aliases,
+            )
+            .await;
+            if let Some(key) = &client_key {
+                models.retain(|(id, _)| key.allows_model(id));
+            }
+
+            let reply = match destination_path.as_str() {
+                "/api/show" => {
+                    let requested = match body.collect().await {
+                        Ok(c) => serde_json::from_slice::<serde_json::Value>(&c.to_bytes()).ok(),
+                        Err(_) => None,
+                    };
+                    // Older clients send `name` rather than `model`.
+                    let name = requested
+                        .as_ref()
+                        .and_then(|v| v.get("model").or_else(|| v.get("name")))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    match models.iter().find(|(id, _)| id == name) {
+                        Some((id, served_by)) => ollama::show_reply(id, served_by),
+                        None => {
+                            let mut error_response =
+                                Response::builder().status(StatusCode::NOT_FOUND);
+                            error_response = add_cors_headers_with_host_and_origin(
+                                error_response,
+                                &host_header,
+                                &origin_header,
+                                &config.trusted_hosts,
+                            );
+                            let error =
+                                serde_json::json!({ "error": format!("model '{name}' not found") });
+                            return Ok(error_response.body(full(error.to_string())).unwrap());
+                        }
+                    }
+                }
+                "/api/ps" => {
+                    // Running models: MLX sessions and router models it has loaded.
+                    let router = router_snapshot(&llama_state, &client).await;
+                    let running: Vec<_> = models
+                        .iter()
+                        .filter(|(id, served_by)| match *served_by {
+                            "mlx" => true,
+                            "llama.cpp" => router
+                                .models
+                                .iter()
+                                .any(|(m, status)| m == id && status == "loaded"),
+                            _ => false,
                         })
-                    })
-                    .collect()
+                        .map(|(id, served_by)| ollama::model_entry(id, served_by))
+                        .collect();
+                    serde_json::json!({ "models": running })
+                }
+                _ => {
+                    let tags: Vec<_> = models
+                        .iter()
+                        .map(|(id, served_by)| ollama::model_entry(id, served_by))
+                        .collect();
+                    serde_json::json!({ "models": tags })
+                }
             };
 
-            // Get remote provider models
-            let pc = provider_configs.lock().await;
-            let remote_models: Vec<_> = pc
-                .values()
-                .flat_map(|provider_cfg| provider_cfg.models.clone())
-                .map(|model_id| {
-                    serde_json::json!({
-                        "id": model_id,
-                        "object": "model",
-                        "created": 1,
-                        "owned_by": "remote"
-                    })
-                })
-                .collect();
-
-            // Store counts before moving
-            let local_count = local_models.len();
-            let mlx_count = mlx_models.len();
-            let remote_count = remote_models.len();
-
-            // Combine all models
-            let mut all_models = Vec::with_capacity(local_count + mlx_count + remote_count);
-            all_models.extend(local_models);
-            all_models.extend(mlx_models);
-            all_models.extend(remote_models);
-
-            let response_json = serde_json::json!({
-                "object": "list",
-                "data": all_models
-            });
-
-            let body_str =
-                serde_json::to_string(&response_json).unwrap_or_else(|_| "{}".to_string());
-
             let mut response_builder = Response::builder()
                 .status(StatusCode::OK)
                 .header(hyper::header::CONTENT_TYPE, "application/json");
-
             response_builder = add_cors_headers_with_host_and_origin(
                 response_builder,
                 &host_header,
                 &origin_header,
                 &config.trusted_hosts,
             );
-
-            log::debug!(
-                "Returning {} models ({} llama.cpp, {} MLX, {} remote)",
-                all_models.len(),
-                local_count,
-                mlx_count,
-                remote_count
-            );
-
-            return Ok(response_builder.body(full(body_str)).unwrap());
+            return Ok(response_builder.body(full(reply.to_string())).unwrap());
         }
 
         (hyper::Method::GET, "/openapi.json") => {
@@ -2441,8 +5682,11 @@ async fn proxy_request(
                             if let Some(server_obj) = server.as_object_mut() {
                                 if let Some(url) = server_obj.get_mut("url") {
                                     let base_url = format!(
-                                        "http://{}:{}{}",
-                                        config.host, config.port, config.prefix
+                                        "{}://{}:{}{}",
+                                        if config.tls { "https" } else { "http" },
+                                        config.host,
+                                        config.port,
+                                        config.prefix
                                     );
                                     *url = serde_json::Value::String(base_url);
                                 }
@@ -2563,6 +5807,50 @@ async fn proxy_request(
         }
     }
 
+    // Opt-in: a local model whose chat template cannot take tools gets them
+    // through a prompt-level protocol instead. Its `/messages` requests go
+    // through chat/completions so the reply can be translated back.
+    if config.enable_tool_emulation && upstream_converter.is_none() && target_base_url.is_some() {
+        let anthropic = is_anthropic_messages && translated_ingress.is_none();
+        let chat_body = buffered_body
+            .as_ref()
+            .and_then(|b| serde_json::from_slice::<serde_json::Value>(b).ok())
+            .and_then(|body| {
+                if anthropic {
+                    transform_anthropic_to_openai(&body)
+                } else if destination_path == "/chat/completions" || translated_ingress.is_some() {
+                    Some(body)
+                } else {
+                    None
+                }
+            });
+        if let Some(chat_body) = chat_body {
+            let model_id = chat_body
+                .get("model")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let emulation = tool_emulation_for(
+                true,
+                &chat_body,
+                model_id,
+                &provider_configs,
+                &mlx_sessions,
+                &jan_data_folder,
+            )
+            .await;
+            if let Some(converter) = emulation {
+                if anthropic {
+                    target_base_url = target_base_url.map(|url| {
+                        format!("{}/chat/completions", url.trim_end_matches("/messages"))
+                    });
+                    buffered_body = serde_json::to_vec(&chat_body).ok().map(Bytes::from);
+                    translated_ingress = Some(TranslatedIngress::Anthropic);
+                }
+                upstream_converter = Some(Box::new(converter));
+            }
+        }
+    }
+
     let upstream_url = match target_base_url.clone() {
         Some(p) => p,
         None => {
@@ -2612,6 +5900,40 @@ async fn proxy_request(
         }
     }
 
+    if upstream_converter.is_none() && upstream_url.ends_with("/chat/completions") {
+        if let Ok(mut v) = serde_json::from_slice::<serde_json::Value>(&body_bytes_for_proxy) {
+            if v.get("response_format").is_some() {
+                constrain_local_response_format(&mut v, &provider_configs, &mlx_sessions).await;
+                if let Ok(bytes) = serde_json::to_vec(&v) {
+                    body_bytes_for_proxy = Bytes::from(bytes);
+                }
+            }
+        }
+    }
+
+    // Prompt-cache breakpoints become llama-server's prompt cache and a slot
+    // pinned by the cached prefix, so long shared prefixes are evaluated once.
+    if upstream_converter.is_none()
+        && (upstream_url.ends_with("/chat/completions") || upstream_url.ends_with("/messages"))
+    {
+        if let Ok(mut v) = serde_json::from_slice::<serde_json::Value>(&body_bytes_for_proxy) {
+            let model_id = v
+                .get("model")
+                .and_then(|m| m.as_str())
+                .unwrap_or("")
+                .to_string();
+            if prompt_cache::has_cache_hints(&v)
+                && is_llamacpp_model(&model_id, &provider_configs, &mlx_sessions).await
+            {
+                let slots = router_total_slots(&client, &llama_state, &model_id).await;
+                prompt_cache::apply_local_cache_hints(&mut v, slots);
+                if let Ok(bytes) = serde_json::to_vec(&v) {
+                    body_bytes_for_proxy = Bytes::from(bytes);
+                }
+            }
+        }
+    }
+
     // MLX targets carry no router preset, so apply the model's stored sampling
     // defaults here for keys the caller omitted (llamacpp uses the preset;
     // remote providers are intentionally left untouched).
@@ -2636,9 +5958,6 @@ async fn proxy_request(
         session_api_keys.iter().cloned().map(Some).collect()
     };
 
-    // For Anthropic /messages, we need to track if we should transform the response
-    let destination_path = path.clone();
-
     for (key_idx, key_opt) in key_attempts.iter().enumerate() {
         let mut outbound_req = client.request(method.clone(), upstream_url.clone());
 
@@ -2690,7 +6009,7 @@ async fn proxy_request(
             }
 
             // For Anthropic /messages requests with errors, try /chat/completions
-            if is_error && is_anthropic_messages {
+            if is_error && is_anthropic_messages && translated_ingress.is_none() {
                 log::warn!("Request failed for /messages with status {status}, trying /chat/completions...");
 
                 // Read the error body to return to client if fallback fails
@@ -2790,18 +6109,23 @@ async fn proxy_request(
                             .unwrap_or(false);
 
                         let (sender, body) = body_channel();
-                        let dest_path = destination_path.clone();
 
                         tokio::spawn(async move {
                             if is_streaming {
-                                let stream = res.bytes_stream();
-                                transform_and_forward_stream(stream, sender, &dest_path).await;
+                                forward_translated_stream(
+                                    res.bytes_stream(),
+                                    sender,
+                                    None,
+                                    AnthropicStreamTranslator::new(),
+                                    sse_event,
+                                )
+                                .await;
                             } else {
-                                let response_body = res.bytes().await;
-                                forward_non_streaming(
-                                    response_body,
+                                forward_translated_non_streaming(
+                                    res.bytes(),
                                     sender,
-                                    &dest_path,
+                                    None,
+                                    transform_openai_response_to_anthropic,
                                 )
                                 .await;
                             }
@@ -2855,21 +6179,104 @@ async fn proxy_request(
                 &config.trusted_hosts,
             );
 
+            let is_sse = response
+                .headers()
+                .get(hyper::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|ct| ct.contains("event-stream") || ct.contains(NDJSON_CONTENT_TYPE))
+                .unwrap_or(false);
+
+            // Non-chat ingress served through chat/completions: translate the
+            // reply (via the provider converter first, when there is one).
+            if let Some(ingress) = translated_ingress.take() {
+                let content_type = match (&ingress, is_sse) {
+                    (TranslatedIngress::Ollama { .. }, true) => NDJSON_CONTENT_TYPE,
+                    (_, true) => "text/event-stream",
+                    (_, false) => "application/json",
+                };
+                replace_content_type(&mut builder, content_type);
+                let converter = upstream_converter.take();
+                let (sender, body) = body_channel();
+                tokio::spawn(async move {
+                    match ingress {
+                        TranslatedIngress::Responses(request) if is_sse => {
+                            let translator = ResponsesStreamTranslator::new(&request);
+                            forward_translated_stream(
+                                response.bytes_stream(),
+                                sender,
+                                converter,
+                                translator,
+                                sse_event,
+                            )
+                            .await;
+                        }
+                        TranslatedIngress::Responses(request) => {
+                            forward_translated_non_streaming(
+                                response.bytes(),
+                                sender,
+                                converter,
+                                |chat| chat_response_to_responses(chat, &request),
+                            )
+                            .await;
+                        }
+                        TranslatedIngress::Anthropic if is_sse => {
+                            forward_translated_stream(
+                                response.bytes_stream(),
+                                sender,
+                                converter,
+                                AnthropicStreamTranslator::new(),
+                                sse_event,
+                            )
+                            .await;
+                        }
+                        TranslatedIngress::Anthropic => {
+                            forward_translated_non_streaming(
+                                response.bytes(),
+                                sender,
+                                converter,
+                                transform_openai_response_to_anthropic,
+                            )
+                            .await;
+                        }
+                        TranslatedIngress::Ollama { route, model } if is_sse => {
+                            forward_translated_stream(
+                                response.bytes_stream(),
+                                sender,
+                                converter,
+                                OllamaStreamTranslator::new(route, &model),
+                                ndjson_line,
+                            )
+                            .await;
+                        }
+                        TranslatedIngress::Ollama { route, model } => {
+                            forward_translated_non_streaming(
+                                response.bytes(),
+                                sender,
+                                converter,
+                                |reply| openai_response_to_ollama(route, reply, &model),
+                            )
+                            .await;
+                        }
+                    }
+                });
+                return Ok(builder.body(body).unwrap());
+            }
+
             // When the provider fronts a non-chat/completions API, translate the
             // response back to chat shape; otherwise forward bytes verbatim.
             if let Some(converter) = upstream_converter.take() {
-                let is_sse = response
-                    .headers()
-                    .get(hyper::header::CONTENT_TYPE)
-                    .and_then(|v| v.to_str().ok())
-                    .map(|ct| ct.contains("event-stream"))
-                    .unwrap_or(false);
+                let content_type = if is_sse {
+                    "text/event-stream"
+                } else {
+                    "application/json"
+                };
+                replace_content_type(&mut builder, content_type);
                 let (sender, body) = body_channel();
                 tokio::spawn(async move {
                     if is_sse {
                         forward_converted_stream(response.bytes_stream(), sender, converter).await;
                     } else {
-                        forward_converted_non_streaming(response.bytes().await, sender, converter)
+                        forward_converted_non_streaming(response.bytes(), sender, converter)
                             .await;
                     }
                 });
@@ -2882,7 +6289,14 @@ async fn proxy_request(
             tokio::spawn(async move {
                 // Regular passthrough - when /messages succeeds directly,
                 // the response is already in the correct format
-                while let Some(chunk_result) = stream.next().await {
+                loop {
+                    let Some(next) = sender.unless_closed(stream.next()).await else {
+                        log::debug!("Client disconnected; abandoning upstream stream");
+                        break;
+                    };
+                    let Some(chunk_result) = next else {
+                        break;
+                    };
                     match chunk_result {
                         Ok(chunk) => {
                             if sender.send_data(chunk).await.is_err() {
@@ -2989,6 +6403,17 @@ pub async fn start_server(
     mcp_settings: Arc<Mutex<McpSettings>>,
     jan_data_folder: String,
     enable_server_tool_execution: bool,
+    enable_metrics: bool,
+    enable_ollama_api: bool,
+    enable_tool_emulation: bool,
+    tls: TlsOptions,
+    response_cache: ResponseCacheOptions,
+    access_log: AccessLogOptions,
+    client_keys: Arc<ClientKeyStore>,
+    usage: Arc<UsageLedger>,
+    model_aliases: Arc<ModelAliasStore>,
+    batch_concurrency: Option<usize>,
+    admission: AdmissionOptions,
 ) -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
     start_server_internal(
         server_handle,
@@ -3006,6 +6431,17 @@ pub async fn start_server(
         mcp_settings,
         jan_data_folder,
         enable_server_tool_execution,
+        enable_metrics,
+        enable_ollama_api,
+        enable_tool_emulation,
+        tls,
+        response_cache,
+        access_log,
+        client_keys,
+        usage,
+        model_aliases,
+        batch_concurrency,
+        admission,
     )
     .await
 }
@@ -3027,6 +6463,17 @@ async fn start_server_internal(
     mcp_settings: Arc<Mutex<McpSettings>>,
     jan_data_folder: String,
     enable_server_tool_execution: bool,
+    enable_metrics: bool,
+    enable_ollama_api: bool,
+    enable_tool_emulation: bool,
+    tls: TlsOptions,
+    response_cache: ResponseCacheOptions,
+    access_log: AccessLogOptions,
+    client_keys: Arc<ClientKeyStore>,
+    usage: Arc<UsageLedger>,
+    model_aliases: Arc<ModelAliasStore>,
+    batch_concurrency: Option<usize>,
+    admission: AdmissionOptions,
 ) -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
     let mut handle_guard = server_handle.lock().await;
     if handle_guard.is_some() {
@@ -3043,8 +6490,17 @@ async fn start_server_internal(
     // and private-range IP literals, which is safe against DNS rebinding (that sends an
     // attacker *hostname*, not an IP). Hostnames still require an explicit allowlist entry.
 
-    // Calculate this before proxy_api_key is moved into ProxyConfig.
-    let insecure_public_bind = is_insecure_public_bind(&host, &proxy_api_key);
+    // Calculate this before proxy_api_key is moved into ProxyConfig. Client
+    // keys and client certificates also make the server require authentication.
+    let insecure_public_bind = is_insecure_public_bind(&host, &proxy_api_key)
+        && !client_keys.has_keys()
+        && !tls.requires_client_cert();
+    let tls_acceptor = tls::build_acceptor(&tls, Path::new(&jan_data_folder), &host)?;
+    let scheme = if tls_acceptor.is_some() {
+        "https"
+    } else {
+        "http"
+    };
 
     let config = ProxyConfig {
         prefix,
@@ -3053,7 +6509,25 @@ async fn start_server_internal(
         host: host.clone(),
         port,
         enable_server_tool_execution,
+        enable_metrics,
+        enable_ollama_api,
+        enable_tool_emulation,
+        tls: tls_acceptor.is_some(),
     };
+    let server_metrics = Arc::new(ServerMetrics::default());
+    let response_cache =
+        ResponseCache::new(Path::new(&jan_data_folder), &response_cache).map(Arc::new);
+    if response_cache.is_some() {
+        log::info!("Response cache enabled for deterministic requests");
+    }
+    let access_log = AccessLog::new(Path::new(&jan_data_folder), &access_log).map(Arc::new);
+    let admission = AdmissionController::new(&admission);
+    if let Some(admission) = &admission {
+        log::info!(
+            "Admission control: at most {} concurrent requests per local model",
+            admission.limit()
+        );
+    }
 
     let client = Client::builder()
         .timeout(std::time::Duration::from_secs(proxy_timeout))
@@ -3061,6 +6535,124 @@ async fn start_server_internal(
         .pool_idle_timeout(std::time::Duration::from_secs(30))
         .build()?;
 
+    // Batches left queued or running by a previous run pick up again here.
+    let batches = Arc::new(BatchStore::new(
+        Path::new(&jan_data_folder),
+        batch_concurrency,
+    ));
+    let batch_runner = {
+        let client = client.clone();
+        let provider_configs = provider_configs.clone();
+        let llama_state = llama_state.clone();
+        let mlx_sessions = mlx_sessions.clone();
+        let admission = admission.clone();
+        let jan_data_folder = jan_data_folder.clone();
+        let client_keys = client_keys.clone();
+        let usage = usage.clone();
+        let model_aliases = model_aliases.clone();
+        batches::spawn_runner(
+            batches.clone(),
+            move |body: serde_json::Value, owner: Option<String>| {
+                let client = client.clone();
+                let provider_configs = provider_configs.clone();
+                let llama_state = llama_state.clone();
+                let mlx_sessions = mlx_sessions.clone();
+                let admission = admission.clone();
+                let jan_data_folder = jan_data_folder.clone();
+                let client_keys = client_keys.clone();
+                let usage = usage.clone();
+                let model_aliases = model_aliases.clone();
+                async move {
+                    let model = body
+                        .get("model")
+                        .and_then(|m| m.as_str())
+                        .ok_or("Request body is missing 'model'")?
+                        .to_string();
+                    // Each line counts against the batch's client key, as if
+                    // it had been sent on its own.
+                    if let Some(name) = &owner {
+                        let key = client_keys.get(name).ok_or_else(|| {
+                            format!("Client key '{name}' no longer exists or is disabled")
+                        })?;
+                        loop {
+                            match client_keys.admit(&key) {
+                                Ok(()) => break,
+                                // Wait out a per-minute limit; a daily one
+                                // fails the line.
+                                Err(exceeded) if exceeded.retry_after_secs <= 60 => {
+                                    tokio::time::sleep(std::time::Duration::from_secs(
+                                        exceeded.retry_after_secs,
+                                    ))
+                                    .await
+                                }
+                                Err(exceeded) => return Err(exceeded.message),
+                            }
+                        }
+                    }
+
+                    let plan = model_aliases.plan(&model);
+                    let targets = plan
+                        .as_ref()
+                        .map_or_else(|| vec![model.clone()], |plan| plan.models.clone());
+                    for (i, target) in targets.iter().enumerate() {
+                        let started = std::time::Instant::now();
+                        let send = send_batch_request(
+                            body.clone(),
+                            target,
+                            &client,
+                            &provider_configs,
+                            &llama_state,
+                            &mlx_sessions,
+                            admission.as_ref(),
+                            enable_tool_emulation,
+                            &jan_data_folder,
+                        );
+                        let result = match plan.as_ref().and_then(|p| p.first_byte_timeout) {
+                            Some(limit) => tokio::time::timeout(limit, send)
+                                .await
+                                .unwrap_or_else(|_| Err("no response in time".to_string())),
+                            None => send.await,
+                        };
+                        if let (Err(e), Some(plan), Some(next)) =
+                            (&result, &plan, targets.get(i + 1))
+                        {
+                            log::warn!(
+                                "Model alias '{}': target '{target}' failed ({e}), falling back to '{next}'",
+                                plan.alias
+                            );
+                            continue;
+                        }
+
+                        let tokens = result
+                            .as_ref()
+                            .map(TokenCounts::of_reply)
+                            .unwrap_or_default();
+                        if let Some(name) = &owner {
+                            client_keys.record_tokens(name, tokens.total());
+                        }
+                        let event = UsageEvent {
+                            model: target.clone(),
+                            provider: usage_provider_label(
+                                target,
+                                &provider_configs,
+                                &mlx_sessions,
+                            )
+                            .await,
+                            client: owner,
+                            status: if result.is_ok() { 200 } else { 502 },
+                            tokens,
+                            latency_ms: started.elapsed().as_millis() as u64,
+                            ttft_ms: None,
+                        };
+                        tokio::task::spawn_blocking(move || usage.record(event));
+                        return result;
+                    }
+                    Err(format!("Model alias '{model}' has no targets"))
+                }
+            },
+        )
+    };
+
     let listener = match tokio::net::TcpListener::bind(addr).await {
         Ok(l) => l,
         Err(e) => {
@@ -3068,7 +6660,16 @@ async fn start_server_internal(
             return Err(Box::new(e));
         }
     };
-    log::info!("Jan API server started on http://{addr}");
+    log::info!("Jan API server started on {scheme}://{addr}");
+    if enable_metrics {
+        log::info!(
+            "Prometheus metrics available at {scheme}://{addr}{}/metrics",
+            config.prefix
+        );
+    }
+    if enable_ollama_api {
+        log::info!("Ollama-compatible API available at {scheme}://{addr}/api");
+    }
 
     // Security: binding to a non-loopback interface exposes the OpenAI-compatible
     // API on the network. With no API key set, any reachable host can call it
@@ -3084,6 +6685,8 @@ async fn start_server_internal(
     }
 
     let server_task = tokio::spawn(async move {
+        // Stopping the server aborts this task, which stops the batch runner.
+        let _batch_runner = batch_runner;
         loop {
             let (stream, _) = match listener.accept().await {
                 Ok(c) => c,
@@ -3092,7 +6695,7 @@ async fn start_server_internal(
                     continue;
                 }
             };
-            let io = TokioIo::new(stream);
+            let tls_acceptor = tls_acceptor.clone();
 
             let client = client.clone();
             let config = config.clone();
@@ -3103,9 +6706,24 @@ async fn start_server_internal(
             let mcp_servers = mcp_servers.clone();
             let mcp_settings = mcp_settings.clone();
             let jan_data_folder = jan_data_folder.clone();
-
-            let svc = service_fn(move |req| {
-                proxy_request(
+            let client_keys = client_keys.clone();
+            let usage = usage.clone();
+            let model_aliases = model_aliases.clone();
+            let response_cache = response_cache.clone();
+            let access_log = access_log.clone();
+            let server_metrics = server_metrics.clone();
+            let batches = batches.clone();
+            let admission = admission.clone();
+
+            let svc = service_fn(move |req: Request<Incoming>| {
+                let path = get_destination_path(req.uri().path(), &config.prefix);
+                let route = metrics::route_label(&path);
+                let method = req.method().to_string();
+                let received_at = chrono::Utc::now();
+                let started = std::time::Instant::now();
+                let server_metrics = server_metrics.clone();
+                let access_log = access_log.clone();
+                let served = serve_request(
                     req,
                     client.clone(),
                     config.clone(),
@@ -3116,11 +6734,56 @@ async fn start_server_internal(
                     mcp_servers.clone(),
                     mcp_settings.clone(),
                     jan_data_folder.clone(),
-                )
+                    client_keys.clone(),
+                    usage.clone(),
+                    model_aliases.clone(),
+                    response_cache.clone(),
+                    access_log.clone(),
+                    server_metrics.clone(),
+                    batches.clone(),
+                    admission.clone(),
+                );
+                async move {
+                    let response = served.await?;
+                    server_metrics.record_request(route, response.status().as_u16());
+                    // Requests refused before proxying (auth, scopes, quotas).
+                    if let Some(access_log) =
+                        access_log.filter(|_| response.extensions().get::<AccessLogged>().is_none())
+                    {
+                        let record = AccessRecord {
+                            timestamp: received_at.to_rfc3339(),
+                            method,
+                            route: path,
+                            status: response.status().as_u16(),
+                            latency_ms: started.elapsed().as_millis() as u64,
+                            ..AccessRecord::default()
+                        };
+                        tokio::task::spawn_blocking(move || access_log.write(&record));
+                    }
+                    Ok::<_, hyper::Error>(response)
+                }
             });
 
             tokio::spawn(async move {
-                if let Err(e) = http1::Builder::new().serve_connection(io, svc).await {
+                let served = match tls_acceptor {
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(tls_stream) => {
+                            http1::Builder::new()
+                                .serve_connection(TokioIo::new(tls_stream), svc)
+                                .await
+                        }
+                        Err(e) => {
+                            log::debug!("TLS handshake failed: {e}");
+                            return;
+                        }
+                    },
+                    None => {
+                        http1::Builder::new()
+                            .serve_connection(TokioIo::new(stream), svc)
+                            .await
+                    }
+                };
+                if let Err(e) = served {
                     log::debug!("Error serving connection: {e}");
                 }
             });
@@ -3160,6 +6823,22 @@ pub(crate) fn sse_event(data: &serde_json::Value) -> Bytes {
     Bytes::from(format!("event: {event_type}\ndata: {data}\n\n"))
 }
 
+/// Translated replies are re-framed, so the upstream content type (SSE, or
+/// NDJSON from an Ollama provider) no longer applies.
+fn replace_content_type(builder: &mut hyper::http::response::Builder, content_type: &'static str) {
+    if let Some(headers) = builder.headers_mut() {
+        headers.insert(
+            hyper::header::CONTENT_TYPE,
+            hyper::header::HeaderValue::from_static(content_type),
+        );
+    }
+}
+
+/// One line of an Ollama NDJSON stream.
+fn ndjson_line(data: &serde_json::Value) -> Bytes {
+    Bytes::from(format!("{data}\n"))
+}
+
 /// Transform and forward streaming OpenAI response as Anthropic /messages chunks.
 /// Handles both text content and tool_calls streaming.
 /// Stream a native upstream response through an [`UpstreamConverter`], emitting
@@ -3172,9 +6851,16 @@ async fn forward_converted_stream<S>(
 ) where
     S: futures_util::Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
 {
-    let mut acc = SseAccumulator::new();
+    let mut acc = converter.stream_accumulator();
     let mut state = StreamState::default();
-    'outer: while let Some(chunk_result) = stream.next().await {
+    'outer: loop {
+        let Some(next) = sender.unless_closed(stream.next()).await else {
+            log::debug!("Client disconnected; abandoning converter stream");
+            return;
+        };
+        let Some(chunk_result) = next else {
+            break;
+        };
         match chunk_result {
             Ok(chunk) => {
                 let text = String::from_utf8_lossy(&chunk);
@@ -3206,10 +6892,14 @@ async fn forward_converted_stream<S>(
 /// Translate a non-streaming native upstream response into a chat.completion
 /// object and forward it as a single body frame.
 async fn forward_converted_non_streaming(
-    body: Result<Bytes, reqwest::Error>,
+    body: impl std::future::Future<Output = Result<Bytes, reqwest::Error>>,
     mut sender: BodySender,
     converter: Box<dyn UpstreamConverter>,
 ) {
+    let Some(body) = sender.unless_closed(body).await else {
+        log::debug!("Client disconnected; abandoning upstream request");
+        return;
+    };
     match body {
         Ok(bytes) => {
             let out = match serde_json::from_slice::<serde_json::Value>(&bytes) {
@@ -3223,337 +6913,136 @@ async fn forward_converted_non_streaming(
     }
 }
 
-async fn transform_and_forward_stream<S>(
+/// Client-facing wire format of an ingress that was rewritten to
+/// chat/completions before forwarding.
+enum TranslatedIngress {
+    /// `/responses`; carries the original request, echoed into the reply.
+    Responses(serde_json::Value),
+    /// `/messages` sent to a provider through its converter.
+    Anthropic,
+    /// An Ollama `/api/*` inference route; `model` is the name the client used.
+    Ollama { route: OllamaRoute, model: String },
+}
+
+/// Stream a chat/completions reply (native chat SSE, or a provider's native SSE
+/// run through its converter) back to the client in another wire format via
+/// `translator` (`/responses` events, Anthropic `/messages` events, Ollama
+/// objects), each event written with `frame`.
+async fn forward_translated_stream<S, T>(
     mut stream: S,
     mut sender: BodySender,
-    _destination_path: &str,
+    converter: Option<Box<dyn UpstreamConverter>>,
+    mut translator: T,
+    frame: fn(&serde_json::Value) -> Bytes,
 ) where
     S: futures_util::Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
+    T: ChatStreamTranslator,
 {
-    let mut is_first = true;
-    let mut accumulated_content = String::new();
-
-    // Track active Anthropic content blocks
-    let mut text_block_index: Option<usize> = None;
-    let mut tool_blocks: HashMap<usize, usize> = HashMap::new(); // OAI tool index -> Anthropic block index
-    let mut next_block_index: usize = 0;
-
-    while let Some(chunk_result) = stream.next().await {
-        match chunk_result {
-            Ok(chunk) => {
-                let chunk_str = String::from_utf8_lossy(&chunk);
-
-                for line in chunk_str.lines() {
-                    if !line.starts_with("data:") {
-                        continue;
-                    }
-                    let data = line.trim_start_matches("data:").trim();
-
-                    if data == "[DONE]" {
-                        // Close any remaining open blocks
-                        if let Some(idx) = text_block_index.take() {
-                            let stop =
-                                serde_json::json!({"type": "content_block_stop", "index": idx});
-                            if sender.send_data(sse_event(&stop)).await.is_err() {
-                                return;
-                            }
-                        }
-                        let mut tool_indices: Vec<usize> = tool_blocks.values().copied().collect();
-                        tool_indices.sort();
-                        for idx in tool_indices {
-                            let stop =
-                                serde_json::json!({"type": "content_block_stop", "index": idx});
-                            if sender.send_data(sse_event(&stop)).await.is_err() {
-                                return;
-                            }
-                        }
-
-                        let stop_reason = if tool_blocks.is_empty() {
-                            "end_turn"
-                        } else {
-                            "tool_use"
-                        };
-                        let output_tokens = accumulated_content.split_whitespace().count() as u64;
-
-                        let delta_event = serde_json::json!({
-                            "type": "message_delta",
-                            "delta": {
-                                "stop_reason": stop_reason,
-                                "stop_sequence": serde_json::Value::Null
-                            },
-                            "usage": { "output_tokens": output_tokens }
-                        });
-                        if sender.send_data(sse_event(&delta_event)).await.is_err() {
-                            return;
-                        }
+    let mut acc = converter
+        .as_ref()
+        .map_or_else(SseAccumulator::new, |c| c.stream_accumulator());
+    let mut state = StreamState::default();
 
-                        let final_stop = serde_json::json!({"type": "message_stop"});
-                        if sender.send_data(sse_event(&final_stop)).await.is_err() {
-                            return;
-                        }
-                        log::debug!("Sent Anthropic final events");
-                        return;
-                    }
+    let mut pending: Vec<SseEvent> = Vec::new();
+    loop {
+        let Some(next) = sender.unless_closed(stream.next()).await else {
+            log::debug!("Client disconnected; abandoning translated stream");
+            return;
+        };
+        let done = match next {
+            Some(Ok(chunk)) => {
+                pending.extend(acc.push(&String::from_utf8_lossy(&chunk)));
+                false
+            }
+            Some(Err(e)) => {
+                log::error!("Translated stream error: {e}");
+                true
+            }
+            None => true,
+        };
+        if done {
+            pending.extend(acc.finish());
+        }
 
-                    let json_chunk = match serde_json::from_str::<serde_json::Value>(data) {
-                        Ok(v) => v,
+        for event in pending.drain(..) {
+            let payloads = match &converter {
+                Some(conv) => conv.convert_stream_event(&event, &mut state),
+                None => vec![event.data],
+            };
+            for payload in payloads {
+                let events = if payload == "[DONE]" {
+                    translator.finish()
+                } else {
+                    match serde_json::from_str::<serde_json::Value>(&payload) {
+                        Ok(chunk) => match chunk.get("error") {
+                            Some(err) => translator.fail(
+                                err.get("message")
+                                    .and_then(|m| m.as_str())
+                                    .unwrap_or("upstream error"),
+                            ),
+                            None => translator.push_chunk(&chunk),
+                        },
                         Err(_) => continue,
-                    };
-
-                    let choice = json_chunk
-                        .get("choices")
-                        .and_then(|c| c.as_array())
-                        .and_then(|c| c.first());
-                    let delta = match choice.and_then(|c| c.get("delta")) {
-                        Some(d) => d,
-                        None => continue,
-                    };
-                    let finish_reason = choice.and_then(|c| c.get("finish_reason"));
-                    let has_finish = finish_reason.is_some() && !finish_reason.unwrap().is_null();
-
-                    // First chunk: send message_start
-                    if is_first {
-                        let role = delta
-                            .get("role")
-                            .and_then(|r| r.as_str())
-                            .unwrap_or("assistant");
-                        let message_id = json_chunk
-                            .get("id")
-                            .unwrap_or(&serde_json::json!(""))
-                            .clone();
-                        let model = json_chunk
-                            .get("model")
-                            .unwrap_or(&serde_json::json!(""))
-                            .clone();
-
-                        let start_event = serde_json::json!({
-                            "type": "message_start",
-                            "message": {
-                                "id": message_id,
-                                "type": "message",
-                                "role": role,
-                                "content": [],
-                                "model": model,
-                                "stop_reason": serde_json::Value::Null,
-                                "stop_sequence": serde_json::Value::Null,
-                                "usage": { "input_tokens": 0, "output_tokens": 0 }
-                            }
-                        });
-                        if sender.send_data(sse_event(&start_event)).await.is_err() {
-                            return;
-                        }
-                        is_first = false;
-                    }
-
-                    // Handle text content
-                    if let Some(text) =
-                        delta
-                            .get("content")
-                            .and_then(|c| if c.is_null() { None } else { c.as_str() })
-                    {
-                        if !text.is_empty() {
-                            // Open text block if needed
-                            if text_block_index.is_none() {
-                                let idx = next_block_index;
-                                next_block_index += 1;
-                                text_block_index = Some(idx);
-
-                                let block_start = serde_json::json!({
-                                    "type": "content_block_start",
-                                    "index": idx,
-                                    "content_block": { "type": "text", "text": "" }
-                                });
-                                if sender.send_data(sse_event(&block_start)).await.is_err() {
-                                    return;
-                                }
-                            }
-
-                            accumulated_content.push_str(text);
-                            let delta_event = serde_json::json!({
-                                "type": "content_block_delta",
-                                "index": text_block_index.unwrap(),
-                                "delta": { "type": "text_delta", "text": text }
-                            });
-                            if sender.send_data(sse_event(&delta_event)).await.is_err() {
-                                return;
-                            }
-                        }
-                    }
-
-                    // Handle tool calls
-                    if let Some(tool_calls) = delta.get("tool_calls").and_then(|tc| tc.as_array()) {
-                        // Close text block before tool blocks
-                        if let Some(idx) = text_block_index.take() {
-                            let stop = serde_json::json!(
-                                {"type": "content_block_stop", "index": idx}
-                            );
-                            if sender.send_data(sse_event(&stop)).await.is_err() {
-                                return;
-                            }
-                        }
-
-                        for tc in tool_calls {
-                            let tc_index =
-                                tc.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
-
-                            // New tool call (has id + function.name)
-                            if let Some(id) = tc.get("id").and_then(|v| v.as_str()) {
-                                let name = tc
-                                    .get("function")
-                                    .and_then(|f| f.get("name"))
-                                    .and_then(|n| n.as_str())
-                                    .unwrap_or("");
-
-                                let idx = next_block_index;
-                                next_block_index += 1;
-                                tool_blocks.insert(tc_index, idx);
-
-                                let block_start = serde_json::json!({
-                                    "type": "content_block_start",
-                                    "index": idx,
-                                    "content_block": {
-                                        "type": "tool_use",
-                                        "id": id,
-                                        "name": name,
-                                        "input": {}
-                                    }
-                                });
-                                if sender.send_data(sse_event(&block_start)).await.is_err() {
-                                    return;
-                                }
-                            }
-
-                            // Argument delta
-                            if let Some(args) = tc
-                                .get("function")
-                                .and_then(|f| f.get("arguments"))
-                                .and_then(|a| a.as_str())
-                            {
-                                if !args.is_empty() {
-                                    if let Some(&idx) = tool_blocks.get(&tc_index) {
-                                        let delta_event = serde_json::json!({
-                                            "type": "content_block_delta",
-                                            "index": idx,
-                                            "delta": {
-                                                "type": "input_json_delta",
-                                                "partial_json": args
-                                            }
-                                        });
-                                        if sender.send_data(sse_event(&delta_event)).await.is_err()
-                                        {
-                                            return;
-                                        }
-                                    }
-                                }
-                            }
-                        }
                     }
-
-                    // Handle finish
-                    if has_finish {
-                        // Close text block
-                        if let Some(idx) = text_block_index.take() {
-                            let stop = serde_json::json!(
-                                {"type": "content_block_stop", "index": idx}
-                            );
-                            if sender.send_data(sse_event(&stop)).await.is_err() {
-                                return;
-                            }
-                        }
-                        // Close all tool blocks
-                        let mut tool_indices: Vec<usize> = tool_blocks.values().copied().collect();
-                        tool_indices.sort();
-                        for idx in tool_indices {
-                            let stop = serde_json::json!(
-                                {"type": "content_block_stop", "index": idx}
-                            );
-                            if sender.send_data(sse_event(&stop)).await.is_err() {
-                                return;
-                            }
-                        }
-
-                        let reason = finish_reason
-                            .and_then(|fr| fr.as_str())
-                            .unwrap_or("end_turn");
-                        let stop_reason = match reason {
-                            "stop" => "end_turn",
-                            "length" => "max_tokens",
-                            "tool_calls" => "tool_use",
-                            _ => reason,
-                        };
-                        let output_tokens = accumulated_content.split_whitespace().count() as u64;
-
-                        let delta_event = serde_json::json!({
-                            "type": "message_delta",
-                            "delta": {
-                                "stop_reason": stop_reason,
-                                "stop_sequence": serde_json::Value::Null
-                            },
-                            "usage": { "output_tokens": output_tokens }
-                        });
-                        if sender.send_data(sse_event(&delta_event)).await.is_err() {
-                            return;
-                        }
-
-                        let final_stop = serde_json::json!({"type": "message_stop"});
-                        if sender.send_data(sse_event(&final_stop)).await.is_err() {
-                            return;
-                        }
+                };
+                for ev in events {
+                    if sender.send_data(frame(&ev)).await.is_err() {
+                        log::debug!("Client disconnected during translated streaming");
                         return;
                     }
                 }
             }
-            Err(e) => {
-                log::error!("Stream error: {e}");
-                break;
-            }
+        }
+
+        if done {
+            break;
         }
     }
-    log::debug!("Streaming complete (Anthropic format)");
-}
 
-/// Forward non-streaming OpenAI response as Anthropic /messages response
-async fn forward_non_streaming(
-    response_body: Result<Bytes, reqwest::Error>,
-    mut sender: BodySender,
-    destination_path: &str,
-) {
-    let bytes = match response_body {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            log::error!("Failed to get response body: {e}");
+    for ev in translator.finish() {
+        if sender.send_data(frame(&ev)).await.is_err() {
             return;
         }
-    };
+    }
+}
 
-    if let Ok(json_response) = serde_json::from_slice::<serde_json::Value>(&bytes) {
-        if destination_path == "/messages" {
-            // Transform to Anthropic format
-            let anthropic_response = transform_openai_response_to_anthropic(&json_response);
-            if sender
-                .send_data(Bytes::from(anthropic_response.to_string()))
-                .await
-                .is_err()
-            {
-                log::debug!("Client disconnected");
-            }
-        } else {
-            // Pass through as-is
-            if sender.send_data(bytes).await.is_err() {
+/// Translate a non-streaming chat/completions (or converter-native) reply into
+/// the client's wire format with `translate`. Unparseable bodies pass through.
+async fn forward_translated_non_streaming<F>(
+    body: impl std::future::Future<Output = Result<Bytes, reqwest::Error>>,
+    mut sender: BodySender,
+    converter: Option<Box<dyn UpstreamConverter>>,
+    translate: F,
+) where
+    F: FnOnce(&serde_json::Value) -> serde_json::Value,
+{
+    let Some(body) = sender.unless_closed(body).await else {
+        log::debug!("Client disconnected; abandoning upstream request");
+        return;
+    };
+    match body {
+        Ok(bytes) => {
+            let out = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+                Ok(v) => {
+                    let chat = match &converter {
+                        Some(conv) => conv.convert_response(&v),
+                        None => v,
+                    };
+                    serde_json::to_vec(&translate(&chat)).unwrap_or_else(|_| bytes.to_vec())
+                }
+                Err(_) => bytes.to_vec(),
+            };
+            if sender.send_data(Bytes::from(out)).await.is_err() {
                 log::debug!("Client disconnected");
             }
         }
-    } else {
-        // Pass through raw response
-        if sender.send_data(bytes).await.is_err() {
-            log::debug!("Client disconnected");
-        }
+        Err(e) => log::error!("Failed to get response body: {e}"),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::is_insecure_public_bind;
+    use super::{body_channel, is_insecure_public_bind};
 
     #[test]
     fn loopback_never_warns() {
@@ -3574,4 +7063,14 @@ mod tests {
         assert!(!is_insecure_public_bind("0.0.0.0", "secret"));
         assert!(!is_insecure_public_bind("192.168.1.10", "secret"));
     }
+
+    #[tokio::test]
+    async fn dropped_client_body_abandons_upstream() {
+        let (sender, body) = body_channel();
+        assert_eq!(sender.unless_closed(async { 7 }).await, Some(7));
+
+        drop(body);
+        let never = std::future::pending::<()>();
+        assert_eq!(sender.unless_closed(never).await, None);
+    }
 }
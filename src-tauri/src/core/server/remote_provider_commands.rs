@@ -24,7 +24,8 @@ pub struct RegisterProviderRequest {
     pub custom_headers: Vec<ProviderCustomHeader>,
     pub models: Vec<String>,
     /// Upstream wire API (`"openai"` default, or `"openai-responses"` /
-    /// `"google"` / `"anthropic"` to engage a translating converter).
+    /// `"google"` / `"anthropic"` / `"ollama"` / `"azure-openai"` to engage a
+    /// translating converter).
     #[serde(default)]
     pub api_type: Option<String>,
 }
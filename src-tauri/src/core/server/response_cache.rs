@@ -0,0 +1,400 @@
+//! Opt-in on-disk cache for deterministic `/chat/completions` and
+//! `/embeddings` replies.
+//!
+//! Evaluation scripts replay the same `temperature: 0` prompts and embedding
+//! batches many times; with `response_cache` enabled the proxy answers repeats
+//! from `<jan_data>/response_cache/` instead of the model. Entries are keyed by
+//! a SHA-256 of the route and the request body with its keys sorted, so the
+//! model, messages and every sampling parameter take part. Streamed replies are
+//! stored as their SSE text and replayed event by event. Entries expire after
+//! the TTL, and the oldest are evicted once the folder exceeds its size cap.
+//!
+//! Chat requests are only cached when sampling is deterministic
+//! (`temperature: 0` or `top_k: 1`); clients can skip the cache with
+//! `Cache-Control: no-cache` or `no-store`.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Reply header reporting `hit` or `miss` for cacheable requests.
+pub const RESPONSE_CACHE_HEADER: &str = "x-jan-cache";
+
+const RESPONSE_CACHE_DIR_NAME: &str = "response_cache";
+const DEFAULT_MAX_BYTES: u64 = 256 * 1024 * 1024;
+const DEFAULT_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Request fields that don't change what the model generates.
+const IGNORED_FIELDS: &[&str] = &["user", "metadata", "store"];
+
+/// Cache settings from `StartServerConfig`.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseCacheOptions {
+    pub enabled: bool,
+    /// Size cap for the cache folder; defaults to 256 MiB.
+    pub max_bytes: Option<u64>,
+    /// How long an entry may be served; defaults to seven days.
+    pub ttl_secs: Option<u64>,
+}
+
+/// Whether the request headers ask to bypass the cache.
+pub fn bypasses_cache(headers: &hyper::HeaderMap) -> bool {
+    headers
+        .get_all(hyper::header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|directive| matches!(directive.trim(), "no-cache" | "no-store"))
+}
+
+/// Cache key for a request to `route` with JSON `body`, or `None` when the
+/// request isn't cacheable.
+pub fn cache_key(route: &str, body: &Value) -> Option<String> {
+    let object = body.as_object()?;
+    object.get("model")?.as_str()?;
+    match route {
+        "/embeddings" => {}
+        "/chat/completions" => {
+            let deterministic = object.get("temperature").and_then(Value::as_f64) == Some(0.0)
+                || object.get("top_k").and_then(Value::as_u64) == Some(1);
+            if !deterministic {
+                return None;
+            }
+        }
+        _ => return None,
+    }
+
+    let mut canonical = String::new();
+    for (name, value) in sorted(object) {
+        if !IGNORED_FIELDS.contains(&name.as_str()) {
+            let _ = write!(canonical, "{}:", Value::String(name.clone()));
+            write_canonical(&mut canonical, value);
+            canonical.push(',');
+        }
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(route.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(canonical.as_bytes());
+    Some(hex::encode(hasher.finalize()))
+}
+
+fn sorted(object: &serde_json::Map<String, Value>) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = object.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+/// JSON with object keys sorted, so key order in the request doesn't matter.
+fn write_canonical(out: &mut String, value: &Value) {
+    match value {
+        Value::Object(object) => {
+            out.push('{');
+            for (i, (name, value)) in sorted(object).into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "{}:", Value::String(name.clone()));
+                write_canonical(out, value);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, item);
+            }
+            out.push(']');
+        }
+        // `0.0` and `0` sample the same way.
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 && f.abs() < 1e15 => {
+                let _ = write!(out, "{}", f as i64);
+            }
+            _ => {
+                let _ = write!(out, "{n}");
+            }
+        },
+        scalar => {
+            let _ = write!(out, "{scalar}");
+        }
+    }
+}
+
+/// A stored reply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub created_at: i64,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl CachedResponse {
+    pub fn is_event_stream(&self) -> bool {
+        self.content_type.contains("event-stream")
+    }
+
+    /// Whether the reply finished normally: a stream that reached `[DONE]`,
+    /// or a JSON body without an `error`.
+    fn is_complete(&self) -> bool {
+        if self.is_event_stream() {
+            return self.body.lines().any(|line| line.trim() == "data: [DONE]");
+        }
+        serde_json::from_str::<Value>(&self.body)
+            .is_ok_and(|json| json.is_object() && json.get("error").is_none())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    size: u64,
+    modified: i64,
+}
+
+/// The cache folder plus an in-memory index of its entries, built from the
+/// folder on first use.
+#[derive(Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    ttl: Duration,
+    index: Mutex<Option<HashMap<String, IndexEntry>>>,
+}
+
+impl ResponseCache {
+    /// The cache under `data_folder`, or `None` when it is disabled.
+    pub fn new(data_folder: &Path, options: &ResponseCacheOptions) -> Option<Self> {
+        options.enabled.then(|| Self {
+            dir: data_folder.join(RESPONSE_CACHE_DIR_NAME),
+            max_bytes: options.max_bytes.unwrap_or(DEFAULT_MAX_BYTES),
+            ttl: Duration::from_secs(options.ttl_secs.unwrap_or(DEFAULT_TTL_SECS)),
+            index: Mutex::new(None),
+        })
+    }
+
+    /// Largest reply worth buffering for the cache.
+    pub fn max_entry_bytes(&self) -> usize {
+        usize::try_from(self.max_bytes).unwrap_or(usize::MAX)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    fn with_index<T>(&self, f: impl FnOnce(&mut HashMap<String, IndexEntry>) -> T) -> T {
+        let mut guard = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let index = guard.get_or_insert_with(|| scan_dir(&self.dir));
+        f(index)
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.get_at(key, chrono::Utc::now().timestamp())
+    }
+
+    fn get_at(&self, key: &str, now: i64) -> Option<CachedResponse> {
+        self.with_index(|index| {
+            index.get(key)?;
+            let path = self.entry_path(key);
+            let entry = fs::read(&path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<CachedResponse>(&bytes).ok());
+            match entry {
+                Some(entry) if !self.is_expired(entry.created_at, now) => Some(entry),
+                _ => {
+                    // Expired, removed by hand, or unreadable.
+                    let _ = fs::remove_file(&path);
+                    index.remove(key);
+                    None
+                }
+            }
+        })
+    }
+
+    /// Store a finished reply under `key`. Incomplete or error replies and
+    /// replies larger than the whole cache are dropped.
+    pub fn put(&self, key: &str, content_type: &str, body: String) {
+        self.put_at(key, content_type, body, chrono::Utc::now().timestamp());
+    }
+
+    fn put_at(&self, key: &str, content_type: &str, body: String, now: i64) {
+        let entry = CachedResponse {
+            created_at: now,
+            content_type: content_type.to_string(),
+            body,
+        };
+        if !entry.is_complete() {
+            return;
+        }
+        let Ok(bytes) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        let size = bytes.len() as u64;
+        if size > self.max_bytes {
+            return;
+        }
+
+        self.with_index(|index| {
+            let path = self.entry_path(key);
+            let tmp = path.with_extension("json.tmp");
+            let written = fs::create_dir_all(&self.dir)
+                .and_then(|_| fs::write(&tmp, &bytes))
+                .and_then(|_| fs::rename(&tmp, &path));
+            if let Err(err) = written {
+                log::warn!(
+                    "Failed to write response cache entry {}: {err}",
+                    path.display()
+                );
+                return;
+            }
+            index.insert(
+                key.to_string(),
+                IndexEntry {
+                    size,
+                    modified: now,
+                },
+            );
+            self.evict(index, now);
+        });
+    }
+
+    fn is_expired(&self, created_at: i64, now: i64) -> bool {
+        now.saturating_sub(created_at) > self.ttl.as_secs() as i64
+    }
+
+    /// Drop expired entries, then the oldest ones until under the size cap.
+    fn evict(&self, index: &mut HashMap<String, IndexEntry>, now: i64) {
+        let mut entries: Vec<(String, IndexEntry)> =
+            index.iter().map(|(k, e)| (k.clone(), *e)).collect();
+        entries.sort_by_key(|(_, e)| e.modified);
+        let mut total: u64 = entries.iter().map(|(_, e)| e.size).sum();
+        for (key, entry) in entries {
+            if total <= self.max_bytes && !self.is_expired(entry.modified, now) {
+                continue;
+            }
+            let _ = fs::remove_file(self.entry_path(&key));
+            index.remove(&key);
+            total -= entry.size;
+        }
+    }
+}
+
+fn scan_dir(dir: &Path) -> HashMap<String, IndexEntry> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let key = name.strip_suffix(".json")?.to_string();
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata
+                .modified()
+                .ok()?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_secs() as i64;
+            Some((
+                key,
+                IndexEntry {
+                    size: metadata.len(),
+                    modified,
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cache(dir: &Path, max_bytes: u64) -> ResponseCache {
+        ResponseCache::new(
+            dir,
+            &ResponseCacheOptions {
+                enabled: true,
+                max_bytes: Some(max_bytes),
+                ttl_secs: Some(60),
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn keys_cover_body_but_not_key_order_or_ignored_fields() {
+        let body = json!({
+            "model": "qwen3-8b",
+            "temperature": 0,
+            "messages": [{"role": "user", "content": "Hi"}],
+        });
+        let reordered = json!({
+            "messages": [{"content": "Hi", "role": "user"}],
+            "user": "eval-run-7",
+            "temperature": 0.0,
+            "model": "qwen3-8b",
+        });
+        let key = cache_key("/chat/completions", &body).unwrap();
+        assert_eq!(
+            cache_key("/chat/completions", &reordered),
+            Some(key.clone())
+        );
+
+        let mut streamed = body.clone();
+        streamed["stream"] = json!(true);
+        assert_ne!(cache_key("/chat/completions", &streamed), Some(key.clone()));
+        let mut other_model = body.clone();
+        other_model["model"] = json!("gemma-3");
+        assert_ne!(cache_key("/chat/completions", &other_model), Some(key));
+
+        let sampled = json!({"model": "qwen3-8b", "temperature": 0.7, "messages": []});
+        assert!(cache_key("/chat/completions", &sampled).is_none());
+        let embeddings = json!({"model": "nomic-embed", "input": ["a", "b"]});
+        assert!(cache_key("/embeddings", &embeddings).is_some());
+        assert!(cache_key("/messages", &body).is_none());
+    }
+
+    #[test]
+    fn stores_complete_replies_until_they_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), 1024 * 1024);
+        let sse = "data: {\"choices\":[]}\n\ndata: [DONE]\n\n".to_string();
+
+        cache.put_at("truncated", "text/event-stream", "data: {}\n\n".into(), 100);
+        cache.put_at("failed", "application/json", "{\"error\":{}}".into(), 100);
+        cache.put_at("stream", "text/event-stream", sse.clone(), 100);
+        assert!(cache.get_at("truncated", 100).is_none());
+        assert!(cache.get_at("failed", 100).is_none());
+        assert_eq!(cache.get_at("stream", 150).unwrap().body, sse);
+
+        // A fresh index (next server start) finds the entry on disk.
+        let reopened = super::tests::cache(dir.path(), 1024 * 1024);
+        assert!(reopened.get_at("stream", 150).unwrap().is_event_stream());
+        assert!(reopened.get_at("stream", 161).is_none());
+        assert!(!cache.entry_path("stream").exists());
+    }
+
+    #[test]
+    fn evicts_oldest_entries_over_the_size_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = format!("{{\"data\":\"{}\"}}", "x".repeat(400));
+        let cache = cache(dir.path(), 1200);
+        cache.put_at("a", "application/json", body.clone(), 100);
+        cache.put_at("b", "application/json", body.clone(), 101);
+        cache.put_at("c", "application/json", body, 102);
+        assert!(cache.get_at("a", 110).is_none());
+        assert!(cache.get_at("b", 110).is_some());
+        assert!(cache.get_at("c", 110).is_some());
+    }
+}
@@ -0,0 +1,1102 @@
+//! OpenAI Responses API (`POST /v1/responses`) ingress.
+//!
+//! The reverse of [`OpenAIResponsesConverter`](super::converters::OpenAIResponsesConverter):
+//! a Responses-shaped request is rewritten into chat/completions so it can be
+//! served by any backend the proxy already routes (llama.cpp router, MLX, or a
+//! registered provider with or without a converter), and the chat-shaped
+//! response is translated back into a `response` object or `response.*` SSE
+//! events.
+//!
+//! The proxy is stateless, so `previous_response_id` and `store` are not
+//! supported; clients must resend the full conversation as input items.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use super::converters::ChatStreamTranslator;
+use super::media;
+
+/// Prefix a fresh random id the way OpenAI does (`resp_…`, `msg_…`, `fc_…`).
+fn new_id(prefix: &str) -> String {
+    format!("{prefix}_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Text of a Responses `content` value: a bare string, or an array of
+/// `input_text` / `output_text` / `summary_text` parts.
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    }
+}
+
+/// Map Responses message content to chat content. Text-only content collapses
+/// to a string; images, audio and files become their chat parts so the
+/// upstream still sees them.
+fn content_to_chat(content: &Value) -> Value {
+    let Some(parts) = content.as_array() else {
+        return Value::String(content_text(content));
+    };
+    let mut out: Vec<Value> = Vec::new();
+    for part in parts {
+        match part.get("type").and_then(|t| t.as_str()) {
+            Some("input_text") | Some("output_text") | Some("text") => {
+                if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                    out.push(json!({"type": "text", "text": text}));
+                }
+            }
+            _ => out.extend(media::from_responses_part(part).and_then(|m| media::to_chat_part(&m))),
+        }
+    }
+    if out.iter().all(|p| p.get("type").and_then(|t| t.as_str()) == Some("text")) {
+        Value::String(
+            out.iter()
+                .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join(""),
+        )
+    } else {
+        Value::Array(out)
+    }
+}
+
+/// Text of a `function_call_output.output`, which is a string or an array of
+/// content parts.
+fn output_text(output: Option<&Value>) -> String {
+    match output {
+        Some(Value::String(s)) => s.clone(),
+        Some(v @ Value::Array(_)) => content_text(v),
+        Some(Value::Null) | None => String::new(),
+        Some(v) => v.to_string(),
+    }
+}
+
+/// Attach a tool call to the trailing assistant message, or start a new one,
+/// along with any reasoning that preceded it. Responses lists each
+/// `function_call` as its own item; chat groups a turn's calls on one
+/// assistant message.
+fn push_tool_call(messages: &mut Vec<Value>, call: Value, reasoning: &mut Option<String>) {
+    let continues_turn = messages.last().is_some_and(|last| {
+        last.get("role").and_then(|r| r.as_str()) == Some("assistant")
+            && last.get("tool_calls").map_or(true, Value::is_array)
+    });
+    if !continues_turn {
+        messages.push(json!({"role": "assistant", "content": Value::Null}));
+    }
+    let Some(turn) = messages.last_mut() else {
+        return;
+    };
+    match turn.get_mut("tool_calls").and_then(|c| c.as_array_mut()) {
+        Some(calls) => calls.push(call),
+        None => turn["tool_calls"] = json!([call]),
+    }
+    if let Some(r) = reasoning.take() {
+        // Reasoning between a turn's calls follows what the turn already has.
+        turn["reasoning_content"] = match turn.get("reasoning_content").and_then(|v| v.as_str()) {
+            Some(earlier) if !earlier.is_empty() => json!(format!("{earlier}\n\n{r}")),
+            _ => json!(r),
+        };
+    }
+}
+
+/// Rewrite a Responses request body into a chat/completions body.
+pub(crate) fn responses_request_to_chat(body: &Value) -> Result<Value, String> {
+    let model = body
+        .get("model")
+        .and_then(|m| m.as_str())
+        .ok_or("Request body must contain a 'model' field")?;
+    if body
+        .get("previous_response_id")
+        .is_some_and(|v| !v.is_null())
+    {
+        return Err(
+            "previous_response_id is not supported; send the full conversation as input items"
+                .to_string(),
+        );
+    }
+
+    let mut messages: Vec<Value> = Vec::new();
+    if let Some(instructions) = body.get("instructions").and_then(|i| i.as_str()) {
+        if !instructions.is_empty() {
+            messages.push(json!({"role": "system", "content": instructions}));
+        }
+    }
+
+    // Reasoning items precede the assistant output they produced; carry their
+    // summary onto that assistant message as `reasoning_content`.
+    let mut pending_reasoning: Option<String> = None;
+    match body.get("input") {
+        Some(Value::String(text)) => messages.push(json!({"role": "user", "content": text})),
+        Some(Value::Array(items)) => {
+            for item in items {
+                let kind = item
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("message");
+                match kind {
+                    "message" => {
+                        let role = item.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+                        let content = content_to_chat(item.get("content").unwrap_or(&Value::Null));
+                        let mut msg = json!({"role": role, "content": content});
+                        if role == "assistant" {
+                            if let Some(r) = pending_reasoning.take() {
+                                msg["reasoning_content"] = json!(r);
+                            }
+                        }
+                        messages.push(msg);
+                    }
+                    "function_call" => {
+                        let call = json!({
+                            "id": item.get("call_id").cloned().unwrap_or(Value::Null),
+                            "type": "function",
+                            "function": {
+                                "name": item.get("name").cloned().unwrap_or(Value::Null),
+                                "arguments": item
+                                    .get("arguments")
+                                    .cloned()
+                                    .unwrap_or_else(|| json!("{}")),
+                            }
+                        });
+                        push_tool_call(&mut messages, call, &mut pending_reasoning);
+                    }
+                    "function_call_output" => {
+                        messages.push(json!({
+                            "role": "tool",
+                            "tool_call_id": item.get("call_id").cloned().unwrap_or(Value::Null),
+                            "content": output_text(item.get("output")),
+                        }));
+                    }
+                    "reasoning" => {
+                        let summary = item
+                            .get("summary")
+                            .map(content_text)
+                            .unwrap_or_default();
+                        if !summary.is_empty() {
+                            pending_reasoning = Some(summary);
+                        }
+                    }
+                    other => log::debug!("Dropping unsupported Responses input item '{other}'"),
+                }
+            }
+        }
+        _ => return Err("Request body must contain 'input' as a string or array".to_string()),
+    }
+
+    let stream = body.get("stream").and_then(|s| s.as_bool()).unwrap_or(false);
+    let mut out = json!({
+        "model": model,
+        "messages": messages,
+        "stream": stream,
+    });
+    if stream {
+        // Usage only arrives in streamed chat responses when asked for; the
+        // terminal `response.completed` event needs it.
+        out["stream_options"] = json!({"include_usage": true});
+    }
+
+    if let Some(v) = body.get("max_output_tokens") {
+        out["max_tokens"] = v.clone();
+    }
+    for key in ["temperature", "top_p", "parallel_tool_calls", "user"] {
+        if let Some(v) = body.get(key) {
+            out[key] = v.clone();
+        }
+    }
+    if let Some(effort) = body
+        .get("reasoning")
+        .and_then(|r| r.get("effort"))
+        .and_then(|e| e.as_str())
+    {
+        out["reasoning_effort"] = json!(effort);
+    }
+
+    if let Some(format) = body.get("text").and_then(|t| t.get("format")) {
+        match format.get("type").and_then(|t| t.as_str()) {
+            Some("json_schema") => {
+                let mut schema = json!({
+                    "name": format.get("name").cloned().unwrap_or_else(|| json!("response")),
+                    "schema": format.get("schema").cloned().unwrap_or_else(|| json!({})),
+                });
+                if let Some(strict) = format.get("strict") {
+                    schema["strict"] = strict.clone();
+                }
+                out["response_format"] = json!({"type": "json_schema", "json_schema": schema});
+            }
+            Some("json_object") => out["response_format"] = json!({"type": "json_object"}),
+            _ => {}
+        }
+    }
+
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        let functions: Vec<Value> = tools
+            .iter()
+            .filter_map(|t| {
+                if t.get("type").and_then(|v| v.as_str()) != Some("function") {
+                    log::debug!("Dropping unsupported Responses tool type: {:?}", t.get("type"));
+                    return None;
+                }
+                Some(json!({
+                    "type": "function",
+                    "function": {
+                        "name": t.get("name").cloned().unwrap_or(Value::Null),
+                        "description": t.get("description").cloned().unwrap_or_else(|| json!("")),
+                        "parameters": t.get("parameters").cloned().unwrap_or_else(|| json!({"type": "object"})),
+                    }
+                }))
+            })
+            .collect();
+        if !functions.is_empty() {
+            out["tools"] = json!(functions);
+        }
+    }
+    if let Some(tc) = body.get("tool_choice") {
+        out["tool_choice"] = match tc.get("type").and_then(|t| t.as_str()) {
+            Some("function") => json!({
+                "type": "function",
+                "function": {"name": tc.get("name").cloned().unwrap_or(Value::Null)}
+            }),
+            _ => tc.clone(),
+        };
+    }
+
+    Ok(out)
+}
+
+/// Map chat/completions usage to Responses usage.
+fn responses_usage(usage: Option<&Value>) -> Value {
+    let Some(u) = usage.filter(|u| !u.is_null()) else {
+        return Value::Null;
+    };
+    let input = u.get("prompt_tokens").and_then(|v| v.as_i64()).unwrap_or(0);
+    let output = u.get("completion_tokens").and_then(|v| v.as_i64()).unwrap_or(0);
+    let cached = u
+        .get("prompt_tokens_details")
+        .and_then(|d| d.get("cached_tokens"))
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    let reasoning = u
+        .get("completion_tokens_details")
+        .and_then(|d| d.get("reasoning_tokens"))
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    json!({
+        "input_tokens": input,
+        "input_tokens_details": {"cached_tokens": cached},
+        "output_tokens": output,
+        "output_tokens_details": {"reasoning_tokens": reasoning},
+        "total_tokens": u.get("total_tokens").and_then(|v| v.as_i64()).unwrap_or(input + output),
+    })
+}
+
+/// Build a `response` object, echoing the request parameters clients read back.
+fn response_envelope(
+    id: &str,
+    created_at: i64,
+    model: &Value,
+    request: &Value,
+    status: &str,
+    output: Vec<Value>,
+    usage: Value,
+) -> Value {
+    let mut resp = json!({
+        "id": id,
+        "object": "response",
+        "created_at": created_at,
+        "status": status,
+        "model": model,
+        "output": output,
+        "usage": usage,
+        "error": Value::Null,
+        "incomplete_details": Value::Null,
+        "previous_response_id": Value::Null,
+        "instructions": request.get("instructions").cloned().unwrap_or(Value::Null),
+        "max_output_tokens": request.get("max_output_tokens").cloned().unwrap_or(Value::Null),
+        "metadata": request.get("metadata").cloned().unwrap_or_else(|| json!({})),
+        "parallel_tool_calls": request.get("parallel_tool_calls").cloned().unwrap_or(json!(true)),
+        "temperature": request.get("temperature").cloned().unwrap_or(Value::Null),
+        "top_p": request.get("top_p").cloned().unwrap_or(Value::Null),
+        "tool_choice": request.get("tool_choice").cloned().unwrap_or_else(|| json!("auto")),
+        "tools": request.get("tools").cloned().unwrap_or_else(|| json!([])),
+        "text": request.get("text").cloned().unwrap_or_else(|| json!({"format": {"type": "text"}})),
+    });
+    if status == "incomplete" {
+        resp["incomplete_details"] = json!({"reason": "max_output_tokens"});
+    }
+    resp
+}
+
+fn reasoning_item(id: &str, text: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "reasoning",
+        "summary": [{"type": "summary_text", "text": text}],
+    })
+}
+
+fn message_item(id: &str, text: &str, status: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "message",
+        "status": status,
+        "role": "assistant",
+        "content": [{"type": "output_text", "text": text, "annotations": []}],
+    })
+}
+
+fn function_call_item(id: &str, call_id: &Value, name: &Value, arguments: &str, status: &str) -> Value {
+    json!({
+        "id": id,
+        "type": "function_call",
+        "status": status,
+        "call_id": call_id,
+        "name": name,
+        "arguments": arguments,
+    })
+}
+
+/// Translate a non-streaming chat.completion into a `response` object.
+pub(crate) fn chat_response_to_responses(chat: &Value, request: &Value) -> Value {
+    let choice = chat
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .and_then(|c| c.first());
+    let message = choice.and_then(|c| c.get("message"));
+
+    let mut output: Vec<Value> = Vec::new();
+    if let Some(reasoning) = message
+        .and_then(|m| m.get("reasoning_content"))
+        .and_then(|r| r.as_str())
+        .filter(|r| !r.is_empty())
+    {
+        output.push(reasoning_item(&new_id("rs"), reasoning));
+    }
+    if let Some(text) = message
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_str())
+        .filter(|t| !t.is_empty())
+    {
+        output.push(message_item(&new_id("msg"), text, "completed"));
+    }
+    if let Some(calls) = message
+        .and_then(|m| m.get("tool_calls"))
+        .and_then(|c| c.as_array())
+    {
+        for call in calls {
+            let func = call.get("function");
+            output.push(function_call_item(
+                &new_id("fc"),
+                call.get("id").unwrap_or(&Value::Null),
+                func.and_then(|f| f.get("name")).unwrap_or(&Value::Null),
+                func.and_then(|f| f.get("arguments"))
+                    .and_then(|a| a.as_str())
+                    .unwrap_or("{}"),
+                "completed",
+            ));
+        }
+    }
+
+    let status = match choice
+        .and_then(|c| c.get("finish_reason"))
+        .and_then(|f| f.as_str())
+    {
+        Some("length") => "incomplete",
+        _ => "completed",
+    };
+    let created_at = chat
+        .get("created")
+        .and_then(|c| c.as_i64())
+        .filter(|c| *c > 0)
+        .unwrap_or_else(|| chrono::Utc::now().timestamp());
+
+    response_envelope(
+        &new_id("resp"),
+        created_at,
+        chat.get("model").unwrap_or(&Value::Null),
+        request,
+        status,
+        output,
+        responses_usage(chat.get("usage")),
+    )
+}
+
+/// An output item still receiving deltas.
+#[derive(Debug)]
+enum OpenItem {
+    Reasoning { id: String, text: String },
+    Message { id: String, text: String },
+    FunctionCall {
+        id: String,
+        call_id: Value,
+        name: Value,
+        arguments: String,
+    },
+}
+
+/// Translates a chat/completions chunk stream into Responses `response.*`
+/// events.
+#[derive(Debug)]
+pub(crate) struct ResponsesStreamTranslator {
+    request: Value,
+    id: String,
+    created_at: i64,
+    model: Value,
+    sequence: u64,
+    started: bool,
+    finished: bool,
+    /// Items still receiving deltas, in output order: one reasoning or
+    /// message item, or the function calls of a turn, whose deltas may
+    /// interleave.
+    open: Vec<OpenItem>,
+    /// Call id of each chat tool call index; later chunks of a call only
+    /// carry the index.
+    call_ids: HashMap<u64, Value>,
+    output: Vec<Value>,
+    usage: Value,
+    finish_reason: Option<String>,
+}
+
+impl ResponsesStreamTranslator {
+    pub(crate) fn new(request: &Value) -> Self {
+        Self {
+            request: request.clone(),
+            id: new_id("resp"),
+            created_at: chrono::Utc::now().timestamp(),
+            model: request.get("model").cloned().unwrap_or(Value::Null),
+            sequence: 0,
+            started: false,
+            finished: false,
+            open: Vec::new(),
+            call_ids: HashMap::new(),
+            output: Vec::new(),
+            usage: Value::Null,
+            finish_reason: None,
+        }
+    }
+
+    fn event(&mut self, kind: &str, mut payload: Value) -> Value {
+        payload["type"] = json!(kind);
+        payload["sequence_number"] = json!(self.sequence);
+        self.sequence += 1;
+        payload
+    }
+
+    fn envelope(&self, status: &str) -> Value {
+        response_envelope(
+            &self.id,
+            self.created_at,
+            &self.model,
+            &self.request,
+            status,
+            self.output.clone(),
+            self.usage.clone(),
+        )
+    }
+
+    fn ensure_started(&mut self, events: &mut Vec<Value>) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        let created = json!({"response": self.envelope("in_progress")});
+        let ev = self.event("response.created", created);
+        events.push(ev);
+        let in_progress = json!({"response": self.envelope("in_progress")});
+        let ev = self.event("response.in_progress", in_progress);
+        events.push(ev);
+    }
+
+    /// Close the open items, emitting their `*.done` events and recording
+    /// them in the final output list.
+    fn close_open(&mut self, events: &mut Vec<Value>) {
+        for item in std::mem::take(&mut self.open) {
+            self.close_item(item, events);
+        }
+    }
+
+    fn close_item(&mut self, item: OpenItem, events: &mut Vec<Value>) {
+        let output_index = self.output.len();
+        let done_item = match item {
+            OpenItem::Reasoning { id, text } => {
+                let ev = self.event(
+                    "response.reasoning_summary_text.done",
+                    json!({"item_id": id, "output_index": output_index, "summary_index": 0, "text": text}),
+                );
+                events.push(ev);
+                let ev = self.event(
+                    "response.reasoning_summary_part.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "summary_index": 0,
+                        "part": {"type": "summary_text", "text": text},
+                    }),
+                );
+                events.push(ev);
+                reasoning_item(&id, &text)
+            }
+            OpenItem::Message { id, text } => {
+                let ev = self.event(
+                    "response.output_text.done",
+                    json!({"item_id": id, "output_index": output_index, "content_index": 0, "text": text}),
+                );
+                events.push(ev);
+                let ev = self.event(
+                    "response.content_part.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "part": {"type": "output_text", "text": text, "annotations": []},
+                    }),
+                );
+                events.push(ev);
+                message_item(&id, &text, "completed")
+            }
+            OpenItem::FunctionCall {
+                id,
+                call_id,
+                name,
+                arguments,
+                ..
+            } => {
+                let ev = self.event(
+                    "response.function_call_arguments.done",
+                    json!({"item_id": id, "output_index": output_index, "arguments": arguments}),
+                );
+                events.push(ev);
+                function_call_item(&id, &call_id, &name, &arguments, "completed")
+            }
+        };
+        let ev = self.event(
+            "response.output_item.done",
+            json!({"output_index": output_index, "item": done_item}),
+        );
+        events.push(ev);
+        self.output.push(done_item);
+    }
+
+    /// Open `item` after the open ones. A function call opens alongside the
+    /// turn's other calls; anything else closes what is open first.
+    fn open_item(&mut self, item: OpenItem, events: &mut Vec<Value>) {
+        let joins_calls = matches!(item, OpenItem::FunctionCall { .. })
+            && self
+                .open
+                .iter()
+                .all(|open| matches!(open, OpenItem::FunctionCall { .. }));
+        if !joins_calls {
+            self.close_open(events);
+        }
+        let output_index = self.output.len() + self.open.len();
+        match &item {
+            OpenItem::Reasoning { id, .. } => {
+                let ev = self.event(
+                    "response.output_item.added",
+                    json!({"output_index": output_index, "item": {"id": id, "type": "reasoning", "summary": []}}),
+                );
+                events.push(ev);
+                let ev = self.event(
+                    "response.reasoning_summary_part.added",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "summary_index": 0,
+                        "part": {"type": "summary_text", "text": ""},
+                    }),
+                );
+                events.push(ev);
+            }
+            OpenItem::Message { id, .. } => {
+                let added = json!({
+                    "id": id,
+                    "type": "message",
+                    "status": "in_progress",
+                    "role": "assistant",
+                    "content": [],
+                });
+                let ev = self.event(
+                    "response.output_item.added",
+                    json!({"output_index": output_index, "item": added}),
+                );
+                events.push(ev);
+                let ev = self.event(
+                    "response.content_part.added",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "part": {"type": "output_text", "text": "", "annotations": []},
+                    }),
+                );
+                events.push(ev);
+            }
+            OpenItem::FunctionCall {
+                id, call_id, name, ..
+            } => {
+                let added = function_call_item(id, call_id, name, "", "in_progress");
+                let ev = self.event(
+                    "response.output_item.added",
+                    json!({"output_index": output_index, "item": added}),
+                );
+                events.push(ev);
+            }
+        }
+        self.open.push(item);
+    }
+}
+
+impl ChatStreamTranslator for ResponsesStreamTranslator {
+    fn push_chunk(&mut self, chunk: &Value) -> Vec<Value> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+        if let Some(model) = chunk.get("model").filter(|m| m.as_str().is_some_and(|s| !s.is_empty())) {
+            self.model = model.clone();
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.usage = responses_usage(Some(usage));
+        }
+        self.ensure_started(&mut events);
+
+        let choice = chunk
+            .get("choices")
+            .and_then(|c| c.as_array())
+            .and_then(|c| c.first());
+        let Some(delta) = choice.and_then(|c| c.get("delta")) else {
+            return events;
+        };
+
+        if let Some(text) = delta
+            .get("reasoning_content")
+            .and_then(|r| r.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            if !matches!(self.open.last(), Some(OpenItem::Reasoning { .. })) {
+                self.open_item(
+                    OpenItem::Reasoning {
+                        id: new_id("rs"),
+                        text: String::new(),
+                    },
+                    &mut events,
+                );
+            }
+            let output_index = self.output.len();
+            let item_id = match self.open.last_mut() {
+                Some(OpenItem::Reasoning { id, text: acc }) => {
+                    acc.push_str(text);
+                    id.clone()
+                }
+                _ => String::new(),
+            };
+            let ev = self.event(
+                "response.reasoning_summary_text.delta",
+                json!({"item_id": item_id, "output_index": output_index, "summary_index": 0, "delta": text}),
+            );
+            events.push(ev);
+        }
+
+        if let Some(text) = delta
+            .get("content")
+            .and_then(|c| c.as_str())
+            .filter(|t| !t.is_empty())
+        {
+            if !matches!(self.open.last(), Some(OpenItem::Message { .. })) {
+                self.open_item(
+                    OpenItem::Message {
+                        id: new_id("msg"),
+                        text: String::new(),
+                    },
+                    &mut events,
+                );
+            }
+            let output_index = self.output.len();
+            let item_id = match self.open.last_mut() {
+                Some(OpenItem::Message { id, text: acc }) => {
+                    acc.push_str(text);
+                    id.clone()
+                }
+                _ => String::new(),
+            };
+            let ev = self.event(
+                "response.output_text.delta",
+                json!({"item_id": item_id, "output_index": output_index, "content_index": 0, "delta": text}),
+            );
+            events.push(ev);
+        }
+
+        if let Some(calls) = delta.get("tool_calls").and_then(|c| c.as_array()) {
+            for call in calls {
+                let chat_index = call.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+                let call_id = match call.get("id").filter(|id| !id.is_null()) {
+                    Some(id) => {
+                        self.call_ids.insert(chat_index, id.clone());
+                        id.clone()
+                    }
+                    None => self
+                        .call_ids
+                        .entry(chat_index)
+                        .or_insert_with(|| json!(new_id("call")))
+                        .clone(),
+                };
+                let open_call = self.open.iter().position(|item| {
+                    matches!(item, OpenItem::FunctionCall { call_id: id, .. } if *id == call_id)
+                });
+                let position = match open_call {
+                    Some(position) => position,
+                    None if self.output.iter().any(|item| item["call_id"] == call_id) => {
+                        log::warn!("Dropping a late delta for closed tool call {call_id}");
+                        continue;
+                    }
+                    None => {
+                        let func = call.get("function");
+                        self.open_item(
+                            OpenItem::FunctionCall {
+                                id: new_id("fc"),
+                                call_id,
+                                name: func
+                                    .and_then(|f| f.get("name"))
+                                    .cloned()
+                                    .unwrap_or(Value::Null),
+                                arguments: String::new(),
+                            },
+                            &mut events,
+                        );
+                        self.open.len() - 1
+                    }
+                };
+                if let Some(args) = call
+                    .get("function")
+                    .and_then(|f| f.get("arguments"))
+                    .and_then(|a| a.as_str())
+                    .filter(|a| !a.is_empty())
+                {
+                    let output_index = self.output.len() + position;
+                    let item_id = match self.open.get_mut(position) {
+                        Some(OpenItem::FunctionCall { id, arguments, .. }) => {
+                            arguments.push_str(args);
+                            id.clone()
+                        }
+                        _ => String::new(),
+                    };
+                    let ev = self.event(
+                        "response.function_call_arguments.delta",
+                        json!({"item_id": item_id, "output_index": output_index, "delta": args}),
+                    );
+                    events.push(ev);
+                }
+            }
+        }
+
+        if let Some(reason) = choice
+            .and_then(|c| c.get("finish_reason"))
+            .and_then(|f| f.as_str())
+        {
+            self.finish_reason = Some(reason.to_string());
+            self.close_open(&mut events);
+        }
+        events
+    }
+
+    /// Emits `response.completed`, or `response.incomplete` when the model hit
+    /// its token limit.
+    fn finish(&mut self) -> Vec<Value> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+        self.ensure_started(&mut events);
+        self.close_open(&mut events);
+        self.finished = true;
+        let (kind, status) = match self.finish_reason.as_deref() {
+            Some("length") => ("response.incomplete", "incomplete"),
+            _ => ("response.completed", "completed"),
+        };
+        let response = json!({"response": self.envelope(status)});
+        let ev = self.event(kind, response);
+        events.push(ev);
+        events
+    }
+
+    fn fail(&mut self, message: &str) -> Vec<Value> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+        self.ensure_started(&mut events);
+        self.finished = true;
+        let mut response = self.envelope("failed");
+        response["error"] = json!({"code": "server_error", "message": message});
+        let ev = self.event("response.failed", json!({"response": response}));
+        events.push(ev);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(events: &[Value]) -> Vec<&str> {
+        events
+            .iter()
+            .map(|e| e["type"].as_str().unwrap_or(""))
+            .collect()
+    }
+
+    #[test]
+    fn request_maps_instructions_string_input_and_params() {
+        let body = json!({
+            "model": "qwen3",
+            "instructions": "be brief",
+            "input": "hi",
+            "max_output_tokens": 64,
+            "temperature": 0.2,
+            "reasoning": {"effort": "low"},
+            "stream": true
+        });
+        let out = responses_request_to_chat(&body).unwrap();
+        assert_eq!(
+            out["messages"],
+            json!([
+                {"role": "system", "content": "be brief"},
+                {"role": "user", "content": "hi"}
+            ])
+        );
+        assert_eq!(out["max_tokens"], json!(64));
+        assert_eq!(out["temperature"], json!(0.2));
+        assert_eq!(out["reasoning_effort"], json!("low"));
+        assert_eq!(out["stream_options"], json!({"include_usage": true}));
+    }
+
+    #[test]
+    fn request_groups_function_calls_and_maps_outputs() {
+        let body = json!({
+            "model": "m",
+            "input": [
+                {"role": "user", "content": [{"type": "input_text", "text": "weather?"}]},
+                {"type": "reasoning", "id": "rs_1", "summary": [{"type": "summary_text", "text": "need tools"}]},
+                {"type": "function_call", "call_id": "c1", "name": "get_weather", "arguments": "{\"city\":\"Hanoi\"}"},
+                {"type": "function_call", "call_id": "c2", "name": "get_time", "arguments": "{}"},
+                {"type": "function_call_output", "call_id": "c1", "output": "sunny"},
+                {"type": "function_call_output", "call_id": "c2", "output": [{"type": "input_text", "text": "noon"}]}
+            ]
+        });
+        let out = responses_request_to_chat(&body).unwrap();
+        let msgs = out["messages"].as_array().unwrap();
+        assert_eq!(msgs.len(), 4);
+        assert_eq!(msgs[0], json!({"role": "user", "content": "weather?"}));
+        assert_eq!(msgs[1]["role"], json!("assistant"));
+        assert_eq!(msgs[1]["reasoning_content"], json!("need tools"));
+        assert_eq!(msgs[1]["tool_calls"].as_array().unwrap().len(), 2);
+        assert_eq!(msgs[1]["tool_calls"][1]["function"]["name"], json!("get_time"));
+        assert_eq!(
+            msgs[2],
+            json!({"role": "tool", "tool_call_id": "c1", "content": "sunny"})
+        );
+        assert_eq!(msgs[3]["content"], json!("noon"));
+    }
+
+    #[test]
+    fn request_keeps_reasoning_between_calls_of_one_turn() {
+        let body = json!({
+            "model": "m",
+            "input": [
+                {"role": "user", "content": "weather?"},
+                {"type": "message", "role": "assistant", "content": "Checking."},
+                {"type": "reasoning", "summary": [{"type": "summary_text", "text": "need weather"}]},
+                {"type": "function_call", "call_id": "c1", "name": "get_weather", "arguments": "{}"},
+                {"type": "reasoning", "summary": [{"type": "summary_text", "text": "and time"}]},
+                {"type": "function_call", "call_id": "c2", "name": "get_time", "arguments": "{}"}
+            ]
+        });
+        let out = responses_request_to_chat(&body).unwrap();
+        let msgs = out["messages"].as_array().unwrap();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[1]["content"], json!("Checking."));
+        assert_eq!(msgs[1]["tool_calls"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            msgs[1]["reasoning_content"],
+            json!("need weather\n\nand time")
+        );
+    }
+
+    #[test]
+    fn request_maps_tools_tool_choice_and_text_format() {
+        let body = json!({
+            "model": "m",
+            "input": "x",
+            "tools": [
+                {"type": "function", "name": "f", "description": "d", "parameters": {"type": "object"}},
+                {"type": "web_search_preview"}
+            ],
+            "tool_choice": {"type": "function", "name": "f"},
+            "text": {"format": {"type": "json_schema", "name": "out", "schema": {"type": "object"}, "strict": true}}
+        });
+        let out = responses_request_to_chat(&body).unwrap();
+        assert_eq!(
+            out["tools"],
+            json!([{"type": "function", "function": {"name": "f", "description": "d", "parameters": {"type": "object"}}}])
+        );
+        assert_eq!(out["tool_choice"], json!({"type": "function", "function": {"name": "f"}}));
+        assert_eq!(out["response_format"]["json_schema"]["name"], json!("out"));
+        assert_eq!(out["response_format"]["json_schema"]["strict"], json!(true));
+    }
+
+    #[test]
+    fn request_rejects_previous_response_id_and_missing_input() {
+        assert!(responses_request_to_chat(&json!({"model": "m", "input": "x", "previous_response_id": "resp_1"})).is_err());
+        assert!(responses_request_to_chat(&json!({"model": "m"})).is_err());
+        assert!(responses_request_to_chat(&json!({"input": "x"})).is_err());
+    }
+
+    #[test]
+    fn response_maps_reasoning_text_tool_calls_and_usage() {
+        let chat = json!({
+            "id": "chatcmpl-1",
+            "created": 42,
+            "model": "qwen3",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "done",
+                    "reasoning_content": "thinking",
+                    "tool_calls": [{"id": "c1", "type": "function", "function": {"name": "f", "arguments": "{}"}}]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        });
+        let resp = chat_response_to_responses(&chat, &json!({"model": "qwen3", "instructions": "sys"}));
+        assert_eq!(resp["object"], json!("response"));
+        assert_eq!(resp["status"], json!("completed"));
+        assert_eq!(resp["created_at"], json!(42));
+        assert_eq!(resp["instructions"], json!("sys"));
+        let output = resp["output"].as_array().unwrap();
+        assert_eq!(output[0]["type"], json!("reasoning"));
+        assert_eq!(output[1]["content"][0]["text"], json!("done"));
+        assert_eq!(output[2]["type"], json!("function_call"));
+        assert_eq!(output[2]["call_id"], json!("c1"));
+        assert_eq!(resp["usage"]["input_tokens"], json!(10));
+        assert_eq!(resp["usage"]["output_tokens"], json!(5));
+    }
+
+    #[test]
+    fn response_length_finish_is_incomplete() {
+        let chat = json!({
+            "choices": [{"message": {"content": "cut"}, "finish_reason": "length"}]
+        });
+        let resp = chat_response_to_responses(&chat, &json!({}));
+        assert_eq!(resp["status"], json!("incomplete"));
+        assert_eq!(resp["incomplete_details"]["reason"], json!("max_output_tokens"));
+    }
+
+    #[test]
+    fn stream_text_emits_item_lifecycle_and_completed() {
+        let mut t = ResponsesStreamTranslator::new(&json!({"model": "m"}));
+        let mut events = t.push_chunk(&json!({"choices": [{"delta": {"role": "assistant", "content": "Hel"}}]}));
+        events.extend(t.push_chunk(&json!({"choices": [{"delta": {"content": "lo"}}]})));
+        events.extend(t.push_chunk(&json!({"choices": [{"delta": {}, "finish_reason": "stop"}]})));
+        events.extend(t.push_chunk(&json!({"choices": [], "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}})));
+        events.extend(t.finish());
+        assert_eq!(
+            kinds(&events),
+            vec![
+                "response.created",
+                "response.in_progress",
+                "response.output_item.added",
+                "response.content_part.added",
+                "response.output_text.delta",
+                "response.output_text.delta",
+                "response.output_text.done",
+                "response.content_part.done",
+                "response.output_item.done",
+                "response.completed",
+            ]
+        );
+        let seqs: Vec<u64> = events.iter().map(|e| e["sequence_number"].as_u64().unwrap()).collect();
+        assert_eq!(seqs, (0..events.len() as u64).collect::<Vec<_>>());
+        let done = events.last().unwrap();
+        assert_eq!(done["response"]["output"][0]["content"][0]["text"], json!("Hello"));
+        assert_eq!(done["response"]["usage"]["total_tokens"], json!(5));
+        assert!(t.finish().is_empty());
+    }
+
+    #[test]
+    fn stream_reasoning_then_tool_calls_close_in_order() {
+        let mut t = ResponsesStreamTranslator::new(&json!({"model": "m"}));
+        let mut events = t.push_chunk(&json!({"choices": [{"delta": {"reasoning_content": "hmm"}}]}));
+        events.extend(t.push_chunk(&json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 0, "id": "c1", "type": "function", "function": {"name": "a", "arguments": ""}}
+        ]}}]})));
+        events.extend(t.push_chunk(&json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 0, "function": {"arguments": "{\"x\":1}"}}
+        ]}}]})));
+        events.extend(t.push_chunk(&json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 1, "id": "c2", "type": "function", "function": {"name": "b", "arguments": "{}"}}
+        ]}}]})));
+        events.extend(t.finish());
+        let k = kinds(&events);
+        assert!(k.contains(&"response.reasoning_summary_text.delta"));
+        assert_eq!(k.iter().filter(|e| **e == "response.output_item.done").count(), 3);
+        let done = events.last().unwrap();
+        let output = done["response"]["output"].as_array().unwrap();
+        assert_eq!(output[0]["type"], json!("reasoning"));
+        assert_eq!(output[1]["arguments"], json!("{\"x\":1}"));
+        assert_eq!(output[2]["call_id"], json!("c2"));
+        assert_eq!(output[2]["arguments"], json!("{}"));
+    }
+
+    #[test]
+    fn stream_interleaved_tool_calls_are_emitted_once() {
+        let mut t = ResponsesStreamTranslator::new(&json!({"model": "m"}));
+        let mut events = Vec::new();
+        for call in [
+            json!({"index": 0, "id": "c1", "type": "function", "function": {"name": "a", "arguments": ""}}),
+            json!({"index": 1, "id": "c2", "type": "function", "function": {"name": "b", "arguments": ""}}),
+            json!({"index": 0, "function": {"arguments": "{\"x\":"}}),
+            json!({"index": 1, "function": {"arguments": "{}"}}),
+            json!({"index": 0, "function": {"arguments": "1}"}}),
+        ] {
+            events.extend(t.push_chunk(&json!({"choices": [{"delta": {"tool_calls": [call]}}]})));
+        }
+        events.extend(t.finish());
+        let k = kinds(&events);
+        for kind in ["response.output_item.added", "response.output_item.done"] {
+            assert_eq!(k.iter().filter(|e| **e == kind).count(), 2, "{kind}");
+        }
+        let deltas: Vec<_> = events
+            .iter()
+            .filter(|e| e["type"] == "response.function_call_arguments.delta")
+            .map(|e| (e["output_index"].as_u64(), e["delta"].as_str()))
+            .collect();
+        assert_eq!(
+            deltas,
+            [
+                (Some(0), Some("{\"x\":")),
+                (Some(1), Some("{}")),
+                (Some(0), Some("1}"))
+            ]
+        );
+        let output = &events.last().unwrap()["response"]["output"];
+        assert_eq!(output[0]["call_id"], json!("c1"));
+        assert_eq!(output[0]["arguments"], json!("{\"x\":1}"));
+        assert_eq!(output[1]["call_id"], json!("c2"));
+        assert_eq!(output[1]["arguments"], json!("{}"));
+    }
+
+    #[test]
+    fn stream_fail_is_terminal() {
+        let mut t = ResponsesStreamTranslator::new(&json!({"model": "m"}));
+        let events = t.fail("boom");
+        assert_eq!(kinds(&events).last(), Some(&"response.failed"));
+        assert_eq!(events.last().unwrap()["response"]["error"]["message"], json!("boom"));
+        assert!(t.finish().is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+//! `response_format` enforcement for upstreams that do not honour it natively.
+//!
+//! Local llama.cpp models get the requested JSON schema as llama-server's
+//! `json_schema` parameter, which it compiles into a sampling grammar with its
+//! own converter, so output cannot leave the schema. Converters for APIs
+//! without structured output (Anthropic) force a call to a synthetic
+//! [`STRUCTURED_OUTPUT_TOOL`] whose input schema is the requested one, and
+//! unwrap its arguments into the message content.
+
+use serde_json::{json, Value};
+
+/// Name of the synthetic tool a forced-tool-call converter offers.
+pub const STRUCTURED_OUTPUT_TOOL: &str = "structured_output";
+
+/// The JSON schema a chat/completions body's `response_format` asks for:
+/// the `json_schema` schema, or any object for `json_object`.
+pub fn requested_schema(body: &Value) -> Option<Value> {
+    let format = body.get("response_format")?;
+    match format.get("type").and_then(|t| t.as_str()) {
+        Some("json_schema") => Some(
+            format
+                .get("json_schema")
+                .and_then(|s| s.get("schema"))
+                .cloned()
+                .unwrap_or_else(|| json!({})),
+        ),
+        Some("json_object") => Some(json!({"type": "object"})),
+        _ => None,
+    }
+}
+
+/// Replace `response_format` in a llama-server chat/completions body with
+/// `json_schema` for the requested schema. Bodies offering tools are left
+/// alone: llama-server builds its own grammar for tool calls.
+pub fn constrain_with_json_schema(body: &mut Value) {
+    if body
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .is_some_and(|t| !t.is_empty())
+    {
+        return;
+    }
+    let Some(schema) = requested_schema(body) else {
+        return;
+    };
+    let Some(obj) = body.as_object_mut() else {
+        return;
+    };
+    obj.remove("response_format");
+    obj.insert("json_schema".to_string(), schema);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requested_schema_reads_both_json_formats() {
+        let schema = json!({"type": "object", "properties": {"a": {"type": "string"}}});
+        let body = json!({"response_format": {"type": "json_schema", "json_schema": {"name": "x", "schema": schema}}});
+        assert_eq!(requested_schema(&body), Some(schema));
+        let body = json!({"response_format": {"type": "json_object"}});
+        assert_eq!(requested_schema(&body), Some(json!({"type": "object"})));
+        assert_eq!(
+            requested_schema(&json!({"response_format": {"type": "text"}})),
+            None
+        );
+    }
+
+    #[test]
+    fn llama_server_gets_the_schema_unless_tools_are_offered() {
+        let schema = json!({"type": "string", "pattern": "^a+$"});
+        let mut body = json!({
+            "response_format": {"type": "json_schema", "json_schema": {"schema": schema}}
+        });
+        constrain_with_json_schema(&mut body);
+        assert!(body.get("response_format").is_none());
+        assert_eq!(body["json_schema"], schema);
+
+        let mut body = json!({
+            "response_format": {"type": "json_object"},
+            "tools": [{"type": "function", "function": {"name": "f"}}]
+        });
+        let untouched = body.clone();
+        constrain_with_json_schema(&mut body);
+        assert_eq!(body, untouched);
+    }
+}
@@ -70,6 +70,10 @@ mod server_tests {
             host: "localhost".to_string(),
             port: 1337,
             enable_server_tool_execution: false,
+            enable_metrics: false,
+            enable_ollama_api: false,
+            enable_tool_emulation: false,
+            tls: false,
         };
         assert_eq!(config.prefix, "/v1");
         assert_eq!(config.proxy_api_key, "test-key");
@@ -87,6 +91,10 @@ mod server_tests {
             host: "127.0.0.1".to_string(),
             port: 8080,
             enable_server_tool_execution: false,
+            enable_metrics: false,
+            enable_ollama_api: false,
+            enable_tool_emulation: false,
+            tls: false,
         };
         assert_eq!(config.prefix, "");
         assert_eq!(config.proxy_api_key, "");
@@ -852,6 +860,100 @@ mod server_tests {
         assert_eq!(out["stop_reason"], "max_tokens");
     }
 
+    #[test]
+    fn transform_openai_response_to_anthropic_maps_chat_usage() {
+        let resp = json!({
+            "choices": [{"message": {"content": "x"}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15}
+        });
+        let out = proxy::transform_openai_response_to_anthropic(&resp);
+        assert_eq!(out["usage"], json!({"input_tokens": 12, "output_tokens": 3}));
+    }
+
+    #[test]
+    fn transform_anthropic_to_openai_maps_tool_choice() {
+        let body = json!({
+            "model": "m",
+            "messages": [{"role": "user", "content": "hi"}],
+            "tool_choice": {"type": "tool", "name": "search"}
+        });
+        let out = proxy::transform_anthropic_to_openai(&body).unwrap();
+        assert_eq!(
+            out["tool_choice"],
+            json!({"type": "function", "function": {"name": "search"}})
+        );
+
+        let any = json!({"model": "m", "messages": [], "tool_choice": {"type": "any"}});
+        let out = proxy::transform_anthropic_to_openai(&any).unwrap();
+        assert_eq!(out["tool_choice"], "required");
+    }
+
+    #[test]
+    fn anthropic_stream_translator_text_then_tool_use() {
+        use crate::core::server::converters::ChatStreamTranslator;
+
+        let mut t = proxy::AnthropicStreamTranslator::new();
+        let mut events = t.push_chunk(&json!({
+            "id": "c1", "model": "gemini-2.5-flash",
+            "choices": [{"index": 0, "delta": {"role": "assistant", "content": "Let me look"}}]
+        }));
+        events.extend(t.push_chunk(&json!({
+            "choices": [{"index": 0, "delta": {"tool_calls": [{
+                "index": 0, "id": "call_1", "type": "function",
+                "function": {"name": "search", "arguments": "{\"q\":1}"}
+            }]}}]
+        })));
+        events.extend(t.push_chunk(&json!({
+            "choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]
+        })));
+        events.extend(t.push_chunk(&json!({
+            "choices": [],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 7}
+        })));
+        events.extend(t.finish());
+        assert!(t.finish().is_empty());
+
+        let kinds: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+        assert_eq!(events[0]["message"]["model"], "gemini-2.5-flash");
+        assert_eq!(events[4]["index"], 1);
+        assert_eq!(events[4]["content_block"]["name"], "search");
+        assert_eq!(events[5]["delta"]["partial_json"], "{\"q\":1}");
+        assert_eq!(events[7]["delta"]["stop_reason"], "tool_use");
+        assert_eq!(events[7]["usage"]["output_tokens"], 7);
+    }
+
+    #[test]
+    fn anthropic_stream_translator_estimates_tokens_and_fails_terminally() {
+        use crate::core::server::converters::ChatStreamTranslator;
+
+        let mut t = proxy::AnthropicStreamTranslator::new();
+        t.push_chunk(&json!({"choices": [{"delta": {"content": "one two three"}}]}));
+        let end = t.finish();
+        let delta = end.iter().find(|e| e["type"] == "message_delta").unwrap();
+        assert_eq!(delta["delta"]["stop_reason"], "end_turn");
+        assert_eq!(delta["usage"]["output_tokens"], 3);
+
+        let mut t = proxy::AnthropicStreamTranslator::new();
+        let err = t.fail("quota exceeded");
+        assert_eq!(err[0]["type"], "error");
+        assert_eq!(err[0]["error"]["message"], "quota exceeded");
+        assert!(t.finish().is_empty());
+    }
+
     #[test]
     fn parse_openai_messages_ok() {
         let msgs = json!([
@@ -881,6 +983,65 @@ mod server_tests {
         assert!(proxy::parse_openai_messages(&msgs).is_err());
     }
 
+    #[test]
+    fn parse_openai_messages_keeps_tool_turns() {
+        let call = json!({"id": "call_1", "type": "function", "function": {"name": "run", "arguments": "{}"}});
+        let msgs = json!([
+            {"role": "user", "content": "hi", "name": "dropped"},
+            {"role": "assistant", "content": null, "tool_calls": [call]},
+            {"role": "tool", "tool_call_id": "call_1", "content": "ok"}
+        ]);
+        let out = proxy::parse_openai_messages(&msgs).unwrap();
+        assert_eq!(out[0], json!({"role": "user", "content": "hi"}));
+        assert_eq!(out[1]["tool_calls"][0]["id"], "call_1");
+        assert!(out[1]["content"].is_null());
+        assert_eq!(out[2]["tool_call_id"], "call_1");
+        // Null content is only accepted alongside tool calls.
+        let msgs = json!([{"role": "assistant", "content": null}]);
+        assert!(proxy::parse_openai_messages(&msgs).is_err());
+    }
+
+    #[test]
+    fn unanswered_tool_calls_resume_the_last_assistant_turn() {
+        let call = |id: &str| json!({"id": id, "type": "function", "function": {"name": "run", "arguments": "{}"}});
+        let mut messages = vec![
+            json!({"role": "user", "content": "go"}),
+            json!({"role": "assistant", "content": null, "tool_calls": [call("a"), call("b")]}),
+            json!({"role": "tool", "tool_call_id": "a", "content": "done"}),
+        ];
+        assert_eq!(proxy::unanswered_tool_calls(&messages), vec![call("b")]);
+        // A conversation that moved on has nothing to resume.
+        messages.push(json!({"role": "user", "content": "and then?"}));
+        assert!(proxy::unanswered_tool_calls(&messages).is_empty());
+    }
+
+    #[test]
+    fn only_keys_set_to_approve_tool_calls_answer_them() {
+        use crate::core::mcp::approval::ApprovalMode;
+        use crate::core::server::client_keys::{ClientKey, ClientKeySettings};
+
+        let mut key = ClientKey {
+            name: "ci".to_string(),
+            key_prefix: "jan-ck-1a2b3c".to_string(),
+            key_hash: String::new(),
+            created_at: 0,
+            settings: ClientKeySettings::default(),
+        };
+        assert_eq!(
+            proxy::orchestration_approval_mode(None),
+            ApprovalMode::Desktop
+        );
+        assert_eq!(
+            proxy::orchestration_approval_mode(Some(&key)),
+            ApprovalMode::Desktop
+        );
+        key.settings.approve_tool_calls = true;
+        assert_eq!(
+            proxy::orchestration_approval_mode(Some(&key)),
+            ApprovalMode::Client("ci".to_string())
+        );
+    }
+
     #[test]
     fn set_system_prompt_replaces_existing_system() {
         let mut messages = vec![
@@ -1072,11 +1233,18 @@ mod server_tests {
             host: "h".to_string(),
             port: 1,
             enable_server_tool_execution: true,
+            enable_metrics: true,
+            enable_ollama_api: true,
+            enable_tool_emulation: true,
+            tls: true,
         };
         let cloned = cfg.clone();
         assert_eq!(cloned.prefix, "/p");
         assert_eq!(cloned.proxy_api_key, "k");
         assert!(cloned.enable_server_tool_execution);
+        assert!(cloned.enable_metrics);
+        assert!(cloned.enable_ollama_api);
+        assert!(cloned.enable_tool_emulation);
     }
 
     #[test]
@@ -1184,4 +1352,203 @@ mod server_tests {
         proxy::strip_billing_header_in_body(&mut body);
         assert_eq!(body["messages"][0]["content"][0]["text"], json!(PROMPT));
     }
+
+    #[test]
+    fn find_provider_for_model_matches_listed_model_prefix_or_name() {
+        use crate::core::state::ProviderConfig;
+        use std::collections::HashMap;
+
+        let mut configs = HashMap::new();
+        configs.insert(
+            "openai".to_string(),
+            ProviderConfig {
+                provider: "openai".to_string(),
+                models: vec!["gpt-4o".to_string()],
+                ..Default::default()
+            },
+        );
+        configs.insert(
+            "gemini".to_string(),
+            ProviderConfig {
+                provider: "gemini".to_string(),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            proxy::find_provider_for_model(&configs, "gpt-4o").as_deref(),
+            Some("openai")
+        );
+        assert_eq!(
+            proxy::find_provider_for_model(&configs, "gemini/gemini-2.5-pro").as_deref(),
+            Some("gemini")
+        );
+        assert_eq!(
+            proxy::find_provider_for_model(&configs, "gemini").as_deref(),
+            Some("gemini")
+        );
+        assert_eq!(proxy::find_provider_for_model(&configs, "qwen3-4b"), None);
+    }
+
+    #[tokio::test]
+    async fn parallel_tool_calls_are_capped_and_time_out_one_by_one() {
+        use crate::core::mcp::helpers::with_timeout;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let calls = (0..5).map(|i| {
+            let (running, peak) = (&running, &peak);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // Call 3 hangs; the others finish out of order.
+                let call = async move {
+                    if i == 3 {
+                        std::future::pending::<()>().await;
+                    }
+                    tokio::time::sleep(Duration::from_millis(20 - 4 * i)).await;
+                    Ok::<_, String>(format!("result {i}"))
+                };
+                let result = with_timeout(call, Duration::from_millis(50), "Tool call 'search'")
+                    .await
+                    .unwrap_or_else(|e| format!("ERROR: {e}"));
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(result)
+            }
+        });
+
+        let results = proxy::run_bounded(calls, 2).await.unwrap();
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(results[0], "result 0");
+        assert_eq!(results[2], "result 2");
+        assert!(results[3].starts_with("ERROR: Tool call 'search' timed out"));
+        assert_eq!(results[4], "result 4");
+    }
+
+    #[tokio::test]
+    async fn alias_targets_take_admission_slots_on_their_own_models() {
+        use crate::core::server::admission::{AdmissionController, AdmissionOptions, Priority};
+        use crate::core::server::model_aliases::AliasPlan;
+        use crate::core::state::ProviderConfig;
+        use hyper::{Response, StatusCode};
+        use std::collections::HashMap;
+        use std::convert::Infallible;
+        use std::sync::Arc;
+        use tokio::sync::Mutex;
+
+        let admission = AdmissionController::new(&AdmissionOptions {
+            max_concurrent_per_model: Some(1),
+            max_queued_per_model: Some(0),
+            queue_timeout_secs: Some(1),
+        })
+        .unwrap();
+        let mut configs = HashMap::new();
+        configs.insert(
+            "openai".to_string(),
+            ProviderConfig {
+                provider: "openai".to_string(),
+                models: vec!["gpt-4o".to_string()],
+                ..Default::default()
+            },
+        );
+        let provider_configs = Arc::new(Mutex::new(configs));
+        let plan = AliasPlan {
+            alias: "fast".to_string(),
+            models: vec!["qwen3-4b".to_string(), "gpt-4o".to_string()],
+            first_byte_timeout: None,
+        };
+        let attempt = |status: StatusCode| {
+            proxy::try_alias_targets(
+                &plan,
+                Some(&admission),
+                &provider_configs,
+                "script",
+                Priority::Normal,
+                move |target: &str| {
+                    let status = if target == "qwen3-4b" {
+                        status
+                    } else {
+                        StatusCode::OK
+                    };
+                    let response = Response::builder().status(status).body(()).unwrap();
+                    async move { Ok::<_, Infallible>(response) }
+                },
+            )
+        };
+        let served = |attempt| match attempt {
+            Ok(proxy::AliasAttempt::Served { target, permit, .. }) => (target, permit),
+            _ => panic!("expected a target to serve the alias"),
+        };
+
+        // The local target holds a slot on its own model, not on the alias.
+        let (target, held) = served(attempt(StatusCode::OK).await);
+        assert_eq!(target, "qwen3-4b");
+        assert!(held.is_some());
+        assert_eq!(admission.snapshot(), [("qwen3-4b".to_string(), 1, 0)]);
+
+        // With that model at capacity the remote target serves, unthrottled.
+        let (target, permit) = served(attempt(StatusCode::OK).await);
+        assert_eq!(target, "gpt-4o");
+        assert!(permit.is_none());
+        drop(held);
+
+        // A failing local target gives its slot back before falling over.
+        let (target, _) = served(attempt(StatusCode::INTERNAL_SERVER_ERROR).await);
+        assert_eq!(target, "gpt-4o");
+        assert_eq!(admission.snapshot(), [("qwen3-4b".to_string(), 0, 0)]);
+    }
+
+    #[test]
+    fn model_scoped_keys_batch_only_their_models() {
+        use crate::core::server::batches::BatchStore;
+        use crate::core::server::client_keys::{ClientKey, ClientKeySettings};
+
+        // Only routes that run a model need the body to name one.
+        assert!(proxy::names_model("/chat/completions") && proxy::names_model("/tokenize"));
+        assert!(!proxy::names_model("/files") && !proxy::names_model("/batches"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = BatchStore::new(dir.path(), None);
+        let key = ClientKey {
+            name: "ci".to_string(),
+            key_prefix: "jan-ck-1a2b3c".to_string(),
+            key_hash: String::new(),
+            created_at: 0,
+            settings: ClientKeySettings {
+                allowed_models: vec!["qwen3-*".to_string()],
+                ..ClientKeySettings::default()
+            },
+        };
+        let create = |model: &str| {
+            let line = json!({
+                "custom_id": "a",
+                "method": "POST",
+                "url": "/v1/chat/completions",
+                "body": {"model": model, "messages": [{"role": "user", "content": "hi"}]},
+            });
+            let file = store
+                .create_file("in.jsonl", "batch", line.to_string().as_bytes(), Some("ci"))
+                .unwrap();
+            let request = json!({
+                "input_file_id": file.id,
+                "endpoint": "/v1/chat/completions",
+                "completion_window": "24h",
+            });
+            proxy::batch_api_reply(
+                &store,
+                &hyper::Method::POST,
+                "/batches",
+                "application/json",
+                request.to_string().as_bytes(),
+                Some(&key),
+            )
+        };
+
+        assert!(create("qwen3-8b").is_ok());
+        let (status, message) = create("gpt-4o").unwrap_err();
+        assert_eq!(status, hyper::StatusCode::FORBIDDEN);
+        assert_eq!(message, "API key 'ci' is not allowed to use model 'gpt-4o'");
+    }
 }
@@ -0,0 +1,235 @@
+//! HTTPS termination for the local API server.
+//!
+//! TLS is off unless a certificate is configured. The server either loads a
+//! PEM certificate chain and private key from user-supplied paths, or — with
+//! `tls_self_signed` — generates a self-signed pair under `<jan_data>/tls/` on
+//! first start and reuses it afterwards, so clients only have to trust it
+//! once. An optional CA bundle turns on client-certificate (mTLS) verification.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+use super::client_keys::write_private;
+
+const TLS_DIR_NAME: &str = "tls";
+const SELF_SIGNED_CERT_FILE_NAME: &str = "server.crt";
+const SELF_SIGNED_KEY_FILE_NAME: &str = "server.key";
+
+/// TLS settings from `StartServerConfig`.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    pub cert_path: Option<PathBuf>,
+    pub key_path: Option<PathBuf>,
+    /// Generate (once) and use a self-signed certificate when no cert/key
+    /// paths are given.
+    pub self_signed: bool,
+    /// CA bundle client certificates must chain to; enables mTLS.
+    pub client_ca_path: Option<PathBuf>,
+}
+
+impl TlsOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.self_signed || self.cert_path.is_some() || self.key_path.is_some()
+    }
+
+    pub fn requires_client_cert(&self) -> bool {
+        self.is_enabled() && self.client_ca_path.is_some()
+    }
+}
+
+/// Build the acceptor for `options`, or `None` when TLS is off. `host` is
+/// added to a freshly generated self-signed certificate's names.
+pub fn build_acceptor(
+    options: &TlsOptions,
+    data_folder: &Path,
+    host: &str,
+) -> Result<Option<TlsAcceptor>, String> {
+    if !options.is_enabled() {
+        // Refuse rather than silently serving without the requested mTLS.
+        if options.client_ca_path.is_some() {
+            return Err("Client certificate verification requires TLS to be enabled".to_string());
+        }
+        return Ok(None);
+    }
+
+    let (cert_path, key_path) = match (&options.cert_path, &options.key_path) {
+        (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+        (None, None) => ensure_self_signed(data_folder, host)?,
+        _ => return Err("Both a TLS certificate and a private key path are required".to_string()),
+    };
+    let certs = load_certs(&cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(&key_path)
+        .map_err(|e| format!("Failed to read TLS private key {}: {e}", key_path.display()))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?;
+    let builder = match &options.client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| format!("Invalid client CA in {}: {e}", ca_path.display()))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|e| format!("Failed to set up client certificate verification: {e}"))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Invalid TLS certificate or key: {e}"))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    log::info!("TLS enabled with certificate {}", cert_path.display());
+    Ok(Some(TlsAcceptor::from(Arc::new(config))))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read certificates from {}: {e}", path.display()))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+/// Paths of the persisted self-signed pair, generating it on first use.
+fn ensure_self_signed(data_folder: &Path, host: &str) -> Result<(PathBuf, PathBuf), String> {
+    let dir = data_folder.join(TLS_DIR_NAME);
+    let cert_path = dir.join(SELF_SIGNED_CERT_FILE_NAME);
+    let key_path = dir.join(SELF_SIGNED_KEY_FILE_NAME);
+    if cert_path.exists() && key_path.exists() {
+        return Ok((cert_path, key_path));
+    }
+
+    let mut names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    if let Ok(name) = hostname::get() {
+        names.push(name.to_string_lossy().into_owned());
+    }
+    if !matches!(host, "0.0.0.0" | "::") {
+        names.push(host.to_string());
+    }
+    names.sort();
+    names.dedup();
+
+    let generated = rcgen::generate_simple_self_signed(names.clone())
+        .map_err(|e| format!("Failed to generate a self-signed certificate: {e}"))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    write_private(&key_path, generated.key_pair.serialize_pem().as_bytes())
+        .map_err(|e| e.to_string())?;
+    fs::write(&cert_path, generated.cert.pem()).map_err(|e| e.to_string())?;
+
+    log::info!(
+        "Generated self-signed TLS certificate for {} at {}",
+        names.join(", "),
+        cert_path.display()
+    );
+    Ok((cert_path, key_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_without_cert_or_self_signed() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(
+            build_acceptor(&TlsOptions::default(), dir.path(), "127.0.0.1")
+                .unwrap()
+                .is_none()
+        );
+
+        let client_ca_only = TlsOptions {
+            client_ca_path: Some(dir.path().join("ca.pem")),
+            ..TlsOptions::default()
+        };
+        assert!(!client_ca_only.requires_client_cert());
+        assert!(build_acceptor(&client_ca_only, dir.path(), "127.0.0.1").is_err());
+    }
+
+    #[test]
+    fn self_signed_pair_is_generated_once_and_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = TlsOptions {
+            self_signed: true,
+            ..TlsOptions::default()
+        };
+        assert!(build_acceptor(&options, dir.path(), "0.0.0.0")
+            .unwrap()
+            .is_some());
+        let cert_path = dir
+            .path()
+            .join(TLS_DIR_NAME)
+            .join(SELF_SIGNED_CERT_FILE_NAME);
+        let first = fs::read(&cert_path).unwrap();
+
+        assert!(build_acceptor(&options, dir.path(), "0.0.0.0")
+            .unwrap()
+            .is_some());
+        assert_eq!(fs::read(&cert_path).unwrap(), first);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let key_path = dir
+                .path()
+                .join(TLS_DIR_NAME)
+                .join(SELF_SIGNED_KEY_FILE_NAME);
+            let mode = fs::metadata(key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn user_certificate_needs_both_paths_and_valid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let only_cert = TlsOptions {
+            cert_path: Some(dir.path().join("cert.pem")),
+            ..TlsOptions::default()
+        };
+        assert!(build_acceptor(&only_cert, dir.path(), "127.0.0.1").is_err());
+
+        let missing = TlsOptions {
+            cert_path: Some(dir.path().join("cert.pem")),
+            key_path: Some(dir.path().join("key.pem")),
+            ..TlsOptions::default()
+        };
+        let Err(err) = build_acceptor(&missing, dir.path(), "127.0.0.1") else {
+            panic!("missing certificate files must be rejected");
+        };
+        assert!(err.contains("cert.pem"), "{err}");
+    }
+
+    #[test]
+    fn client_ca_enables_mtls() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ca_cert, _) = ensure_self_signed(dir.path(), "127.0.0.1").unwrap();
+        let options = TlsOptions {
+            self_signed: true,
+            client_ca_path: Some(ca_cert),
+            ..TlsOptions::default()
+        };
+        assert!(options.requires_client_cert());
+        assert!(build_acceptor(&options, dir.path(), "127.0.0.1")
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,137 @@
+//! Token counting for `/messages/count_tokens`, `/tokenize` and `/detokenize`.
+//!
+//! Local llama.cpp models are counted exactly with llama-server's own
+//! tokenizer through the router: messages are rendered by the model's chat
+//! template (`/apply-template`) and the prompt is tokenized the way a chat
+//! request would be. Anthropic providers answer `count_tokens` themselves.
+//! Every other backend gets [`estimate_chat_tokens`], a character-based
+//! estimate reported with `"estimated": true`.
+
+use serde_json::{json, Value};
+
+/// Characters per token assumed by the estimate, the usual figure for
+/// English text with BPE tokenizers.
+pub const ESTIMATE_CHARS_PER_TOKEN: usize = 4;
+/// Tokens the estimate adds per message for role markers and separators.
+pub const ESTIMATE_MESSAGE_OVERHEAD: u64 = 4;
+/// Tokens the estimate charges per image, the low-detail OpenAI price.
+pub const ESTIMATE_IMAGE_TOKENS: u64 = 85;
+
+/// Estimate the prompt tokens of a chat/completions body: message text,
+/// tool calls and tool definitions at [`ESTIMATE_CHARS_PER_TOKEN`], plus
+/// [`ESTIMATE_MESSAGE_OVERHEAD`] per message and [`ESTIMATE_IMAGE_TOKENS`]
+/// per image part.
+pub fn estimate_chat_tokens(body: &Value) -> u64 {
+    let mut chars = 0usize;
+    let mut tokens = 0u64;
+    for message in body
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .into_iter()
+        .flatten()
+    {
+        tokens += ESTIMATE_MESSAGE_OVERHEAD;
+        match message.get("content") {
+            Some(Value::String(text)) => chars += text.chars().count(),
+            Some(Value::Array(parts)) => {
+                for part in parts {
+                    match part.get("type").and_then(|t| t.as_str()) {
+                        Some("image_url") | Some("input_image") | Some("image") => {
+                            tokens += ESTIMATE_IMAGE_TOKENS
+                        }
+                        _ => {
+                            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                chars += text.chars().count();
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        if let Some(calls) = message.get("tool_calls").filter(|c| !c.is_null()) {
+            chars += calls.to_string().chars().count();
+        }
+    }
+    if let Some(tools) = body.get("tools").filter(|t| !t.is_null()) {
+        chars += tools.to_string().chars().count();
+    }
+    tokens + chars.div_ceil(ESTIMATE_CHARS_PER_TOKEN) as u64
+}
+
+/// The `/messages/count_tokens` reply for `input_tokens`.
+pub fn count_tokens_reply(input_tokens: u64, estimated: bool) -> Value {
+    let mut reply = json!({"input_tokens": input_tokens});
+    if estimated {
+        reply["estimated"] = json!(true);
+    }
+    reply
+}
+
+/// Token ids from a llama-server `/tokenize` reply, which holds plain ids or,
+/// with `with_pieces`, `{id, piece}` objects.
+pub fn token_ids(reply: &Value) -> Option<Vec<u64>> {
+    reply
+        .get("tokens")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_u64().or_else(|| t.get("id").and_then(|id| id.as_u64())))
+        .collect()
+}
+
+/// The `/tokenize` reply: the ids, their count and, when known, the model's
+/// context length.
+pub fn tokenize_reply(tokens: &[u64], max_model_len: Option<u64>) -> Value {
+    let mut reply = json!({"count": tokens.len(), "tokens": tokens});
+    if let Some(len) = max_model_len {
+        reply["max_model_len"] = json!(len);
+    }
+    reply
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_counts_text_tools_images_and_message_overhead() {
+        let body = json!({
+            "messages": [
+                {"role": "system", "content": "12345678"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "abcd"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+                ]}
+            ]
+        });
+        // 12 chars -> 3 tokens, two messages, one image.
+        assert_eq!(
+            estimate_chat_tokens(&body),
+            3 + 2 * ESTIMATE_MESSAGE_OVERHEAD + ESTIMATE_IMAGE_TOKENS
+        );
+        assert_eq!(estimate_chat_tokens(&json!({})), 0);
+    }
+
+    #[test]
+    fn token_ids_accept_plain_ids_and_pieces() {
+        assert_eq!(token_ids(&json!({"tokens": [1, 2, 3]})), Some(vec![1, 2, 3]));
+        assert_eq!(
+            token_ids(&json!({"tokens": [{"id": 7, "piece": "hi"}]})),
+            Some(vec![7])
+        );
+        assert_eq!(token_ids(&json!({"error": "x"})), None);
+    }
+
+    #[test]
+    fn replies_mark_estimates_and_known_context_length() {
+        assert_eq!(count_tokens_reply(5, false), json!({"input_tokens": 5}));
+        assert_eq!(
+            count_tokens_reply(5, true),
+            json!({"input_tokens": 5, "estimated": true})
+        );
+        assert_eq!(
+            tokenize_reply(&[4, 2], Some(4096)),
+            json!({"count": 2, "tokens": [4, 2], "max_model_len": 4096})
+        );
+    }
+}
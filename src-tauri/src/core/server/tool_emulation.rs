@@ -0,0 +1,605 @@
+//! Prompt-level tool calling for local models whose chat template has no tool
+//! support (opt-in with `enable_tool_emulation`).
+//!
+//! A request offering `tools` to such a model is rewritten: the tool
+//! definitions and a calling protocol go into the system prompt, earlier
+//! calls and results become `<tool_call>` / `<tool_response>` text, and
+//! `tools` / `tool_choice` are dropped. The reply is parsed back into
+//! `tool_calls`: whole for non-streaming replies; for streams, text is passed
+//! on until a call starts, and the held call is parsed when the model
+//! finishes. Only calls naming an offered tool count, so ordinary JSON answers
+//! are left alone (though a streamed answer that opens with a JSON object is
+//! only shown once complete).
+
+use serde_json::{json, Value};
+
+use super::converters::{chunk_str, message_text, SseEvent, StreamState, UpstreamConverter};
+
+const CALL_OPEN: &str = "<tool_call>";
+const CALL_CLOSE: &str = "</tool_call>";
+/// Fence a reply may wrap a bare JSON call in.
+const JSON_FENCE: &str = "```json";
+
+/// One parsed call: tool name and JSON-encoded arguments.
+type Call = (String, String);
+
+/// Emulates tool calling for one request. Built from the chat/completions
+/// body so replies can be checked against the offered tool names.
+#[derive(Debug, Clone)]
+pub struct ToolEmulationConverter {
+    tool_names: Vec<String>,
+}
+
+impl ToolEmulationConverter {
+    /// `None` when the request offers no tools or forbids calling them.
+    pub fn for_request(body: &Value) -> Option<Self> {
+        if body.get("tool_choice").and_then(Value::as_str) == Some("none") {
+            return None;
+        }
+        let tool_names: Vec<String> = body
+            .get("tools")
+            .and_then(Value::as_array)?
+            .iter()
+            .filter_map(|tool| tool.get("function")?.get("name")?.as_str())
+            .map(str::to_owned)
+            .collect();
+        (!tool_names.is_empty()).then_some(Self { tool_names })
+    }
+
+    /// Splits assistant text into its prose and the calls it makes; `None`
+    /// unless it calls offered tools only.
+    fn parse(&self, text: &str) -> Option<(String, Vec<Call>)> {
+        let mut calls = Vec::new();
+        let prose = match text.find(CALL_OPEN) {
+            Some(start) => {
+                let mut rest = &text[start..];
+                while let Some(open) = rest.find(CALL_OPEN) {
+                    let body = &rest[open + CALL_OPEN.len()..];
+                    // The last block may be cut off by a stop sequence
+                    let (inner, after) = match body.find(CALL_CLOSE) {
+                        Some(end) => (&body[..end], &body[end + CALL_CLOSE.len()..]),
+                        None => (body, ""),
+                    };
+                    let value: Value = serde_json::from_str(strip_fence(inner)).ok()?;
+                    if !calls_in_json(&value, &mut calls) {
+                        return None;
+                    }
+                    rest = after;
+                }
+                text[..start].trim().to_string()
+            }
+            None => {
+                let value: Value = serde_json::from_str(strip_fence(text)).ok()?;
+                if !calls_in_json(&value, &mut calls) {
+                    return None;
+                }
+                String::new()
+            }
+        };
+        let offered = calls.iter().all(|(name, _)| self.tool_names.contains(name));
+        (offered && !calls.is_empty()).then_some((prose, calls))
+    }
+
+    /// Ends the held text: tool-call chunks when it is a call, otherwise a
+    /// text chunk. The flag says whether calls were emitted.
+    fn flush(&self, state: &mut StreamState) -> (Vec<String>, bool) {
+        let held = std::mem::take(&mut state.held_text);
+        state.holding_tool_call = false;
+        if held.trim().is_empty() {
+            return (Vec::new(), false);
+        }
+        let Some((prose, calls)) = self.parse(&held) else {
+            return (
+                vec![chunk_str(state, json!({"content": held}), None)],
+                false,
+            );
+        };
+        let mut out = Vec::new();
+        if !prose.is_empty() {
+            out.push(chunk_str(state, json!({"content": prose}), None));
+        }
+        let deltas: Vec<Value> = calls
+            .into_iter()
+            .enumerate()
+            .map(|(index, call)| {
+                let mut delta = tool_call(call);
+                delta["index"] = json!(index);
+                delta
+            })
+            .collect();
+        out.push(chunk_str(state, json!({"tool_calls": deltas}), None));
+        state.saw_tool_call = true;
+        (out, true)
+    }
+}
+
+fn tool_call((name, arguments): Call) -> Value {
+    json!({
+        "id": format!("call_{}", uuid::Uuid::new_v4().simple()),
+        "type": "function",
+        "function": {"name": name, "arguments": arguments},
+    })
+}
+
+/// Unwraps a Markdown code fence around a JSON call.
+fn strip_fence(text: &str) -> &str {
+    let text = text.trim();
+    let Some(rest) = text.strip_prefix("```") else {
+        return text;
+    };
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+    rest.strip_suffix("```").unwrap_or(rest).trim()
+}
+
+/// Collects the calls in `value`: `{"name", "arguments"}` (or `parameters`),
+/// the same nested under `function`, an array of those, or
+/// `{"tool_calls": [...]}`. False when anything else is found.
+fn calls_in_json(value: &Value, out: &mut Vec<Call>) -> bool {
+    match value {
+        Value::Array(items) => {
+            !items.is_empty() && items.iter().all(|item| calls_in_json(item, out))
+        }
+        Value::Object(obj) => {
+            if let Some(calls) = obj.get("tool_calls") {
+                return calls_in_json(calls, out);
+            }
+            if let Some(function) = obj.get("function").filter(|f| f.is_object()) {
+                return calls_in_json(function, out);
+            }
+            let Some(name) = obj.get("name").and_then(Value::as_str) else {
+                return false;
+            };
+            let arguments = match obj.get("arguments").or_else(|| obj.get("parameters")) {
+                Some(Value::String(s)) => s.clone(),
+                Some(v) => v.to_string(),
+                None => "{}".to_string(),
+            };
+            out.push((name.to_string(), arguments));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The system-prompt section teaching the model the calling protocol.
+fn protocol_prompt(tools: &[Value], tool_choice: Option<&Value>, parallel: bool) -> String {
+    let mut prompt = String::from(
+        "# Tools\n\nYou can call the tools below. Each is described as JSON, with a JSON Schema of its arguments:\n\n<tools>\n",
+    );
+    for function in tools.iter().filter_map(|tool| tool.get("function")) {
+        let definition = json!({
+            "name": function.get("name"),
+            "description": function.get("description").cloned().unwrap_or(json!("")),
+            "parameters": function
+                .get("parameters")
+                .cloned()
+                .unwrap_or(json!({"type": "object", "properties": {}})),
+        });
+        prompt.push_str(&definition.to_string());
+        prompt.push('\n');
+    }
+    prompt.push_str(
+        "</tools>\n\nTo call a tool, reply with a <tool_call></tool_call> block holding a JSON object with the tool's name and arguments, and write nothing after it:\n<tool_call>\n{\"name\": \"<tool name>\", \"arguments\": {\"<argument>\": \"<value>\"}}\n</tool_call>\n",
+    );
+    prompt.push_str(if parallel {
+        "You may make several calls at once, one block each. "
+    } else {
+        "Make at most one call per reply. "
+    });
+    prompt.push_str("Tool results come back in <tool_response></tool_response> blocks. If no tool is needed, answer directly without a <tool_call> block.");
+    match tool_choice {
+        Some(Value::String(choice)) if choice == "required" => {
+            prompt.push_str("\n\nYou must call at least one tool.");
+        }
+        Some(choice) => {
+            if let Some(name) = choice
+                .get("function")
+                .and_then(|f| f.get("name"))
+                .and_then(Value::as_str)
+            {
+                prompt.push_str(&format!("\n\nYou must call the `{name}` tool."));
+            }
+        }
+        None => {}
+    }
+    prompt
+}
+
+/// Rewrites earlier calls as `<tool_call>` text and results as
+/// `<tool_response>` user turns (consecutive results share one turn, since
+/// templates without tool support often require alternating roles).
+fn rewrite_messages(messages: &[Value]) -> Vec<Value> {
+    let mut out = Vec::with_capacity(messages.len());
+    let mut results: Vec<String> = Vec::new();
+    for message in messages {
+        let role = message.get("role").and_then(Value::as_str).unwrap_or("");
+        if role == "tool" || role == "function" {
+            let content = message_text(message.get("content").unwrap_or(&Value::Null));
+            results.push(format!("<tool_response>\n{content}\n</tool_response>"));
+            continue;
+        }
+        if !results.is_empty() {
+            out.push(json!({"role": "user", "content": results.join("\n")}));
+            results.clear();
+        }
+        let calls = message
+            .get("tool_calls")
+            .and_then(Value::as_array)
+            .filter(|calls| role == "assistant" && !calls.is_empty());
+        let Some(calls) = calls else {
+            out.push(message.clone());
+            continue;
+        };
+        let mut blocks = vec![message_text(message.get("content").unwrap_or(&Value::Null))];
+        blocks.retain(|text| !text.trim().is_empty());
+        for function in calls.iter().filter_map(|call| call.get("function")) {
+            let arguments = function
+                .get("arguments")
+                .and_then(Value::as_str)
+                .map(|args| serde_json::from_str(args).unwrap_or_else(|_| json!(args)))
+                .unwrap_or_else(|| json!({}));
+            let call = json!({"name": function.get("name"), "arguments": arguments});
+            blocks.push(format!("{CALL_OPEN}\n{call}\n{CALL_CLOSE}"));
+        }
+        out.push(json!({"role": "assistant", "content": blocks.join("\n")}));
+    }
+    if !results.is_empty() {
+        out.push(json!({"role": "user", "content": results.join("\n")}));
+    }
+    out
+}
+
+/// Appends `prompt` to the leading system message, or adds one.
+fn with_system_prompt(mut messages: Vec<Value>, prompt: &str) -> Vec<Value> {
+    let leading_system = messages.first_mut().filter(|m| {
+        matches!(
+            m.get("role").and_then(Value::as_str),
+            Some("system") | Some("developer")
+        )
+    });
+    match leading_system.and_then(|m| m.get_mut("content")) {
+        Some(Value::String(content)) => {
+            content.push_str("\n\n");
+            content.push_str(prompt);
+        }
+        Some(Value::Array(parts)) => parts.push(json!({"type": "text", "text": prompt})),
+        _ => messages.insert(0, json!({"role": "system", "content": prompt})),
+    }
+    messages
+}
+
+/// Adds streamed text to the held text and returns what can be shown now:
+/// text before a `<tool_call>`, minus any tail that may be starting one.
+/// A reply opening with a JSON object is held whole.
+fn release_text(state: &mut StreamState, piece: &str) -> String {
+    state.held_text.push_str(piece);
+    if state.holding_tool_call {
+        return String::new();
+    }
+    if let Some(start) = state.held_text.find(CALL_OPEN) {
+        state.holding_tool_call = true;
+        return state.held_text.drain(..start).collect();
+    }
+    if !state.text_sent {
+        let lead = state.held_text.trim_start();
+        if lead.starts_with('{') || lead.starts_with(JSON_FENCE) {
+            state.holding_tool_call = true;
+            return String::new();
+        }
+        if JSON_FENCE.starts_with(lead) {
+            return String::new();
+        }
+    }
+    let partial = (1..CALL_OPEN.len())
+        .rev()
+        .find(|&n| state.held_text.ends_with(&CALL_OPEN[..n]))
+        .unwrap_or(0);
+    let shown: String = state
+        .held_text
+        .drain(..state.held_text.len() - partial)
+        .collect();
+    state.text_sent |= !shown.is_empty();
+    shown
+}
+
+impl UpstreamConverter for ToolEmulationConverter {
+    /// Local upstreams are addressed directly; this is never joined to a base URL.
+    fn upstream_path(&self, _body: &Value) -> String {
+        "/chat/completions".to_string()
+    }
+
+    fn convert_request(&self, body: &Value) -> Value {
+        let mut out = body.clone();
+        let Some(obj) = out.as_object_mut() else {
+            return out;
+        };
+        let tools = match obj.remove("tools") {
+            Some(Value::Array(tools)) => tools,
+            _ => Vec::new(),
+        };
+        let tool_choice = obj.remove("tool_choice");
+        let parallel = obj
+            .remove("parallel_tool_calls")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let messages = obj
+            .get("messages")
+            .and_then(Value::as_array)
+            .map(|messages| rewrite_messages(messages))
+            .unwrap_or_default();
+        let prompt = protocol_prompt(&tools, tool_choice.as_ref(), parallel);
+        obj.insert(
+            "messages".to_string(),
+            Value::Array(with_system_prompt(messages, &prompt)),
+        );
+        out
+    }
+
+    fn convert_response(&self, upstream: &Value) -> Value {
+        let mut out = upstream.clone();
+        let Some(choices) = out.get_mut("choices").and_then(Value::as_array_mut) else {
+            return out;
+        };
+        for choice in choices {
+            let Some(message) = choice.get_mut("message") else {
+                continue;
+            };
+            let native = message
+                .get("tool_calls")
+                .and_then(Value::as_array)
+                .is_some_and(|calls| !calls.is_empty());
+            let parsed = message
+                .get("content")
+                .and_then(Value::as_str)
+                .and_then(|text| self.parse(text));
+            let Some((prose, calls)) = parsed.filter(|_| !native) else {
+                continue;
+            };
+            message["content"] = if prose.is_empty() {
+                Value::Null
+            } else {
+                json!(prose)
+            };
+            message["tool_calls"] = calls.into_iter().map(tool_call).collect();
+            choice["finish_reason"] = json!("tool_calls");
+        }
+        out
+    }
+
+    fn convert_stream_event(&self, event: &SseEvent, state: &mut StreamState) -> Vec<String> {
+        if state.finished {
+            return Vec::new();
+        }
+        if event.data == "[DONE]" {
+            // The upstream ended without a finish chunk
+            let (mut out, called) = self.flush(state);
+            if called {
+                out.push(chunk_str(state, json!({}), Some("tool_calls")));
+            }
+            state.finished = true;
+            out.push(event.data.clone());
+            return out;
+        }
+        let Ok(mut chunk) = serde_json::from_str::<Value>(&event.data) else {
+            return vec![event.data.clone()];
+        };
+        if let Some(id) = chunk.get("id").and_then(Value::as_str) {
+            state.id = id.to_string();
+        }
+        if let Some(model) = chunk.get("model").and_then(Value::as_str) {
+            state.model = model.to_string();
+        }
+        if let Some(created) = chunk.get("created").and_then(Value::as_i64) {
+            state.created = created;
+        }
+        // Usage-only chunks carry no choice
+        let Some(choice) = chunk
+            .get_mut("choices")
+            .and_then(|c| c.get_mut(0))
+            .and_then(Value::as_object_mut)
+        else {
+            return vec![event.data.clone()];
+        };
+
+        let piece = match choice.get_mut("delta").and_then(Value::as_object_mut) {
+            Some(delta) if delta.get("content").is_some_and(Value::is_string) => {
+                match delta.remove("content") {
+                    Some(Value::String(piece)) => piece,
+                    _ => String::new(),
+                }
+            }
+            _ => String::new(),
+        };
+        let shown = release_text(state, &piece);
+        let finishing = choice.get("finish_reason").is_some_and(|f| !f.is_null());
+
+        if !finishing {
+            if !shown.is_empty() {
+                if let Some(delta) = choice.get_mut("delta").and_then(Value::as_object_mut) {
+                    delta.insert("content".to_string(), json!(shown));
+                }
+            }
+            let empty_delta = choice
+                .get("delta")
+                .and_then(Value::as_object)
+                .map_or(true, |delta| delta.is_empty());
+            if empty_delta && chunk.get("usage").map_or(true, Value::is_null) {
+                return Vec::new();
+            }
+            return vec![chunk.to_string()];
+        }
+
+        let mut out = Vec::new();
+        if !shown.is_empty() {
+            out.push(chunk_str(state, json!({"content": shown}), None));
+        }
+        let (flushed, called) = self.flush(state);
+        out.extend(flushed);
+        if called {
+            choice.insert("finish_reason".to_string(), json!("tool_calls"));
+        }
+        out.push(chunk.to_string());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn converter() -> ToolEmulationConverter {
+        ToolEmulationConverter::for_request(&json!({
+            "tools": [{"type": "function", "function": {"name": "get_weather", "parameters": {"type": "object"}}}]
+        }))
+        .unwrap()
+    }
+
+    fn sse(data: Value) -> SseEvent {
+        SseEvent {
+            event: String::new(),
+            data: data.to_string(),
+        }
+    }
+
+    fn delta(content: &str, finish: Option<&str>) -> SseEvent {
+        sse(json!({
+            "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 7, "model": "tiny",
+            "choices": [{"index": 0, "delta": {"content": content}, "finish_reason": finish}]
+        }))
+    }
+
+    #[test]
+    fn request_moves_tools_and_history_into_the_prompt() {
+        let body = json!({
+            "model": "tiny",
+            "tool_choice": "required",
+            "tools": [{"type": "function", "function": {"name": "get_weather", "description": "Weather by city", "parameters": {"type": "object"}}}],
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Weather in Paris and Rome?"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "a", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}},
+                    {"id": "b", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Rome\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "a", "content": "sunny"},
+                {"role": "tool", "tool_call_id": "b", "content": "rainy"}
+            ]
+        });
+        assert!(
+            ToolEmulationConverter::for_request(&json!({"tools": [], "messages": []})).is_none()
+        );
+        let out = converter().convert_request(&body);
+        assert!(out.get("tools").is_none() && out.get("tool_choice").is_none());
+
+        let messages = out["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        let system = messages[0]["content"].as_str().unwrap();
+        assert!(system.starts_with("Be brief.\n\n# Tools"));
+        assert!(system.contains("\"name\":\"get_weather\"") && system.contains("Weather by city"));
+        assert!(system.ends_with("You must call at least one tool."));
+        let assistant = messages[2]["content"].as_str().unwrap();
+        assert_eq!(assistant.matches(CALL_OPEN).count(), 2);
+        assert!(assistant.contains(r#"{"arguments":{"city":"Paris"},"name":"get_weather"}"#));
+        assert_eq!(messages[3]["role"], "user");
+        assert_eq!(
+            messages[3]["content"],
+            "<tool_response>\nsunny\n</tool_response>\n<tool_response>\nrainy\n</tool_response>"
+        );
+    }
+
+    #[test]
+    fn replies_parse_into_tool_calls_for_offered_tools_only() {
+        let reply = |content: &str| json!({"choices": [{"index": 0, "message": {"role": "assistant", "content": content}, "finish_reason": "stop"}]});
+        let conv = converter();
+
+        let out = conv.convert_response(&reply(
+            "Checking.\n<tool_call>\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Paris\"}}\n</tool_call>",
+        ));
+        let choice = &out["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(choice["message"]["content"], "Checking.");
+        let call = &choice["message"]["tool_calls"][0];
+        assert_eq!(call["function"]["name"], "get_weather");
+        assert_eq!(call["function"]["arguments"], r#"{"city":"Paris"}"#);
+        assert!(call["id"].as_str().unwrap().starts_with("call_"));
+
+        // A bare (fenced) JSON call is accepted too
+        let out = conv.convert_response(&reply(
+            "```json\n{\"name\": \"get_weather\", \"parameters\": {\"city\": \"Rome\"}}\n```",
+        ));
+        assert_eq!(out["choices"][0]["message"]["content"], Value::Null);
+        assert_eq!(
+            out["choices"][0]["message"]["tool_calls"][0]["function"]["arguments"],
+            r#"{"city":"Rome"}"#
+        );
+
+        // Unknown tools and plain JSON answers stay text
+        for text in [
+            r#"{"name": "rm_rf", "arguments": {}}"#,
+            r#"{"city": "Paris"}"#,
+            "Sunny.",
+        ] {
+            let untouched = reply(text);
+            assert_eq!(conv.convert_response(&untouched), untouched);
+        }
+    }
+
+    #[test]
+    fn streams_pass_text_and_turn_held_calls_into_deltas() {
+        let conv = converter();
+        let mut state = StreamState::default();
+        let mut out = Vec::new();
+        for event in [
+            delta("Let me check", None),
+            delta(". <tool_", None),
+            delta("call>{\"name\": \"get_weather\", ", None),
+            delta("\"arguments\": {\"city\": \"Paris\"}}</tool_call>", None),
+            delta("", Some("stop")),
+        ] {
+            out.extend(conv.convert_stream_event(&event, &mut state));
+        }
+        out.extend(conv.convert_stream_event(
+            &SseEvent {
+                event: String::new(),
+                data: "[DONE]".into(),
+            },
+            &mut state,
+        ));
+
+        let chunks: Vec<Value> = out
+            .iter()
+            .filter(|p| *p != "[DONE]")
+            .map(|p| serde_json::from_str(p).unwrap())
+            .collect();
+        let text: String = chunks
+            .iter()
+            .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+            .collect();
+        assert_eq!(text, "Let me check. ");
+        let calls = chunks
+            .iter()
+            .find_map(|c| c["choices"][0]["delta"]["tool_calls"].as_array())
+            .unwrap();
+        assert_eq!(calls[0]["index"], 0);
+        assert_eq!(calls[0]["function"]["name"], "get_weather");
+        assert_eq!(
+            chunks.last().unwrap()["choices"][0]["finish_reason"],
+            "tool_calls"
+        );
+        assert_eq!(chunks[0]["id"], "chatcmpl-1");
+        assert_eq!(out.last().unwrap(), "[DONE]");
+
+        // A held JSON answer that is not a call is released as text at the end
+        let mut state = StreamState::default();
+        let mut out = conv.convert_stream_event(&delta("{\"city\": ", None), &mut state);
+        assert!(out.is_empty());
+        out.extend(conv.convert_stream_event(&delta("\"Paris\"}", Some("stop")), &mut state));
+        let first: Value = serde_json::from_str(&out[0]).unwrap();
+        assert_eq!(
+            first["choices"][0]["delta"]["content"],
+            r#"{"city": "Paris"}"#
+        );
+        let last: Value = serde_json::from_str(out.last().unwrap()).unwrap();
+        assert_eq!(last["choices"][0]["finish_reason"], "stop");
+    }
+}
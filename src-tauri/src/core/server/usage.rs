@@ -0,0 +1,680 @@
+//! Request-level usage accounting for the local API server.
+//!
+//! Every inference reply is metered as its body streams past the client:
+//! token counts come from the upstream's own `usage` block (whatever its wire
+//! format), latency and time-to-first-token from the proxy's clock. Totals are
+//! aggregated per UTC day, model, provider and client key, and persisted to
+//! `<jan_data>/usage.json` so both `GET /v1/usage` and `jan usage` can report
+//! them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use super::client_keys::write_private;
+use crate::core::app::commands::resolve_jan_data_folder;
+
+pub const USAGE_FILE_NAME: &str = "usage.json";
+
+/// Serializes snapshot-write-rename of the usage file, so a write never
+/// interleaves with another or replaces a newer snapshot with an older one.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Routes whose replies carry model output worth accounting for.
+const METERED_ROUTES: &[&str] = &[
+    "/chat/completions",
+    "/completions",
+    "/embeddings",
+    "/messages",
+    "/responses",
+    "/orchestrations",
+    "/api/chat",
+    "/api/generate",
+    "/api/embeddings",
+    "/api/embed",
+];
+
+pub(crate) fn is_metered_route(route: &str) -> bool {
+    METERED_ROUTES.contains(&route)
+}
+
+/// Prompt and completion token counts reported by an upstream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenCounts {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenCounts {
+    pub fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// Counts reported in a complete, non-streamed reply.
+    pub(crate) fn of_reply(reply: &serde_json::Value) -> Self {
+        let mut scanner = UsageScanner::default();
+        scanner.observe(reply);
+        scanner.counts
+    }
+}
+
+/// Pulls token counts out of a response body as it streams past, whatever the
+/// wire format: chat/completions (`prompt_tokens`/`completion_tokens`),
+/// Anthropic (`message.usage` then `message_delta.usage`), Responses
+/// (`response.usage`) or Ollama (top-level `prompt_eval_count`/`eval_count`).
+/// SSE and NDJSON streams are scanned line by line; other bodies are parsed
+/// once complete.
+#[derive(Debug, Default)]
+pub(crate) struct UsageScanner {
+    /// Line-delimited stream (SSE or NDJSON).
+    sse: bool,
+    buf: Vec<u8>,
+    counts: TokenCounts,
+}
+
+/// Cap on buffered non-streaming bodies; larger bodies go unmetered.
+const MAX_SCANNED_BODY: usize = 16 * 1024 * 1024;
+
+impl UsageScanner {
+    pub(crate) fn new(sse: bool) -> Self {
+        Self {
+            sse,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        if !self.sse {
+            if self.buf.len() + bytes.len() <= MAX_SCANNED_BODY {
+                self.buf.extend_from_slice(bytes);
+            }
+            return;
+        }
+        self.buf.extend_from_slice(bytes);
+        while let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            self.scan_line(&line);
+        }
+    }
+
+    fn scan_line(&mut self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        // NDJSON lines are bare objects; SSE `event:` lines fail to parse.
+        let data = line.strip_prefix("data:").unwrap_or(line);
+        if let Ok(v) = serde_json::from_str::<serde_json::Value>(data.trim()) {
+            self.observe(&v);
+        }
+    }
+
+    fn observe(&mut self, v: &serde_json::Value) {
+        let candidates = [
+            v.get("usage"),
+            v.get("response").and_then(|r| r.get("usage")),
+            v.get("message").and_then(|m| m.get("usage")),
+            Some(v),
+        ];
+        for usage in candidates.into_iter().flatten() {
+            let field = |names: &[&str]| {
+                names
+                    .iter()
+                    .find_map(|n| usage.get(*n).and_then(|x| x.as_u64()))
+                    .unwrap_or(0)
+            };
+            let input = field(&["prompt_tokens", "input_tokens", "prompt_eval_count"]);
+            let output = field(&["completion_tokens", "output_tokens", "eval_count"]);
+            if input > 0 {
+                self.counts.input_tokens = input;
+            }
+            if output > 0 {
+                self.counts.output_tokens = output;
+            }
+        }
+    }
+
+    /// Token counts seen once the body has ended.
+    pub(crate) fn finish(mut self) -> TokenCounts {
+        let rest = std::mem::take(&mut self.buf);
+        if self.sse {
+            self.scan_line(&rest);
+        } else if let Ok(v) = serde_json::from_slice::<serde_json::Value>(&rest) {
+            self.observe(&v);
+        }
+        self.counts
+    }
+}
+
+/// One metered request, as recorded once its reply has finished.
+#[derive(Debug, Clone)]
+pub struct UsageEvent {
+    pub model: String,
+    /// Provider name for remote models; `llamacpp` / `mlx` for local ones.
+    pub provider: String,
+    /// Client key name, when the request authenticated with one.
+    pub client: Option<String>,
+    pub status: u16,
+    pub tokens: TokenCounts,
+    pub latency_ms: u64,
+    pub ttft_ms: Option<u64>,
+}
+
+/// Summed counters for a bucket or report row.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    #[serde(default)]
+    pub errors: u64,
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    /// Sum of request latencies; divide by `requests` for the mean.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Sum of time-to-first-byte over `ttft_samples` requests.
+    #[serde(default)]
+    pub ttft_ms: u64,
+    #[serde(default)]
+    pub ttft_samples: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, other: &UsageTotals) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.latency_ms += other.latency_ms;
+        self.ttft_ms += other.ttft_ms;
+        self.ttft_samples += other.ttft_samples;
+    }
+
+    fn from_event(event: &UsageEvent) -> Self {
+        Self {
+            requests: 1,
+            errors: u64::from(event.status >= 400),
+            input_tokens: event.tokens.input_tokens,
+            output_tokens: event.tokens.output_tokens,
+            latency_ms: event.latency_ms,
+            ttft_ms: event.ttft_ms.unwrap_or(0),
+            ttft_samples: u64::from(event.ttft_ms.is_some()),
+        }
+    }
+}
+
+/// Persisted aggregate for one (day, model, provider, client) combination.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageBucket {
+    /// UTC day, `YYYY-MM-DD`.
+    pub day: String,
+    pub model: String,
+    pub provider: String,
+    #[serde(default)]
+    pub client: Option<String>,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// Dimension a usage report is grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageDimension {
+    Day,
+    Model,
+    Provider,
+    Client,
+}
+
+impl std::str::FromStr for UsageDimension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "day" => Ok(Self::Day),
+            "model" => Ok(Self::Model),
+            "provider" => Ok(Self::Provider),
+            "client" => Ok(Self::Client),
+            other => Err(format!(
+                "Unknown usage dimension '{other}' (expected day, model, provider or client)"
+            )),
+        }
+    }
+}
+
+/// Filters and grouping for a usage report. Days are inclusive `YYYY-MM-DD`.
+#[derive(Debug, Clone, Default)]
+pub struct UsageQuery {
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    pub client: Option<String>,
+    pub group_by: Vec<UsageDimension>,
+}
+
+impl UsageQuery {
+    /// Parse `since`, `until`, `model`, `provider`, `client` and a
+    /// comma-separated `group_by` from a URL query string.
+    pub fn from_query_string(query: &str) -> Result<Self, String> {
+        let mut out = Self::default();
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            let value = value.into_owned();
+            match key.as_ref() {
+                "since" => out.since = Some(value),
+                "until" => out.until = Some(value),
+                "model" => out.model = Some(value),
+                "provider" => out.provider = Some(value),
+                "client" => out.client = Some(value),
+                "group_by" => {
+                    for dim in value.split(',').filter(|d| !d.trim().is_empty()) {
+                        out.group_by.push(dim.parse()?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    fn matches(&self, bucket: &UsageBucket) -> bool {
+        self.since
+            .as_deref()
+            .map_or(true, |s| bucket.day.as_str() >= s)
+            && self
+                .until
+                .as_deref()
+                .map_or(true, |u| bucket.day.as_str() <= u)
+            && self.model.as_deref().map_or(true, |m| bucket.model == m)
+            && self
+                .provider
+                .as_deref()
+                .map_or(true, |p| bucket.provider == p)
+            && self
+                .client
+                .as_deref()
+                .map_or(true, |c| bucket.client.as_deref() == Some(c))
+    }
+}
+
+/// One row of a usage report: the grouped dimensions plus derived averages.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    pub requests: u64,
+    pub errors: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    pub avg_latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_ttft_ms: Option<u64>,
+    /// Output tokens per second of wall-clock request time.
+    pub output_tokens_per_second: f64,
+}
+
+impl UsageRow {
+    fn from_totals(totals: &UsageTotals) -> Self {
+        Self {
+            requests: totals.requests,
+            errors: totals.errors,
+            input_tokens: totals.input_tokens,
+            output_tokens: totals.output_tokens,
+            total_tokens: totals.input_tokens + totals.output_tokens,
+            avg_latency_ms: totals.latency_ms.checked_div(totals.requests).unwrap_or(0),
+            avg_ttft_ms: totals.ttft_ms.checked_div(totals.ttft_samples),
+            output_tokens_per_second: if totals.latency_ms == 0 {
+                0.0
+            } else {
+                (totals.output_tokens as f64 * 1000.0 / totals.latency_ms as f64 * 100.0).round()
+                    / 100.0
+            },
+            ..Self::default()
+        }
+    }
+}
+
+/// Response body of `GET /v1/usage` and `jan usage`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub object: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    pub group_by: Vec<UsageDimension>,
+    pub data: Vec<UsageRow>,
+    pub totals: UsageRow,
+}
+
+/// Aggregate `buckets` into a report for `query`.
+pub fn summarize(buckets: &[UsageBucket], query: &UsageQuery) -> UsageReport {
+    let mut grouped: BTreeMap<[Option<String>; 4], UsageTotals> = BTreeMap::new();
+    let mut totals = UsageTotals::default();
+    for bucket in buckets.iter().filter(|b| query.matches(b)) {
+        let pick = |dim: UsageDimension, value: Option<&String>| {
+            query
+                .group_by
+                .contains(&dim)
+                .then(|| value.cloned().unwrap_or_default())
+        };
+        let key = [
+            pick(UsageDimension::Day, Some(&bucket.day)),
+            pick(UsageDimension::Model, Some(&bucket.model)),
+            pick(UsageDimension::Provider, Some(&bucket.provider)),
+            pick(UsageDimension::Client, bucket.client.as_ref()),
+        ];
+        grouped.entry(key).or_default().add(&bucket.totals);
+        totals.add(&bucket.totals);
+    }
+
+    let data = if query.group_by.is_empty() {
+        Vec::new()
+    } else {
+        grouped
+            .into_iter()
+            .map(|([day, model, provider, client], t)| UsageRow {
+                day,
+                model,
+                provider,
+                client,
+                ..UsageRow::from_totals(&t)
+            })
+            .collect()
+    };
+
+    UsageReport {
+        object: "usage",
+        since: query.since.clone(),
+        until: query.until.clone(),
+        group_by: query.group_by.clone(),
+        data,
+        totals: UsageRow::from_totals(&totals),
+    }
+}
+
+fn usage_file_path() -> PathBuf {
+    resolve_jan_data_folder().join(USAGE_FILE_NAME)
+}
+
+/// Read the persisted buckets; a missing or unreadable file is empty.
+pub fn read_usage_file(path: &Path) -> Vec<UsageBucket> {
+    let Ok(bytes) = fs::read(path) else {
+        return Vec::new();
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(buckets) => buckets,
+        Err(err) => {
+            log::warn!("Failed to parse {}: {err}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+fn write_usage_file(path: &Path, buckets: &[UsageBucket]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let bytes = serde_json::to_vec(buckets).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    write_private(&tmp, &bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+type BucketKey = (String, String, String, Option<String>);
+
+/// Usage totals for the running server. Loaded from disk on first use and
+/// written through after every recorded request.
+#[derive(Debug, Default)]
+pub struct UsageLedger {
+    /// Overrides the data-folder location (tests).
+    path: Option<PathBuf>,
+    buckets: Mutex<Option<HashMap<BucketKey, UsageTotals>>>,
+}
+
+impl UsageLedger {
+    #[cfg(test)]
+    fn at(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            ..Self::default()
+        }
+    }
+
+    fn file_path(&self) -> PathBuf {
+        self.path.clone().unwrap_or_else(usage_file_path)
+    }
+
+    fn with_buckets<T>(&self, f: impl FnOnce(&mut HashMap<BucketKey, UsageTotals>) -> T) -> T {
+        let mut guard = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let buckets = guard.get_or_insert_with(|| {
+            read_usage_file(&self.file_path())
+                .into_iter()
+                .map(|b| ((b.day, b.model, b.provider, b.client), b.totals))
+                .collect()
+        });
+        f(buckets)
+    }
+
+    fn snapshot(buckets: &HashMap<BucketKey, UsageTotals>) -> Vec<UsageBucket> {
+        let mut out: Vec<UsageBucket> = buckets
+            .iter()
+            .map(|((day, model, provider, client), totals)| UsageBucket {
+                day: day.clone(),
+                model: model.clone(),
+                provider: provider.clone(),
+                client: client.clone(),
+                totals: totals.clone(),
+            })
+            .collect();
+        out.sort_by(|a, b| {
+            (&a.day, &a.model, &a.provider, &a.client).cmp(&(
+                &b.day,
+                &b.model,
+                &b.provider,
+                &b.client,
+            ))
+        });
+        out
+    }
+
+    /// Add one request to its day's bucket and persist. Blocking (file I/O).
+    pub fn record(&self, event: UsageEvent) {
+        let day = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        self.record_on(day, event);
+    }
+
+    fn record_on(&self, day: String, event: UsageEvent) {
+        let path = self.file_path();
+        let _file_guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let snapshot = self.with_buckets(|buckets| {
+            buckets
+                .entry((
+                    day,
+                    event.model.clone(),
+                    event.provider.clone(),
+                    event.client.clone(),
+                ))
+                .or_default()
+                .add(&UsageTotals::from_event(&event));
+            Self::snapshot(buckets)
+        });
+        if let Err(err) = write_usage_file(&path, &snapshot) {
+            log::warn!("Failed to persist usage to {}: {err}", path.display());
+        }
+    }
+
+    pub fn report(&self, query: &UsageQuery) -> UsageReport {
+        let buckets = self.with_buckets(|buckets| Self::snapshot(buckets));
+        summarize(&buckets, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(model: &str, provider: &str, client: Option<&str>, status: u16) -> UsageEvent {
+        UsageEvent {
+            model: model.into(),
+            provider: provider.into(),
+            client: client.map(str::to_string),
+            status,
+            tokens: TokenCounts {
+                input_tokens: 100,
+                output_tokens: 50,
+            },
+            latency_ms: 2_000,
+            ttft_ms: Some(200),
+        }
+    }
+
+    #[test]
+    fn usage_scanner_reads_chat_anthropic_responses_and_ollama_shapes() {
+        let mut chat = UsageScanner::new(true);
+        chat.push(
+            b"data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\ndata: {\"choices\":[],\"us",
+        );
+        chat.push(b"age\":{\"prompt_tokens\":10,\"completion_tokens\":5}}\n\ndata: [DONE]\n\n");
+        assert_eq!(
+            chat.finish(),
+            TokenCounts {
+                input_tokens: 10,
+                output_tokens: 5
+            }
+        );
+
+        let mut anthropic = UsageScanner::new(true);
+        for ev in [
+            json!({"type": "message_start", "message": {"usage": {"input_tokens": 7, "output_tokens": 1}}}),
+            json!({"type": "message_delta", "usage": {"output_tokens": 9}}),
+        ] {
+            anthropic.push(format!("event: x\ndata: {ev}\n\n").as_bytes());
+        }
+        assert_eq!(anthropic.finish().total(), 16);
+
+        let mut responses = UsageScanner::new(false);
+        let body = json!({"object": "response", "usage": {"input_tokens": 3, "output_tokens": 4}});
+        responses.push(body.to_string().as_bytes());
+        assert_eq!(responses.finish().total(), 7);
+
+        let mut ollama = UsageScanner::new(true);
+        ollama.push(b"{\"message\":{\"content\":\"hi\"},\"done\":false}\n");
+        ollama.push(b"{\"done\":true,\"prompt_eval_count\":6,\"eval_count\":2}\n");
+        assert_eq!(ollama.finish().total(), 8);
+    }
+
+    #[test]
+    fn ledger_aggregates_and_survives_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(USAGE_FILE_NAME);
+        let ledger = UsageLedger::at(path.clone());
+        ledger.record_on(
+            "2026-10-01".into(),
+            event("qwen3-8b", "llamacpp", None, 200),
+        );
+        ledger.record_on(
+            "2026-10-01".into(),
+            event("qwen3-8b", "llamacpp", None, 500),
+        );
+        ledger.record_on(
+            "2026-10-02".into(),
+            event("gpt-4o", "openai", Some("ci"), 200),
+        );
+
+        let reloaded = UsageLedger::at(path);
+        let report = reloaded.report(&UsageQuery {
+            group_by: vec![UsageDimension::Model],
+            ..UsageQuery::default()
+        });
+        assert_eq!(report.totals.requests, 3);
+        assert_eq!(report.totals.total_tokens, 450);
+        let qwen = report
+            .data
+            .iter()
+            .find(|r| r.model.as_deref() == Some("qwen3-8b"))
+            .unwrap();
+        assert_eq!(qwen.requests, 2);
+        assert_eq!(qwen.errors, 1);
+        assert_eq!(qwen.avg_latency_ms, 2_000);
+        assert_eq!(qwen.avg_ttft_ms, Some(200));
+        assert_eq!(qwen.output_tokens_per_second, 25.0);
+        assert!(qwen.provider.is_none());
+    }
+
+    #[test]
+    fn concurrent_records_all_reach_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(USAGE_FILE_NAME);
+        let ledger = UsageLedger::at(path.clone());
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..20 {
+                        ledger.record_on(
+                            "2026-10-01".into(),
+                            event("qwen3-8b", "llamacpp", None, 200),
+                        );
+                    }
+                });
+            }
+        });
+        let buckets = read_usage_file(&path);
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].totals.requests, 160);
+    }
+
+    #[test]
+    fn report_filters_by_day_range_and_client() {
+        let buckets = vec![
+            UsageBucket {
+                day: "2026-09-30".into(),
+                model: "m".into(),
+                provider: "p".into(),
+                client: Some("alice".into()),
+                totals: UsageTotals {
+                    requests: 1,
+                    ..UsageTotals::default()
+                },
+            },
+            UsageBucket {
+                day: "2026-10-01".into(),
+                model: "m".into(),
+                provider: "p".into(),
+                client: Some("bob".into()),
+                totals: UsageTotals {
+                    requests: 2,
+                    ..UsageTotals::default()
+                },
+            },
+        ];
+        let query = UsageQuery::from_query_string("since=2026-10-01&group_by=day,client").unwrap();
+        let report = summarize(&buckets, &query);
+        assert_eq!(report.totals.requests, 2);
+        assert_eq!(report.data.len(), 1);
+        assert_eq!(report.data[0].day.as_deref(), Some("2026-10-01"));
+        assert_eq!(report.data[0].client.as_deref(), Some("bob"));
+
+        let alice = summarize(
+            &buckets,
+            &UsageQuery {
+                client: Some("alice".into()),
+                ..UsageQuery::default()
+            },
+        );
+        assert_eq!(alice.totals.requests, 1);
+        assert!(alice.data.is_empty());
+
+        assert!(UsageQuery::from_query_string("group_by=weekday").is_err());
+    }
+}
@@ -7,6 +7,7 @@ use crate::core::{
     downloads::models::DownloadManagerState,
     mcp::models::{McpSettings, ToolWithServer},
     mcp::progress::JanClientHandler,
+    server::{client_keys::ClientKeyStore, model_aliases::ModelAliasStore, usage::UsageLedger},
 };
 use rmcp::{service::RunningService, RoleClient};
 use tokio::sync::{oneshot, Mutex, Notify};
@@ -30,8 +31,8 @@ pub struct ProviderConfig {
     /// Upstream wire API this provider speaks. `None`/`"openai"` = OpenAI
     /// chat/completions (verbatim passthrough). Other values select a
     /// translating converter (e.g. `"openai-responses"`, `"google"`,
-    /// `"anthropic"`) so the proxy can accept OpenAI-shaped requests and talk
-    /// the provider's native API.
+    /// `"anthropic"`, `"ollama"`, `"azure-openai"`) so the proxy can accept
+    /// OpenAI-shaped requests and talk the provider's native API.
     #[serde(default)]
     pub api_type: Option<String>,
 }
@@ -86,6 +87,14 @@ pub struct AppState {
     /// Cleared only on explicit user deactivation, never on a transient
     /// list-tools failure.
     pub mcp_last_known_tools: Arc<Mutex<HashMap<String, Vec<ToolWithServer>>>>,
+    /// Named per-client API keys for the local API server, with their scopes
+    /// and quota windows.
+    pub client_keys: Arc<ClientKeyStore>,
+    /// Per-day token and latency totals for requests served by the local API
+    /// server, persisted to `usage.json`.
+    pub usage: Arc<UsageLedger>,
+    /// Model aliases with fallback targets for the local API server.
+    pub model_aliases: Arc<ModelAliasStore>,
 }
 
 impl Default for AppState {
@@ -107,6 +116,9 @@ impl Default for AppState {
             model_param_defaults: Default::default(),
             mcp_reconnect_notify: Arc::new(Notify::new()),
             mcp_last_known_tools: Default::default(),
+            client_keys: Default::default(),
+            usage: Default::default(),
+            model_aliases: Default::default(),
         }
     }
 }
@@ -205,6 +205,11 @@ pub async fn db_list_messages<R: Runtime>(
     _app_handle: AppHandle<R>,
     thread_id: &str,
 ) -> Result<Vec<Value>, String> {
+    db_thread_messages(thread_id).await
+}
+
+/// List all messages for a thread, for callers without an app handle
+pub async fn db_thread_messages(thread_id: &str) -> Result<Vec<Value>, String> {
     let pool = get_pool().await?;
 
     let rows =
@@ -261,6 +266,11 @@ pub async fn db_modify_message<R: Runtime>(
     _app_handle: AppHandle<R>,
     message: Value,
 ) -> Result<Value, String> {
+    db_modify_message_row(&message).await?;
+    Ok(message)
+}
+
+async fn db_modify_message_row(message: &Value) -> Result<(), String> {
     let pool = get_pool().await?;
 
     let message_id = message
@@ -273,7 +283,7 @@ pub async fn db_modify_message<R: Runtime>(
         .and_then(|v| v.as_str())
         .ok_or("Missing thread_id")?;
 
-    let data = serde_json::to_string(&message).map_err(|e| e.to_string())?;
+    let data = serde_json::to_string(message).map_err(|e| e.to_string())?;
 
     // Upsert so a modify ahead of create still lands instead of UPDATEing 0 rows.
     sqlx::query(
@@ -287,7 +297,7 @@ pub async fn db_modify_message<R: Runtime>(
     .await
     .map_err(|e| format!("Failed to modify message: {}", e))?;
 
-    Ok(message)
+    Ok(())
 }
 
 /// Delete a message from database
@@ -307,6 +317,55 @@ pub async fn db_delete_message<R: Runtime>(
     Ok(())
 }
 
+/// Get a thread by id, for callers without an app handle
+pub async fn db_get_thread(thread_id: &str) -> Result<Option<Value>, String> {
+    let pool = get_pool().await?;
+
+    let row = sqlx::query("SELECT data FROM threads WHERE id = ?1")
+        .bind(thread_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| format!("Failed to get thread: {}", e))?;
+
+    row.map(|row| {
+        let data: String = row.get("data");
+        serde_json::from_str(&data).map_err(|e| e.to_string())
+    })
+    .transpose()
+}
+
+/// Insert or replace a thread, for callers without an app handle
+pub async fn db_upsert_thread(thread: &Value) -> Result<(), String> {
+    let pool = get_pool().await?;
+
+    let thread_id = thread
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or("Missing thread id")?;
+
+    let data = serde_json::to_string(thread).map_err(|e| e.to_string())?;
+
+    sqlx::query(
+        "INSERT INTO threads (id, data) VALUES (?1, ?2) \
+         ON CONFLICT(id) DO UPDATE SET data = excluded.data, updated_at = strftime('%s', 'now')",
+    )
+    .bind(thread_id)
+    .bind(&data)
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to save thread: {}", e))?;
+
+    Ok(())
+}
+
+/// Insert or replace messages, in order, for callers without an app handle
+pub async fn db_upsert_messages(messages: &[Value]) -> Result<(), String> {
+    for message in messages {
+        db_modify_message_row(message).await?;
+    }
+    Ok(())
+}
+
 /// Get thread assistant information from thread metadata
 pub async fn db_get_thread_assistant<R: Runtime>(
     _app_handle: AppHandle<R>,
@@ -57,6 +57,13 @@ macro_rules! invoke_commands_with_extras {
         core::app::settings_store::settings_remove,
         core::server::provider_secrets::set_secret,
         core::server::provider_secrets::get_secret,
+        core::server::client_keys::list_client_keys,
+        core::server::client_keys::create_client_key,
+        core::server::client_keys::update_client_key,
+        core::server::client_keys::delete_client_key,
+        core::server::model_aliases::list_model_aliases,
+        core::server::model_aliases::set_model_alias,
+        core::server::model_aliases::delete_model_alias,
         // System commands
         core::system::commands::relaunch,
         core::system::commands::open_app_directory,
@@ -88,6 +95,14 @@ macro_rules! invoke_commands_with_extras {
         core::mcp::commands::get_server_summaries,
         core::mcp::commands::call_tool,
         core::mcp::commands::cancel_tool_call,
+        core::mcp::commands::resolve_tool_approval,
+        core::mcp::commands::list_tool_approvals,
+        core::mcp::commands::list_resources,
+        core::mcp::commands::read_resource,
+        core::mcp::commands::subscribe_resource,
+        core::mcp::commands::unsubscribe_resource,
+        core::mcp::commands::list_prompts,
+        core::mcp::commands::get_prompt,
         core::mcp::commands::restart_mcp_servers,
         core::mcp::commands::get_connected_servers,
         core::mcp::commands::save_mcp_configs,
@@ -287,6 +302,9 @@ pub fn run() {
             model_param_defaults: Arc::new(Mutex::new(HashMap::new())),
             mcp_reconnect_notify: Arc::new(tokio::sync::Notify::new()),
             mcp_last_known_tools: Arc::new(Mutex::new(HashMap::new())),
+            client_keys: Default::default(),
+            usage: Default::default(),
+            model_aliases: Default::default(),
         })
         .setup(|app| {
             app.handle().plugin(
@@ -0,0 +1,128 @@
+import { useEffect, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import { Wrench } from 'lucide-react'
+
+import { isPlatformTauri } from '@/lib/platform/utils'
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from '@/components/ui/dialog'
+import { Button } from '@/components/ui/button'
+import { useTranslation } from '@/i18n/react-i18next-compat'
+
+/** A tool call the local API server holds until it is approved here. */
+type ServerToolApproval = {
+  id: string
+  server: string
+  tool: string
+  arguments: unknown
+}
+
+/**
+ * Approval prompt for MCP tool calls made through the local API server
+ * (`/orchestrations` and server-side tool execution), as opposed to calls
+ * made from a chat thread. Requests queue up and are answered one at a time.
+ */
+export default function ServerToolApprovalDialog() {
+  const { t } = useTranslation()
+  const [queue, setQueue] = useState<ServerToolApproval[]>([])
+  const [resolving, setResolving] = useState(false)
+
+  useEffect(() => {
+    if (!isPlatformTauri()) return
+    const enqueue = (requests: ServerToolApproval[]) =>
+      setQueue((current) => [
+        ...current,
+        ...requests.filter((r) => !current.some((c) => c.id === r.id)),
+      ])
+
+    // Requests announced before this window was listening.
+    invoke<ServerToolApproval[]>('list_tool_approvals')
+      .then(enqueue)
+      .catch((e) => console.warn('list_tool_approvals failed:', e))
+
+    const unlisten = listen<ServerToolApproval>('mcp-tool-approval', (event) =>
+      enqueue([event.payload])
+    ).catch((e) => {
+      console.warn('listen mcp-tool-approval failed:', e)
+      return () => {}
+    })
+    return () => {
+      void unlisten.then((fn) => fn?.())
+    }
+  }, [])
+
+  const current = queue[0]
+
+  const resolve = async (approved: boolean) => {
+    if (!current) return
+    setResolving(true)
+    try {
+      await invoke('resolve_tool_approval', { id: current.id, approved })
+    } catch (e) {
+      // Already timed out or answered; nothing is waiting on it any more.
+      console.warn('resolve_tool_approval failed:', e)
+    } finally {
+      setQueue((q) => q.filter((r) => r.id !== current.id))
+      setResolving(false)
+    }
+  }
+
+  return (
+    <Dialog
+      open={current !== undefined}
+      onOpenChange={(open) => !open && !resolving && void resolve(false)}
+    >
+      <DialogContent showCloseButton={false}>
+        <DialogHeader>
+          <div className="flex items-start gap-3">
+            <div className="shrink-0">
+              <Wrench className="size-4" />
+            </div>
+            <div>
+              <DialogTitle>{t('tool-approval:serverTitle')}</DialogTitle>
+              <DialogDescription className="mt-1 text-main-view-fg/70">
+                {t('tool-approval:serverDescription', {
+                  toolName: current?.tool,
+                  serverName: current?.server,
+                })}
+              </DialogDescription>
+            </div>
+          </div>
+        </DialogHeader>
+
+        <div className="bg-main-view-fg/2 p-2 border border-main-view-fg/5 rounded-lg text-sm text-main-view-fg/70">
+          <p className="mb-1 font-medium">{t('tool-approval:parameters')}</p>
+          <pre className="max-h-[200px] overflow-auto whitespace-pre-wrap break-all">
+            {JSON.stringify(current?.arguments ?? {}, null, 2)}
+          </pre>
+        </div>
+
+        <DialogFooter className="flex flex-col gap-2 sm:flex-row sm:justify-end">
+          <Button
+            variant="link"
+            onClick={() => void resolve(false)}
+            disabled={resolving}
+            className="flex-1 text-right sm:flex-none"
+          >
+            {t('tool-approval:deny')}
+          </Button>
+          <Button
+            variant="link"
+            onClick={() => void resolve(true)}
+            disabled={resolving}
+            autoFocus
+            className="flex-1 text-right sm:flex-none border border-main-view-fg/10 !px-2"
+          >
+            {t('tool-approval:approve')}
+          </Button>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  )
+}
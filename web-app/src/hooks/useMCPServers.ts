@@ -23,8 +23,20 @@ export type MCPServers = {
   [key: string]: MCPServerConfig
 }
 
+/** What the local API server does when a model calls an MCP tool. */
+export type ToolApproval = 'allow' | 'deny' | 'ask'
+
+/** Most specific rule wins: `tools` ("server/tool" or bare tool name), then `servers`, then `default`. */
+export type ToolApprovalPolicy = {
+  default: ToolApproval
+  servers: Record<string, ToolApproval>
+  tools: Record<string, ToolApproval>
+}
+
 export type MCPSettings = {
   toolCallTimeoutSeconds: number
+  /** How many tool calls of one model turn the local API server runs at once. */
+  maxParallelToolCalls: number
   baseRestartDelayMs: number
   maxRestartDelayMs: number
   backoffMultiplier: number
@@ -33,10 +45,13 @@ export type MCPSettings = {
   useLightweightRouterModel: boolean
   routerModelProvider: string
   routerModelId: string
+  /** Approval rules for tools the local API server runs on a model's behalf. */
+  toolApproval: ToolApprovalPolicy
 }
 
 export const DEFAULT_MCP_SETTINGS: MCPSettings = {
   toolCallTimeoutSeconds: 30,
+  maxParallelToolCalls: 4,
   baseRestartDelayMs: 1000,
   maxRestartDelayMs: 30000,
   backoffMultiplier: 2,
@@ -44,6 +59,7 @@ export const DEFAULT_MCP_SETTINGS: MCPSettings = {
   useLightweightRouterModel: false,
   routerModelProvider: '',
   routerModelId: '',
+  toolApproval: { default: 'allow', servers: {}, tools: {} },
 }
 
 type MCPServerStoreState = {
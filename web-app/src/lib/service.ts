@@ -103,6 +103,34 @@ export const APIs = {
                 'enable_server_tool_execution',
                 'enableServerToolExecution',
               ]),
+              enable_metrics: pickBoolean(raw, ['enable_metrics', 'enableMetrics']),
+              enable_ollama_api: pickBoolean(raw, ['enable_ollama_api', 'enableOllamaApi']),
+              enable_tool_emulation: pickBoolean(raw, [
+                'enable_tool_emulation',
+                'enableToolEmulation',
+              ]),
+              tls_cert_path: pickString(raw, ['tls_cert_path', 'tlsCertPath']),
+              tls_key_path: pickString(raw, ['tls_key_path', 'tlsKeyPath']),
+              tls_self_signed: pickBoolean(raw, ['tls_self_signed', 'tlsSelfSigned']),
+              tls_client_ca_path: pickString(raw, ['tls_client_ca_path', 'tlsClientCaPath']),
+              response_cache: pickBoolean(raw, ['response_cache', 'responseCache']),
+              response_cache_max_mb: pickNumber(raw, ['response_cache_max_mb', 'responseCacheMaxMb']),
+              response_cache_ttl_secs: pickNumber(raw, [
+                'response_cache_ttl_secs',
+                'responseCacheTtlSecs',
+              ]),
+              access_log: pickBoolean(raw, ['access_log', 'accessLog']),
+              access_log_capture_bodies: pickBoolean(raw, [
+                'access_log_capture_bodies',
+                'accessLogCaptureBodies',
+              ]),
+              batch_concurrency: pickNumber(raw, ['batch_concurrency', 'batchConcurrency']),
+              max_concurrent_per_model: pickNumber(raw, [
+                'max_concurrent_per_model',
+                'maxConcurrentPerModel',
+              ]),
+              max_queued_per_model: pickNumber(raw, ['max_queued_per_model', 'maxQueuedPerModel']),
+              queue_timeout_secs: pickNumber(raw, ['queue_timeout_secs', 'queueTimeoutSecs']),
             }
             return getServiceHub().core().invoke(command, { config })
           }
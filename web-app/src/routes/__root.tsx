@@ -31,6 +31,7 @@ import ErrorDialog from '@/containers/dialogs/ErrorDialog'
 import LlamacppBusyOnExitDialog from '@/containers/dialogs/LlamacppBusyOnExitDialog'
 import LlamacppOomListener from '@/containers/dialogs/LlamacppOomListener'
 import MissingDependenciesDialog from '@/containers/dialogs/MissingDependenciesDialog'
+import ServerToolApprovalDialog from '@/containers/dialogs/ServerToolApprovalDialog'
 
 export const Route = createRootRoute({
   component: RootLayout,
@@ -134,6 +135,7 @@ function RootLayout() {
           <LlamacppOomListener />
           <MissingDependenciesDialog />
           <OutOfContextPromiseModal />
+          <ServerToolApprovalDialog />
         </TranslationProvider>
       </ServiceHubProvider>
     </Fragment>